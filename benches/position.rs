@@ -0,0 +1,37 @@
+//! Position lookup on a large minified document: `cargo bench --bench position`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use json_ls::position::position_to_context;
+
+/// ~5 MB of minified JSON on one line, with the probe key as the last member.
+fn minified_document() -> String {
+    let mut text = String::from(r#"{"items":["#);
+    for i in 0..60_000 {
+        if i > 0 {
+            text.push(',');
+        }
+        text.push_str(&format!(
+            r#"{{"id":{i},"name":"item-{i}","tags":["a","b","ü"],"meta":{{"ok":true}}}}"#
+        ));
+    }
+    text.push_str(r#"],"last":"end"}"#);
+    text
+}
+
+fn minified(c: &mut Criterion) {
+    let text = minified_document();
+    let end = text.encode_utf16().count() as u32 - 4;
+
+    let mut group = c.benchmark_group("minified_document");
+    group.sample_size(20);
+    group.bench_function("context_at_start", |b| {
+        b.iter(|| position_to_context(black_box(&text), 0, black_box(12)))
+    });
+    group.bench_function("context_at_end", |b| {
+        b.iter(|| position_to_context(black_box(&text), 0, black_box(end)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, minified);
+criterion_main!(benches);
@@ -0,0 +1,120 @@
+//! `json-ls.addSchema`: declare a schema in the document itself, as the root object's first
+//! member.
+
+use crate::config::SchemaKeyPrecedence;
+use crate::document::effective_schema_key;
+use crate::formatting::{indent_unit, line_indent};
+use crate::span::{parse_spans, ByteEdit, SpanKind};
+use serde_json::Value;
+
+/// `workspace/executeCommand` taking `[uri, url?]`; without a URL, the catalog entry
+/// matching the document's file name is used.
+pub const ADD_SCHEMA_COMMAND: &str = "json-ls.addSchema";
+
+/// The edit making `url` the document's `$schema`: the value of an existing top-level
+/// `$schema` is replaced, else a member is inserted first in the root object (or a root
+/// object created for a blank document).
+pub fn schema_insertion(
+    text: &str,
+    url: &str,
+    precedence: SchemaKeyPrecedence,
+) -> Result<ByteEdit, String> {
+    let value = Value::String(url.to_owned()).to_string();
+    if let Some(key) = effective_schema_key(text, precedence) {
+        return Ok(ByteEdit {
+            start: key.value_start,
+            end: key.value_end,
+            new_text: value,
+        });
+    }
+
+    let member = format!("\"$schema\": {value}");
+    let end_of_line = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    let Some(root) = parse_spans(text) else {
+        let indent = indent_unit(text);
+        return Ok(ByteEdit {
+            start: 0,
+            end: text.len(),
+            new_text: format!("{{{end_of_line}{indent}{member}{end_of_line}}}{end_of_line}"),
+        });
+    };
+    let SpanKind::Object(members) = &root.kind else {
+        return Err("the document's root is not an object".into());
+    };
+
+    let open = root.start + 1;
+    let inner_indent = format!("{}{}", line_indent(text, root.start), indent_unit(text));
+    let Some(first) = members.first() else {
+        // `{}`: the closing brace moves to its own line.
+        let close = text[open..].find('}').map_or(text.len(), |i| open + i);
+        return Ok(ByteEdit {
+            start: open,
+            end: close,
+            new_text: format!(
+                "{end_of_line}{inner_indent}{member}{end_of_line}{}",
+                line_indent(text, root.start)
+            ),
+        });
+    };
+
+    let gap = &text[open..first.key_start];
+    let new_text = if gap.contains('\n') {
+        format!("{end_of_line}{inner_indent}{member},")
+    } else if gap.is_empty() {
+        format!("{member}, ")
+    } else {
+        format!("{gap}{member},")
+    };
+    Ok(ByteEdit {
+        start: open,
+        end: open,
+        new_text,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "https://example.com/schema.json";
+
+    fn added(text: &str) -> Result<String, String> {
+        let edit = schema_insertion(text, URL, SchemaKeyPrecedence::First)?;
+        let mut out = text.to_owned();
+        out.replace_range(edit.start..edit.end, &edit.new_text);
+        Ok(out)
+    }
+
+    #[test]
+    fn test_inserts_first_member_in_document_style() {
+        assert_eq!(
+            added("{\n    \"name\": \"x\"\n}").unwrap(),
+            format!("{{\n    \"$schema\": \"{URL}\",\n    \"name\": \"x\"\n}}")
+        );
+        assert_eq!(
+            added("{ \"a\": 1 }").unwrap(),
+            format!("{{ \"$schema\": \"{URL}\", \"a\": 1 }}")
+        );
+        assert_eq!(
+            added("{\"a\":1}").unwrap(),
+            format!("{{\"$schema\": \"{URL}\", \"a\":1}}")
+        );
+    }
+
+    #[test]
+    fn test_empty_document_and_object() {
+        let expected = format!("{{\n  \"$schema\": \"{URL}\"\n}}");
+        assert_eq!(added("").unwrap(), format!("{expected}\n"));
+        assert_eq!(added("{}").unwrap(), expected);
+        assert_eq!(added("{\n}").unwrap(), expected);
+    }
+
+    #[test]
+    fn test_replaces_existing_schema_and_rejects_arrays() {
+        assert_eq!(
+            added("{ \"$schema\": \"old.json\", \"a\": 1 }").unwrap(),
+            format!("{{ \"$schema\": \"{URL}\", \"a\": 1 }}")
+        );
+        assert!(added("[1, 2]").is_err());
+    }
+}
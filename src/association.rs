@@ -0,0 +1,68 @@
+use crate::document::DocumentStore;
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::Url;
+
+/// Where a document's schema association came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SchemaSource {
+    /// A `"$schema"` key inside the document itself.
+    Inline,
+    /// The configured `schema_resolver`.
+    Resolver,
+    /// A `schemas` setting whose `fileMatch` matches the document's file name.
+    FileMatch,
+    /// `language_schemas`, by the document's languageId.
+    Language,
+    /// A `catalog` entry matching the document's file name.
+    Catalog,
+}
+
+/// A schema that could apply to a document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaCandidate {
+    pub schema_url: String,
+    pub source: SchemaSource,
+}
+
+/// All schemas that could apply to `uri`, highest precedence first.
+///
+/// The first candidate is the schema in effect (see `DocumentStore::get_schema_url`); the
+/// rest are reported as overridden. The external resolver comes last.
+pub fn schema_candidates(uri: &Url, documents: &DocumentStore) -> Vec<SchemaCandidate> {
+    let mut candidates = Vec::new();
+
+    if let Some(url) = documents.get_inline_schema_url(uri) {
+        candidates.push(SchemaCandidate {
+            schema_url: url,
+            source: SchemaSource::Inline,
+        });
+    }
+    if let Some(url) = documents.get_configured_schema_url(uri) {
+        candidates.push(SchemaCandidate {
+            schema_url: url,
+            source: SchemaSource::FileMatch,
+        });
+    }
+    if let Some(url) = documents.get_language_schema_url(uri) {
+        candidates.push(SchemaCandidate {
+            schema_url: url,
+            source: SchemaSource::Language,
+        });
+    }
+    if let Some(url) = documents.get_catalog_schema_url(uri) {
+        candidates.push(SchemaCandidate {
+            schema_url: url,
+            source: SchemaSource::Catalog,
+        });
+    }
+    if let Some(url) = documents.get_associated_schema_url(uri) {
+        candidates.push(SchemaCandidate {
+            schema_url: url,
+            source: SchemaSource::Resolver,
+        });
+    }
+
+    candidates
+}
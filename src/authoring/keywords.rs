@@ -0,0 +1,426 @@
+use super::META_SCHEMAS;
+
+/// The seven primitive JSON Schema types.
+pub(crate) const TYPES: &[&str] = &[
+    "string", "number", "integer", "boolean", "object", "array", "null",
+];
+
+/// `format` values defined by the JSON Schema validation vocabulary.
+const FORMATS: &[&str] = &[
+    "date-time",
+    "date",
+    "time",
+    "duration",
+    "email",
+    "idn-email",
+    "hostname",
+    "idn-hostname",
+    "ipv4",
+    "ipv6",
+    "uri",
+    "uri-reference",
+    "iri",
+    "iri-reference",
+    "uuid",
+    "uri-template",
+    "json-pointer",
+    "relative-json-pointer",
+    "regex",
+];
+
+/// A suggested value for a keyword, with an optional short explanation.
+pub struct KeywordValue {
+    pub value: &'static str,
+    pub detail: Option<&'static str>,
+}
+
+/// Known string values for `keyword`, or an empty list if the keyword has none.
+pub fn keyword_values(keyword: &str) -> Vec<KeywordValue> {
+    let plain = |values: &[&'static str]| {
+        values
+            .iter()
+            .map(|&value| KeywordValue {
+                value,
+                detail: None,
+            })
+            .collect()
+    };
+
+    match keyword {
+        "type" => plain(TYPES),
+        "format" => plain(FORMATS),
+        "$schema" => META_SCHEMAS
+            .iter()
+            .map(|&(value, draft)| KeywordValue {
+                value,
+                detail: Some(draft),
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Built-in reference for a JSON Schema keyword.
+pub struct KeywordDoc {
+    pub keyword: &'static str,
+    pub summary: &'static str,
+    /// Drafts in which the keyword is defined.
+    pub availability: &'static str,
+}
+
+impl KeywordDoc {
+    pub fn to_markdown(&self) -> String {
+        format!(
+            "**`{}`** — {}\n\n**Available in:** {}",
+            self.keyword, self.summary, self.availability
+        )
+    }
+}
+
+const KEYWORD_DOCS: &[KeywordDoc] = &[
+    // Core
+    KeywordDoc {
+        keyword: "$schema",
+        summary: "Declares which meta-schema (draft) this schema is written against.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "$id",
+        summary: "Sets the canonical URI of the schema and the base URI for relative `$ref`s.",
+        availability: "Draft 6+ (`id` in Draft 4)",
+    },
+    KeywordDoc {
+        keyword: "$ref",
+        summary: "References another schema by URI or JSON Pointer. Before 2019-09, sibling keywords are ignored.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "$defs",
+        summary: "Container for reusable subschemas, referenced via `#/$defs/<name>`.",
+        availability: "Draft 2019-09+ (replaces `definitions`)",
+    },
+    KeywordDoc {
+        keyword: "definitions",
+        summary: "Container for reusable subschemas, referenced via `#/definitions/<name>`.",
+        availability: "Draft 4–7 (superseded by `$defs`)",
+    },
+    KeywordDoc {
+        keyword: "$anchor",
+        summary: "Names this subschema so it can be referenced as `#<name>`.",
+        availability: "Draft 2019-09+",
+    },
+    KeywordDoc {
+        keyword: "$dynamicAnchor",
+        summary: "Declares a dynamic extension point resolved at evaluation time by `$dynamicRef`.",
+        availability: "Draft 2020-12",
+    },
+    KeywordDoc {
+        keyword: "$dynamicRef",
+        summary: "Reference resolved against the outermost matching `$dynamicAnchor` in the dynamic scope.",
+        availability: "Draft 2020-12",
+    },
+    KeywordDoc {
+        keyword: "$comment",
+        summary: "Notes for schema maintainers; ignored by validators.",
+        availability: "Draft 7+",
+    },
+    KeywordDoc {
+        keyword: "$vocabulary",
+        summary: "In a meta-schema, lists the vocabularies it uses and whether each is required.",
+        availability: "Draft 2019-09+",
+    },
+    // Applicators
+    KeywordDoc {
+        keyword: "allOf",
+        summary: "The instance must be valid against every subschema in the array.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "anyOf",
+        summary: "The instance must be valid against at least one subschema in the array.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "oneOf",
+        summary: "The instance must be valid against exactly one subschema in the array.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "not",
+        summary: "The instance must NOT be valid against the given subschema.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "if",
+        summary: "If the instance is valid against this subschema, `then` applies; otherwise `else`.",
+        availability: "Draft 7+",
+    },
+    KeywordDoc {
+        keyword: "then",
+        summary: "Applied when the instance is valid against `if`.",
+        availability: "Draft 7+",
+    },
+    KeywordDoc {
+        keyword: "else",
+        summary: "Applied when the instance is invalid against `if`.",
+        availability: "Draft 7+",
+    },
+    KeywordDoc {
+        keyword: "properties",
+        summary: "Maps property names to the subschemas their values must satisfy.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "patternProperties",
+        summary: "Maps regular expressions to subschemas for every property whose name matches.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "additionalProperties",
+        summary: "Subschema for properties not matched by `properties` or `patternProperties`; `false` forbids them.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "propertyNames",
+        summary: "Subschema every property name (as a string) must satisfy.",
+        availability: "Draft 6+",
+    },
+    KeywordDoc {
+        keyword: "dependentSchemas",
+        summary: "When the named property is present, the whole object must also satisfy the given subschema.",
+        availability: "Draft 2019-09+ (split from `dependencies`)",
+    },
+    KeywordDoc {
+        keyword: "dependencies",
+        summary: "Property dependencies: a list of required names or a subschema applied when the property is present.",
+        availability: "Draft 4–7 (split into `dependentRequired` / `dependentSchemas`)",
+    },
+    KeywordDoc {
+        keyword: "items",
+        summary: "Subschema for array elements. In 2020-12 applies only after `prefixItems`; before that an array form means tuple validation.",
+        availability: "Draft 4+ (array form removed in 2020-12)",
+    },
+    KeywordDoc {
+        keyword: "prefixItems",
+        summary: "Array of subschemas validated positionally against the first elements (tuple validation).",
+        availability: "Draft 2020-12",
+    },
+    KeywordDoc {
+        keyword: "additionalItems",
+        summary: "Subschema for elements beyond a tuple-form `items` array.",
+        availability: "Draft 4–2019-09 (replaced by `items` in 2020-12)",
+    },
+    KeywordDoc {
+        keyword: "contains",
+        summary: "At least one array element must be valid against this subschema.",
+        availability: "Draft 6+",
+    },
+    KeywordDoc {
+        keyword: "unevaluatedProperties",
+        summary: "Subschema for properties not evaluated by any adjacent or nested applicator, including across `allOf`/`$ref`.",
+        availability: "Draft 2019-09+",
+    },
+    KeywordDoc {
+        keyword: "unevaluatedItems",
+        summary: "Subschema for array elements not evaluated by `prefixItems`, `items`, `contains` or nested applicators.",
+        availability: "Draft 2019-09+",
+    },
+    // Validation
+    KeywordDoc {
+        keyword: "type",
+        summary: "Restricts the instance to one (or any of several) primitive types.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "enum",
+        summary: "The instance must equal one of the listed values.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "const",
+        summary: "The instance must equal this exact value.",
+        availability: "Draft 6+",
+    },
+    KeywordDoc {
+        keyword: "multipleOf",
+        summary: "A number must be an exact multiple of this value.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "maximum",
+        summary: "A number must be less than or equal to this value.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "exclusiveMaximum",
+        summary: "A number must be strictly less than this value (a boolean modifier of `maximum` in Draft 4).",
+        availability: "Draft 4+ (numeric since Draft 6)",
+    },
+    KeywordDoc {
+        keyword: "minimum",
+        summary: "A number must be greater than or equal to this value.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "exclusiveMinimum",
+        summary: "A number must be strictly greater than this value (a boolean modifier of `minimum` in Draft 4).",
+        availability: "Draft 4+ (numeric since Draft 6)",
+    },
+    KeywordDoc {
+        keyword: "maxLength",
+        summary: "A string must have at most this many characters.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "minLength",
+        summary: "A string must have at least this many characters.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "pattern",
+        summary: "A string must match this ECMA-262 regular expression (unanchored).",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "maxItems",
+        summary: "An array must have at most this many elements.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "minItems",
+        summary: "An array must have at least this many elements.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "uniqueItems",
+        summary: "When `true`, all array elements must be distinct.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "maxContains",
+        summary: "At most this many elements may match `contains`.",
+        availability: "Draft 2019-09+",
+    },
+    KeywordDoc {
+        keyword: "minContains",
+        summary: "At least this many elements must match `contains` (0 disables `contains`).",
+        availability: "Draft 2019-09+",
+    },
+    KeywordDoc {
+        keyword: "maxProperties",
+        summary: "An object must have at most this many properties.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "minProperties",
+        summary: "An object must have at least this many properties.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "required",
+        summary: "Property names that must be present in the object.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "dependentRequired",
+        summary: "When the named property is present, the listed properties must be present too.",
+        availability: "Draft 2019-09+ (split from `dependencies`)",
+    },
+    // Format and content
+    KeywordDoc {
+        keyword: "format",
+        summary: "Semantic format of a string (e.g. `email`, `date-time`). An annotation by default in 2019-09+; validators may assert it.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "contentEncoding",
+        summary: "Encoding of binary data inside a string, e.g. `base64`.",
+        availability: "Draft 7+",
+    },
+    KeywordDoc {
+        keyword: "contentMediaType",
+        summary: "Media type of the string's content, e.g. `application/json`.",
+        availability: "Draft 7+",
+    },
+    KeywordDoc {
+        keyword: "contentSchema",
+        summary: "Schema for the decoded content described by `contentMediaType`.",
+        availability: "Draft 2019-09+",
+    },
+    // Meta-data
+    KeywordDoc {
+        keyword: "title",
+        summary: "Short human-readable name for the instance.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "description",
+        summary: "Human-readable explanation of the instance's purpose.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "default",
+        summary: "Default value, for documentation and tooling; not applied by validators.",
+        availability: "Draft 4+",
+    },
+    KeywordDoc {
+        keyword: "examples",
+        summary: "Array of example values that should validate against this schema.",
+        availability: "Draft 6+",
+    },
+    KeywordDoc {
+        keyword: "deprecated",
+        summary: "When `true`, the instance should no longer be used.",
+        availability: "Draft 2019-09+",
+    },
+    KeywordDoc {
+        keyword: "readOnly",
+        summary: "The value is managed by the owning authority; attempts to modify it may be ignored or rejected.",
+        availability: "Draft 7+",
+    },
+    KeywordDoc {
+        keyword: "writeOnly",
+        summary: "The value is never returned when reading from the owning authority (e.g. passwords).",
+        availability: "Draft 7+",
+    },
+];
+
+/// Built-in documentation for `keyword`, if it is a known JSON Schema keyword.
+pub fn keyword_doc(keyword: &str) -> Option<&'static KeywordDoc> {
+    KEYWORD_DOCS.iter().find(|doc| doc.keyword == keyword)
+}
+
+/// The json-schema.org "Understanding JSON Schema" page covering `keyword`.
+pub fn keyword_reference_url(keyword: &str) -> Option<String> {
+    let page = match keyword {
+        "type" => "reference/type",
+        "enum" => "reference/enum",
+        "const" => "reference/const",
+        "minLength" | "maxLength" | "pattern" | "format" => "reference/string",
+        "minimum" | "maximum" | "exclusiveMinimum" | "exclusiveMaximum" | "multipleOf" => {
+            "reference/numeric"
+        }
+        "properties"
+        | "patternProperties"
+        | "additionalProperties"
+        | "unevaluatedProperties"
+        | "required"
+        | "propertyNames"
+        | "minProperties"
+        | "maxProperties"
+        | "dependentRequired"
+        | "dependencies" => "reference/object",
+        "items" | "prefixItems" | "additionalItems" | "unevaluatedItems" | "contains"
+        | "minContains" | "maxContains" | "minItems" | "maxItems" | "uniqueItems" => {
+            "reference/array"
+        }
+        "allOf" | "anyOf" | "oneOf" | "not" => "reference/combining",
+        "if" | "then" | "else" | "dependentSchemas" => "reference/conditionals",
+        "contentEncoding" | "contentMediaType" | "contentSchema" => "reference/non_json_data",
+        "$ref" | "$dynamicRef" => "structuring",
+        _ => return None,
+    };
+    Some(format!(
+        "https://json-schema.org/understanding-json-schema/{page}"
+    ))
+}
@@ -0,0 +1,183 @@
+//! Schema-authoring support: features that apply when the open document is itself a
+//! JSON Schema (its `$schema` points at a JSON Schema meta-schema).
+
+pub mod keywords;
+pub mod refactor;
+pub mod refs;
+pub mod snippets;
+
+use crate::position::PathSegment;
+use serde_json::Value;
+
+/// Well-known meta-schema URLs, newest draft first.
+pub const META_SCHEMAS: &[(&str, &str)] = &[
+    (
+        "https://json-schema.org/draft/2020-12/schema",
+        "Draft 2020-12",
+    ),
+    (
+        "https://json-schema.org/draft/2019-09/schema",
+        "Draft 2019-09",
+    ),
+    ("http://json-schema.org/draft-07/schema#", "Draft 7"),
+    ("http://json-schema.org/draft-06/schema#", "Draft 6"),
+    ("http://json-schema.org/draft-04/schema#", "Draft 4"),
+];
+
+/// Keywords whose value is a map of name → subschema. A key directly beneath one of these
+/// is a user-chosen name, not a keyword.
+pub(crate) const SCHEMA_MAP_KEYWORDS: &[&str] = &[
+    "properties",
+    "patternProperties",
+    "$defs",
+    "definitions",
+    "dependentSchemas",
+];
+
+/// True if `schema_url` names a JSON Schema meta-schema, i.e. the document is a schema.
+pub fn is_meta_schema_url(schema_url: &str) -> bool {
+    let url = schema_url
+        .trim_end_matches('#')
+        .trim_start_matches("https://")
+        .trim_start_matches("http://");
+    url.starts_with("json-schema.org/") && url.ends_with("/schema")
+}
+
+/// If `path` ends at a keyword (rather than a user-chosen property/definition name, or a
+/// key inside instance data such as a `default` value), return that keyword. Array elements
+/// of a keyword value (e.g. `"type": ["string"]`) report the enclosing keyword.
+pub fn keyword_at(path: &[PathSegment]) -> Option<&str> {
+    let path = match path.last()? {
+        PathSegment::Index(_) => &path[..path.len() - 1],
+        PathSegment::Key(_) => path,
+    };
+    let (PathSegment::Key(keyword), ancestors) = path.split_last()? else {
+        return None;
+    };
+    let mut in_name_map = false;
+    for segment in ancestors {
+        in_name_map = match segment {
+            PathSegment::Key(key) if refs::is_data_member(key, in_name_map) => return None,
+            PathSegment::Key(key) => refs::holds_name_map(key, in_name_map),
+            // Elements of `allOf`, `prefixItems`, ... are schemas
+            PathSegment::Index(_) => false,
+        };
+    }
+    (!in_name_map).then_some(keyword.as_str())
+}
+
+/// Top-level keys that only make sense in a schema. Annotations such as `title` or
+/// `description` are left out — plenty of ordinary documents have those.
+const SCHEMA_SIGNATURE_KEYWORDS: &[&str] = &[
+    "$schema",
+    "$id",
+    "$ref",
+    "$defs",
+    "definitions",
+    "properties",
+    "patternProperties",
+    "additionalProperties",
+    "required",
+    "items",
+    "prefixItems",
+    "enum",
+    "const",
+    "allOf",
+    "anyOf",
+    "oneOf",
+    "not",
+    "if",
+];
+
+/// Heuristic: does a fetched `$schema` target look like a JSON Schema at all?
+/// Boolean schemas and `{}` count; so does any object with a schema-only keyword or a
+/// `type` naming a JSON Schema type.
+pub fn looks_like_schema(value: &Value) -> bool {
+    let map = match value {
+        Value::Bool(_) => return true,
+        Value::Object(map) => map,
+        _ => return false,
+    };
+    if map.is_empty()
+        || SCHEMA_SIGNATURE_KEYWORDS
+            .iter()
+            .any(|k| map.contains_key(*k))
+    {
+        return true;
+    }
+    let is_type_name = |v: &Value| v.as_str().is_some_and(|t| keywords::TYPES.contains(&t));
+    match map.get("type") {
+        Some(Value::Array(types)) => !types.is_empty() && types.iter().all(is_type_name),
+        Some(ty) => is_type_name(ty),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(k: &str) -> PathSegment {
+        PathSegment::Key(k.into())
+    }
+
+    #[test]
+    fn test_is_meta_schema_url() {
+        assert!(is_meta_schema_url(
+            "http://json-schema.org/draft-07/schema#"
+        ));
+        assert!(is_meta_schema_url(
+            "https://json-schema.org/draft/2020-12/schema"
+        ));
+        assert!(!is_meta_schema_url("https://example.com/schema.json"));
+    }
+
+    #[test]
+    fn test_keyword_at() {
+        assert_eq!(keyword_at(&[key("type")]), Some("type"));
+        assert_eq!(
+            keyword_at(&[key("properties"), key("a"), key("type")]),
+            Some("type")
+        );
+        assert_eq!(
+            keyword_at(&[key("type"), PathSegment::Index(1)]),
+            Some("type")
+        );
+        // A property that happens to be named "type" is not the keyword
+        assert_eq!(keyword_at(&[key("properties"), key("type")]), None);
+        // Nor is a key inside instance data
+        assert_eq!(keyword_at(&[key("default"), key("type")]), None);
+        assert_eq!(
+            keyword_at(&[key("examples"), PathSegment::Index(0), key("format")]),
+            None
+        );
+        // ... unless the data keyword is itself a property name
+        assert_eq!(
+            keyword_at(&[key("properties"), key("default"), key("type")]),
+            Some("type")
+        );
+    }
+
+    #[test]
+    fn test_no_keyword_inside_default_value() {
+        let text = r#"{"default": {"type": ""}}"#;
+        let context = crate::position::position_to_context(text, 0, 22);
+        assert_eq!(context.path(), &[key("default"), key("type")]);
+        assert_eq!(keyword_at(context.path()), None);
+    }
+
+    #[test]
+    fn test_looks_like_schema() {
+        use serde_json::json;
+
+        assert!(looks_like_schema(&json!({ "type": "object" })));
+        assert!(looks_like_schema(&json!({ "properties": {} })));
+        assert!(looks_like_schema(&json!({})));
+        assert!(looks_like_schema(&json!(true)));
+        // package.json-like documents
+        assert!(!looks_like_schema(
+            &json!({ "name": "x", "description": "y", "type": "module" })
+        ));
+        assert!(!looks_like_schema(&json!([1, 2])));
+    }
+}
@@ -0,0 +1,339 @@
+//! Text-level schema refactorings. Each function returns byte edits against the
+//! original document text, or `None` when the refactoring does not apply.
+
+use super::refs::{holds_name_map, is_data_member, ref_sites};
+use super::META_SCHEMAS;
+use crate::formatting::json_string;
+use crate::span::{escape_pointer_token, ByteEdit, SpanKind, SpanMember, SpanNode};
+
+/// The definitions container conversion that fits the document's draft, as `(from, to,
+/// edits)`:
+///
+/// - `definitions` → `$defs`, moving a `$schema` naming draft-07 or earlier to Draft
+///   2019-09, the first draft with `$defs`;
+/// - `$defs` → `definitions` when `$schema` names draft-07 or earlier, which do not know
+///   `$defs`.
+///
+/// Only the root container is converted; containers nested in subschemas are left as
+/// they are.
+pub fn definitions_container_conversion(
+    spans: &SpanNode,
+) -> Option<(&'static str, &'static str, Vec<ByteEdit>)> {
+    let legacy = legacy_draft(spans);
+    if let Some(mut edits) = rename_definitions_container(spans, "definitions", "$defs") {
+        if let Some(dialect) = legacy {
+            edits.push(ByteEdit {
+                start: dialect.value.start,
+                end: dialect.value.end,
+                new_text: json_string(META_SCHEMAS[1].0),
+            });
+        }
+        return Some(("definitions", "$defs", edits));
+    }
+    legacy?;
+    let edits = rename_definitions_container(spans, "$defs", "definitions")?;
+    Some(("$defs", "definitions", edits))
+}
+
+/// The root `$schema` member, if it names draft-07 or an earlier draft.
+fn legacy_draft(spans: &SpanNode) -> Option<&SpanMember> {
+    let dialect = spans.member("$schema")?;
+    let SpanKind::String(url) = &dialect.value.kind else {
+        return None;
+    };
+    let url = url.trim_end_matches('#');
+    META_SCHEMAS[2..]
+        .iter()
+        .any(|(legacy, _)| legacy.trim_end_matches('#') == url)
+        .then_some(dialect)
+}
+
+/// Rename the root `from` definitions container (`definitions` or `$defs`) to `to`,
+/// rewriting every `$ref` that points into it.
+fn rename_definitions_container(spans: &SpanNode, from: &str, to: &str) -> Option<Vec<ByteEdit>> {
+    let container = spans.member(from)?;
+    if spans.member(to).is_some() {
+        // Both containers exist — merging them is out of scope for a rename.
+        return None;
+    }
+
+    let mut edits = vec![ByteEdit {
+        start: container.key_start,
+        end: container.key_end,
+        new_text: json_string(to),
+    }];
+
+    let old_prefix = format!("#/{from}");
+    for site in ref_sites(spans) {
+        let Some(rest) = site.reference.strip_prefix(&old_prefix) else {
+            continue;
+        };
+        if !rest.is_empty() && !rest.starts_with('/') {
+            continue;
+        }
+        edits.push(ByteEdit {
+            start: site.start,
+            end: site.end,
+            new_text: json_string(&format!("#/{to}{rest}")),
+        });
+    }
+
+    Some(edits)
+}
+
+/// Convert every draft-07 tuple-form `"items": [...]` into `"prefixItems": [...]`, turning
+/// a sibling `additionalItems` into `items` (its 2020-12 meaning). `$ref`s that point into
+/// converted locations are rewritten too. Earlier drafts ignore `prefixItems`, so a
+/// `$schema` naming one is moved to Draft 2020-12 in the same edit.
+pub fn tuple_items_to_prefix_items(spans: &SpanNode) -> Option<Vec<ByteEdit>> {
+    let mut edits = Vec::new();
+    // (old pointer prefix, new pointer prefix) for $ref rewriting
+    let mut moved: Vec<(String, String)> = Vec::new();
+    let (root, new_root) = (String::new(), String::new());
+    collect_tuple_items(spans, root, new_root, false, &mut edits, &mut moved);
+
+    if edits.is_empty() {
+        return None;
+    }
+
+    let draft_2020_12 = META_SCHEMAS[0].0;
+    if let Some(dialect) = spans.member("$schema") {
+        if !matches!(&dialect.value.kind, SpanKind::String(url) if url.trim_end_matches('#') == draft_2020_12)
+        {
+            edits.push(ByteEdit {
+                start: dialect.value.start,
+                end: dialect.value.end,
+                new_text: json_string(draft_2020_12),
+            });
+        }
+    }
+
+    for site in ref_sites(spans) {
+        let Some(pointer) = site.reference.strip_prefix('#') else {
+            continue;
+        };
+        // The innermost moved location holding the target; its new pointer accounts for
+        // the moves around it.
+        let target = moved
+            .iter()
+            .filter_map(|(old, new)| {
+                let rest = pointer.strip_prefix(old.as_str())?;
+                (rest.is_empty() || rest.starts_with('/')).then_some((old.len(), new, rest))
+            })
+            .max_by_key(|(len, ..)| *len);
+        if let Some((_, new, rest)) = target {
+            edits.push(ByteEdit {
+                start: site.start,
+                end: site.end,
+                new_text: json_string(&format!("#{new}{rest}")),
+            });
+        }
+    }
+
+    Some(edits)
+}
+
+/// Convert the tuples in `node`, found at `pointer` before the conversion and at
+/// `new_pointer` after it. `in_name_map` for the value of `properties`, `$defs`, ...
+fn collect_tuple_items(
+    node: &SpanNode,
+    pointer: String,
+    new_pointer: String,
+    in_name_map: bool,
+    edits: &mut Vec<ByteEdit>,
+    moved: &mut Vec<(String, String)>,
+) {
+    match &node.kind {
+        SpanKind::Object(members) if in_name_map => {
+            for member in members {
+                let token = escape_pointer_token(&member.key);
+                let child = format!("{pointer}/{token}");
+                let new_child = format!("{new_pointer}/{token}");
+                collect_tuple_items(&member.value, child, new_child, false, edits, moved);
+            }
+        }
+        SpanKind::Object(members) => {
+            let tuple = node
+                .member("items")
+                .filter(|items| matches!(items.value.kind, SpanKind::Array(_)));
+            let converted = tuple.is_some() && node.member("prefixItems").is_none();
+            if let Some(items) = tuple.filter(|_| converted) {
+                edits.push(ByteEdit {
+                    start: items.key_start,
+                    end: items.key_end,
+                    new_text: json_string("prefixItems"),
+                });
+                moved.push((
+                    format!("{pointer}/items"),
+                    format!("{new_pointer}/prefixItems"),
+                ));
+
+                if let Some(additional) = node.member("additionalItems") {
+                    edits.push(ByteEdit {
+                        start: additional.key_start,
+                        end: additional.key_end,
+                        new_text: json_string("items"),
+                    });
+                    moved.push((
+                        format!("{pointer}/additionalItems"),
+                        format!("{new_pointer}/items"),
+                    ));
+                }
+            }
+
+            for member in members {
+                if is_data_member(&member.key, false) {
+                    continue;
+                }
+                let new_key = match member.key.as_str() {
+                    "items" if converted => "prefixItems",
+                    "additionalItems" if converted => "items",
+                    key => key,
+                };
+                let child = format!("{pointer}/{}", escape_pointer_token(&member.key));
+                let new_child = format!("{new_pointer}/{}", escape_pointer_token(new_key));
+                let name_map = holds_name_map(&member.key, false);
+                collect_tuple_items(&member.value, child, new_child, name_map, edits, moved);
+            }
+        }
+        SpanKind::Array(items) => {
+            for (idx, item) in items.iter().enumerate() {
+                collect_tuple_items(
+                    item,
+                    format!("{pointer}/{idx}"),
+                    format!("{new_pointer}/{idx}"),
+                    false,
+                    edits,
+                    moved,
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::parse_spans;
+
+    fn apply(text: &str, mut edits: Vec<ByteEdit>) -> String {
+        edits.sort_by_key(|e| std::cmp::Reverse(e.start));
+        let mut out = text.to_owned();
+        for edit in edits {
+            out.replace_range(edit.start..edit.end, &edit.new_text);
+        }
+        out
+    }
+
+    #[test]
+    fn test_definitions_to_defs() {
+        let text = r##"{
+  "definitions": { "A": { "type": "string" } },
+  "properties": {
+    "a": { "$ref": "#/definitions/A" },
+    "b": { "$ref": "#/definitionsX" }
+  }
+}"##;
+        let spans = parse_spans(text).unwrap();
+        let edits = rename_definitions_container(&spans, "definitions", "$defs").unwrap();
+        let out = apply(text, edits);
+        assert!(out.contains(r#""$defs": {"#));
+        assert!(out.contains(r##""$ref": "#/$defs/A""##));
+        // Not a pointer into the container — untouched
+        assert!(out.contains(r##""$ref": "#/definitionsX""##));
+    }
+
+    #[test]
+    fn test_tuple_items_to_prefix_items() {
+        let text = r##"{
+  "properties": {
+    "point": {
+      "type": "array",
+      "items": [{ "type": "number" }, { "type": "number" }],
+      "additionalItems": false
+    },
+    "x": { "$ref": "#/properties/point/items/0" },
+    "list": { "items": { "type": "string" } }
+  }
+}"##;
+        let spans = parse_spans(text).unwrap();
+        let out = apply(text, tuple_items_to_prefix_items(&spans).unwrap());
+        assert!(out.contains(r#""prefixItems": [{ "type": "number" }"#));
+        assert!(out.contains(r#""items": false"#));
+        assert!(out.contains(r##""$ref": "#/properties/point/prefixItems/0""##));
+        // Schema-form items is left alone
+        assert!(out.contains(r#""list": { "items": { "type": "string" } }"#));
+    }
+
+    #[test]
+    fn test_nested_tuples_and_dialect() {
+        let text = r##"{
+  "$schema": "http://json-schema.org/draft-07/schema#",
+  "items": [
+    { "items": [{ "type": "string" }], "additionalItems": { "type": "number" } }
+  ],
+  "properties": {
+    "inner": { "$ref": "#/items/0/items/0" },
+    "rest": { "$ref": "#/items/0/additionalItems" },
+    "outer": { "$ref": "#/items/0" }
+  }
+}"##;
+        let spans = parse_spans(text).unwrap();
+        let out = apply(text, tuple_items_to_prefix_items(&spans).unwrap());
+        assert!(out.contains(r#""$schema": "https://json-schema.org/draft/2020-12/schema""#));
+        assert!(out.contains(r#"{ "prefixItems": [{ "type": "string" }], "items": { "#));
+        assert!(out.contains(r##""$ref": "#/prefixItems/0/prefixItems/0""##));
+        assert!(out.contains(r##""$ref": "#/prefixItems/0/items""##));
+        assert!(out.contains(r##""$ref": "#/prefixItems/0""##));
+
+        // Already 2020-12: `$schema` is left as it is.
+        let text =
+            r#"{ "$schema": "https://json-schema.org/draft/2020-12/schema", "items": [{}] }"#;
+        let spans = parse_spans(text).unwrap();
+        assert_eq!(tuple_items_to_prefix_items(&spans).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_container_conversion_follows_the_draft() {
+        let convert = |text: &str| {
+            let spans = parse_spans(text).unwrap();
+            definitions_container_conversion(&spans).map(|(from, to, edits)| {
+                let out = apply(text, edits);
+                (from, to, out)
+            })
+        };
+
+        // 2020-12 (or no `$schema`): `definitions` → `$defs` only
+        let modern =
+            r#"{ "$schema": "https://json-schema.org/draft/2020-12/schema", "$defs": {} }"#;
+        assert_eq!(convert(modern), None);
+        let (from, to, out) = convert(r#"{ "definitions": {} }"#).unwrap();
+        assert_eq!((from, to), ("definitions", "$defs"));
+        assert_eq!(out, r#"{ "$defs": {} }"#);
+
+        // Draft-07 moving to `$defs` moves to 2019-09
+        let (_, _, out) = convert(
+            r#"{ "$schema": "http://json-schema.org/draft-07/schema#", "definitions": {} }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            out,
+            r#"{ "$schema": "https://json-schema.org/draft/2019-09/schema", "$defs": {} }"#
+        );
+
+        // Draft-07 with `$defs`: back to `definitions`, `$schema` kept
+        let (from, to, out) =
+            convert(r#"{ "$schema": "http://json-schema.org/draft-07/schema#", "$defs": {} }"#)
+                .unwrap();
+        assert_eq!((from, to), ("$defs", "definitions"));
+        assert!(out.contains("draft-07") && out.contains(r#""definitions": {}"#));
+    }
+
+    #[test]
+    fn test_rename_refused_when_target_exists() {
+        let text = r#"{ "definitions": {}, "$defs": {} }"#;
+        let spans = parse_spans(text).unwrap();
+        assert!(rename_definitions_container(&spans, "definitions", "$defs").is_none());
+    }
+}
@@ -0,0 +1,142 @@
+use super::SCHEMA_MAP_KEYWORDS;
+use crate::percent::percent_decode;
+use crate::span::{SpanKind, SpanNode};
+use serde_json::Value;
+
+/// Keywords whose values are instance data, not subschemas — a `"$ref"` inside them is
+/// just a string and must not be checked.
+pub(crate) const DATA_KEYWORDS: &[&str] = &["const", "default", "enum", "examples"];
+
+/// True if member `key` holds instance data. In a name → subschema map (`in_name_map`, the
+/// value of `properties`, `$defs`, ...) keys are names, so `"default"` is just a property.
+pub(crate) fn is_data_member(key: &str, in_name_map: bool) -> bool {
+    !in_name_map && DATA_KEYWORDS.contains(&key)
+}
+
+/// True if the value of member `key` is a name → subschema map.
+pub(crate) fn holds_name_map(key: &str, in_name_map: bool) -> bool {
+    !in_name_map && SCHEMA_MAP_KEYWORDS.contains(&key)
+}
+
+/// A `"$ref"` whose target could not be resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenRef {
+    /// Byte range of the `$ref` string value (including quotes).
+    pub start: usize,
+    pub end: usize,
+    pub reference: String,
+}
+
+/// A `"$ref"` string value in a schema document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefSite<'a> {
+    /// Byte range of the string value (including quotes).
+    pub start: usize,
+    pub end: usize,
+    pub reference: &'a str,
+}
+
+/// Collect every `"$ref"` string in a schema document, skipping instance-data keywords.
+pub fn ref_sites(spans: &SpanNode) -> Vec<RefSite<'_>> {
+    let mut sites = Vec::new();
+    collect_ref_sites(spans, false, &mut sites);
+    sites
+}
+
+fn collect_ref_sites<'a>(node: &'a SpanNode, in_name_map: bool, sites: &mut Vec<RefSite<'a>>) {
+    match &node.kind {
+        SpanKind::Object(members) => {
+            for member in members {
+                if is_data_member(&member.key, in_name_map) {
+                    continue;
+                }
+                if member.key == "$ref" && !in_name_map {
+                    if let SpanKind::String(reference) = &member.value.kind {
+                        sites.push(RefSite {
+                            start: member.value.start,
+                            end: member.value.end,
+                            reference,
+                        });
+                        continue;
+                    }
+                }
+                let name_map = holds_name_map(&member.key, in_name_map);
+                collect_ref_sites(&member.value, name_map, sites);
+            }
+        }
+        SpanKind::Array(items) => {
+            for item in items {
+                collect_ref_sites(item, false, sites);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Find document-local `$ref`s (`#/...` JSON Pointers) that do not resolve within `root`.
+pub fn find_broken_refs(spans: &SpanNode, root: &Value) -> Vec<BrokenRef> {
+    ref_sites(spans)
+        .into_iter()
+        .filter(|site| !resolves_locally(site.reference, root))
+        .map(|site| BrokenRef {
+            start: site.start,
+            end: site.end,
+            reference: site.reference.to_owned(),
+        })
+        .collect()
+}
+
+/// True unless `reference` is a local JSON Pointer that fails to resolve. External refs
+/// and anchors are not checked here.
+fn resolves_locally(reference: &str, root: &Value) -> bool {
+    let Some(pointer) = reference.strip_prefix('#') else {
+        return true;
+    };
+    if !pointer.is_empty() && !pointer.starts_with('/') {
+        // Plain-name fragment (anchor)
+        return true;
+    }
+    root.pointer(pointer).is_some() || root.pointer(&percent_decode(pointer)).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::parse_spans;
+
+    #[test]
+    fn test_refs_under_properties_named_like_data_keywords() {
+        let text = r##"{
+  "properties": {
+    "default": { "$ref": "#/$defs/A" },
+    "enum": { "properties": { "const": { "$ref": "#/$defs/B" } } }
+  },
+  "$defs": { "examples": { "$ref": "#/$defs/C" } },
+  "default": { "$ref": "#/not-a-ref" }
+}"##;
+        let spans = parse_spans(text).unwrap();
+        let refs: Vec<&str> = ref_sites(&spans)
+            .into_iter()
+            .map(|site| site.reference)
+            .collect();
+        assert_eq!(refs, ["#/$defs/A", "#/$defs/B", "#/$defs/C"]);
+    }
+
+    #[test]
+    fn test_find_broken_refs() {
+        let text = r##"{
+  "$defs": { "Name": { "type": "string" } },
+  "properties": {
+    "a": { "$ref": "#/$defs/Name" },
+    "b": { "$ref": "#/$defs/Nmae" },
+    "c": { "$ref": "other.json#/x" },
+    "d": { "const": { "$ref": "#/nope" } }
+  }
+}"##;
+        let root: Value = serde_json::from_str(text).unwrap();
+        let broken = find_broken_refs(&parse_spans(text).unwrap(), &root);
+        assert_eq!(broken.len(), 1, "{broken:?}");
+        assert_eq!(broken[0].reference, "#/$defs/Nmae");
+        assert_eq!(&text[broken[0].start..broken[0].end], "\"#/$defs/Nmae\"");
+    }
+}
@@ -0,0 +1,293 @@
+use crate::authoring::SCHEMA_MAP_KEYWORDS;
+use crate::formatting::{indent_unit, line_indent};
+use crate::position::PathSegment;
+use crate::span::{ByteEdit, SpanNode};
+
+/// Name given to the definition created by the "$ref to new $def" snippet.
+const NEW_DEF_NAME: &str = "NewDefinition";
+
+/// Where in a schema a snippet makes sense.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SnippetLevel {
+    /// A named entry inside `properties` / `$defs` etc.
+    Member,
+    /// Keywords of a schema object.
+    Schema,
+}
+
+/// A multi-line schema construct offered at key positions in schema documents.
+pub struct SchemaSnippet {
+    pub label: &'static str,
+    pub detail: &'static str,
+    /// LSP snippet body, without the leading `"` of the first key.
+    pub body: &'static str,
+    /// Whether accepting the snippet should also create the definition it references; see
+    /// [`new_def`].
+    pub creates_def: bool,
+    level: SnippetLevel,
+}
+
+const SNIPPETS: &[SchemaSnippet] = &[
+    SchemaSnippet {
+        label: "string enum property",
+        detail: "Property restricted to a fixed set of strings",
+        body: "${1:name}\": {\n\t\"type\": \"string\",\n\t\"enum\": [\"${2:a}\", \"${3:b}\"]\n}",
+        creates_def: false,
+        level: SnippetLevel::Member,
+    },
+    SchemaSnippet {
+        label: "array of objects",
+        detail: "Array property whose items are objects",
+        body: "${1:items}\": {\n\t\"type\": \"array\",\n\t\"items\": {\n\t\t\"type\": \"object\",\n\t\t\"properties\": {\n\t\t\t$0\n\t\t},\n\t\t\"required\": []\n\t}\n}",
+        creates_def: false,
+        level: SnippetLevel::Member,
+    },
+    SchemaSnippet {
+        label: "oneOf discriminated union",
+        detail: "Variants selected by a constant discriminator property",
+        body: "oneOf\": [\n\t{\n\t\t\"properties\": { \"${1:kind}\": { \"const\": \"${2:a}\" } },\n\t\t\"required\": [\"${1:kind}\"]\n\t},\n\t{\n\t\t\"properties\": { \"${1:kind}\": { \"const\": \"${3:b}\" } },\n\t\t\"required\": [\"${1:kind}\"]\n\t}\n]",
+        creates_def: false,
+        level: SnippetLevel::Schema,
+    },
+    SchemaSnippet {
+        label: "$ref to new $def",
+        detail: "Reference a new entry created under $defs",
+        // The definition comes from an additional edit, which a tabstop cannot rename along
+        // with the reference: the name is fixed to `NEW_DEF_NAME`. [`new_def`] points it at
+        // `definitions` instead when the document uses that.
+        body: "\\$ref\": \"#/\\$defs/NewDefinition\"",
+        creates_def: true,
+        level: SnippetLevel::Schema,
+    },
+];
+
+/// Containers of reusable definitions, in the order a new definition looks for them.
+const DEFINITION_CONTAINERS: &[&str] = &["$defs", "definitions"];
+
+/// Snippets applicable when completing a key inside the object at `parent_path`.
+pub fn snippets_for(parent_path: &[PathSegment]) -> impl Iterator<Item = &'static SchemaSnippet> {
+    let level = match parent_path.last() {
+        Some(PathSegment::Key(k)) if SCHEMA_MAP_KEYWORDS.contains(&k.as_str()) => {
+            SnippetLevel::Member
+        }
+        _ => SnippetLevel::Schema,
+    };
+    SNIPPETS.iter().filter(move |s| s.level == level)
+}
+
+/// The "$ref to new $def" snippet fitted to a document: its body, referencing the root's
+/// definitions container, and the edit creating the definition (`None` if it exists).
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewDef {
+    pub body: String,
+    pub edit: Option<ByteEdit>,
+}
+
+/// Fit the "$ref to new $def" snippet to the root schema `spans` of `text`, with the cursor
+/// at byte `cursor`. The root's `$defs` or (draft-07 and earlier) `definitions` is reused; a
+/// new `$defs` goes next to the root member being edited, never inside or after the key being
+/// typed. Inserted lines follow the document's indentation and line endings.
+pub fn new_def(text: &str, spans: &SpanNode, cursor: usize) -> NewDef {
+    let entry = format!("\"{NEW_DEF_NAME}\": {{}}");
+    let existing = DEFINITION_CONTAINERS
+        .iter()
+        .find_map(|&name| spans.member(name).map(|member| (name, member)));
+    let container = existing.map_or("$defs", |(name, _)| name);
+    let body = format!(
+        "\\$ref\": \"#/{}/{NEW_DEF_NAME}\"",
+        container.replace('$', "\\$")
+    );
+
+    let end_of_line = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    let unit = indent_unit(text);
+    let member_indent = format!("{}{unit}", line_indent(text, spans.start));
+
+    if let Some((_, defs)) = existing {
+        if defs.value.member(NEW_DEF_NAME).is_some() {
+            return NewDef { body, edit: None };
+        }
+        // Insert right after the opening '{' of the container object
+        let container_indent = line_indent(text, defs.key_start);
+        let new_text = match defs.value.members().is_empty() {
+            true => format!(
+                "{end_of_line}{container_indent}{unit}{entry}{end_of_line}{container_indent}"
+            ),
+            false => format!("{end_of_line}{container_indent}{unit}{entry},"),
+        };
+        let edit = ByteEdit {
+            start: defs.value.start + 1,
+            end: defs.value.start + 1,
+            new_text,
+        };
+        return NewDef {
+            body,
+            edit: Some(edit),
+        };
+    }
+
+    let defs = format!(
+        "\"$defs\": {{{end_of_line}{member_indent}{unit}{entry}{end_of_line}{member_indent}}}"
+    );
+    let members = spans.members();
+    let before = members.iter().rev().find(|m| m.value.end < cursor);
+    let after = members.iter().find(|m| m.key_start > cursor);
+    let around = members
+        .iter()
+        .find(|m| m.key_start <= cursor && cursor <= m.value.end);
+    let edit = if let Some(member) = before {
+        // After the member preceding the one being edited
+        ByteEdit {
+            start: member.value.end,
+            end: member.value.end,
+            new_text: format!(",{end_of_line}{member_indent}{defs}"),
+        }
+    } else if let Some(member) = after {
+        // Before the member following the cursor
+        ByteEdit {
+            start: member.key_start,
+            end: member.key_start,
+            new_text: format!("{defs},{end_of_line}{member_indent}"),
+        }
+    } else if let Some(member) = around {
+        // After the only member, which holds the cursor
+        ByteEdit {
+            start: member.value.end,
+            end: member.value.end,
+            new_text: format!(",{end_of_line}{member_indent}{defs}"),
+        }
+    } else {
+        // An empty root: the snippet lands after the new member
+        ByteEdit {
+            start: spans.start + 1,
+            end: spans.start + 1,
+            new_text: format!("{end_of_line}{member_indent}{defs},"),
+        }
+    };
+    NewDef {
+        body,
+        edit: Some(edit),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::parse_spans;
+
+    #[test]
+    fn test_snippet_levels() {
+        let member: Vec<_> = snippets_for(&[PathSegment::Key("properties".into())])
+            .map(|s| s.label)
+            .collect();
+        assert!(member.contains(&"string enum property"));
+        assert!(!member.contains(&"oneOf discriminated union"));
+        assert!(snippets_for(&[PathSegment::Key("dependentSchemas".into())])
+            .all(|s| s.level == SnippetLevel::Member));
+
+        let schema: Vec<_> = snippets_for(&[]).map(|s| s.label).collect();
+        assert!(schema.contains(&"$ref to new $def"));
+    }
+
+    #[test]
+    fn test_new_def_snippet_references_the_created_name() {
+        let snippet = SNIPPETS.iter().find(|s| s.creates_def).unwrap();
+        assert!(snippet.body.ends_with(&format!("/{NEW_DEF_NAME}\"")));
+        assert!(!snippet.body.contains("${"));
+    }
+
+    /// Apply the snippet body at `cursor` (given as `|` in `text`) and the definition edit.
+    fn accept_new_def(text: &str) -> serde_json::Value {
+        let cursor = text.find('|').unwrap();
+        let text = text.replacen('|', "", 1);
+        let NewDef { body, edit } = new_def(&text, &parse_spans(&text).unwrap(), cursor);
+        let edit = edit.unwrap();
+        assert!(edit.start != cursor, "edit collides with the cursor");
+        let reference = format!("\"{}", body.replace("\\$", "$"));
+        let mut out = text.clone();
+        if edit.start > cursor {
+            out.replace_range(edit.start..edit.end, &edit.new_text);
+            out.insert_str(cursor, &reference);
+        } else {
+            out.insert_str(cursor, &reference);
+            out.replace_range(edit.start..edit.end, &edit.new_text);
+        }
+        serde_json::from_str(&out).unwrap_or_else(|e| panic!("{e}: {out}"))
+    }
+
+    #[test]
+    fn test_new_def_creates_container() {
+        let parsed = accept_new_def("{\n  \"type\": \"object\",\n  |\n}");
+        assert!(parsed.pointer("/$defs/NewDefinition").is_some());
+        assert_eq!(parsed["$ref"], "#/$defs/NewDefinition");
+    }
+
+    #[test]
+    fn test_new_def_in_empty_root() {
+        let parsed = accept_new_def("{\n  |\n}");
+        assert!(parsed.pointer("/$defs/NewDefinition").is_some());
+        assert_eq!(parsed["$ref"], "#/$defs/NewDefinition");
+    }
+
+    #[test]
+    fn test_new_def_goes_before_the_member_after_the_cursor() {
+        let parsed = accept_new_def("{\n  |,\n  \"type\": \"object\"\n}");
+        assert!(parsed.pointer("/$defs/NewDefinition").is_some());
+        assert_eq!(parsed["$ref"], "#/$defs/NewDefinition");
+    }
+
+    #[test]
+    fn test_new_def_skips_the_key_being_typed() {
+        let text = "{\n  \"type\": \"object\",\n  \"$r\"\n}";
+        let cursor = text.find("$r").unwrap() + 2;
+        let edit = new_def(text, &parse_spans(text).unwrap(), cursor)
+            .edit
+            .unwrap();
+        // After `"object"`, not after the `"$r"` member being typed
+        assert_eq!(edit.start, text.find(',').unwrap());
+    }
+
+    #[test]
+    fn test_new_def_follows_the_document_indentation() {
+        let text = "{\n\t\"type\": \"object\",\n\t\n}";
+        let cursor = text.rfind('\t').unwrap() + 1;
+        let edit = new_def(text, &parse_spans(text).unwrap(), cursor)
+            .edit
+            .unwrap();
+        assert_eq!(
+            edit.new_text,
+            ",\n\t\"$defs\": {\n\t\t\"NewDefinition\": {}\n\t}"
+        );
+
+        let text = "{\n    \"$defs\": {\n        \"A\": {}\n    },\n    \n}";
+        let cursor = text.len() - 2;
+        let edit = new_def(text, &parse_spans(text).unwrap(), cursor)
+            .edit
+            .unwrap();
+        assert_eq!(edit.new_text, "\n        \"NewDefinition\": {},");
+    }
+
+    #[test]
+    fn test_new_def_in_nested_schema() {
+        let parsed = accept_new_def("{\n  \"properties\": { \"a\": { | } }\n}");
+        assert!(parsed.pointer("/$defs/NewDefinition").is_some());
+        assert_eq!(
+            parsed.pointer("/properties/a/$ref").unwrap(),
+            "#/$defs/NewDefinition"
+        );
+    }
+
+    #[test]
+    fn test_new_def_appends_to_existing_defs() {
+        let parsed = accept_new_def("{\n  \"$defs\": { \"A\": {} },\n  |\n}");
+        assert!(parsed.pointer("/$defs/NewDefinition").is_some());
+        assert!(parsed.pointer("/$defs/A").is_some());
+    }
+
+    #[test]
+    fn test_new_def_uses_existing_definitions() {
+        let parsed = accept_new_def("{\n  \"definitions\": {},\n  |\n}");
+        assert!(parsed.get("$defs").is_none());
+        assert!(parsed.pointer("/definitions/NewDefinition").is_some());
+        assert_eq!(parsed["$ref"], "#/definitions/NewDefinition");
+    }
+}
@@ -1,79 +1,923 @@
+use crate::add_schema::{schema_insertion, ADD_SCHEMA_COMMAND};
+use crate::code_action::{handle_code_action, workspace_edit};
+use crate::code_lens::{handle_code_lens, subtree_title, VALIDATE_SUBTREE_COMMAND};
 use crate::completion::handle_completion;
-use crate::config::ServerConfig;
-use crate::diagnostics::validate_document;
-use crate::document::DocumentStore;
+use crate::config::{ServerConfig, SyncPreference, ValidateOn};
+use crate::definition::handle_goto_definition;
+use crate::diagnostics::{subtree_error_count, validate_document};
+use crate::document::{negotiate_sync_kind, DocumentStore};
+use crate::formatting::handle_formatting;
+use crate::goto_property::{goto_property, GOTO_PROPERTY_COMMAND};
 use crate::hover::handle_hover;
-use crate::schema::SchemaCache;
+use crate::json_patch::{apply_patch, APPLY_PATCH_COMMAND};
+use crate::metrics::{Feature, METRICS};
+use crate::notebook::{
+    is_json_cell, notebook_sync_options, DidChangeNotebookDocumentParams,
+    DidCloseNotebookDocumentParams, DidOpenNotebookDocumentParams, NOTEBOOK_SYNC_METHOD,
+};
+use crate::notifications::{
+    resolve_schema_status, MetricsNotification, MetricsParams, SchemaStatus, SchemaStatusParams,
+    WorkspaceDiagnosticProgress, WorkspaceDiagnosticProgressParams,
+};
+use crate::offsets::lsp_position_to_byte_offset;
+use crate::on_type::{handle_on_type_formatting, ON_TYPE_TRIGGER};
+use crate::paste::{normalize_pasted, NORMALIZE_PASTED_COMMAND};
+use crate::references::handle_references;
+use crate::resolver::{inline_schema_url, resolve_schema, ResolvedSchema};
+use crate::schema::catalog::{entry_for, file_matches, match_path, REFRESH_CATALOG_COMMAND};
+use crate::schema::loader::NativeLoader;
+use crate::schema::refs::{unresolved_refs, BUNDLE_SCHEMA_COMMAND};
+use crate::schema::{Catalog, SchemaCache};
+use crate::span::parse_spans;
+use crate::symbols::handle_document_symbol;
+use crate::tolerant::Dialect;
+use crate::usage::UsageLog;
+use crate::workspace::{workspace_roots, Workspace};
+use crate::workspace_symbol::{handle_workspace_symbol, SymbolIndex};
+use crate::workspace_validation::{
+    document_report, workspace_report, ClosedFiles, VALIDATE_WORKSPACE_COMMAND,
+};
 use dashmap::DashMap;
-use std::sync::Arc;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 use tokio::task::JoinHandle;
 use tower_lsp::jsonrpc::Result;
 use tower_lsp::lsp_types::*;
-use tower_lsp::{Client, LanguageServer};
-use tracing::{debug, info};
+use tower_lsp::{Client, ClientSocket, LanguageServer, LspService};
+use tracing::{debug, info, warn};
 
-const DEBOUNCE_MS: u64 = 300;
+/// How often the usage log is written while the server runs (it is also written on shutdown).
+const USAGE_SAVE_SECS: u64 = 300;
 
 pub struct Backend {
     client: Client,
     documents: Arc<DocumentStore>,
     schema_cache: Arc<SchemaCache>,
+    /// The cache's loader, kept to apply fetch settings once they arrive.
+    loader: Arc<NativeLoader>,
+    /// Settings from `initializationOptions`.
+    config: Arc<RwLock<Arc<ServerConfig>>>,
     pending_diagnostics: Arc<DashMap<Url, JoinHandle<()>>>,
+    /// Last `json-ls/schemaStatus` sent per document, to only notify on change.
+    schema_status: Arc<DashMap<Url, SchemaStatusParams>>,
+    /// Last diagnostics published per document, to skip re-publishing an identical set.
+    published: Arc<DashMap<Url, Vec<Diagnostic>>>,
+    /// `--cache-dir` from the command line; overrides `cache_dir` in the settings.
+    cache_dir_override: Option<PathBuf>,
+    workspace: Arc<Workspace>,
+    symbol_index: Arc<SymbolIndex>,
+    catalog: Arc<Catalog>,
+    /// The client answers `workspace/configuration` requests.
+    pull_configuration: AtomicBool,
+    /// languageId each open document was opened with, to re-apply `language_schemas`.
+    language_ids: DashMap<Url, String>,
+    /// Set when `usage_metrics` is enabled and a cache dir exists.
+    usage: RwLock<Option<Arc<UsageLog>>>,
+    /// Closed files the last publishing `json-ls.validateWorkspace` run published
+    /// diagnostics for, cleared when they drop out of the next run.
+    workspace_published: Mutex<HashSet<Url>>,
 }
 
 impl Backend {
     pub fn new(client: Client) -> Self {
         let config = ServerConfig::default();
-        let schema_cache = Arc::new(SchemaCache::new(&config));
+        let loader = Arc::new(NativeLoader::default());
+        let schema_cache = Arc::new(SchemaCache::with_loader(&config, loader.clone()));
 
         Self {
             client,
             documents: Arc::new(DocumentStore::new()),
             schema_cache,
+            loader: loader.clone(),
+            config: Arc::new(RwLock::new(Arc::new(config))),
             pending_diagnostics: Arc::new(DashMap::new()),
+            schema_status: Arc::new(DashMap::new()),
+            published: Arc::new(DashMap::new()),
+            cache_dir_override: None,
+            workspace: Arc::new(Workspace::new()),
+            symbol_index: Arc::new(SymbolIndex::new()),
+            catalog: Arc::new(Catalog::new(loader.clone())),
+            pull_configuration: AtomicBool::new(false),
+            language_ids: DashMap::new(),
+            usage: RwLock::new(None),
+            workspace_published: Mutex::new(HashSet::new()),
         }
     }
 
+    /// Use `dir` as the cache directory regardless of `initializationOptions`.
+    pub fn with_cache_dir(mut self, dir: PathBuf) -> Self {
+        self.cache_dir_override = Some(dir);
+        self
+    }
+
+    /// The LSP service with json-ls' custom methods registered, ready for a
+    /// [`tower_lsp::Server`] on any transport.
+    pub fn service(cache_dir: Option<PathBuf>) -> (LspService<Self>, ClientSocket) {
+        LspService::build(move |client| {
+            let backend = Backend::new(client);
+            match cache_dir {
+                Some(dir) => backend.with_cache_dir(dir),
+                None => backend,
+            }
+        })
+        .custom_method("json-ls/status", Backend::status)
+        .custom_method("notebookDocument/didOpen", Backend::did_open_notebook)
+        .custom_method("notebookDocument/didChange", Backend::did_change_notebook)
+        .custom_method("notebookDocument/didClose", Backend::did_close_notebook)
+        .finish()
+    }
+
+    /// Snapshot of the current server settings.
+    fn config(&self) -> Arc<ServerConfig> {
+        self.config.read().expect("config lock poisoned").clone()
+    }
+
+    /// Make `config` the settings in effect, for everything that can follow them at runtime.
+    fn apply_config(&self, mut config: ServerConfig) {
+        if let Some(dir) = &self.cache_dir_override {
+            config.cache_dir = Some(dir.clone());
+        }
+        self.documents
+            .set_schema_key_precedence(config.schema_key_precedence);
+        self.documents
+            .set_schema_associations(config.schemas.clone());
+        self.loader.configure(&config);
+        self.schema_cache.reconfigure(&config);
+        self.catalog.configure(&config);
+        *self.config.write().expect("config lock poisoned") = Arc::new(config);
+    }
+
+    /// Settings from `workspace/didChangeConfiguration`: the `json-ls` section when present,
+    /// else the whole payload. Clients that send no settings are asked for the section if
+    /// they support `workspace/configuration`.
+    async fn changed_settings(&self, settings: Value) -> Option<Value> {
+        match settings {
+            Value::Object(mut map) if map.contains_key("json-ls") => map.remove("json-ls"),
+            Value::Object(map) if !map.is_empty() => Some(Value::Object(map)),
+            _ if !self.pull_configuration.load(Ordering::Relaxed) => None,
+            _ => {
+                let items = vec![ConfigurationItem {
+                    scope_uri: None,
+                    section: Some("json-ls".into()),
+                }];
+                match self.client.configuration(items).await {
+                    Ok(mut values) if !values.is_empty() => Some(values.swap_remove(0)),
+                    Ok(_) => None,
+                    Err(e) => {
+                        debug!("workspace/configuration failed: {e}");
+                        None
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handle for scheduling validations, also from spawned tasks.
+    fn diagnostics(&self) -> DiagnosticsScheduler {
+        DiagnosticsScheduler {
+            client: self.client.clone(),
+            documents: self.documents.clone(),
+            schema_cache: self.schema_cache.clone(),
+            config: self.config.clone(),
+            pending: self.pending_diagnostics.clone(),
+            schema_status: self.schema_status.clone(),
+            published: self.published.clone(),
+        }
+    }
+
+    /// Tell the user their settings were rejected and the current ones stay in effect.
+    async fn report_invalid_settings(&self, error: &serde_json::Error) {
+        warn!("Invalid json-ls settings, keeping the current ones: {error}");
+        self.client
+            .show_message(
+                MessageType::WARNING,
+                format!("json-ls: invalid settings ignored: {error}"),
+            )
+            .await;
+    }
+
+    /// Validate `uri` once edits pause for `debounce_ms`.
     fn schedule_diagnostics(&self, uri: Url) {
+        self.diagnostics().schedule(uri);
+    }
+
+    /// Validate `uri` after `delay`, replacing any validation already scheduled.
+    fn schedule_diagnostics_in(&self, uri: Url, delay: Duration) {
+        self.diagnostics().schedule_in(uri, delay);
+    }
+
+    async fn open_text_document(&self, uri: Url, version: i32, text: String, language_id: &str) {
+        debug!("did_open: {uri} ({language_id})");
+        let config = self.config();
+        self.documents.open(uri.clone(), version, text);
+        let language_schema = config.language_schemas.get(language_id).cloned();
+        self.documents
+            .set_language_schema_url(&uri, language_schema);
+        self.documents
+            .set_dialect(&uri, Dialect::detect(language_id, uri.path()));
+        self.language_ids
+            .insert(uri.clone(), language_id.to_owned());
+        // The catalog loads in the background (first from `initialized`, again once
+        // `catalog.ttl_secs` have passed); documents are re-associated when it finishes.
+        let (entries, current) = self.catalog.current(&config.catalog);
+        self.documents.set_catalog(entries);
+        if !current {
+            self.load_catalog_in_background();
+        }
+        self.resolve_association(&uri).await;
+
+        // Prefetch the schema eagerly so it is cached before the first completion request.
+        // This runs in its own task so it is never cancelled by did_change debouncing.
+        if let Some(schema_url) = self.documents.get_schema_url(&uri) {
+            let cache = self.schema_cache.clone();
+            tokio::spawn(async move {
+                let _ = cache.get_or_fetch(&schema_url).await;
+            });
+        }
+
+        self.schedule_diagnostics(uri);
+    }
+
+    /// Load the catalogs without holding up the caller, then re-associate and revalidate the
+    /// open documents if the entries changed.
+    fn load_catalog_in_background(&self) {
+        let catalog = self.catalog.clone();
+        let config = self.config();
+        let diagnostics = self.diagnostics();
+        tokio::spawn(async move {
+            let entries = catalog.entries(&config.catalog).await;
+            if diagnostics.documents.set_catalog(entries) {
+                for uri in diagnostics.documents.uris() {
+                    diagnostics.schedule(uri);
+                }
+            }
+        });
+    }
+
+    /// Ask the configured `schema_resolver` for a schema when no other association matches
+    /// the document.
+    async fn resolve_association(&self, uri: &Url) {
+        let Some(resolver) = self.config().schema_resolver.clone() else {
+            return;
+        };
+        self.documents.set_associated_schema_url(uri, None);
+        if self.documents.get_schema_url(uri).is_some() {
+            return;
+        }
+        let schema_url = match resolve_schema(&resolver, &self.client, uri).await {
+            Some(ResolvedSchema::SchemaUrl(url)) => url,
+            Some(ResolvedSchema::Schema(schema)) => {
+                let url = inline_schema_url(uri);
+                self.schema_cache.insert(&url, schema).await;
+                url
+            }
+            None => return,
+        };
+        debug!("Schema resolver associated {uri} with {schema_url}");
+        self.documents
+            .set_associated_schema_url(uri, Some(schema_url));
+    }
+
+    async fn change_text_document(
+        &self,
+        uri: Url,
+        version: i32,
+        changes: Vec<TextDocumentContentChangeEvent>,
+    ) {
+        debug!("did_change: {uri} v{version}");
+
+        if !self.documents.contains(&uri) {
+            self.recover_unknown_document(uri, version, changes).await;
+            return;
+        }
+
+        if let Err(e) = self.documents.update(&uri, version, changes) {
+            self.client
+                .log_message(
+                    MessageType::ERROR,
+                    format!("Failed to update document: {e}"),
+                )
+                .await;
+            return;
+        }
+
+        if self.config().validate_on == ValidateOn::Change {
+            self.schedule_diagnostics(uri);
+        }
+    }
+
+    /// `didChange` for a document we never saw opened (server restart, client hiccup):
+    /// adopt it if the change carries the full text, otherwise drop whatever we know about
+    /// it so no stale diagnostics remain.
+    async fn recover_unknown_document(
+        &self,
+        uri: Url,
+        version: i32,
+        changes: Vec<TextDocumentContentChangeEvent>,
+    ) {
+        match self
+            .documents
+            .open_from_changes(uri.clone(), version, changes)
+        {
+            Ok(true) => {
+                info!("did_change for unknown document {uri}: opened from full text");
+                self.resolve_association(&uri).await;
+                self.schedule_diagnostics(uri);
+            }
+            outcome => {
+                let reason = match outcome {
+                    Err(e) => e.to_string(),
+                    _ => "only incremental changes".into(),
+                };
+                self.client
+                    .log_message(
+                        MessageType::WARNING,
+                        format!("Ignoring change to unknown document {uri} ({reason}); reopen it to resume validation"),
+                    )
+                    .await;
+                self.close_text_document(&uri).await;
+            }
+        }
+    }
+
+    async fn close_text_document(&self, uri: &Url) {
+        debug!("did_close: {uri}");
+
+        // Clear pending diagnostics
+        if let Some((_, handle)) = self.pending_diagnostics.remove(uri) {
+            handle.abort();
+        }
+
+        self.documents.close(uri);
+        self.language_ids.remove(uri);
+        self.schema_status.remove(uri);
+        self.published.remove(uri);
+
+        // Clear diagnostics for closed file
+        self.client
+            .publish_diagnostics(uri.clone(), vec![], None)
+            .await;
+    }
+
+    fn usage_log(&self) -> Option<Arc<UsageLog>> {
+        self.usage.read().expect("usage lock poisoned").clone()
+    }
+
+    // ─── Custom requests ─────────────────────────────────────────────────────
+
+    /// `json-ls/status` — server version, open documents and cached schemas; with
+    /// `usage_metrics`, also this session's counters and the accumulated totals.
+    pub async fn status(&self) -> Result<Value> {
+        let usage = self.usage_log().map(|log| {
+            serde_json::json!({
+                "file": log.path(),
+                "session": METRICS.snapshot(),
+                "total": log.totals(),
+            })
+        });
+        Ok(serde_json::json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "openDocuments": self.documents.uris().len(),
+            "cachedSchemas": self.schema_cache.cached_urls().len(),
+            "usage": usage,
+        }))
+    }
+
+    // ─── Notebook documents ──────────────────────────────────────────────────
+
+    /// `notebookDocument/didOpen` — track each JSON cell as its own document.
+    pub async fn did_open_notebook(&self, params: DidOpenNotebookDocumentParams) {
+        debug!(
+            "did_open_notebook: {} ({})",
+            params.notebook_document.uri, params.notebook_document.notebook_type
+        );
+        for cell in params.cell_text_documents {
+            if is_json_cell(&cell) {
+                self.open_text_document(cell.uri, cell.version, cell.text, &cell.language_id)
+                    .await;
+            }
+        }
+    }
+
+    /// `notebookDocument/didChange` — cells added / removed / edited.
+    pub async fn did_change_notebook(&self, params: DidChangeNotebookDocumentParams) {
+        debug!("did_change_notebook: {}", params.notebook_document.uri);
+        let Some(cells) = params.change.cells else {
+            return;
+        };
+        if let Some(structure) = cells.structure {
+            for cell in structure.did_close {
+                // Non-JSON cells were never opened; there are no diagnostics to clear.
+                if self.documents.get_text(&cell.uri).is_some() {
+                    self.close_text_document(&cell.uri).await;
+                }
+            }
+            for cell in structure.did_open {
+                if is_json_cell(&cell) {
+                    self.open_text_document(cell.uri, cell.version, cell.text, &cell.language_id)
+                        .await;
+                }
+            }
+        }
+        for content in cells.text_content {
+            // Non-JSON cells were never opened; ignore their edits.
+            if self.documents.get_text(&content.document.uri).is_some() {
+                self.change_text_document(
+                    content.document.uri,
+                    content.document.version,
+                    content.changes,
+                )
+                .await;
+            }
+        }
+    }
+
+    /// `notebookDocument/didClose` — drop every cell of the notebook.
+    pub async fn did_close_notebook(&self, params: DidCloseNotebookDocumentParams) {
+        debug!("did_close_notebook: {}", params.notebook_document.uri);
+        for cell in params.cell_text_documents {
+            if self.documents.get_text(&cell.uri).is_some() {
+                self.close_text_document(&cell.uri).await;
+            }
+        }
+    }
+
+    /// `json-ls.validateSubtree [uri, pointer]` — report the error count under `pointer`.
+    async fn validate_subtree(&self, arguments: Vec<Value>) -> Option<Value> {
+        let uri = arguments
+            .first()?
+            .as_str()
+            .and_then(|u| Url::parse(u).ok())?;
+        let pointer = arguments.get(1)?.as_str()?.to_owned();
+
+        // The lens may be stale after edits — only validate pointers that still exist.
+        let text = self.documents.get_text(&uri)?;
+        parse_spans(&text)?.find_pointer(&pointer)?;
+
+        let count = subtree_error_count(
+            &uri,
+            &self.documents,
+            &self.schema_cache,
+            &self.config(),
+            &pointer,
+        )
+        .await?;
+
+        self.client
+            .show_message(
+                MessageType::INFO,
+                format!("{pointer}: {}", subtree_title(count)),
+            )
+            .await;
+
+        Some(serde_json::json!({ "pointer": pointer, "errors": count }))
+    }
+
+    /// The workspace's JSON files, walked off the async runtime.
+    async fn workspace_files(&self) -> Vec<PathBuf> {
+        let workspace = self.workspace.clone();
+        match tokio::task::spawn_blocking(move || workspace.json_files()).await {
+            Ok(files) => files,
+            Err(e) => {
+                debug!("Workspace walk failed: {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    /// `json-ls.validateWorkspace [{ publish }?]`: validate every workspace JSON file, open
+    /// documents as edited and the others as saved on disk.
+    async fn validate_workspace(&self, arguments: &[Value]) -> Value {
+        let publish = arguments
+            .first()
+            .and_then(|options| options.get("publish"))
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let config = self.config();
+        let closed = ClosedFiles::new(&config, self.catalog.entries(&config.catalog).await);
+        let mut results = Vec::new();
+        let mut published = HashSet::new();
+        for path in self.workspace_files().await {
+            let Ok(uri) = Url::from_file_path(&path) else {
+                continue;
+            };
+            if self.documents.contains(&uri) {
+                // Its diagnostics are already published as it is edited.
+                let diagnostics =
+                    validate_document(&uri, &self.documents, &self.schema_cache, &config)
+                        .await
+                        .unwrap_or_default();
+                results.push((uri, diagnostics));
+                continue;
+            }
+            let Some((uri, diagnostics)) =
+                closed.validate(&path, &self.schema_cache, &config).await
+            else {
+                continue;
+            };
+            if publish {
+                self.client
+                    .publish_diagnostics(uri.clone(), diagnostics.clone(), None)
+                    .await;
+                published.insert(uri.clone());
+            }
+            results.push((uri, diagnostics));
+        }
+        if publish {
+            // Files deleted, opened or left without a schema since the last run keep no
+            // stale diagnostics; open documents publish their own.
+            let previous = std::mem::replace(
+                &mut *self
+                    .workspace_published
+                    .lock()
+                    .expect("workspace_published lock poisoned"),
+                published.clone(),
+            );
+            for uri in previous.difference(&published) {
+                if !self.documents.contains(uri) {
+                    self.client
+                        .publish_diagnostics(uri.clone(), Vec::new(), None)
+                        .await;
+                }
+            }
+        }
+        workspace_report(&results)
+    }
+
+    /// `workspace/diagnostic`: a report per closed workspace file matching
+    /// `workspace_diagnostics.include`, streamed to the partial result token when the client
+    /// sends one. Open documents are left out: their diagnostics are pushed as they change.
+    async fn workspace_diagnostics(
+        &self,
+        params: WorkspaceDiagnosticParams,
+    ) -> WorkspaceDiagnosticReportResult {
+        let config = self.config();
+        let include = &config.workspace_diagnostics.include;
+        let previous: HashMap<Url, String> = params
+            .previous_result_ids
+            .into_iter()
+            .map(|previous| (previous.uri, previous.value))
+            .collect();
+        let token = params.partial_result_params.partial_result_token;
+        let closed = ClosedFiles::new(&config, self.catalog.entries(&config.catalog).await);
+
+        let mut items = Vec::new();
+        for path in self.workspace_files().await {
+            let Ok(uri) = Url::from_file_path(&path) else {
+                continue;
+            };
+            if self.documents.contains(&uri)
+                || !(include.is_empty() || file_matches(include, &match_path(&uri)))
+            {
+                continue;
+            }
+            let Some((uri, diagnostics)) =
+                closed.validate(&path, &self.schema_cache, &config).await
+            else {
+                continue;
+            };
+            let previous_result_id = previous.get(&uri).map(String::as_str);
+            let report = document_report(uri, diagnostics, previous_result_id);
+            match &token {
+                Some(token) => {
+                    let progress = WorkspaceDiagnosticProgressParams {
+                        token: token.clone(),
+                        value: WorkspaceDiagnosticReportPartialResult {
+                            items: vec![report],
+                        },
+                    };
+                    self.client
+                        .send_notification::<WorkspaceDiagnosticProgress>(progress)
+                        .await;
+                }
+                None => items.push(report),
+            }
+        }
+        WorkspaceDiagnosticReportResult::Report(WorkspaceDiagnosticReport { items })
+    }
+
+    /// `json-ls.refreshCatalog`: reload the catalogs and re-associate open documents.
+    async fn refresh_catalog(&self) -> Value {
+        let config = self.config();
+        let report = self.catalog.refresh(&config.catalog).await;
+        self.documents
+            .set_catalog(self.catalog.entries(&config.catalog).await);
+        for uri in self.documents.uris() {
+            self.schedule_diagnostics(uri);
+        }
+        report
+    }
+
+    /// `json-ls.gotoProperty [uri, query]` → the best-matching key's `Location`, or null.
+    fn goto_property(&self, arguments: &[Value]) -> Option<Value> {
+        let uri = arguments
+            .first()?
+            .as_str()
+            .and_then(|u| Url::parse(u).ok())?;
+        let query = arguments.get(1)?.as_str()?;
+        let location = goto_property(&self.documents, &uri, query)?;
+        serde_json::to_value(location).ok()
+    }
+
+    /// `json-ls.applyPatch [uri, patch]` → a `WorkspaceEdit` applying the RFC 6902 patch.
+    /// Malformed arguments and failed operations are `InvalidParams` errors.
+    fn apply_patch(&self, arguments: &[Value]) -> Result<Value> {
+        use tower_lsp::jsonrpc::Error;
+        let uri = arguments
+            .first()
+            .and_then(Value::as_str)
+            .and_then(|u| Url::parse(u).ok())
+            .ok_or_else(|| Error::invalid_params("expected [uri, patch]"))?;
+        let patch = arguments
+            .get(1)
+            .ok_or_else(|| Error::invalid_params("expected [uri, patch]"))?;
+        let text = self
+            .documents
+            .get_text(&uri)
+            .ok_or_else(|| Error::invalid_params(format!("{uri} is not open")))?;
+        let edits = apply_patch(&text, patch).map_err(Error::invalid_params)?;
+        debug!("applyPatch {uri}: {} edit(s)", edits.len());
+        serde_json::to_value(workspace_edit(&uri, &text, edits))
+            .map_err(|_| Error::internal_error())
+    }
+
+    /// `json-ls.normalizePasted [uri, range, { sortKeys }?]` → a `WorkspaceEdit` replacing
+    /// the pasted range with strict, re-indented JSON.
+    fn normalize_pasted(&self, arguments: &[Value]) -> Result<Value> {
+        use tower_lsp::jsonrpc::Error;
+
+        let uri = arguments
+            .first()
+            .and_then(Value::as_str)
+            .and_then(|u| Url::parse(u).ok())
+            .ok_or_else(|| Error::invalid_params("expected [uri, range]"))?;
+        let range: Range = arguments
+            .get(1)
+            .and_then(|range| serde_json::from_value(range.clone()).ok())
+            .ok_or_else(|| Error::invalid_params("expected [uri, range]"))?;
+        let sort_keys = arguments
+            .get(2)
+            .and_then(|options| options.get("sortKeys"))
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let text = self
+            .documents
+            .get_text(&uri)
+            .ok_or_else(|| Error::invalid_params(format!("{uri} is not open")))?;
+
+        let offset = |pos: Position| {
+            lsp_position_to_byte_offset(&text, pos.line, pos.character)
+                .ok_or_else(|| Error::invalid_params("range is outside the document"))
+        };
+        let (start, end) = (offset(range.start)?, offset(range.end)?);
+        let edit = normalize_pasted(&text, start, end, sort_keys).map_err(Error::invalid_params)?;
+        serde_json::to_value(workspace_edit(&uri, &text, vec![edit]))
+            .map_err(|_| Error::internal_error())
+    }
+
+    /// `json-ls.addSchema` with `[uri, url?]`: a `WorkspaceEdit` declaring `url` (else the
+    /// catalog's schema for the file) as the document's `$schema`.
+    async fn add_schema(&self, arguments: &[Value]) -> Result<Value> {
+        use tower_lsp::jsonrpc::Error;
+
+        let uri = arguments
+            .first()
+            .and_then(Value::as_str)
+            .and_then(|u| Url::parse(u).ok())
+            .ok_or_else(|| Error::invalid_params("expected [uri, url?]"))?;
+        let text = self
+            .documents
+            .get_text(&uri)
+            .ok_or_else(|| Error::invalid_params(format!("{uri} is not open")))?;
+        let config = self.config();
+
+        let url = match arguments.get(1).and_then(Value::as_str) {
+            Some(url) => url.to_owned(),
+            None => {
+                let path = match_path(&uri);
+                let entries = self.catalog.entries(&config.catalog).await;
+                entry_for(&entries, &path)
+                    .map(|entry| entry.url.clone())
+                    .ok_or_else(|| {
+                        Error::invalid_params(format!(
+                            "no schema URL given and no catalog entry matches {path}"
+                        ))
+                    })?
+            }
+        };
+        let edit = schema_insertion(&text, &url, config.schema_key_precedence)
+            .map_err(Error::invalid_params)?;
+        serde_json::to_value(workspace_edit(&uri, &text, vec![edit]))
+            .map_err(|_| Error::internal_error())
+    }
+
+    /// `json-ls.bundleSchema` — the schema at `url` with its external `$ref`s inlined, or
+    /// with `output`, written there. Refs that could not be loaded are listed as
+    /// `unresolved`.
+    async fn bundle_schema(&self, arguments: &[Value]) -> Result<Value> {
+        use tower_lsp::jsonrpc::{Error, ErrorCode};
+
+        let url = arguments
+            .first()
+            .and_then(Value::as_str)
+            .ok_or_else(|| Error::invalid_params("expected [url, { output? }]"))?;
+        let output = arguments
+            .get(1)
+            .and_then(|options| options.get("output"))
+            .and_then(Value::as_str);
+        let failed = |message: String| Error {
+            code: ErrorCode::InternalError,
+            message: message.into(),
+            data: None,
+        };
+
+        let schema = self
+            .schema_cache
+            .get_or_fetch(url)
+            .await
+            .map_err(|e| failed(format!("Could not load {url}: {e}")))?;
+        let unresolved = unresolved_refs(&schema);
+        let Some(output) = output else {
+            return Ok(serde_json::json!({ "schema": *schema, "unresolved": unresolved }));
+        };
+        // A relative path means the first workspace folder, not the server's working directory.
+        let output = match PathBuf::from(output) {
+            path if path.is_absolute() => path,
+            path => self
+                .workspace
+                .roots()
+                .first()
+                .map(|root| root.join(&path))
+                .ok_or_else(|| {
+                    Error::invalid_params(format!(
+                        "relative output path {output} needs a workspace folder"
+                    ))
+                })?,
+        };
+        let mut text =
+            serde_json::to_string_pretty(&*schema).map_err(|_| Error::internal_error())?;
+        text.push('\n');
+        tokio::fs::write(&output, text)
+            .await
+            .map_err(|e| failed(format!("Could not write {}: {e}", output.display())))?;
+        Ok(serde_json::json!({ "path": output, "unresolved": unresolved }))
+    }
+}
+
+/// Start the Prometheus exporter in the background.
+#[cfg(feature = "metrics")]
+fn serve_metrics(addr: String) {
+    tokio::spawn(async move {
+        if let Err(e) = crate::metrics::serve_prometheus(&addr).await {
+            warn!("Metrics endpoint on {addr} failed: {e}");
+        }
+    });
+}
+
+#[cfg(not(feature = "metrics"))]
+fn serve_metrics(addr: String) {
+    warn!("metrics_addr {addr} ignored: json-ls was built without the `metrics` feature");
+}
+
+/// What a validation task needs, cloned out of the [`Backend`] so tasks spawned outside an
+/// LSP handler (e.g. a background catalog load) can schedule validations too.
+#[derive(Clone)]
+struct DiagnosticsScheduler {
+    client: Client,
+    documents: Arc<DocumentStore>,
+    schema_cache: Arc<SchemaCache>,
+    config: Arc<RwLock<Arc<ServerConfig>>>,
+    pending: Arc<DashMap<Url, JoinHandle<()>>>,
+    schema_status: Arc<DashMap<Url, SchemaStatusParams>>,
+    published: Arc<DashMap<Url, Vec<Diagnostic>>>,
+}
+
+impl DiagnosticsScheduler {
+    fn config(&self) -> Arc<ServerConfig> {
+        self.config.read().expect("config lock poisoned").clone()
+    }
+
+    /// Validate `uri` once edits pause for `debounce_ms`.
+    fn schedule(&self, uri: Url) {
+        let delay = Duration::from_millis(self.config().debounce_ms);
+        self.schedule_in(uri, delay);
+    }
+
+    /// Validate `uri` after `delay`, replacing any validation already scheduled.
+    fn schedule_in(&self, uri: Url, delay: Duration) {
         // Abort any in-flight diagnostic task for this document
-        if let Some((_, handle)) = self.pending_diagnostics.remove(&uri) {
+        if let Some((_, handle)) = self.pending.remove(&uri) {
             handle.abort();
         }
 
         let client = self.client.clone();
         let documents = self.documents.clone();
         let schema_cache = self.schema_cache.clone();
-        let pending = self.pending_diagnostics.clone();
+        let config = self.config();
+        let pending = self.pending.clone();
+        let schema_status = self.schema_status.clone();
+        let published = self.published.clone();
         let task_uri = uri.clone();
+        // The text the diagnostics must describe; a newer edit makes them stale.
+        let mut version = self.documents.get_version(&uri);
 
         let handle = tokio::spawn(async move {
-            tokio::time::sleep(tokio::time::Duration::from_millis(DEBOUNCE_MS)).await;
+            tokio::time::sleep(delay).await;
 
-            let diagnostics = validate_document(&task_uri, &documents, &schema_cache)
-                .await
-                .unwrap_or_default();
+            let status = resolve_schema_status(&task_uri, &documents, &schema_cache).await;
+            let changed = schema_status
+                .get(&task_uri)
+                .is_none_or(|prev| prev.differs_from(&status));
+            if changed {
+                schema_status.insert(task_uri.clone(), status.clone());
+                client.send_notification::<SchemaStatus>(status).await;
+            }
 
-            client
-                .publish_diagnostics(task_uri.clone(), diagnostics, None)
-                .await;
+            let diagnostics = loop {
+                let started = Instant::now();
+                let diagnostics = validate_document(&task_uri, &documents, &schema_cache, &config)
+                    .await
+                    .unwrap_or_default();
+                METRICS.diagnostics.observe(started.elapsed());
+
+                // The debounce or a slow schema fetch can outlive further edits (or the
+                // document itself). Under `validate_on: "change"` their own tasks publish the
+                // fresh results; otherwise nothing will, so validate the new text here.
+                let current = documents.get_version(&task_uri);
+                if current == version {
+                    break diagnostics;
+                }
+                debug!("Dropping stale diagnostics for {task_uri} (v{version:?})");
+                if current.is_none() || config.validate_on == ValidateOn::Change {
+                    return;
+                }
+                version = current;
+            };
+
+            let unchanged = published
+                .get(&task_uri)
+                .is_some_and(|prev| *prev == diagnostics);
+            if !unchanged {
+                published.insert(task_uri.clone(), diagnostics.clone());
+                client
+                    .publish_diagnostics(task_uri.clone(), diagnostics, version)
+                    .await;
+            }
 
             pending.remove(&task_uri);
         });
 
-        self.pending_diagnostics.insert(uri, handle);
+        self.pending.insert(uri, handle);
     }
 }
 
 #[tower_lsp::async_trait]
 impl LanguageServer for Backend {
     async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        self.workspace.set_roots(workspace_roots(&params));
+
         // Parse server config from initializationOptions
-        let config = params
-            .initialization_options
-            .map(ServerConfig::from_value)
-            .unwrap_or_default();
+        let mut config = match params.initialization_options.map(ServerConfig::from_value) {
+            Some(Ok(config)) => config,
+            Some(Err(e)) => {
+                self.report_invalid_settings(&e).await;
+                ServerConfig::default()
+            }
+            None => ServerConfig::default(),
+        };
+        if let Some(dir) = &self.cache_dir_override {
+            config.cache_dir = Some(dir.clone());
+        }
 
         info!("json-ls initializing with config: {config:?}");
+        if let Some(addr) = config.metrics_addr.clone() {
+            serve_metrics(addr);
+        }
+        if config.usage_metrics {
+            if let Some(dir) = config.cache_dir() {
+                let log = Arc::new(UsageLog::open(&dir));
+                info!("Recording usage metrics to {}", log.path().display());
+                *self.usage.write().expect("usage lock poisoned") = Some(log);
+            }
+        }
+        let sync_kind = negotiate_sync_kind(
+            config.text_document_sync.map(SyncPreference::kind),
+            &params.capabilities,
+        );
+        info!("Text document sync: {sync_kind:?}");
+        let pull_configuration = params
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.configuration)
+            .unwrap_or(false);
+        self.pull_configuration
+            .store(pull_configuration, Ordering::Relaxed);
+        self.apply_config(config);
 
         Ok(InitializeResult {
             server_info: Some(ServerInfo {
@@ -81,14 +925,64 @@ impl LanguageServer for Backend {
                 version: Some(env!("CARGO_PKG_VERSION").into()),
             }),
             capabilities: ServerCapabilities {
-                text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                    TextDocumentSyncKind::INCREMENTAL,
+                text_document_sync: Some(TextDocumentSyncCapability::Options(
+                    TextDocumentSyncOptions {
+                        open_close: Some(true),
+                        change: Some(sync_kind),
+                        save: Some(TextDocumentSyncSaveOptions::SaveOptions(SaveOptions {
+                            include_text: Some(true),
+                        })),
+                        ..Default::default()
+                    },
                 )),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
+                definition_provider: Some(OneOf::Left(true)),
+                references_provider: Some(OneOf::Left(true)),
+                document_symbol_provider: Some(OneOf::Left(true)),
+                workspace_symbol_provider: Some(OneOf::Left(true)),
+                document_formatting_provider: Some(OneOf::Left(true)),
+                document_on_type_formatting_provider: Some(DocumentOnTypeFormattingOptions {
+                    first_trigger_character: ON_TYPE_TRIGGER.into(),
+                    more_trigger_character: None,
+                }),
                 completion_provider: Some(CompletionOptions {
                     trigger_characters: Some(vec!["\"".into(), ":".into()]),
                     ..Default::default()
                 }),
+                code_action_provider: Some(CodeActionProviderCapability::Options(
+                    CodeActionOptions {
+                        code_action_kinds: Some(vec![
+                            CodeActionKind::QUICKFIX,
+                            CodeActionKind::REFACTOR_REWRITE,
+                            CodeActionKind::SOURCE,
+                        ]),
+                        ..Default::default()
+                    },
+                )),
+                diagnostic_provider: Some(DiagnosticServerCapabilities::Options(
+                    DiagnosticOptions {
+                        identifier: Some("json-ls".into()),
+                        inter_file_dependencies: false,
+                        workspace_diagnostics: true,
+                        ..Default::default()
+                    },
+                )),
+                code_lens_provider: Some(CodeLensOptions {
+                    resolve_provider: Some(false),
+                }),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: vec![
+                        VALIDATE_SUBTREE_COMMAND.into(),
+                        GOTO_PROPERTY_COMMAND.into(),
+                        APPLY_PATCH_COMMAND.into(),
+                        NORMALIZE_PASTED_COMMAND.into(),
+                        REFRESH_CATALOG_COMMAND.into(),
+                        VALIDATE_WORKSPACE_COMMAND.into(),
+                        ADD_SCHEMA_COMMAND.into(),
+                        BUNDLE_SCHEMA_COMMAND.into(),
+                    ],
+                    ..Default::default()
+                }),
                 ..Default::default()
             },
         })
@@ -96,6 +990,64 @@ impl LanguageServer for Backend {
 
     async fn initialized(&self, _: InitializedParams) {
         info!("json-ls server ready");
+
+        self.load_catalog_in_background();
+
+        // lsp-types has no static notebook capability; register it dynamically. Clients
+        // without notebook support simply reject the registration.
+        let registration = Registration {
+            id: NOTEBOOK_SYNC_METHOD.into(),
+            method: NOTEBOOK_SYNC_METHOD.into(),
+            register_options: Some(notebook_sync_options()),
+        };
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            if let Err(e) = client.register_capability(vec![registration]).await {
+                debug!("Notebook sync not registered: {e}");
+            }
+        });
+
+        for url in self.config().preload_schemas.clone() {
+            let schema_cache = self.schema_cache.clone();
+            tokio::spawn(async move {
+                // Failures are logged by the cache.
+                if schema_cache.get_or_fetch(&url).await.is_ok() {
+                    debug!("Schema preloaded: {url}");
+                }
+            });
+        }
+
+        let metrics_secs = self.config().metrics_notification_secs;
+        if metrics_secs > 0 {
+            let (client, documents) = (self.client.clone(), self.documents.clone());
+            tokio::spawn(async move {
+                let mut interval =
+                    tokio::time::interval(tokio::time::Duration::from_secs(metrics_secs));
+                loop {
+                    interval.tick().await;
+                    client
+                        .send_notification::<MetricsNotification>(MetricsParams::current(
+                            &documents,
+                        ))
+                        .await;
+                }
+            });
+        }
+
+        if let Some(log) = self.usage_log() {
+            tokio::spawn(async move {
+                let mut interval =
+                    tokio::time::interval(tokio::time::Duration::from_secs(USAGE_SAVE_SECS));
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = log.save() {
+                        warn!("Failed to write {}: {e}", log.path().display());
+                    }
+                }
+            });
+        }
+
         self.client
             .log_message(MessageType::INFO, "json-ls initialized")
             .await;
@@ -106,70 +1058,214 @@ impl LanguageServer for Backend {
         for entry in self.pending_diagnostics.iter() {
             entry.value().abort();
         }
+        if let Some(log) = self.usage_log() {
+            if let Err(e) = log.save() {
+                warn!("Failed to write {}: {e}", log.path().display());
+            }
+        }
         Ok(())
     }
 
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
-        let uri = params.text_document.uri.clone();
-        let version = params.text_document.version;
-        let text = params.text_document.text;
+        let doc = params.text_document;
+        self.open_text_document(doc.uri, doc.version, doc.text, &doc.language_id)
+            .await;
+    }
 
-        debug!("did_open: {uri}");
-        self.documents.open(uri.clone(), version, text);
+    async fn did_change(&self, params: DidChangeTextDocumentParams) {
+        let doc = params.text_document;
+        self.change_text_document(doc.uri, doc.version, params.content_changes)
+            .await;
+    }
 
-        // Prefetch the schema eagerly so it is cached before the first completion request.
-        // This runs in its own task so it is never cancelled by did_change debouncing.
-        if let Some(schema_url) = self.documents.get_schema_url(&uri) {
-            let cache = self.schema_cache.clone();
-            tokio::spawn(async move {
-                let _ = cache.get_or_fetch(&schema_url).await;
-            });
+    /// Saves are validated at once, against the saved text when the client sends it (a
+    /// missed edit would otherwise linger until the next change).
+    async fn did_save(&self, params: DidSaveTextDocumentParams) {
+        let uri = params.text_document.uri;
+        debug!("did_save: {uri}");
+        let Some(version) = self.documents.get_version(&uri) else {
+            return;
+        };
+        if let Some(text) = params.text {
+            if self.documents.get_text(&uri).as_ref() != Some(&text) {
+                info!("did_save: {uri} differs from the saved text; resyncing");
+                let change = TextDocumentContentChangeEvent {
+                    range: None,
+                    range_length: None,
+                    text,
+                };
+                if let Err(e) = self.documents.update(&uri, version, vec![change]) {
+                    warn!("Failed to resync {uri} on save: {e}");
+                }
+            }
+        }
+        if self.config().validate_on != ValidateOn::Open {
+            self.schedule_diagnostics_in(uri, Duration::ZERO);
         }
+    }
 
-        self.schedule_diagnostics(uri);
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        self.close_text_document(&params.text_document.uri).await;
     }
 
-    async fn did_change(&self, params: DidChangeTextDocumentParams) {
-        let uri = params.text_document.uri.clone();
-        let version = params.text_document.version;
+    /// Open documents' diagnostics are pushed; pulling them too would show each twice.
+    async fn diagnostic(
+        &self,
+        _: DocumentDiagnosticParams,
+    ) -> Result<DocumentDiagnosticReportResult> {
+        Ok(DocumentDiagnosticReportResult::Report(
+            DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport::default()),
+        ))
+    }
 
-        debug!("did_change: {uri} v{version}");
+    async fn workspace_diagnostic(
+        &self,
+        params: WorkspaceDiagnosticParams,
+    ) -> Result<WorkspaceDiagnosticReportResult> {
+        Ok(self.workspace_diagnostics(params).await)
+    }
 
-        if let Err(e) = self.documents.update(&uri, version, params.content_changes) {
-            self.client
-                .log_message(
-                    MessageType::ERROR,
-                    format!("Failed to update document: {e}"),
-                )
-                .await;
+    /// New settings replace the current ones, unless any field is invalid; open documents
+    /// are re-associated and revalidated. `metrics_addr`, `usage_metrics`, `text_document_sync` and
+    /// `metrics_notification_secs` keep their startup values.
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        let Some(settings) = self.changed_settings(params.settings).await else {
             return;
+        };
+        let previous = self.config();
+        match ServerConfig::from_value(settings) {
+            Ok(config) => self.apply_config(config),
+            Err(e) => {
+                self.report_invalid_settings(&e).await;
+                return;
+            }
         }
+        let config = self.config();
+        info!("json-ls configuration changed: {config:?}");
 
-        self.schedule_diagnostics(uri);
+        let (entries, current) = self.catalog.current(&config.catalog);
+        self.documents.set_catalog(entries);
+        if !current {
+            self.load_catalog_in_background();
+        }
+        let resolver_changed = previous.schema_resolver != config.schema_resolver;
+        for uri in self.documents.uris() {
+            let language_schema = self
+                .language_ids
+                .get(&uri)
+                .and_then(|id| config.language_schemas.get(id.as_str()).cloned());
+            self.documents
+                .set_language_schema_url(&uri, language_schema);
+            if resolver_changed {
+                self.documents.set_associated_schema_url(&uri, None);
+                self.resolve_association(&uri).await;
+            }
+            self.schedule_diagnostics(uri);
+        }
     }
 
-    async fn did_close(&self, params: DidCloseTextDocumentParams) {
-        let uri = &params.text_document.uri;
-        debug!("did_close: {uri}");
+    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        METRICS.record(Feature::Hover);
+        let started = Instant::now();
+        let hover = handle_hover(&self.documents, &self.schema_cache, &self.config(), params).await;
+        METRICS.hover.observe(started.elapsed());
+        Ok(hover)
+    }
 
-        // Clear pending diagnostics
-        if let Some((_, handle)) = self.pending_diagnostics.remove(uri) {
-            handle.abort();
-        }
+    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        METRICS.record(Feature::Completion);
+        let started = Instant::now();
+        let completion =
+            handle_completion(&self.documents, &self.schema_cache, &self.config(), params).await;
+        METRICS.completion.observe(started.elapsed());
+        Ok(completion)
+    }
 
-        self.documents.close(uri);
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> Result<Option<GotoDefinitionResponse>> {
+        METRICS.record(Feature::Definition);
+        Ok(
+            handle_goto_definition(&self.documents, &self.schema_cache, &self.config(), params)
+                .await,
+        )
+    }
 
-        // Clear diagnostics for closed file
-        self.client
-            .publish_diagnostics(uri.clone(), vec![], None)
-            .await;
+    async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        METRICS.record(Feature::References);
+        Ok(handle_references(&self.documents, &self.schema_cache, &self.workspace, params).await)
     }
 
-    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
-        Ok(handle_hover(&self.documents, &self.schema_cache, params).await)
+    async fn document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> Result<Option<DocumentSymbolResponse>> {
+        METRICS.record(Feature::DocumentSymbol);
+        Ok(handle_document_symbol(&self.documents, params))
     }
 
-    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
-        Ok(handle_completion(&self.documents, &self.schema_cache, params).await)
+    async fn symbol(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> Result<Option<Vec<SymbolInformation>>> {
+        METRICS.record(Feature::WorkspaceSymbol);
+        Ok(handle_workspace_symbol(
+            &self.documents,
+            &self.workspace,
+            &self.symbol_index,
+            &self.config(),
+            params,
+        )
+        .await)
+    }
+
+    async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        METRICS.record(Feature::Formatting);
+        Ok(handle_formatting(&self.documents, params))
+    }
+
+    async fn on_type_formatting(
+        &self,
+        params: DocumentOnTypeFormattingParams,
+    ) -> Result<Option<Vec<TextEdit>>> {
+        METRICS.record(Feature::Formatting);
+        Ok(handle_on_type_formatting(&self.documents, params))
+    }
+
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        METRICS.record(Feature::CodeAction);
+        Ok(handle_code_action(&self.documents, &self.schema_cache, &self.config(), params).await)
+    }
+
+    async fn code_lens(&self, params: CodeLensParams) -> Result<Option<Vec<CodeLens>>> {
+        METRICS.record(Feature::CodeLens);
+        Ok(handle_code_lens(
+            &self.documents,
+            &self.published,
+            &self.config(),
+            params,
+        ))
+    }
+
+    async fn execute_command(&self, params: ExecuteCommandParams) -> Result<Option<Value>> {
+        METRICS.record(Feature::ExecuteCommand);
+        debug!("execute_command: {}", params.command);
+        match params.command.as_str() {
+            VALIDATE_SUBTREE_COMMAND => Ok(self.validate_subtree(params.arguments).await),
+            GOTO_PROPERTY_COMMAND => Ok(self.goto_property(&params.arguments)),
+            APPLY_PATCH_COMMAND => self.apply_patch(&params.arguments).map(Some),
+            NORMALIZE_PASTED_COMMAND => self.normalize_pasted(&params.arguments).map(Some),
+            ADD_SCHEMA_COMMAND => self.add_schema(&params.arguments).await.map(Some),
+            VALIDATE_WORKSPACE_COMMAND => {
+                Ok(Some(self.validate_workspace(&params.arguments).await))
+            }
+            REFRESH_CATALOG_COMMAND => Ok(Some(self.refresh_catalog().await)),
+            BUNDLE_SCHEMA_COMMAND => self.bundle_schema(&params.arguments).await.map(Some),
+            other => {
+                warn!("Unknown command: {other}");
+                Ok(None)
+            }
+        }
     }
 }
@@ -0,0 +1,252 @@
+//! Schema-driven cleanups for data documents, offered as code actions.
+
+use crate::position::PathSegment;
+use crate::schema::SchemaNode;
+use crate::span::{ByteEdit, SpanKind, SpanNode};
+use serde_json::Value;
+
+/// Edits deleting the members of `object` (found at `path`) whose value equals the schema
+/// `default` for that property, unless the object's schema (or an `allOf` member of it)
+/// lists the property in `required`. With `recursive`, kept members and array items are searched
+/// too. Returns the edits and the number of members removed.
+pub fn default_removals(
+    text: &str,
+    object: &SpanNode,
+    path: &mut Vec<PathSegment>,
+    schema: &SchemaNode,
+    recursive: bool,
+) -> (Vec<ByteEdit>, usize) {
+    let mut edits = Vec::new();
+    let mut removed = 0;
+    collect_removals(
+        text,
+        object,
+        path,
+        schema,
+        recursive,
+        &mut edits,
+        &mut removed,
+    );
+    edits.sort_by_key(|edit| edit.start);
+    (edits, removed)
+}
+
+/// The innermost object whose range contains byte `offset`, with its path.
+pub fn object_at(root: &SpanNode, offset: usize) -> Option<(&SpanNode, Vec<PathSegment>)> {
+    let mut found = None;
+    let mut node = root;
+    let mut path = Vec::new();
+    loop {
+        if !(node.start..=node.end).contains(&offset) {
+            return found;
+        }
+        let next = match &node.kind {
+            SpanKind::Object(members) => {
+                found = Some((node, path.clone()));
+                members.iter().find_map(|m| {
+                    (m.value.start..=m.value.end)
+                        .contains(&offset)
+                        .then(|| (&m.value, PathSegment::Key(m.key.clone())))
+                })
+            }
+            SpanKind::Array(items) => items.iter().enumerate().find_map(|(idx, item)| {
+                (item.start..=item.end)
+                    .contains(&offset)
+                    .then_some((item, PathSegment::Index(idx)))
+            }),
+            _ => None,
+        };
+        let Some((child, segment)) = next else {
+            return found;
+        };
+        node = child;
+        path.push(segment);
+    }
+}
+
+fn collect_removals(
+    text: &str,
+    node: &SpanNode,
+    path: &mut Vec<PathSegment>,
+    schema: &SchemaNode,
+    recursive: bool,
+    edits: &mut Vec<ByteEdit>,
+    removed: &mut usize,
+) {
+    match &node.kind {
+        SpanKind::Object(members) => {
+            // Removing a required member would only trade the default for an error.
+            let object_schema = schema.navigate(path);
+            let required = object_schema
+                .as_ref()
+                .map(SchemaNode::required_names)
+                .unwrap_or_default();
+            let mut is_default = Vec::with_capacity(members.len());
+            for member in members {
+                path.push(PathSegment::Key(member.key.clone()));
+                let removable = !required.contains(member.key.as_str());
+                let matches = removable
+                    && schema_default(schema, path).is_some_and(|default| {
+                        serde_json::from_str::<Value>(&text[member.value.start..member.value.end])
+                            .is_ok_and(|value| value == *default)
+                    });
+                if !matches && recursive {
+                    collect_removals(text, &member.value, path, schema, true, edits, removed);
+                }
+                path.pop();
+                is_default.push(matches);
+            }
+            *removed += is_default.iter().filter(|d| **d).count();
+            member_deletions(node, &is_default, edits);
+        }
+        SpanKind::Array(items) if recursive => {
+            for (idx, item) in items.iter().enumerate() {
+                path.push(PathSegment::Index(idx));
+                collect_removals(text, item, path, schema, true, edits, removed);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The `default` declared for the property at `path`: next to the property's `$ref` if
+/// there is one, else on the schema it resolves to.
+fn schema_default<'a>(schema: &SchemaNode<'a>, path: &[PathSegment]) -> Option<&'a Value> {
+    schema
+        .declaration(path)
+        .and_then(|declared| declared.schema.get("default"))
+        .or_else(|| schema.navigate(path)?.keyword("default"))
+}
+
+/// Delete the flagged members of `object`, taking each run of them out together with the
+/// separators that would otherwise be left dangling.
+fn member_deletions(object: &SpanNode, flagged: &[bool], edits: &mut Vec<ByteEdit>) {
+    let members = object.members();
+    if !flagged.contains(&true) {
+        return;
+    }
+    if !flagged.contains(&false) {
+        edits.push(ByteEdit {
+            start: object.start + 1,
+            end: object.end - 1,
+            new_text: String::new(),
+        });
+        return;
+    }
+
+    let mut idx = 0;
+    while idx < members.len() {
+        if !flagged[idx] {
+            idx += 1;
+            continue;
+        }
+        let first = idx;
+        while idx < members.len() && flagged[idx] {
+            idx += 1;
+        }
+        let (start, end) = match members.get(idx) {
+            Some(next) => (members[first].key_start, next.key_start),
+            None => (members[first - 1].value.end, members[idx - 1].value.end),
+        };
+        edits.push(ByteEdit {
+            start,
+            end,
+            new_text: String::new(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::parse_spans;
+    use serde_json::json;
+
+    fn cleaned(text: &str, at: Option<usize>) -> (String, usize) {
+        let schema = json!({
+            "properties": {
+                "port": { "type": "integer", "default": 8080 },
+                "host": { "type": "string", "default": "localhost" },
+                "tls": { "$ref": "#/$defs/tls", "default": { "enabled": false } },
+                "name": { "type": "string" }
+            },
+            "$defs": {
+                "tls": { "properties": { "enabled": { "default": false }, "port": { "default": 443 } } }
+            }
+        });
+        cleaned_with(&schema, text, at)
+    }
+
+    fn cleaned_with(schema: &Value, text: &str, at: Option<usize>) -> (String, usize) {
+        let node = SchemaNode::new(schema, schema);
+        let root = parse_spans(text).unwrap();
+        let (object, mut path) = match at {
+            Some(offset) => object_at(&root, offset).unwrap(),
+            None => (&root, Vec::new()),
+        };
+        let (mut edits, removed) = default_removals(text, object, &mut path, &node, at.is_none());
+        edits.reverse();
+        let mut out = text.to_owned();
+        for edit in edits {
+            out.replace_range(edit.start..edit.end, &edit.new_text);
+        }
+        (out, removed)
+    }
+
+    #[test]
+    fn test_removes_default_members_across_document() {
+        let text = "{\n  \"port\": 8080,\n  \"name\": \"api\",\n  \"host\": \"localhost\",\n  \"tls\": { \"enabled\": true, \"port\": 443 }\n}";
+        assert_eq!(
+            cleaned(text, None),
+            (
+                "{\n  \"name\": \"api\",\n  \"tls\": { \"enabled\": true }\n}".into(),
+                3
+            )
+        );
+
+        let all_defaults = r#"{ "port": 8080, "tls": { "enabled": false } }"#;
+        assert_eq!(cleaned(all_defaults, None), ("{}".into(), 2));
+    }
+
+    #[test]
+    fn test_single_object_variant_is_shallow() {
+        let text = r#"{ "port": 8080, "tls": { "enabled": false, "port": 443, "x": 1 } }"#;
+        let inside_tls = text.find("\"x\"").unwrap();
+        assert_eq!(
+            cleaned(text, Some(inside_tls)),
+            (r#"{ "port": 8080, "tls": { "x": 1 } }"#.into(), 2)
+        );
+        assert_eq!(
+            cleaned(text, Some(1)),
+            (
+                r#"{ "tls": { "enabled": false, "port": 443, "x": 1 } }"#.into(),
+                1
+            )
+        );
+    }
+
+    #[test]
+    fn test_required_members_are_kept() {
+        let schema = json!({
+            "properties": {
+                "port": { "default": 8080 },
+                "host": { "default": "localhost" },
+                "tls": { "$ref": "#/$defs/tls" }
+            },
+            "required": ["port"],
+            "$defs": {
+                "tls": {
+                    "allOf": [{ "required": ["enabled"] }],
+                    "properties": { "enabled": { "default": false }, "port": { "default": 443 } }
+                }
+            }
+        });
+        let text =
+            r#"{ "port": 8080, "host": "localhost", "tls": { "enabled": false, "port": 443 } }"#;
+        assert_eq!(
+            cleaned_with(&schema, text, None),
+            (r#"{ "port": 8080, "tls": { "enabled": false } }"#.into(), 2)
+        );
+    }
+}
@@ -0,0 +1,231 @@
+use crate::authoring::is_meta_schema_url;
+use crate::authoring::refactor::{definitions_container_conversion, tuple_items_to_prefix_items};
+use crate::cleanup::{default_removals, object_at};
+use crate::config::ServerConfig;
+use crate::document::DocumentStore;
+use crate::offsets::{byte_range_to_lsp_range, lsp_position_to_byte_offset};
+use crate::position::PathSegment;
+use crate::schema::{SchemaCache, SchemaNode};
+use crate::span::{parse_spans, path_to_pointer, pointer_to_path, ByteEdit};
+use crate::suggest::{Suggestion, SuggestionTarget};
+use crate::tolerant::Dialect;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, CodeActionParams, CodeActionResponse,
+    Diagnostic, Position, TextEdit, Url, WorkspaceEdit,
+};
+use tracing::debug;
+
+pub async fn handle_code_action(
+    documents: &Arc<DocumentStore>,
+    schema_cache: &Arc<SchemaCache>,
+    config: &ServerConfig,
+    params: CodeActionParams,
+) -> Option<CodeActionResponse> {
+    let uri = &params.text_document.uri;
+    let line = params.range.start.line;
+    let text = documents.get_instance_text(uri, line)?;
+    let schema_url = documents.get_schema_url(uri)?;
+
+    // A JSON Lines diagnostic's instance path is into its own line.
+    let diagnostics: Vec<Diagnostic> = match documents.get_dialect(uri) {
+        Dialect::JsonLines => params
+            .context
+            .diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.range.start.line == line)
+            .cloned()
+            .collect(),
+        Dialect::Json | Dialect::Json5 => params.context.diagnostics.clone(),
+    };
+    let mut actions = suggestion_actions(uri, &text, &diagnostics);
+
+    if is_meta_schema_url(&schema_url) {
+        let spans = parse_spans(&text)?;
+        if let Some((from, to, edits)) = definitions_container_conversion(&spans) {
+            actions.push(refactor_action(
+                format!("Convert `{from}` to `{to}`"),
+                uri,
+                &text,
+                edits,
+            ));
+        }
+        if let Some(edits) = tuple_items_to_prefix_items(&spans) {
+            actions.push(refactor_action(
+                "Convert tuple-style `items` to `prefixItems`".into(),
+                uri,
+                &text,
+                edits,
+            ));
+        }
+    } else if let Ok(schema) = schema_cache.get_or_fetch(&schema_url).await {
+        let node = SchemaNode::new(&schema, &schema).with_limits(config.schema_limits);
+        actions.extend(default_removal_actions(
+            uri,
+            &text,
+            &node,
+            params.range.start,
+        ));
+    }
+
+    debug!("Code actions for {uri}: {}", actions.len());
+    if actions.is_empty() {
+        return None;
+    }
+    Some(actions)
+}
+
+/// Quick fixes applying the "did you mean" suggestions carried in `diagnostics`' data.
+fn suggestion_actions(
+    uri: &Url,
+    text: &str,
+    diagnostics: &[Diagnostic],
+) -> Vec<CodeActionOrCommand> {
+    let Some(root) = parse_spans(text) else {
+        return Vec::new();
+    };
+    let mut actions = Vec::new();
+    for diagnostic in diagnostics {
+        let Some(suggestions) = diagnostic
+            .data
+            .as_ref()
+            .and_then(|data| data.get("suggestions"))
+            .and_then(|s| serde_json::from_value::<Vec<Suggestion>>(s.clone()).ok())
+        else {
+            continue;
+        };
+        for suggestion in suggestions {
+            let Some(path) = pointer_to_path(&suggestion.instance_path, &root) else {
+                continue;
+            };
+            let (edit, title) = match (suggestion.target, path.split_last()) {
+                (SuggestionTarget::Key, Some((PathSegment::Key(key), parent))) => {
+                    let Some(member) = root.find(parent).and_then(|object| object.member(key))
+                    else {
+                        continue;
+                    };
+                    let Some(name) = suggestion.replacement.as_str() else {
+                        continue;
+                    };
+                    let edit = ByteEdit {
+                        start: member.key_start,
+                        end: member.key_end,
+                        new_text: Value::from(name).to_string(),
+                    };
+                    (edit, format!("Rename `{key}` to `{name}`"))
+                }
+                (SuggestionTarget::Value, _) => {
+                    let Some(node) = root.find(&path) else {
+                        continue;
+                    };
+                    let replacement = suggestion.replacement.to_string();
+                    let edit = ByteEdit {
+                        start: node.start,
+                        end: node.end,
+                        new_text: replacement.clone(),
+                    };
+                    (edit, format!("Replace with `{replacement}`"))
+                }
+                _ => continue,
+            };
+            actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                title,
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![diagnostic.clone()]),
+                edit: Some(workspace_edit(uri, text, vec![edit])),
+                is_preferred: Some(true),
+                ..Default::default()
+            }));
+        }
+    }
+    actions
+}
+
+/// "Remove properties equal to their schema defaults", for the whole document and for the
+/// object at `position` alone.
+fn default_removal_actions(
+    uri: &Url,
+    text: &str,
+    schema: &SchemaNode,
+    position: Position,
+) -> Vec<CodeActionOrCommand> {
+    let Some(root) = parse_spans(text) else {
+        return Vec::new();
+    };
+    let mut actions = Vec::new();
+
+    let (document_edits, removed) = default_removals(text, &root, &mut Vec::new(), schema, true);
+    if removed > 0 {
+        actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+            title: format!(
+                "Remove {removed} {} equal to the schema default",
+                noun(removed)
+            ),
+            kind: Some(CodeActionKind::SOURCE),
+            edit: Some(workspace_edit(uri, text, document_edits.clone())),
+            ..Default::default()
+        }));
+    }
+
+    let offset = lsp_position_to_byte_offset(text, position.line, position.character);
+    if let Some((object, mut path)) = offset.and_then(|offset| object_at(&root, offset)) {
+        let (edits, removed) = default_removals(text, object, &mut path, schema, false);
+        if removed > 0 && edits != document_edits {
+            let target = if path.is_empty() {
+                "the root object".to_owned()
+            } else {
+                format!("`{}`", path_to_pointer(&path))
+            };
+            actions.push(refactor_action(
+                format!(
+                    "Remove {removed} default-valued {} from {target}",
+                    noun(removed)
+                ),
+                uri,
+                text,
+                edits,
+            ));
+        }
+    }
+    actions
+}
+
+fn noun(count: usize) -> &'static str {
+    if count == 1 {
+        "property"
+    } else {
+        "properties"
+    }
+}
+
+fn refactor_action(
+    title: String,
+    uri: &Url,
+    text: &str,
+    edits: Vec<ByteEdit>,
+) -> CodeActionOrCommand {
+    CodeActionOrCommand::CodeAction(CodeAction {
+        title,
+        kind: Some(CodeActionKind::REFACTOR_REWRITE),
+        edit: Some(workspace_edit(uri, text, edits)),
+        ..Default::default()
+    })
+}
+
+/// Convert byte edits against `text` into a single-document `WorkspaceEdit`.
+pub fn workspace_edit(uri: &Url, text: &str, edits: Vec<ByteEdit>) -> WorkspaceEdit {
+    let text_edits = edits
+        .into_iter()
+        .map(|edit| TextEdit {
+            range: byte_range_to_lsp_range(text, edit.start, edit.end),
+            new_text: edit.new_text,
+        })
+        .collect();
+
+    WorkspaceEdit {
+        changes: Some(HashMap::from([(uri.clone(), text_edits)])),
+        ..Default::default()
+    }
+}
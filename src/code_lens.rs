@@ -0,0 +1,113 @@
+use crate::config::ServerConfig;
+use crate::document::DocumentStore;
+use crate::offsets::byte_offset_to_lsp_pos;
+use crate::span::{escape_pointer_token, parse_spans, SpanKind};
+use dashmap::DashMap;
+use serde_json::json;
+use std::sync::Arc;
+use tower_lsp::lsp_types::{
+    CodeLens, CodeLensParams, Command, Diagnostic, DiagnosticSeverity, Position, Range, Url,
+};
+use tracing::debug;
+
+/// Command run by the "Validate this subtree" lens: `[uri, pointer]`.
+pub const VALIDATE_SUBTREE_COMMAND: &str = "json-ls.validateSubtree";
+
+/// Top-level members shorter than this many lines don't get a lens — they fit on screen.
+const MIN_LENS_LINES: u32 = 20;
+
+/// Offer a "Validate this subtree" lens above each large top-level object/array member,
+/// titled with the number of errors inside it among the diagnostics last `published` for
+/// the document.
+pub fn handle_code_lens(
+    documents: &Arc<DocumentStore>,
+    published: &DashMap<Url, Vec<Diagnostic>>,
+    config: &ServerConfig,
+    params: CodeLensParams,
+) -> Option<Vec<CodeLens>> {
+    let uri = &params.text_document.uri;
+    let text = documents.get_text(uri)?;
+    documents.get_schema_url(uri)?;
+    // Lens titles come from validation, which large documents skip.
+    if !config.validates_size(text.len()) {
+        return None;
+    }
+    let diagnostics = published.get(uri)?;
+
+    let root = parse_spans(&text)?;
+
+    let mut anchors = Vec::new();
+    let mut pointers = Vec::new();
+    for member in root.members() {
+        if !matches!(member.value.kind, SpanKind::Object(_) | SpanKind::Array(_)) {
+            continue;
+        }
+        let (start_line, start_char) = byte_offset_to_lsp_pos(&text, member.key_start);
+        let (end_line, _) = byte_offset_to_lsp_pos(&text, member.value.end);
+        if end_line - start_line + 1 < MIN_LENS_LINES {
+            continue;
+        }
+        anchors.push(Position {
+            line: start_line,
+            character: start_char,
+        });
+        pointers.push(format!("/{}", escape_pointer_token(&member.key)));
+    }
+
+    if pointers.is_empty() {
+        return None;
+    }
+
+    let counts = pointers
+        .iter()
+        .map(|pointer| error_count(&diagnostics, pointer))
+        .collect::<Vec<_>>();
+    debug!("Code lens: {} subtree(s) in {uri}", pointers.len());
+
+    let lenses = anchors
+        .into_iter()
+        .zip(pointers)
+        .zip(counts)
+        .map(|((anchor, pointer), count)| CodeLens {
+            range: Range {
+                start: anchor,
+                end: anchor,
+            },
+            command: Some(Command {
+                title: subtree_title(count),
+                command: VALIDATE_SUBTREE_COMMAND.into(),
+                arguments: Some(vec![json!(uri), json!(pointer)]),
+            }),
+            data: None,
+        })
+        .collect();
+
+    Some(lenses)
+}
+
+/// Lens / message text for a subtree with `count` errors.
+pub fn subtree_title(count: usize) -> String {
+    match count {
+        0 => "Validate this subtree: no errors".into(),
+        1 => "Validate this subtree: 1 error".into(),
+        n => format!("Validate this subtree: {n} errors"),
+    }
+}
+
+/// The errors among `diagnostics` located at or below the JSON Pointer `pointer`.
+fn error_count(diagnostics: &[Diagnostic], pointer: &str) -> usize {
+    diagnostics
+        .iter()
+        .filter(|d| d.severity == Some(DiagnosticSeverity::ERROR))
+        .filter_map(|d| d.data.as_ref()?["instancePath"].as_str())
+        .filter(|path| is_under_pointer(path, pointer))
+        .count()
+}
+
+/// True if the JSON Pointer `path` equals `pointer` or lies beneath it.
+fn is_under_pointer(path: &str, pointer: &str) -> bool {
+    path == pointer
+        || path
+            .strip_prefix(pointer)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
@@ -1,72 +1,154 @@
+use crate::authoring::keywords::keyword_values;
+use crate::authoring::snippets::{new_def, snippets_for};
+use crate::authoring::{is_meta_schema_url, keyword_at};
+use crate::config::{MarkdownConfig, ServerConfig};
 use crate::document::DocumentStore;
+use crate::markdown::render_description;
+use crate::offsets::{byte_range_to_lsp_range, lsp_position_to_byte_offset};
+use crate::on_type::{completion_commas, CompletionCommas};
+use crate::path_completion::{path_completions, path_input, wants_paths, PathInput};
 use crate::position::{position_to_context, PathSegment, PositionContext};
+use crate::schema::navigator::StringConstraints;
 use crate::schema::{SchemaCache, SchemaNode};
+use crate::span::{is_identifier, is_identifier_byte, parse_spans};
+use serde_json::Value;
 use std::sync::Arc;
 use tower_lsp::lsp_types::{
     CompletionItem, CompletionItemKind, CompletionParams, CompletionResponse, Documentation,
-    InsertTextFormat, MarkupContent, MarkupKind,
+    InsertTextFormat, MarkupContent, MarkupKind, TextEdit,
 };
 use tracing::debug;
 
 pub async fn handle_completion(
     documents: &Arc<DocumentStore>,
     schema_cache: &Arc<SchemaCache>,
+    config: &ServerConfig,
     params: CompletionParams,
 ) -> Option<CompletionResponse> {
     let uri = &params.text_document_position.text_document.uri;
     let pos = params.text_document_position.position;
 
-    let text = documents.get_text(uri)?;
+    let text = documents.get_instance_text(uri, pos.line)?;
     let schema_url = documents.get_schema_url(uri)?;
 
     let context = position_to_context(&text, pos.line, pos.character);
     debug!("Completion context: {context:?}");
 
-    let schema_value = schema_cache.get_or_fetch(&schema_url).await.ok()?;
-    let root_node = SchemaNode::new(&schema_value, &schema_value);
+    // Schema-authoring path: the document is itself a schema, and the cursor is on the
+    // value of a keyword with a fixed vocabulary (`type`, `format`, `$schema`).
+    if is_meta_schema_url(&schema_url) {
+        let items = keyword_value_completions(&context);
+        if !items.is_empty() {
+            return Some(CompletionResponse::Array(items));
+        }
+    }
+
+    let path_input = match context {
+        PositionContext::Value { .. } => path_input(uri, &text, pos.line, pos.character),
+        _ => None,
+    };
+    let mut items = schema_completions(
+        &context,
+        path_input.as_ref(),
+        schema_cache,
+        config,
+        &schema_url,
+    )
+    .await
+    .unwrap_or_default();
+    if let Some(commas) = member_commas(&text, pos.line, pos.character, &context) {
+        add_member_commas(&mut items, &text, commas);
+    }
 
-    let items = match &context {
-        PositionContext::Key { path } => {
+    if is_meta_schema_url(&schema_url) {
+        let cursor = lsp_position_to_byte_offset(&text, pos.line, pos.character);
+        items.extend(schema_snippet_completions(&context, &text, cursor));
+    }
+
+    if items.is_empty() {
+        return None;
+    }
+
+    Some(CompletionResponse::Array(items))
+}
+
+/// Property-name / value completions driven by the document's schema.
+async fn schema_completions(
+    context: &PositionContext,
+    path_input: Option<&PathInput>,
+    schema_cache: &Arc<SchemaCache>,
+    config: &ServerConfig,
+    schema_url: &str,
+) -> Option<Vec<CompletionItem>> {
+    let schema_value = schema_cache.get_or_fetch(schema_url).await.ok()?;
+    let root_node = SchemaNode::new(&schema_value, &schema_value).with_limits(config.schema_limits);
+    let memo = schema_cache
+        .property_memo(schema_url, &schema_value, config.schema_limits)
+        .await;
+
+    let items = match context {
+        PositionContext::Key { path, quote } => {
             // Cursor is inside an existing quoted key (e.g. between autopairs "").
             // `path` now includes the key itself; drop the last segment to get the parent.
             // insert_text must NOT include a leading '"' — the opening quote is already there.
+            // An unquoted JSON5 key is replaced by the bare name.
             let parent_path = if path.is_empty() {
                 &[][..]
             } else {
                 &path[..path.len() - 1]
             };
             let parent_node = if parent_path.is_empty() {
-                SchemaNode::new(&schema_value, &schema_value)
+                SchemaNode::new(&schema_value, &schema_value).with_limits(config.schema_limits)
             } else {
                 root_node.navigate(parent_path)?
             };
-            let names = parent_node.property_names();
+            let names = parent_node.property_names_memoized(&memo);
             debug!(
                 "Completion Key: found {} property names at parent {parent_path:?}",
                 names.len()
             );
-            property_completions_from_names(names, &parent_node, false)
+            let insert = match quote {
+                Some(quote) => KeyInsert::Quoted(*quote),
+                None => KeyInsert::Bare,
+            };
+            property_completions_from_names(
+                &names,
+                &parent_node,
+                insert,
+                schema_url,
+                &config.markdown,
+            )
         }
 
         PositionContext::KeyStart { path } => {
             // Cursor is at the opening '"' of a key — include it in insert_text.
             let parent_node = if path.is_empty() {
-                SchemaNode::new(&schema_value, &schema_value)
+                SchemaNode::new(&schema_value, &schema_value).with_limits(config.schema_limits)
             } else {
                 root_node.navigate(path)?
             };
-            let names = parent_node.property_names();
+            let names = parent_node.property_names_memoized(&memo);
             debug!(
                 "Completion KeyStart: found {} property names at path {path:?}",
                 names.len()
             );
-            property_completions_from_names(names, &parent_node, true)
+            property_completions_from_names(
+                &names,
+                &parent_node,
+                KeyInsert::Start,
+                schema_url,
+                &config.markdown,
+            )
         }
 
         PositionContext::Value { path } | PositionContext::ValueStart { path } => {
             // Suggest enum values or type-based snippets for the value position
             let node = root_node.navigate(path)?;
-            value_completions(&node)
+            let mut items = value_completions(&node);
+            if let Some(input) = path_input.filter(|_| wants_paths(&node, path)) {
+                items.extend(path_completions(input, &config.path_completion));
+            }
+            items
         }
 
         PositionContext::Unknown => {
@@ -75,20 +157,29 @@ pub async fn handle_completion(
         }
     };
 
-    if items.is_empty() {
-        return None;
-    }
+    Some(items)
+}
 
-    Some(CompletionResponse::Array(items))
+/// Where a completed property name goes, which decides the quotes it is inserted with.
+#[derive(Debug, Clone, Copy)]
+enum KeyInsert {
+    /// At the opening quote of a key.
+    Start,
+    /// After the opening quote of a key quoted with this character.
+    Quoted(char),
+    /// In an unquoted JSON5 key.
+    Bare,
 }
 
 fn property_completions_from_names(
-    names: Vec<String>,
+    names: &[String],
     node: &SchemaNode,
-    include_leading_quote: bool,
+    insert: KeyInsert,
+    schema_url: &str,
+    markdown: &MarkdownConfig,
 ) -> Vec<CompletionItem> {
     names
-        .into_iter()
+        .iter()
         .map(|name| {
             let info = node
                 .navigate(&[PathSegment::Key(name.clone())])
@@ -99,18 +190,19 @@ fn property_completions_from_names(
                 i.description.map(|d| {
                     Documentation::MarkupContent(MarkupContent {
                         kind: MarkupKind::Markdown,
-                        value: d,
+                        value: render_description(&d, schema_url, markdown),
                     })
                 })
             });
 
             // When cursor is inside existing quotes (Key context), the opening '"' is
             // already in the buffer — autopairs inserts it. Only add it when the cursor
-            // sits at the quote itself (KeyStart context).
-            let insert_text = if include_leading_quote {
-                format!("\"{name}\": ")
-            } else {
-                format!("{name}\": ")
+            // sits at the quote itself (KeyStart context). A bare key stays bare when the
+            // name is an identifier.
+            let insert_text = match insert {
+                KeyInsert::Quoted(quote) => format!("{name}{quote}: "),
+                KeyInsert::Bare if is_identifier(name) => format!("{name}: "),
+                KeyInsert::Start | KeyInsert::Bare => format!("\"{name}\": "),
             };
 
             CompletionItem {
@@ -126,6 +218,78 @@ fn property_completions_from_names(
         .collect()
 }
 
+/// Commas needed around a property name completed at the cursor, if it is in a key.
+fn member_commas(
+    text: &str,
+    line: u32,
+    character: u32,
+    context: &PositionContext,
+) -> Option<CompletionCommas> {
+    let offset = lsp_position_to_byte_offset(text, line, character)?;
+    let (key_start, key_end) = match context {
+        // Inside quotes: the key runs from its opening quote through the closing one.
+        PositionContext::Key {
+            quote: Some(quote), ..
+        } => {
+            let start = text[..offset].rfind(*quote)?;
+            let rest = &text[offset..];
+            let end = match rest.find([*quote, '\n', ':', ',', '}']) {
+                Some(i) if rest[i..].starts_with(*quote) => offset + i + 1,
+                Some(i) => offset + i,
+                None => text.len(),
+            };
+            (start, end)
+        }
+        // An unquoted key is the run of identifier characters around the cursor.
+        PositionContext::Key { quote: None, .. } => {
+            let bytes = text.as_bytes();
+            let start = bytes[..offset]
+                .iter()
+                .rposition(|&b| !is_identifier_byte(b))
+                .map_or(0, |i| i + 1);
+            let end = bytes[offset..]
+                .iter()
+                .position(|&b| !is_identifier_byte(b))
+                .map_or(text.len(), |i| offset + i);
+            (start, end)
+        }
+        PositionContext::KeyStart { .. } => (offset, offset),
+        _ => return None,
+    };
+    Some(completion_commas(text, key_start, key_end))
+}
+
+/// End property-name items with `,` when a sibling follows, and add the comma missing
+/// after the previous member.
+fn add_member_commas(items: &mut [CompletionItem], text: &str, commas: CompletionCommas) {
+    let previous = commas.after_previous.map(|at| TextEdit {
+        range: byte_range_to_lsp_range(text, at, at),
+        new_text: ",".into(),
+    });
+    for item in items
+        .iter_mut()
+        .filter(|item| item.kind == Some(CompletionItemKind::FIELD))
+    {
+        if commas.trailing {
+            if let Some(insert_text) = &item.insert_text {
+                item.insert_text = Some(format!("{}$0,", escape_snippet(insert_text)));
+                item.insert_text_format = Some(InsertTextFormat::SNIPPET);
+            }
+        }
+        if let Some(edit) = &previous {
+            item.additional_text_edits
+                .get_or_insert_with(Vec::new)
+                .push(edit.clone());
+        }
+    }
+}
+
+fn escape_snippet(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('$', "\\$")
+        .replace('}', "\\}")
+}
+
 fn value_completions(node: &SchemaNode) -> Vec<CompletionItem> {
     let enum_values = node.enum_values();
     if !enum_values.is_empty() {
@@ -141,15 +305,130 @@ fn value_completions(node: &SchemaNode) -> Vec<CompletionItem> {
             .collect();
     }
 
-    // Type-based snippets
+    // Examples the value's own constraints accept, then a type-based snippet.
+    let mut items: Vec<CompletionItem> = node
+        .matching_examples()
+        .into_iter()
+        .map(|example| {
+            let json = Value::String(example.to_owned()).to_string();
+            CompletionItem {
+                label: json.clone(),
+                kind: Some(CompletionItemKind::VALUE),
+                detail: Some("example".into()),
+                insert_text: Some(json),
+                insert_text_format: Some(InsertTextFormat::PLAIN_TEXT),
+                ..Default::default()
+            }
+        })
+        .collect();
+
     match node.schema_type() {
-        Some("boolean") => vec![make_snippet("true", "true"), make_snippet("false", "false")],
-        Some("null") => vec![make_snippet("null", "null")],
-        Some("array") => vec![make_snippet("[]", "[$1]")],
-        Some("object") => vec![make_snippet("{}", "{$1}")],
-        Some("string") => vec![make_snippet("\"\"", "\"$1\"")],
-        _ => vec![],
+        Some("boolean") => {
+            items.extend([make_snippet("true", "true"), make_snippet("false", "false")])
+        }
+        Some("null") => items.push(make_snippet("null", "null")),
+        Some("array") => items.push(make_snippet("[]", "[$1]")),
+        Some("object") => items.push(make_snippet("{}", "{$1}")),
+        Some("string") => items.push(CompletionItem {
+            detail: constraints_detail(&node.string_constraints()),
+            ..make_snippet("\"\"", "\"$1\"")
+        }),
+        _ => {}
     }
+    items
+}
+
+/// "pattern ^[a-z]+$, 3 to 20 characters": what a string value must satisfy.
+fn constraints_detail(constraints: &StringConstraints) -> Option<String> {
+    let parts: Vec<String> = constraints
+        .pattern
+        .map(|pattern| format!("pattern {pattern}"))
+        .into_iter()
+        .chain(constraints.length())
+        .collect();
+    (!parts.is_empty()).then(|| parts.join(", "))
+}
+
+fn keyword_value_completions(context: &PositionContext) -> Vec<CompletionItem> {
+    // Inside an existing string only the bare value is inserted; at the value start we
+    // also supply the quotes.
+    let (path, quoted) = match context {
+        PositionContext::Value { path } => (path, false),
+        PositionContext::ValueStart { path } => (path, true),
+        _ => return vec![],
+    };
+    let Some(keyword) = keyword_at(path) else {
+        return vec![];
+    };
+
+    keyword_values(keyword)
+        .into_iter()
+        .map(|kv| CompletionItem {
+            label: kv.value.to_owned(),
+            kind: Some(CompletionItemKind::ENUM_MEMBER),
+            detail: kv.detail.map(str::to_owned),
+            insert_text: Some(if quoted {
+                format!("\"{}\"", kv.value)
+            } else {
+                kv.value.to_owned()
+            }),
+            insert_text_format: Some(InsertTextFormat::PLAIN_TEXT),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Multi-line schema construct snippets at key positions in a schema document, with the
+/// cursor at byte `cursor`.
+fn schema_snippet_completions(
+    context: &PositionContext,
+    text: &str,
+    cursor: Option<usize>,
+) -> Vec<CompletionItem> {
+    let (parent_path, include_leading_quote) = match context {
+        PositionContext::Key {
+            path,
+            quote: Some('"'),
+        } => (&path[..path.len().saturating_sub(1)], false),
+        // The snippets close the key with `"`, so a bare key gets both quotes.
+        PositionContext::Key { path, quote: None } => (&path[..path.len().saturating_sub(1)], true),
+        PositionContext::KeyStart { path } => (&path[..], true),
+        _ => return vec![],
+    };
+    let spans = parse_spans(text);
+    let new_def = spans
+        .as_ref()
+        .zip(cursor)
+        .map(|(spans, cursor)| new_def(text, spans, cursor));
+
+    snippets_for(parent_path)
+        .map(|snippet| {
+            let (body, edit) = match (&new_def, snippet.creates_def) {
+                (Some(new_def), true) => (new_def.body.clone(), new_def.edit.clone()),
+                _ => (snippet.body.to_owned(), None),
+            };
+            let additional_text_edits = edit.map(|edit| {
+                vec![TextEdit {
+                    range: byte_range_to_lsp_range(text, edit.start, edit.end),
+                    new_text: edit.new_text,
+                }]
+            });
+
+            CompletionItem {
+                label: snippet.label.to_owned(),
+                kind: Some(CompletionItemKind::SNIPPET),
+                detail: Some(snippet.detail.to_owned()),
+                insert_text: Some(if include_leading_quote {
+                    format!("\"{body}")
+                } else {
+                    body
+                }),
+                insert_text_format: Some(InsertTextFormat::SNIPPET),
+                additional_text_edits,
+                ..Default::default()
+            }
+        })
+        .collect()
 }
 
 fn make_snippet(label: &str, insert_text: &str) -> CompletionItem {
@@ -0,0 +1,330 @@
+//! Validation of content embedded in JSON strings, driven by the `contentEncoding`,
+//! `contentMediaType` and `contentSchema` keywords. Problems are reported on the range of
+//! the outer string, since the embedded text has no positions of its own.
+
+use crate::config::SchemaLimits;
+use crate::offsets::byte_range_to_lsp_range;
+use crate::position::PathSegment;
+use crate::schema::SchemaNode;
+use crate::span::SpanNode;
+use base64::Engine;
+use jsonschema::Validator;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString};
+
+/// Embedded-content problems of every string in `instance` whose schema declares content,
+/// found without the document text so they can be computed within the validation budget.
+/// Each `contentSchema` is compiled once per call; navigation stays within `limits`.
+pub fn content_problems(
+    instance: &Value,
+    schema: &Value,
+    limits: SchemaLimits,
+) -> Vec<ContentProblem> {
+    if !has_content_keywords(schema) {
+        return Vec::new();
+    }
+    let root = SchemaNode::new(schema, schema).with_limits(limits);
+    let mut validators = HashMap::new();
+    let mut problems = Vec::new();
+    let mut path = Vec::new();
+    walk(instance, &mut path, &mut |path, content| {
+        let Some(node) = root.navigate(path) else {
+            return;
+        };
+        problems.extend(
+            check_content(content, &node, schema, &mut validators)
+                .into_iter()
+                .map(|(code, message)| ContentProblem {
+                    path: path.to_vec(),
+                    code,
+                    message,
+                }),
+        );
+    });
+    problems
+}
+
+/// Diagnostics for `problems`, each on the range of its outer string in `text`.
+pub fn content_diagnostics(
+    text: &str,
+    spans: &SpanNode,
+    problems: Vec<ContentProblem>,
+) -> Vec<Diagnostic> {
+    problems
+        .into_iter()
+        .filter_map(|problem| {
+            let span = spans.find(&problem.path)?;
+            Some(Diagnostic {
+                range: byte_range_to_lsp_range(text, span.start, span.end),
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: Some(NumberOrString::String(problem.code.into())),
+                source: Some("json-ls".into()),
+                message: problem.message,
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+/// True if any subschema of `schema` has `contentEncoding` or `contentMediaType`, the
+/// keywords that make a string's content checked.
+fn has_content_keywords(schema: &Value) -> bool {
+    match schema {
+        Value::Object(map) => {
+            map.contains_key("contentEncoding")
+                || map.contains_key("contentMediaType")
+                || map.values().any(has_content_keywords)
+        }
+        Value::Array(items) => items.iter().any(has_content_keywords),
+        _ => false,
+    }
+}
+
+/// Visit every string value in `value` together with its path.
+fn walk(value: &Value, path: &mut Vec<PathSegment>, visit: &mut dyn FnMut(&[PathSegment], &str)) {
+    match value {
+        Value::String(s) => visit(path, s),
+        Value::Object(map) => {
+            for (key, child) in map {
+                path.push(PathSegment::Key(key.clone()));
+                walk(child, path, visit);
+                path.pop();
+            }
+        }
+        Value::Array(items) => {
+            for (idx, child) in items.iter().enumerate() {
+                path.push(PathSegment::Index(idx));
+                walk(child, path, visit);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// A string whose embedded content does not decode, parse or match its `contentSchema`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentProblem {
+    path: Vec<PathSegment>,
+    code: &'static str,
+    message: String,
+}
+
+/// Compiled `contentSchema`s, keyed by the keyword's address in the schema; `None` for one
+/// that does not compile.
+type Validators = HashMap<usize, Option<Validator>>;
+
+/// Decode / parse / validate `content` according to the content keywords on `node`: one
+/// `(code, message)` problem for undecodable content, else one per `contentSchema` error.
+fn check_content(
+    content: &str,
+    node: &SchemaNode,
+    root: &Value,
+    validators: &mut Validators,
+) -> Vec<(&'static str, String)> {
+    let encoding = node.keyword("contentEncoding").and_then(Value::as_str);
+    let media_type = node.keyword("contentMediaType").and_then(Value::as_str);
+
+    let decoded = match encoding {
+        Some(encoding) if encoding.eq_ignore_ascii_case("base64") => {
+            match base64::engine::general_purpose::STANDARD.decode(content.trim()) {
+                Ok(bytes) => String::from_utf8(bytes).ok(),
+                Err(e) => {
+                    return vec![("content-encoding", format!("Invalid base64 content: {e}"))];
+                }
+            }
+        }
+        _ => Some(content.to_owned()),
+    };
+
+    if !media_type.is_some_and(is_json_media_type) {
+        return Vec::new();
+    }
+    let Some(decoded) = decoded else {
+        return vec![(
+            "content-media-type",
+            "Decoded content is not valid UTF-8 JSON".into(),
+        )];
+    };
+
+    let embedded: Value = match serde_json::from_str(&decoded) {
+        Ok(v) => v,
+        Err(e) => {
+            return vec![(
+                "content-media-type",
+                format!("Embedded content is not valid JSON: {e}"),
+            )];
+        }
+    };
+
+    let Some(content_schema) = node.keyword("contentSchema") else {
+        return Vec::new();
+    };
+    let key = content_schema as *const Value as usize;
+    let validator = validators.entry(key).or_insert_with(|| {
+        jsonschema::validator_for(&with_root_definitions(content_schema, root)).ok()
+    });
+    let Some(validator) = validator else {
+        return Vec::new();
+    };
+    validator
+        .iter_errors(&embedded)
+        .map(|error| {
+            let location = error.instance_path().to_string();
+            let location = if location.is_empty() { "/" } else { &location };
+            (
+                "content-schema",
+                format!("Embedded JSON at `{location}` does not match contentSchema: {error}"),
+            )
+        })
+        .collect()
+}
+
+/// `application/json` and structured-syntax `+json` types.
+fn is_json_media_type(media_type: &str) -> bool {
+    let essence = media_type.split(';').next().unwrap_or("").trim();
+    essence.eq_ignore_ascii_case("application/json") || essence.ends_with("+json")
+}
+
+/// Copy the enclosing schema's `$defs` / `definitions` into `content_schema`, so local
+/// `$ref`s written against the outer document still resolve when it is compiled alone.
+fn with_root_definitions(content_schema: &Value, root: &Value) -> Value {
+    let Value::Object(schema) = content_schema else {
+        return content_schema.clone();
+    };
+    let mut schema: Map<String, Value> = schema.clone();
+    for container in ["$defs", "definitions"] {
+        if let Some(defs) = root.get(container) {
+            schema.entry(container).or_insert_with(|| defs.clone());
+        }
+    }
+    Value::Object(schema)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::parse_spans;
+    use serde_json::json;
+
+    fn codes(text: &str, schema: &Value) -> Vec<String> {
+        let spans = parse_spans(text).unwrap();
+        let instance: Value = serde_json::from_str(text).unwrap();
+        content_diagnostics(
+            text,
+            &spans,
+            content_problems(&instance, schema, SchemaLimits::default()),
+        )
+        .into_iter()
+        .map(|d| match d.code {
+            Some(NumberOrString::String(code)) => code,
+            _ => String::new(),
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_embedded_json_validated_against_content_schema() {
+        let schema = json!({
+            "properties": {
+                "payload": {
+                    "type": "string",
+                    "contentMediaType": "application/json",
+                    "contentSchema": { "$ref": "#/$defs/Payload" }
+                },
+                "blob": { "type": "string", "contentEncoding": "base64" }
+            },
+            "$defs": {
+                "Payload": { "type": "object", "required": ["id"] }
+            }
+        });
+
+        assert!(codes(r#"{"payload": "{\"id\": 1}"}"#, &schema).is_empty());
+        assert_eq!(
+            codes(r#"{"payload": "{\"x\": 1}"}"#, &schema),
+            ["content-schema"]
+        );
+        assert_eq!(
+            codes(r#"{"payload": "{oops"}"#, &schema),
+            ["content-media-type"]
+        );
+        assert!(codes(r#"{"blob": "aGVsbG8="}"#, &schema).is_empty());
+        assert_eq!(
+            codes(r#"{"blob": "not base64!"}"#, &schema),
+            ["content-encoding"]
+        );
+    }
+
+    #[test]
+    fn test_base64_encoded_json() {
+        let schema = json!({
+            "properties": {
+                "config": {
+                    "contentEncoding": "base64",
+                    "contentMediaType": "application/json",
+                    "contentSchema": { "type": "object" }
+                }
+            }
+        });
+        // `{"a":1}` and `[1]`
+        assert!(codes(r#"{"config": "eyJhIjoxfQ=="}"#, &schema).is_empty());
+        assert_eq!(codes(r#"{"config": "WzFd"}"#, &schema), ["content-schema"]);
+    }
+
+    #[test]
+    fn test_each_nested_error_is_a_diagnostic_on_the_outer_string() {
+        let schema = json!({
+            "properties": {
+                "payload": {
+                    "contentMediaType": "application/json",
+                    "contentSchema": {
+                        "properties": {
+                            "id": { "type": "integer" },
+                            "tags": { "type": "array" }
+                        }
+                    }
+                }
+            }
+        });
+        let text = r#"{"payload": "{\"id\": \"x\", \"tags\": 1}"}"#;
+        let spans = parse_spans(text).unwrap();
+        let instance: Value = serde_json::from_str(text).unwrap();
+        let diagnostics = content_diagnostics(
+            text,
+            &spans,
+            content_problems(&instance, &schema, SchemaLimits::default()),
+        );
+
+        assert_eq!(diagnostics.len(), 2, "got: {diagnostics:?}");
+        let outer = spans.find(&[PathSegment::Key("payload".into())]).unwrap();
+        let outer = byte_range_to_lsp_range(text, outer.start, outer.end);
+        assert!(diagnostics.iter().all(|d| d.range == outer));
+        let mut messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        messages.sort();
+        assert!(
+            messages[0].starts_with("Embedded JSON at `/id`"),
+            "got: {messages:?}"
+        );
+        assert!(
+            messages[1].starts_with("Embedded JSON at `/tags`"),
+            "got: {messages:?}"
+        );
+    }
+
+    #[test]
+    fn test_content_keywords_found_anywhere_in_schema() {
+        assert!(!has_content_keywords(&json!({
+            "properties": { "name": { "type": "string", "format": "email" } }
+        })));
+        assert!(has_content_keywords(&json!({
+            "$defs": { "Blob": { "anyOf": [{ "contentEncoding": "base64" }] } }
+        })));
+        assert!(content_problems(
+            &json!({"name": "not json"}),
+            &json!({"type": "object"}),
+            SchemaLimits::default()
+        )
+        .is_empty());
+    }
+}
@@ -0,0 +1,164 @@
+use crate::config::ServerConfig;
+use crate::document::DocumentStore;
+use crate::offsets::byte_range_to_lsp_range;
+use crate::position::{position_to_context, PositionContext};
+use crate::schema::disk_cache::url_file_name;
+use crate::schema::loader::local_path;
+use crate::schema::refs::bundle_source;
+use crate::schema::{SchemaCache, SchemaNode};
+use crate::span::{escape_pointer_token, parse_spans, SpanNode};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tower_lsp::lsp_types::{GotoDefinitionParams, GotoDefinitionResponse, Location, Url};
+use tracing::{debug, warn};
+
+/// Jump from a key (or value) in a data document to the subschema declaring it.
+pub async fn handle_goto_definition(
+    documents: &Arc<DocumentStore>,
+    schema_cache: &Arc<SchemaCache>,
+    config: &ServerConfig,
+    params: GotoDefinitionParams,
+) -> Option<GotoDefinitionResponse> {
+    let uri = &params.text_document_position_params.text_document.uri;
+    let pos = params.text_document_position_params.position;
+
+    let text = documents.get_instance_text(uri, pos.line)?;
+    let schema_url = documents.get_schema_url(uri)?;
+
+    let context = position_to_context(&text, pos.line, pos.character);
+    debug!("Definition context: {context:?}");
+
+    let path = match &context {
+        PositionContext::Key { path, .. } | PositionContext::Value { path } => path.clone(),
+        _ => return None,
+    };
+
+    let schema_value = schema_cache.get_or_fetch(&schema_url).await.ok()?;
+    let root_node = SchemaNode::new(&schema_value, &schema_value).with_limits(config.schema_limits);
+    let pointer = root_node.declaration(&path)?.pointer()?;
+
+    let (document, document_value, pointer) =
+        bundled_document(&schema_url, &schema_value, &pointer)?;
+    let (schema_file, schema_text) = schema_source(&document, document_value, config.cache_dir())?;
+    let spans = parse_spans(&schema_text)?;
+    let (start, end) = pointer_range(&spans, &pointer)?;
+
+    Some(GotoDefinitionResponse::Scalar(Location {
+        uri: Url::from_file_path(&schema_file).ok()?,
+        range: byte_range_to_lsp_range(&schema_text, start, end),
+    }))
+}
+
+/// The document a pointer into the bundle of `schema_url` falls in: its URL, its value and
+/// the pointer within it. External documents are embedded under `$defs` (see
+/// [`bundle_source`]), so their definitions are shown in their own file.
+fn bundled_document<'a>(
+    schema_url: &str,
+    bundle: &'a Value,
+    pointer: &str,
+) -> Option<(String, &'a Value, String)> {
+    let (document, pointer) = bundle_source(schema_url, pointer);
+    let value = if document == schema_url {
+        bundle
+    } else {
+        bundle.pointer(&format!("/$defs/{}", escape_pointer_token(&document)))?
+    };
+    Some((document, value, pointer))
+}
+
+/// The on-disk file and text of a schema: the file itself for local schemas, otherwise a
+/// pretty-printed copy written under the cache directory (the temp directory if none).
+fn schema_source(
+    schema_url: &str,
+    schema: &Value,
+    cache_dir: Option<PathBuf>,
+) -> Option<(PathBuf, String)> {
+    if let Some(path) = local_path(schema_url) {
+        let path = std::fs::canonicalize(path).ok()?;
+        let text = std::fs::read_to_string(&path).ok()?;
+        return Some((path, text));
+    }
+
+    let dir = cache_dir
+        .unwrap_or_else(|| std::env::temp_dir().join("json-ls"))
+        .join("schemas");
+    let path = dir.join(url_file_name(schema_url));
+    let text = serde_json::to_string_pretty(schema).ok()?;
+
+    // Rewritten only when the schema changed, so the copy an editor has open stays put.
+    if std::fs::read_to_string(&path).is_ok_and(|existing| existing == text) {
+        return Some((path, text));
+    }
+    let write = std::fs::create_dir_all(&dir).and_then(|_| std::fs::write(&path, &text));
+    if let Err(e) = write {
+        warn!("Could not write schema copy for {schema_url}: {e}");
+        return None;
+    }
+    Some((path, text))
+}
+
+/// Byte range to highlight for `pointer`: the member's key when the pointer ends at an
+/// object member, otherwise the value itself.
+fn pointer_range(spans: &SpanNode, pointer: &str) -> Option<(usize, usize)> {
+    if let Some((parent, token)) = pointer.rsplit_once('/') {
+        let key = token.replace("~1", "/").replace("~0", "~");
+        if let Some(member) = spans.find_pointer(parent).and_then(|p| p.member(&key)) {
+            return Some((member.key_start, member.key_end));
+        }
+    }
+    let node = spans.find_pointer(pointer)?;
+    Some((node.start, node.end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_pointer_into_embedded_document() {
+        let common = "https://example.com/common.json";
+        let bundle = json!({
+            "properties": { "name": { "$ref": "#/$defs/https:~1~1example.com~1common.json/$defs/Name" } },
+            "$defs": { common: { "$defs": { "Name": { "type": "string" } } } }
+        });
+        let root = "https://example.com/root.json";
+
+        let (document, value, pointer) = bundled_document(
+            root,
+            &bundle,
+            "/$defs/https:~1~1example.com~1common.json/$defs/Name",
+        )
+        .unwrap();
+        assert_eq!(document, common);
+        assert_eq!(value, &bundle["$defs"][common]);
+        assert_eq!(pointer, "/$defs/Name");
+
+        let (document, value, pointer) =
+            bundled_document(root, &bundle, "/properties/name").unwrap();
+        assert_eq!(
+            (document.as_str(), pointer.as_str()),
+            (root, "/properties/name")
+        );
+        assert_eq!(value, &bundle);
+    }
+
+    #[test]
+    fn test_remote_copy_is_stable_and_written_once() {
+        let dir = std::env::temp_dir().join(format!("json-ls-definition-{}", std::process::id()));
+        let url = "https://example.com/common.json";
+        let schema = json!({ "$defs": { "Name": { "type": "string" } } });
+
+        let (path, text) = schema_source(url, &schema, Some(dir.clone())).unwrap();
+        let written = std::fs::metadata(&path).unwrap().modified().unwrap();
+        let (again, _) = schema_source(url, &schema, Some(dir.clone())).unwrap();
+        let rewritten = std::fs::metadata(&again).unwrap().modified().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(path, dir.join("schemas").join(url_file_name(url)));
+        assert_eq!(serde_json::from_str::<Value>(&text).unwrap(), schema);
+        assert_eq!(again, path);
+        assert_eq!(rewritten, written);
+    }
+}
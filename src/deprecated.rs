@@ -0,0 +1,87 @@
+//! Warnings for members whose schema is marked `"deprecated": true`, tagged so editors
+//! strike the key through.
+
+use crate::config::ServerConfig;
+use crate::offsets::byte_range_to_lsp_range;
+use crate::position::PathSegment;
+use crate::schema::SchemaNode;
+use crate::span::{walk_objects, SpanNode};
+use serde_json::Value;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, DiagnosticTag, NumberOrString};
+
+/// One warning per deprecated member, on its key, with the schema's `deprecationMessage`
+/// when it has one.
+pub fn deprecated_diagnostics(
+    text: &str,
+    spans: &SpanNode,
+    schema: &Value,
+    config: &ServerConfig,
+) -> Vec<Diagnostic> {
+    let root = SchemaNode::new(schema, schema).with_limits(config.schema_limits);
+    let mut diagnostics = Vec::new();
+    let mut path = Vec::new();
+    walk_objects(spans, &mut path, &mut |path, members| {
+        for member in members {
+            let mut member_path = path.to_vec();
+            member_path.push(PathSegment::Key(member.key.clone()));
+            let Some(node) = root
+                .declaration(&member_path)
+                .filter(SchemaNode::is_deprecated)
+            else {
+                continue;
+            };
+            let message = match node.deprecation_message() {
+                Some(note) => format!("`{}` is deprecated: {note}", member.key),
+                None => format!("`{}` is deprecated", member.key),
+            };
+            diagnostics.push(Diagnostic {
+                range: byte_range_to_lsp_range(text, member.key_start, member.key_end),
+                severity: Some(DiagnosticSeverity::WARNING),
+                code: Some(NumberOrString::String("deprecated".into())),
+                source: Some("json-ls".into()),
+                message,
+                tags: Some(vec![DiagnosticTag::DEPRECATED]),
+                ..Default::default()
+            });
+        }
+    });
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::parse_spans;
+    use serde_json::json;
+
+    #[test]
+    fn test_deprecated_members_are_tagged() {
+        let schema = json!({
+            "properties": {
+                "legacy": { "deprecated": true, "deprecationMessage": "Use `modern`." },
+                "server": { "$ref": "#/$defs/Server", "deprecated": true },
+                "items": { "items": { "$ref": "#/$defs/Item" } }
+            },
+            "$defs": {
+                "Server": { "properties": { "port": {} } },
+                "Item": { "properties": { "old": { "deprecated": true } } }
+            }
+        });
+        let text = r#"{"legacy": 1, "server": {"port": 1}, "items": [{"old": 1}, {}]}"#;
+        let spans = parse_spans(text).unwrap();
+        let found = deprecated_diagnostics(text, &spans, &schema, &ServerConfig::default());
+
+        let messages: Vec<&str> = found.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "`legacy` is deprecated: Use `modern`.",
+                "`server` is deprecated",
+                "`old` is deprecated"
+            ]
+        );
+        assert_eq!(found[0].tags, Some(vec![DiagnosticTag::DEPRECATED]));
+        assert_eq!(found[0].range.start.character, 1);
+        assert_eq!(found[0].range.end.character, 9);
+    }
+}
@@ -1,18 +1,92 @@
-use crate::document::DocumentStore;
-use crate::schema::SchemaCache;
+use crate::authoring::keywords::keyword_reference_url;
+use crate::authoring::refs::find_broken_refs;
+use crate::authoring::{is_meta_schema_url, looks_like_schema};
+use crate::config::{
+    Aggregation, DiagnosticsConfig, FormatAssertion, SchemaKeyPrecedence, ServerConfig,
+};
+use crate::content::{content_diagnostics, content_problems, ContentProblem};
+use crate::deprecated::deprecated_diagnostics;
+use crate::document::{effective_schema_key, schema_keys, DocumentStore};
+use crate::json_lines::{instance_lines, shift_diagnostic};
+use crate::numbers::number_diagnostics;
+use crate::offsets::byte_range_to_lsp_range;
+use crate::position::PathSegment;
+use crate::schema::cycles::{cyclic_refs, schema_ref_cycle, RefCycle};
+use crate::schema::refs::schema_at_path;
+use crate::schema::source::SchemaSources;
+use crate::schema::{SchemaCache, SchemaNode};
+use crate::span::{parse_spans, pointer_to_path, SpanKind, SpanNode};
+use crate::suggest::{did_you_mean, suggestions};
+use crate::time_box::{find_slow_pattern, time_boxed, SlowPattern, Unfinished};
+use crate::tolerant::{parse_tolerant_with, Dialect};
+use crate::unknown_properties::unknown_property_diagnostics;
 use anyhow::Result;
+use jsonschema::error::ValidationErrorKind;
+use jsonschema::{ValidationOptions, Validator};
 use serde_json::Value;
 use std::sync::Arc;
-use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Position, Range, Url};
+use std::time::Duration;
+use tower_lsp::lsp_types::{
+    CodeDescription, Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location,
+    NumberOrString, Position, Range, Url,
+};
 use tracing::{debug, warn};
 
-/// Validate the document at `uri` against its declared `$schema`.
-/// Returns an empty list if no schema is found, the document cannot be parsed,
-/// or the schema cannot be fetched.
+/// URI the document's schema is registered under while one value is validated against a
+/// subschema of it (`json-ls.validateSubtree`).
+const SUBTREE_SCHEMA_URI: &str = "json-ls:subtree-schema";
+
+/// Validate the document at `uri` against its schema, with `diagnostics.rules` and
+/// `diagnostics.severity` applied and at most `max_diagnostics` kept. Syntax errors
+/// (`json-syntax`) are reported alongside the validation of whatever could be parsed; a
+/// schema that cannot be fetched (`schema-fetch-failed`) or compiled (`schema-invalid`) is
+/// reported at the `$schema` key. Returns an empty list if the document is not open or has
+/// no schema.
 pub async fn validate_document(
     uri: &Url,
     documents: &Arc<DocumentStore>,
     schema_cache: &Arc<SchemaCache>,
+    config: &ServerConfig,
+) -> Result<Vec<Diagnostic>> {
+    let diagnostics = classify_document(uri, documents, schema_cache, config).await?;
+    let diagnostics = apply_rules(diagnostics, &config.diagnostics);
+    Ok(truncate_diagnostics(diagnostics, config.max_diagnostics))
+}
+
+/// Keep the `max` most severe diagnostics (0 keeps all), plus a warning at the first
+/// dropped one saying how many were left out.
+fn truncate_diagnostics(mut diagnostics: Vec<Diagnostic>, max: usize) -> Vec<Diagnostic> {
+    if max == 0 || diagnostics.len() <= max {
+        return diagnostics;
+    }
+    diagnostics.sort_by_key(|d| d.severity.unwrap_or(DiagnosticSeverity::ERROR));
+    let suppressed = diagnostics.split_off(max);
+    let range = suppressed
+        .iter()
+        .map(|d| d.range)
+        .min_by_key(|range| range.start)
+        .unwrap_or_default();
+    diagnostics.push(Diagnostic {
+        range,
+        severity: Some(DiagnosticSeverity::WARNING),
+        code: Some(NumberOrString::String("diagnostics-truncated".into())),
+        source: Some("json-ls".into()),
+        message: format!(
+            "{} more diagnostic(s) not shown: only the first {max} are published \
+             (`max_diagnostics`)",
+            suppressed.len()
+        ),
+        ..Default::default()
+    });
+    diagnostics
+}
+
+/// Every diagnostic for the document, at the severity its check assigns.
+async fn classify_document(
+    uri: &Url,
+    documents: &Arc<DocumentStore>,
+    schema_cache: &Arc<SchemaCache>,
+    config: &ServerConfig,
 ) -> Result<Vec<Diagnostic>> {
     let Some(text) = documents.get_text(uri) else {
         return Ok(vec![]);
@@ -23,75 +97,1026 @@ pub async fn validate_document(
         return Ok(vec![]);
     };
 
-    let schema_value = match schema_cache.get_or_fetch(&schema_url).await {
-        Ok(v) => v,
-        Err(e) => {
-            warn!("Could not fetch schema {schema_url}: {e}");
-            return Ok(vec![]);
+    if !config.validates_size(text.len()) {
+        debug!("Not validating {uri}: {} bytes", text.len());
+        return Ok(vec![too_large_diagnostic(
+            &text,
+            config.max_validate_bytes,
+            config.schema_key_precedence,
+        )]);
+    }
+
+    let dialect = documents.get_dialect(uri);
+    if dialect == Dialect::JsonLines {
+        return Ok(classify_lines(uri, &text, &schema_url, schema_cache, config).await);
+    }
+
+    // Every syntax error is reported, and validation goes on with what could be read.
+    let (instance, syntax_errors) = parse_with_syntax_errors(&text, dialect);
+    let Some(instance) = instance else {
+        return Ok(syntax_errors);
+    };
+
+    let precedence = config.schema_key_precedence;
+    let mut diagnostics = syntax_errors;
+    diagnostics.extend(duplicate_schema_key_diagnostics(uri, &text, precedence));
+
+    // Schema documents: check their own `$ref` pointers, independent of the meta-schema.
+    if is_meta_schema_url(&schema_url) {
+        diagnostics.extend(broken_ref_diagnostics(&text, &instance));
+        diagnostics
+            .extend(cyclic_ref_diagnostics(uri, &text, &instance, schema_cache, config).await);
+    }
+
+    let schema_value = match usable_schema(&text, &schema_url, schema_cache, config).await {
+        Ok(schema_value) => schema_value,
+        Err(problems) => {
+            diagnostics.extend(problems);
+            return Ok(diagnostics);
         }
     };
+    let instance = Arc::new(instance);
+    match validate_instances(&schema_value, vec![instance.clone()], config).await {
+        Ok(mut findings) => diagnostics.extend(instance_diagnostics(
+            uri,
+            &text,
+            &instance,
+            findings.pop().unwrap_or_default(),
+            &schema_url,
+            &schema_value,
+            config,
+        )),
+        Err(ValidationFailure::SchemaInvalid(e)) => {
+            warn!("Could not compile schema {schema_url}: {e}");
+            diagnostics.push(schema_invalid_diagnostic(
+                &text,
+                &schema_url,
+                &e,
+                precedence,
+            ));
+        }
+        Err(ValidationFailure::TimedOut(slow)) => {
+            warn!(
+                "Validation of {uri} exceeded {} ms",
+                config.validation_timeout_ms
+            );
+            diagnostics.push(validation_timeout_diagnostic(
+                &text,
+                parse_spans(&text).as_ref(),
+                precedence,
+                config.validation_timeout_ms,
+                slow.map(|(_, slow)| slow).as_ref(),
+            ));
+        }
+        Err(ValidationFailure::Skipped) => {
+            warn!("Validation of {uri} skipped: earlier timed-out validations are still running");
+            diagnostics.push(validation_skipped_diagnostic(&text, precedence));
+        }
+    }
 
-    let instance: Value = match serde_json::from_str(&text) {
-        Ok(v) => v,
-        Err(e) => {
-            // Return a single syntax-error diagnostic
-            let (line, col) = parse_error_position(&e, &text);
-            return Ok(vec![Diagnostic {
-                range: Range {
-                    start: Position {
-                        line,
-                        character: col,
-                    },
-                    end: Position {
-                        line,
-                        character: col + 1,
-                    },
-                },
-                severity: Some(DiagnosticSeverity::ERROR),
-                code: Some(NumberOrString::String("json-syntax".into())),
-                source: Some("json-ls".into()),
-                message: format!("JSON syntax error: {e}"),
-                ..Default::default()
-            }]);
+    debug!("Validated {uri}: {} error(s)", diagnostics.len());
+
+    Ok(diagnostics)
+}
+
+/// JSON Lines: every non-blank line is read and validated on its own, as one instance. The
+/// schema is compiled once, and all lines share one validation budget.
+async fn classify_lines(
+    uri: &Url,
+    text: &str,
+    schema_url: &str,
+    schema_cache: &Arc<SchemaCache>,
+    config: &ServerConfig,
+) -> Vec<Diagnostic> {
+    let schema_value = match usable_schema(text, schema_url, schema_cache, config).await {
+        Ok(schema_value) => schema_value,
+        Err(problems) => return problems,
+    };
+    let mut diagnostics = Vec::new();
+    let mut lines = Vec::new();
+    for (line, line_text) in instance_lines(text) {
+        let (instance, found) = parse_with_syntax_errors(line_text, Dialect::JsonLines);
+        diagnostics.extend(found.into_iter().map(|d| shift_diagnostic(d, uri, line)));
+        if let Some(instance) = instance {
+            lines.push((line, line_text, Arc::new(instance)));
+        }
+    }
+
+    let instances = lines
+        .iter()
+        .map(|(.., instance)| instance.clone())
+        .collect();
+    let precedence = config.schema_key_precedence;
+    match validate_instances(&schema_value, instances, config).await {
+        Ok(findings) => {
+            for ((line, line_text, instance), findings) in lines.iter().zip(findings) {
+                let found = instance_diagnostics(
+                    uri,
+                    line_text,
+                    instance,
+                    findings,
+                    schema_url,
+                    &schema_value,
+                    config,
+                );
+                diagnostics.extend(found.into_iter().map(|d| shift_diagnostic(d, uri, *line)));
+            }
+        }
+        Err(ValidationFailure::SchemaInvalid(e)) => {
+            warn!("Could not compile schema {schema_url}: {e}");
+            diagnostics.push(schema_invalid_diagnostic(text, schema_url, &e, precedence));
+        }
+        Err(ValidationFailure::TimedOut(slow)) => {
+            warn!(
+                "Validation of {uri} exceeded {} ms",
+                config.validation_timeout_ms
+            );
+            let timeout_ms = config.validation_timeout_ms;
+            let diagnostic = match slow.and_then(|(idx, slow)| Some((lines.get(idx)?, slow))) {
+                Some(((line, line_text, _), slow)) => shift_diagnostic(
+                    validation_timeout_diagnostic(
+                        line_text,
+                        parse_spans(line_text).as_ref(),
+                        precedence,
+                        timeout_ms,
+                        Some(&slow),
+                    ),
+                    uri,
+                    *line,
+                ),
+                None => validation_timeout_diagnostic(text, None, precedence, timeout_ms, None),
+            };
+            diagnostics.push(diagnostic);
         }
+        Err(ValidationFailure::Skipped) => {
+            warn!("Validation of {uri} skipped: earlier timed-out validations are still running");
+            diagnostics.push(validation_skipped_diagnostic(text, precedence));
+        }
+    }
+    // Syntax errors were collected first; keep each line's diagnostics together.
+    diagnostics.sort_by_key(|d| d.range.start.line);
+    debug!("Validated {uri}: {} error(s)", diagnostics.len());
+    diagnostics
+}
+
+/// The value read from `text`, and an error for every syntax problem on the way.
+fn parse_with_syntax_errors(text: &str, dialect: Dialect) -> (Option<Value>, Vec<Diagnostic>) {
+    let parsed = parse_tolerant_with(text, dialect);
+    let language = match dialect {
+        Dialect::Json | Dialect::JsonLines => "JSON",
+        Dialect::Json5 => "JSON5",
     };
+    let syntax_errors = parsed
+        .errors
+        .iter()
+        .map(|error| Diagnostic {
+            range: byte_range_to_lsp_range(text, error.start, error.end),
+            severity: Some(DiagnosticSeverity::ERROR),
+            code: Some(NumberOrString::String("json-syntax".into())),
+            source: Some("json-ls".into()),
+            message: format!("{language} syntax error: {}", error.message),
+            ..Default::default()
+        })
+        .collect();
+    (parsed.value, syntax_errors)
+}
 
-    let validator = match jsonschema::validator_for(&schema_value) {
+/// The schema at `schema_url`, or the diagnostics explaining why the document cannot be
+/// validated against it: it cannot be fetched, is not a schema, or its `$ref`s loop.
+async fn usable_schema(
+    text: &str,
+    schema_url: &str,
+    schema_cache: &Arc<SchemaCache>,
+    config: &ServerConfig,
+) -> std::result::Result<Arc<Value>, Vec<Diagnostic>> {
+    let precedence = config.schema_key_precedence;
+    let schema_value = match schema_cache.get_or_fetch(schema_url).await {
         Ok(v) => v,
         Err(e) => {
-            warn!("Could not compile schema {schema_url}: {e}");
-            return Ok(vec![]);
+            warn!("Could not fetch schema {schema_url}: {e}");
+            return Err(vec![schema_fetch_failed_diagnostic(
+                text,
+                schema_url,
+                &e,
+                precedence,
+                config.error_retry_secs,
+            )]);
         }
     };
 
+    // A `$schema` URL pointing at some other JSON document would yield baffling errors.
+    if !looks_like_schema(&schema_value) {
+        return Err(not_a_schema_diagnostic(text, schema_url, precedence)
+            .into_iter()
+            .collect());
+    }
+
+    // A `$ref` chain that never reaches a concrete schema cannot be compiled.
+    let max_depth = config.schema_limits.max_depth;
+    if let Some(cycle) = schema_ref_cycle(schema_cache, schema_url, max_depth).await {
+        warn!("Cyclic $ref in schema {schema_url}: {}", cycle.describe());
+        return Err(schema_cycle_diagnostic(text, &cycle, precedence)
+            .into_iter()
+            .collect());
+    }
+    Ok(schema_value)
+}
+
+/// Diagnostics for the validation `findings` of `instance`, read from `text`, plus the checks
+/// that go beyond validation (numbers, deprecation, unknown properties).
+fn instance_diagnostics(
+    uri: &Url,
+    text: &str,
+    instance: &Value,
+    findings: Findings,
+    schema_url: &str,
+    schema_value: &Value,
+    config: &ServerConfig,
+) -> Vec<Diagnostic> {
     let mut diagnostics = Vec::new();
+    let spans = parse_spans(text);
 
-    for error in validator.iter_errors(&instance) {
+    let mut sources = SchemaSources::new(schema_url, schema_value);
+    let mut schema_errors = Vec::new();
+    for (error, note) in findings.errors.into_iter().flat_map(best_branch_errors) {
         let path_str = error.instance_path().to_string();
-        let range = instance_path_to_range(&path_str, &text);
+        let (range, mut related_information) = spans
+            .as_ref()
+            .and_then(|spans| array_error_location(uri, text, spans, instance, &error, &path_str))
+            .unwrap_or_else(|| {
+                let range = instance_path_to_range(&path_str, text, spans.as_ref());
+                (range, None)
+            });
 
-        diagnostics.push(Diagnostic {
+        let severity = match (error.kind(), config.format_mode()) {
+            (ValidationErrorKind::Format { .. }, Some(FormatAssertion::Warning)) => {
+                DiagnosticSeverity::WARNING
+            }
+            _ => DiagnosticSeverity::ERROR,
+        };
+
+        // The failing keyword's location tells which allOf/oneOf branch rejected the value.
+        let schema_path = error.schema_path().to_string();
+        if let Some(keyword) = sources.related_information(&schema_path) {
+            related_information
+                .get_or_insert_with(Vec::new)
+                .push(keyword);
+        }
+        let suggestions = suggestions(&error, schema_value);
+        let mut message = format!(
+            "{error} (schema: {schema_path}){}",
+            did_you_mean(&suggestions)
+        );
+        if let Some(note) = note {
+            message.push('\n');
+            message.push_str(&note);
+        }
+        let mut data = serde_json::json!({
+            "instancePath": path_str,
+            "schemaPath": schema_path,
+        });
+        if !suggestions.is_empty() {
+            data["suggestions"] = serde_json::json!(suggestions);
+        }
+        let diagnostic = Diagnostic {
             range,
-            severity: Some(DiagnosticSeverity::ERROR),
+            severity: Some(severity),
             code: Some(NumberOrString::String("schema-validation".into())),
+            code_description: code_description(schema_value, &schema_path, error.kind().keyword()),
             source: Some("json-ls".into()),
-            message: error.to_string(),
+            message,
+            related_information,
+            data: Some(data),
             ..Default::default()
-        });
+        };
+        schema_errors.push((path_str, diagnostic));
     }
+    diagnostics.extend(aggregate(schema_errors, config.diagnostics.aggregation));
 
-    debug!("Validated {uri}: {} error(s)", diagnostics.len());
+    if let Some(spans) = &spans {
+        diagnostics.extend(content_diagnostics(text, spans, findings.content));
+        diagnostics.extend(number_diagnostics(text, spans, schema_value, config));
+        diagnostics.extend(deprecated_diagnostics(text, spans, schema_value, config));
+        diagnostics.extend(unknown_property_diagnostics(
+            text,
+            spans,
+            schema_value,
+            config,
+        ));
+    }
 
-    Ok(diagnostics)
+    diagnostics
+}
+
+/// "Learn more" link for a failed `keyword`: the `externalDocs.url` of the subschema holding
+/// it (the OpenAPI convention, for schemas documenting their own fields), else the keyword's
+/// json-schema.org reference page.
+fn code_description(schema: &Value, schema_path: &str, keyword: &str) -> Option<CodeDescription> {
+    let own_docs = schema_path
+        .rsplit_once('/')
+        .and_then(|(parent, _)| schema_at_path(schema, parent))
+        .and_then(|parent| parent.pointer("/externalDocs/url"))
+        .and_then(Value::as_str)
+        .and_then(|url| Url::parse(url).ok());
+    let href = own_docs
+        .or_else(|| keyword_reference_url(keyword).and_then(|url| Url::parse(&url).ok()))?;
+    Some(CodeDescription { href })
+}
+
+/// A failed `oneOf` / `anyOf` becomes the errors of its best-matching branch, each with a
+/// note on the alternatives; other errors pass through. The best branch is the first one
+/// whose discriminator (a `const` / `enum` property of the union's value) matches, with the
+/// fewest errors.
+fn best_branch_errors(
+    error: jsonschema::ValidationError<'static>,
+) -> Vec<(jsonschema::ValidationError<'static>, Option<String>)> {
+    let union_path = error.instance_path().to_string();
+    let (keyword, best) = match error.kind() {
+        ValidationErrorKind::OneOfNotValid { context } => {
+            ("oneOf", best_branch(&union_path, context))
+        }
+        ValidationErrorKind::AnyOf { context } => ("anyOf", best_branch(&union_path, context)),
+        _ => return vec![(error, None)],
+    };
+    let Some(index) = best else {
+        return vec![(error, None)];
+    };
+    let (_, kind, _, schema_path, _) = error.into_parts();
+    let (ValidationErrorKind::OneOfNotValid { mut context }
+    | ValidationErrorKind::AnyOf { mut context }) = kind
+    else {
+        unreachable!("matched as a union above");
+    };
+    let note = format!(
+        "Closest of {} `{keyword}` alternatives: {schema_path}/{index}; the others fail too",
+        context.len()
+    );
+    context
+        .swap_remove(index)
+        .into_iter()
+        .flat_map(best_branch_errors)
+        .map(|(error, inner)| (error, Some(inner.unwrap_or_else(|| note.clone()))))
+        .collect()
+}
+
+/// Index of the first branch without a discriminator mismatch, with the fewest errors.
+fn best_branch(union_path: &str, branches: &[Vec<jsonschema::ValidationError>]) -> Option<usize> {
+    branches
+        .iter()
+        .enumerate()
+        .filter(|(_, branch)| !branch.is_empty())
+        .min_by_key(|(_, branch)| (discriminator_mismatch(union_path, branch), branch.len()))
+        .map(|(index, _)| index)
+}
+
+/// Whether `errors` reject a `const` / `enum` property directly under `union_path`, i.e.
+/// the branch is for another kind of value.
+fn discriminator_mismatch(union_path: &str, errors: &[jsonschema::ValidationError]) -> bool {
+    errors.iter().any(|error| {
+        matches!(
+            error.kind(),
+            ValidationErrorKind::Constant { .. } | ValidationErrorKind::Enum { .. }
+        ) && error
+            .instance_path()
+            .to_string()
+            .rsplit_once('/')
+            .is_some_and(|(parent, _)| parent == union_path)
+    })
+}
+
+/// Drop or re-grade diagnostics per `diagnostics.rules` and `diagnostics.severity`. A
+/// validation error matches `<code>/<keyword>` (the last segment of its schema path) in
+/// `rules`, then its keyword in `severity`, before plain `<code>` in `rules`.
+fn apply_rules(diagnostics: Vec<Diagnostic>, settings: &DiagnosticsConfig) -> Vec<Diagnostic> {
+    let DiagnosticsConfig {
+        rules, severity, ..
+    } = settings;
+    if rules.is_empty() && severity.is_empty() {
+        return diagnostics;
+    }
+    diagnostics
+        .into_iter()
+        .filter_map(|mut diagnostic| {
+            let Some(NumberOrString::String(code)) = &diagnostic.code else {
+                return Some(diagnostic);
+            };
+            let keyword = diagnostic
+                .data
+                .as_ref()
+                .and_then(|data| data["schemaPath"].as_str())
+                .and_then(|path| path.rsplit('/').next());
+            let level = keyword
+                .and_then(|keyword| rules.get(&format!("{code}/{keyword}")))
+                .or_else(|| {
+                    keyword
+                        .filter(|_| code == "schema-validation")
+                        .and_then(|keyword| severity.get(keyword))
+                })
+                .or_else(|| rules.get(code));
+            match level {
+                None => Some(diagnostic),
+                Some(level) => {
+                    diagnostic.severity = Some(level.severity()?);
+                    Some(diagnostic)
+                }
+            }
+        })
+        .collect()
+}
+
+/// Group validation errors by instance path and render them per `mode`. Groups keep the
+/// order in which their first error was reported.
+fn aggregate(errors: Vec<(String, Diagnostic)>, mode: Aggregation) -> Vec<Diagnostic> {
+    if mode == Aggregation::All {
+        return errors.into_iter().map(|(_, d)| d).collect();
+    }
+
+    let mut groups: Vec<(String, Vec<Diagnostic>)> = Vec::new();
+    for (path, diagnostic) in errors {
+        match groups.iter_mut().find(|(p, _)| *p == path) {
+            Some((_, group)) => group.push(diagnostic),
+            None => groups.push((path, vec![diagnostic])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter_map(|(_, group)| {
+            let mut group = group.into_iter();
+            let mut first = group.next()?;
+            if mode == Aggregation::Merged {
+                for other in group {
+                    first.message.push('\n');
+                    first.message.push_str(&other.message);
+                    // Lower values are more severe.
+                    first.severity = first.severity.min(other.severity);
+                    if let Some(related) = other.related_information {
+                        first
+                            .related_information
+                            .get_or_insert_with(Vec::new)
+                            .extend(related);
+                    }
+                }
+            }
+            Some(first)
+        })
+        .collect()
+}
+
+/// Compile `schema`, applying the configured `format` assertion mode (`format_assertion` /
+/// `validate_formats`).
+fn build_validator(
+    schema: &Value,
+    config: &ServerConfig,
+) -> Result<Validator, jsonschema::ValidationError<'static>> {
+    validator_options(config).build(schema)
+}
+
+/// Validator options applying the configured `format` assertion mode.
+fn validator_options(config: &ServerConfig) -> ValidationOptions {
+    let options = jsonschema::options();
+    match config.format_mode() {
+        None => options,
+        Some(mode) => options.should_validate_formats(mode != FormatAssertion::Ignore),
+    }
+}
+
+/// What validating one instance found.
+#[derive(Default)]
+struct Findings {
+    errors: Vec<jsonschema::ValidationError<'static>>,
+    /// Strings whose embedded content fails its content keywords.
+    content: Vec<ContentProblem>,
+}
+
+/// Compile `schema` once and collect every validation error and content problem for each of
+/// `instances`.
+fn run_validation(
+    schema: &Value,
+    instances: &[Arc<Value>],
+    config: &ServerConfig,
+) -> Result<Vec<Findings>, String> {
+    let validator = build_validator(schema, config).map_err(|e| e.to_string())?;
+    Ok(instances
+        .iter()
+        .map(|instance| Findings {
+            errors: validator
+                .iter_errors(instance)
+                .map(|error| error.to_owned())
+                .collect(),
+            content: content_problems(instance, schema, config.schema_limits),
+        })
+        .collect())
+}
+
+/// [`run_validation`] on a blocking thread, bounded by `validation_timeout_ms` for all the
+/// instances together.
+async fn validate_within_budget(
+    schema: &Arc<Value>,
+    instances: Vec<Arc<Value>>,
+    config: &ServerConfig,
+) -> std::result::Result<Result<Vec<Findings>, String>, Unfinished> {
+    if config.validation_timeout_ms == 0 {
+        return Ok(run_validation(schema, &instances, config));
+    }
+    let (schema, task_config) = (schema.clone(), config.clone());
+    time_boxed(
+        Duration::from_millis(config.validation_timeout_ms),
+        move || run_validation(&schema, &instances, &task_config),
+    )
+    .await
+}
+
+/// Why a document's instances have no validation results.
+enum ValidationFailure {
+    /// The schema does not compile.
+    SchemaInvalid(String),
+    /// Validation ran out of time; with the slow pattern the probe found, and the index of
+    /// the instance it was matching.
+    TimedOut(Option<(usize, SlowPattern)>),
+    /// Validation did not start, with too many earlier timed-out runs still going.
+    Skipped,
+}
+
+/// [`validate_within_budget`], probing for the slow `pattern` when the budget runs out.
+async fn validate_instances(
+    schema: &Arc<Value>,
+    instances: Vec<Arc<Value>>,
+    config: &ServerConfig,
+) -> std::result::Result<Vec<Findings>, ValidationFailure> {
+    match validate_within_budget(schema, instances.clone(), config).await {
+        Ok(Ok(findings)) => Ok(findings),
+        Ok(Err(e)) => Err(ValidationFailure::SchemaInvalid(e)),
+        Err(Unfinished::Skipped) => Err(ValidationFailure::Skipped),
+        Err(Unfinished::TimedOut) => {
+            // Probe all instances at once, as elements of one array, within one budget.
+            let budget = Duration::from_millis(config.validation_timeout_ms);
+            let all = Value::Array(instances.iter().map(|i| Value::clone(i)).collect());
+            let slow = find_slow_pattern(schema.clone(), Arc::new(all), budget)
+                .await
+                .and_then(|mut slow| {
+                    let pointer = slow.instance_pointer.strip_prefix('/')?;
+                    let (idx, rest) = pointer.split_once('/').unwrap_or((pointer, ""));
+                    let idx = idx.parse().ok()?;
+                    slow.instance_pointer = if rest.is_empty() {
+                        String::new()
+                    } else {
+                        format!("/{rest}")
+                    };
+                    Some((idx, slow))
+                });
+            Err(ValidationFailure::TimedOut(slow))
+        }
+    }
+}
+
+/// Warning reported instead of validation results when validation was not started because
+/// earlier timed-out runs are still going; on `$schema`.
+fn validation_skipped_diagnostic(text: &str, precedence: SchemaKeyPrecedence) -> Diagnostic {
+    let range = effective_schema_key(text, precedence).map_or_else(Range::default, |key| {
+        byte_range_to_lsp_range(text, key.value_start, key.value_end)
+    });
+    Diagnostic {
+        range,
+        severity: Some(DiagnosticSeverity::WARNING),
+        code: Some(NumberOrString::String("validation-skipped".into())),
+        source: Some("json-ls".into()),
+        message: "Validation skipped: too many slow validations are still running; errors are \
+                  not reported for this document"
+            .into(),
+        ..Default::default()
+    }
+}
+
+/// Warning reported instead of validation results when validation ran out of time: on the
+/// string a slow `pattern` was matching, if the probe found one, else on `$schema`.
+fn validation_timeout_diagnostic(
+    text: &str,
+    spans: Option<&SpanNode>,
+    precedence: SchemaKeyPrecedence,
+    timeout_ms: u64,
+    slow: Option<&SlowPattern>,
+) -> Diagnostic {
+    let target = match slow {
+        Some(slow) => spans
+            .and_then(|spans| spans.find_pointer(&slow.instance_pointer))
+            .map(|node| (node.start, node.end)),
+        None => effective_schema_key(text, precedence).map(|key| (key.value_start, key.value_end)),
+    };
+    let range = target.map_or_else(Range::default, |(start, end)| {
+        byte_range_to_lsp_range(text, start, end)
+    });
+
+    let message = match slow {
+        Some(slow) => {
+            let keyword = if slow.schema_pointer.ends_with("/pattern") {
+                "pattern"
+            } else {
+                "patternProperties"
+            };
+            format!(
+                "Validation stopped after {timeout_ms} ms: `{keyword}` at {} is too slow on \
+                 this value; other errors are not reported",
+                slow.schema_pointer
+            )
+        }
+        None => format!(
+            "Validation stopped after {timeout_ms} ms; errors are not reported for this document"
+        ),
+    };
+
+    Diagnostic {
+        range,
+        severity: Some(DiagnosticSeverity::WARNING),
+        code: Some(NumberOrString::String("validation-timeout".into())),
+        source: Some("json-ls".into()),
+        message,
+        data: slow.map(|slow| {
+            serde_json::json!({
+                "instancePath": slow.instance_pointer,
+                "schemaPath": slow.schema_pointer,
+            })
+        }),
+        ..Default::default()
+    }
 }
 
-/// Best-effort conversion of a JSON Pointer path (e.g. "/name/0") to an LSP Range
-/// by scanning the document text for the matching location.
-fn instance_path_to_range(path: &str, text: &str) -> Range {
-    // If we can locate the field in the document, return a precise range.
-    // Otherwise fall back to the top of the document.
-    if let Some(range) = try_locate_path(path, text) {
-        return range;
+/// Locate array-level errors precisely: the duplicated element for `uniqueItems` (with the
+/// first occurrence as related information), the whole array for `minItems`, `maxItems`,
+/// `contains` and friends. Returns `None` for other errors.
+fn array_error_location(
+    uri: &Url,
+    text: &str,
+    spans: &SpanNode,
+    instance: &Value,
+    error: &jsonschema::ValidationError,
+    pointer: &str,
+) -> Option<(Range, Option<Vec<DiagnosticRelatedInformation>>)> {
+    let is_array_error = matches!(
+        error.kind(),
+        ValidationErrorKind::UniqueItems
+            | ValidationErrorKind::MinItems { .. }
+            | ValidationErrorKind::MaxItems { .. }
+            | ValidationErrorKind::Contains
+            | ValidationErrorKind::AdditionalItems { .. }
+            | ValidationErrorKind::UnevaluatedItems { .. }
+    );
+    if !is_array_error {
+        return None;
+    }
+
+    let node = spans.find_pointer(pointer)?;
+    let whole_array = byte_range_to_lsp_range(text, node.start, node.end);
+
+    if !matches!(error.kind(), ValidationErrorKind::UniqueItems) {
+        return Some((whole_array, None));
+    }
+
+    let (SpanKind::Array(item_spans), Some(Value::Array(items))) =
+        (&node.kind, instance.pointer(pointer))
+    else {
+        return Some((whole_array, None));
+    };
+    let duplicate = items.iter().enumerate().find_map(|(idx, item)| {
+        let first = items[..idx].iter().position(|earlier| earlier == item)?;
+        Some((first, idx))
+    });
+    let Some((first, duplicate)) = duplicate else {
+        return Some((whole_array, None));
+    };
+    let (Some(first_span), Some(duplicate_span)) =
+        (item_spans.get(first), item_spans.get(duplicate))
+    else {
+        return Some((whole_array, None));
+    };
+
+    let related = DiagnosticRelatedInformation {
+        location: Location {
+            uri: uri.clone(),
+            range: byte_range_to_lsp_range(text, first_span.start, first_span.end),
+        },
+        message: format!("First occurrence (index {first})"),
+    };
+    Some((
+        byte_range_to_lsp_range(text, duplicate_span.start, duplicate_span.end),
+        Some(vec![related]),
+    ))
+}
+
+/// Warning on the `$schema` value when its target does not look like a JSON Schema.
+fn not_a_schema_diagnostic(
+    text: &str,
+    schema_url: &str,
+    precedence: SchemaKeyPrecedence,
+) -> Option<Diagnostic> {
+    let key = effective_schema_key(text, precedence)?;
+
+    Some(Diagnostic {
+        range: byte_range_to_lsp_range(text, key.value_start, key.value_end),
+        severity: Some(DiagnosticSeverity::WARNING),
+        code: Some(NumberOrString::String("schema-not-a-schema".into())),
+        source: Some("json-ls".into()),
+        message: format!(
+            "{schema_url} does not look like a JSON Schema (no schema keywords at the top \
+             level) — is the $schema URL correct?"
+        ),
+        ..Default::default()
+    })
+}
+
+/// Range of the effective `$schema` value, else the document start.
+fn schema_key_range(text: &str, precedence: SchemaKeyPrecedence) -> Range {
+    effective_schema_key(text, precedence).map_or_else(Range::default, |key| {
+        byte_range_to_lsp_range(text, key.value_start, key.value_end)
+    })
+}
+
+/// Informational note on the `$schema` value (else the document start) of a document over
+/// `max_validate_bytes`, saying validation was skipped and why.
+fn too_large_diagnostic(text: &str, limit: usize, precedence: SchemaKeyPrecedence) -> Diagnostic {
+    Diagnostic {
+        range: schema_key_range(text, precedence),
+        severity: Some(DiagnosticSeverity::INFORMATION),
+        code: Some(NumberOrString::String("validation-skipped".into())),
+        source: Some("json-ls".into()),
+        message: format!(
+            "Schema validation skipped: the document is {} bytes, over `max_validate_bytes` \
+             ({limit})",
+            text.len()
+        ),
+        ..Default::default()
+    }
+}
+
+/// Warning on the `$schema` value (else the document start, for a schema associated by
+/// other means) when the schema could not be fetched, so a document is not silently left
+/// unvalidated.
+fn schema_fetch_failed_diagnostic(
+    text: &str,
+    schema_url: &str,
+    error: &anyhow::Error,
+    precedence: SchemaKeyPrecedence,
+    error_retry_secs: u64,
+) -> Diagnostic {
+    let retry = match error_retry_secs {
+        0 => "It is fetched again on the next change.".to_owned(),
+        secs => format!(
+            "The fetch is not retried for {secs} s (`error_retry_secs`); the document is not \
+             validated until then."
+        ),
+    };
+    Diagnostic {
+        range: schema_key_range(text, precedence),
+        severity: Some(DiagnosticSeverity::WARNING),
+        code: Some(NumberOrString::String("schema-fetch-failed".into())),
+        source: Some("json-ls".into()),
+        message: format!("Could not load schema {schema_url}: {error}\n{retry}"),
+        ..Default::default()
+    }
+}
+
+/// Error on the `$schema` value (else the document start) when the schema does not compile,
+/// e.g. an invalid `pattern` or a keyword of the wrong type.
+fn schema_invalid_diagnostic(
+    text: &str,
+    schema_url: &str,
+    error: &str,
+    precedence: SchemaKeyPrecedence,
+) -> Diagnostic {
+    Diagnostic {
+        range: schema_key_range(text, precedence),
+        severity: Some(DiagnosticSeverity::ERROR),
+        code: Some(NumberOrString::String("schema-invalid".into())),
+        source: Some("json-ls".into()),
+        message: format!("Schema {schema_url} is invalid and cannot be used: {error}"),
+        ..Default::default()
+    }
+}
+
+/// Error on the `$schema` value when the schema's root `$ref` chain is cyclic.
+fn schema_cycle_diagnostic(
+    text: &str,
+    cycle: &RefCycle,
+    precedence: SchemaKeyPrecedence,
+) -> Option<Diagnostic> {
+    let key = effective_schema_key(text, precedence)?;
+    Some(ref_cycle_diagnostic(
+        text,
+        key.value_start,
+        key.value_end,
+        cycle,
+    ))
+}
+
+/// Warning on each top-level `$schema` key that `schema_key_precedence` overrides, pointing
+/// at the one the document is validated against.
+fn duplicate_schema_key_diagnostics(
+    uri: &Url,
+    text: &str,
+    precedence: SchemaKeyPrecedence,
+) -> Vec<Diagnostic> {
+    let keys: Vec<_> = schema_keys(text).collect();
+    let effective = match precedence {
+        SchemaKeyPrecedence::First => keys.first(),
+        SchemaKeyPrecedence::Last => keys.last(),
+    };
+    let Some(effective) = effective.filter(|_| keys.len() > 1) else {
+        return vec![];
+    };
+    let (setting, position) = match precedence {
+        SchemaKeyPrecedence::First => ("first", "earlier"),
+        SchemaKeyPrecedence::Last => ("last", "later"),
+    };
+    let effective_key = byte_range_to_lsp_range(
+        text,
+        effective.key_start,
+        effective.key_start + "\"$schema\"".len(),
+    );
+
+    keys.iter()
+        .filter(|key| key.key_start != effective.key_start)
+        .map(|key| Diagnostic {
+            range: byte_range_to_lsp_range(text, key.key_start, key.value_end),
+            severity: Some(DiagnosticSeverity::WARNING),
+            code: Some(NumberOrString::String("duplicate-schema-key".into())),
+            source: Some("json-ls".into()),
+            message: format!(
+                "Duplicate $schema key, ignored: the {position} one on line {} applies \
+                 (schema_key_precedence: {setting})",
+                effective_key.start.line + 1
+            ),
+            related_information: Some(vec![DiagnosticRelatedInformation {
+                location: Location {
+                    uri: uri.clone(),
+                    range: effective_key,
+                },
+                message: "$schema in effect".into(),
+            }]),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Report external `$ref`s in a schema document whose resolution chain is cyclic.
+async fn cyclic_ref_diagnostics(
+    uri: &Url,
+    text: &str,
+    root: &Value,
+    schema_cache: &SchemaCache,
+    config: &ServerConfig,
+) -> Vec<Diagnostic> {
+    let Some(spans) = parse_spans(text) else {
+        return vec![];
+    };
+
+    cyclic_refs(
+        schema_cache,
+        uri,
+        &spans,
+        root,
+        config.schema_limits.max_depth,
+    )
+    .await
+    .into_iter()
+    .map(|cyclic| ref_cycle_diagnostic(text, cyclic.start, cyclic.end, &cyclic.cycle))
+    .collect()
+}
+
+fn ref_cycle_diagnostic(text: &str, start: usize, end: usize, cycle: &RefCycle) -> Diagnostic {
+    Diagnostic {
+        range: byte_range_to_lsp_range(text, start, end),
+        severity: Some(DiagnosticSeverity::ERROR),
+        code: Some(NumberOrString::String("schema-ref-cycle".into())),
+        source: Some("json-ls".into()),
+        message: format!("cyclic $ref via {}", cycle.describe()),
+        data: Some(serde_json::json!({
+            "chain": cycle.chain.iter().map(Url::as_str).collect::<Vec<_>>(),
+        })),
+        ..Default::default()
+    }
+}
+
+/// Report local `$ref` pointers in a schema document that do not resolve.
+fn broken_ref_diagnostics(text: &str, root: &Value) -> Vec<Diagnostic> {
+    let Some(spans) = parse_spans(text) else {
+        return vec![];
+    };
+
+    find_broken_refs(&spans, root)
+        .into_iter()
+        .map(|broken| Diagnostic {
+            range: byte_range_to_lsp_range(text, broken.start, broken.end),
+            severity: Some(DiagnosticSeverity::ERROR),
+            code: Some(NumberOrString::String("schema-ref-unresolved".into())),
+            source: Some("json-ls".into()),
+            message: format!(
+                "$ref \"{}\" does not resolve within this document",
+                broken.reference
+            ),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Count the validation errors in the value at the JSON Pointer `pointer`, validating only
+/// that value against the subschema declared for it. Returns `None` if the document has no
+/// schema, cannot be parsed, or the schema is unavailable — callers treat that as "nothing
+/// to report".
+pub async fn subtree_error_count(
+    uri: &Url,
+    documents: &Arc<DocumentStore>,
+    schema_cache: &Arc<SchemaCache>,
+    config: &ServerConfig,
+    pointer: &str,
+) -> Option<usize> {
+    let text = documents.get_text(uri)?;
+    if !config.validates_size(text.len()) {
+        return None;
+    }
+    let schema_url = documents.get_schema_url(uri)?;
+    let schema_value = schema_cache.get_or_fetch(&schema_url).await.ok()?;
+    let max_depth = config.schema_limits.max_depth;
+    if schema_ref_cycle(schema_cache, &schema_url, max_depth)
+        .await
+        .is_some()
+    {
+        return None;
+    }
+    let path = pointer_to_path(pointer, &parse_spans(&text)?)?;
+    let instance = parse_tolerant_with(&text, documents.get_dialect(uri)).value?;
+    let value = instance.pointer(pointer)?.clone();
+
+    let root = SchemaNode::new(&schema_value, &schema_value).with_limits(config.schema_limits);
+    // Nothing declared for the value: only its parent's keywords can reject it.
+    let Some(node) = root.navigate(&path) else {
+        return Some(0);
+    };
+    let declarations = node.declaration_pointers()?;
+
+    let (schema, task_config) = (schema_value.clone(), config.clone());
+    let count = move || count_subtree_errors(&schema, &declarations, &value, &task_config);
+    let count = if config.validation_timeout_ms == 0 {
+        count()
+    } else {
+        time_boxed(Duration::from_millis(config.validation_timeout_ms), count)
+            .await
+            .ok()?
+    };
+    match count {
+        Ok(count) => Some(count),
+        Err(e) => {
+            debug!("Subtree validation of {pointer} in {uri} failed: {e}");
+            None
+        }
+    }
+}
+
+/// The validation errors of `value` against the subschemas of `schema` at the JSON Pointers
+/// `declarations`, all of which it must satisfy.
+fn count_subtree_errors(
+    schema: &Value,
+    declarations: &[String],
+    value: &Value,
+    config: &ServerConfig,
+) -> Result<usize, String> {
+    // Referenced under a URI of its own, the schema still resolves the `#/…` refs inside
+    // the subschemas.
+    let refs: Vec<Value> = declarations
+        .iter()
+        .map(|pointer| {
+            let reference = format!("{SUBTREE_SCHEMA_URI}#{}", pointer_fragment(pointer));
+            serde_json::json!({ "$ref": reference })
+        })
+        .collect();
+    let validator = validator_options(config)
+        .with_resource(
+            SUBTREE_SCHEMA_URI,
+            jsonschema::Resource::from_contents(schema.clone()),
+        )
+        .build(&serde_json::json!({ "allOf": refs }))
+        .map_err(|e| e.to_string())?;
+    Ok(validator.iter_errors(value).count())
+}
+
+/// `pointer` as a URI fragment, with the bytes a fragment cannot hold as-is percent-encoded.
+fn pointer_fragment(pointer: &str) -> String {
+    let mut fragment = String::with_capacity(pointer.len());
+    for byte in pointer.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/$".contains(&byte) {
+            fragment.push(char::from(byte));
+        } else {
+            fragment.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    fragment
+}
+
+/// The range of the value at the JSON Pointer `path` (e.g. `/items/3/nested/field`): the
+/// key of an object member, else the value itself. The root object, and paths that cannot
+/// be found, map to the top of the document.
+fn instance_path_to_range(path: &str, text: &str, spans: Option<&SpanNode>) -> Range {
+    if let Some((start, end)) = spans.and_then(|spans| path_span(path, spans)) {
+        return byte_range_to_lsp_range(text, start, end);
     }
 
     Range {
@@ -106,56 +1131,337 @@ fn instance_path_to_range(path: &str, text: &str) -> Range {
     }
 }
 
-/// Attempt to locate a JSON Pointer path in the raw text.
-/// Only handles simple single-level key lookups for now.
-fn try_locate_path(path: &str, text: &str) -> Option<Range> {
-    // Only handle simple paths like "/key" for now
-    let key = path.trim_start_matches('/').split('/').next()?;
-    if key.is_empty() {
-        return None;
+/// Byte range for the JSON Pointer `pointer`: an object member's key, an array element or a
+/// scalar / array root.
+fn path_span(pointer: &str, spans: &SpanNode) -> Option<(usize, usize)> {
+    let path = pointer_to_path(pointer, spans)?;
+    let Some((last, parent)) = path.split_last() else {
+        return (!matches!(spans.kind, SpanKind::Object(_))).then_some((spans.start, spans.end));
+    };
+    let parent = spans.find(parent)?;
+    match (last, &parent.kind) {
+        (PathSegment::Key(key), SpanKind::Object(_)) => {
+            let member = parent.member(key)?;
+            Some((member.key_start, member.key_end))
+        }
+        (PathSegment::Index(idx), SpanKind::Array(items)) => {
+            items.get(*idx).map(|item| (item.start, item.end))
+        }
+        _ => None,
     }
+}
 
-    // Try to find `"key":` pattern
-    let needle = format!("\"{}\"", key);
-    let start_byte = text.find(&needle)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RuleLevel;
+    use std::collections::HashMap;
 
-    let (line, character) = byte_offset_to_lsp_pos(text, start_byte);
-    let end_character = character + needle.len() as u32;
+    fn diagnostic(message: &str, severity: DiagnosticSeverity) -> Diagnostic {
+        Diagnostic {
+            severity: Some(severity),
+            message: message.into(),
+            ..Default::default()
+        }
+    }
 
-    Some(Range {
-        start: Position { line, character },
-        end: Position {
-            line,
-            character: end_character,
-        },
-    })
-}
+    fn errors() -> Vec<(String, Diagnostic)> {
+        vec![
+            (
+                "/port".into(),
+                diagnostic("too large", DiagnosticSeverity::WARNING),
+            ),
+            (
+                "/name".into(),
+                diagnostic("too short", DiagnosticSeverity::ERROR),
+            ),
+            (
+                "/port".into(),
+                diagnostic("not a multiple of 2", DiagnosticSeverity::ERROR),
+            ),
+        ]
+    }
 
-/// Convert a byte offset in `text` to an LSP Position (UTF-16 based).
-pub fn byte_offset_to_lsp_pos(text: &str, byte_offset: usize) -> (u32, u32) {
-    let mut line = 0u32;
-    let mut line_start = 0usize;
+    #[test]
+    fn test_nested_instance_paths_map_to_exact_ranges() {
+        let text = "{\n  \"items\": [\n    {},\n    { \"nested\": { \"field\": 1 } },\n    [true, \"x\"]\n  ],\n  \"a/b\": 0\n}";
+        let spans = parse_spans(text);
+        let range = |path: &str| {
+            let range = instance_path_to_range(path, text, spans.as_ref());
+            (range.start.line, range.start.character, range.end.character)
+        };
+        assert_eq!(range("/items"), (1, 2, 9));
+        assert_eq!(range("/items/0"), (2, 4, 6));
+        assert_eq!(range("/items/1/nested/field"), (3, 18, 25));
+        assert_eq!(range("/items/2/1"), (4, 11, 14));
+        assert_eq!(range("/a~1b"), (6, 2, 7));
+        assert_eq!(range(""), (0, 0, 1));
+        assert_eq!(range("/missing/0"), (0, 0, 1));
+    }
 
-    for (i, ch) in text.char_indices() {
-        if i >= byte_offset {
-            break;
-        }
-        if ch == '\n' {
-            line += 1;
-            line_start = i + 1;
-        }
+    #[test]
+    fn test_union_errors_come_from_the_best_branch() {
+        let schema = serde_json::json!({
+            "properties": {
+                "shape": {
+                    "oneOf": [
+                        {
+                            "properties": {
+                                "kind": { "const": "circle" },
+                                "radius": { "type": "number" }
+                            },
+                            "required": ["kind", "radius"]
+                        },
+                        {
+                            "properties": {
+                                "kind": { "const": "rect" },
+                                "width": { "type": "number" },
+                                "height": { "type": "number" }
+                            },
+                            "required": ["kind", "width", "height"]
+                        }
+                    ]
+                }
+            }
+        });
+        // The circle branch has fewer errors, but `kind` says rect.
+        let instance = serde_json::json!({ "shape": { "kind": "rect", "width": "2" } });
+        let errors = run_validation(&schema, &[Arc::new(instance)], &ServerConfig::default())
+            .unwrap()
+            .remove(0)
+            .errors;
+        let reported: Vec<(String, String, String)> = errors
+            .into_iter()
+            .flat_map(best_branch_errors)
+            .map(|(error, note)| {
+                (
+                    error.instance_path().to_string(),
+                    error.schema_path().to_string(),
+                    note.unwrap_or_default(),
+                )
+            })
+            .collect();
+
+        assert_eq!(reported.len(), 2, "got: {reported:?}");
+        assert!(reported
+            .iter()
+            .all(|(_, schema_path, _)| schema_path.starts_with("/properties/shape/oneOf/1/")));
+        assert!(reported.iter().any(|(path, _, _)| path == "/shape/width"));
+        assert_eq!(
+            reported[0].2,
+            "Closest of 2 `oneOf` alternatives: /properties/shape/oneOf/1; the others fail too"
+        );
+    }
+
+    #[test]
+    fn test_code_description_prefers_the_schemas_own_docs() {
+        let schema = serde_json::json!({
+            "properties": {
+                "port": {
+                    "$ref": "#/$defs/Port"
+                },
+                "name": { "type": "string" }
+            },
+            "$defs": {
+                "Port": {
+                    "type": "integer",
+                    "externalDocs": { "url": "https://docs.example.com/port" }
+                }
+            }
+        });
+        let href = |path: &str, keyword: &str| {
+            code_description(&schema, path, keyword).map(|d| d.href.to_string())
+        };
+        assert_eq!(
+            href("/properties/port/$ref/type", "type").as_deref(),
+            Some("https://docs.example.com/port")
+        );
+        assert_eq!(
+            href("/properties/name/type", "type").as_deref(),
+            Some("https://json-schema.org/understanding-json-schema/reference/type")
+        );
+        assert_eq!(href("/properties/name/x-custom", "x-custom"), None);
     }
 
-    // Count UTF-16 units from line_start to byte_offset
-    let col_text = &text[line_start..byte_offset.min(text.len())];
-    let character = col_text.chars().map(|c| c.len_utf16() as u32).sum::<u32>();
+    #[test]
+    fn test_aggregate_modes() {
+        let messages = |diagnostics: Vec<Diagnostic>| -> Vec<String> {
+            diagnostics.into_iter().map(|d| d.message).collect()
+        };
 
-    (line, character)
-}
+        assert_eq!(
+            messages(aggregate(errors(), Aggregation::All)),
+            ["too large", "too short", "not a multiple of 2"]
+        );
+        assert_eq!(
+            messages(aggregate(errors(), Aggregation::First)),
+            ["too large", "too short"]
+        );
+
+        let merged = aggregate(errors(), Aggregation::Merged);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].message, "too large\nnot a multiple of 2");
+        assert_eq!(merged[0].severity, Some(DiagnosticSeverity::ERROR));
+    }
+
+    #[test]
+    fn test_duplicate_schema_key_warning_names_effective_key() {
+        let uri = Url::parse("file:///tmp/doc.json").unwrap();
+        let text = "{\n  \"$schema\": \"a.json\",\n  \"$schema\": \"b.json\"\n}";
+
+        let first = duplicate_schema_key_diagnostics(&uri, text, SchemaKeyPrecedence::First);
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].range.start.line, 2);
+        assert!(first[0].message.contains("earlier one on line 2"));
+
+        let last = duplicate_schema_key_diagnostics(&uri, text, SchemaKeyPrecedence::Last);
+        assert_eq!(last.len(), 1);
+        assert_eq!(last[0].range.start.line, 1);
+        assert!(last[0].message.contains("later one on line 3"));
+
+        let single = "{ \"$schema\": \"a.json\" }";
+        assert!(
+            duplicate_schema_key_diagnostics(&uri, single, SchemaKeyPrecedence::Last).is_empty()
+        );
+    }
+
+    #[test]
+    fn test_skipped_validation_is_not_reported_as_a_timeout() {
+        let text = "{ \"$schema\": \"a.json\" }";
+        let skipped = validation_skipped_diagnostic(text, SchemaKeyPrecedence::First);
+        assert_eq!(
+            skipped.code,
+            Some(NumberOrString::String("validation-skipped".into()))
+        );
+        assert!(skipped
+            .message
+            .starts_with("Validation skipped: too many slow validations"));
+        assert_eq!(skipped.range.start.character, 13);
+    }
+
+    #[test]
+    fn test_subtree_is_validated_against_its_declarations() {
+        let schema = serde_json::json!({
+            "$id": "https://example.com/app.json",
+            "required": ["name"],
+            "properties": {
+                "my server": { "$ref": "#/$defs/server" }
+            },
+            "allOf": [{ "properties": { "my server": { "required": ["host"] } } }],
+            "$defs": {
+                "server": { "properties": { "port": { "type": "integer" } } }
+            }
+        });
+        let config = ServerConfig::default();
+        let declarations = [
+            "/properties/my server".to_owned(),
+            "/allOf/0/properties/my server".to_owned(),
+        ];
+        let count = |value| count_subtree_errors(&schema, &declarations, &value, &config);
+        // The root's `required` does not apply to the subtree.
+        assert_eq!(count(serde_json::json!({ "host": "a", "port": 1 })), Ok(0));
+        assert_eq!(count(serde_json::json!({ "port": "80" })), Ok(2));
+        assert_eq!(pointer_fragment("/a b/%/ü"), "/a%20b/%25/%C3%BC");
+    }
 
-/// Extract line/column from a serde_json error message (best effort).
-fn parse_error_position(e: &serde_json::Error, _text: &str) -> (u32, u32) {
-    let line = e.line().saturating_sub(1) as u32;
-    let col = e.column().saturating_sub(1) as u32;
-    (line, col)
+    #[test]
+    fn test_truncation_keeps_most_severe() {
+        let at = |line: u32, severity: DiagnosticSeverity| Diagnostic {
+            range: Range::new(Position::new(line, 0), Position::new(line, 1)),
+            ..diagnostic(&line.to_string(), severity)
+        };
+        let all = vec![
+            at(0, DiagnosticSeverity::HINT),
+            at(1, DiagnosticSeverity::ERROR),
+            at(2, DiagnosticSeverity::WARNING),
+            at(3, DiagnosticSeverity::ERROR),
+        ];
+        assert_eq!(truncate_diagnostics(all.clone(), 0).len(), 4);
+        assert_eq!(truncate_diagnostics(all.clone(), 4).len(), 4);
+
+        let kept = truncate_diagnostics(all, 2);
+        let messages: Vec<&str> = kept.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(&messages[..2], ["1", "3"]);
+        assert_eq!(
+            messages[2],
+            "2 more diagnostic(s) not shown: only the first 2 are published (`max_diagnostics`)"
+        );
+        assert_eq!(kept[2].range.start.line, 0);
+    }
+
+    #[test]
+    fn test_severity_by_keyword() {
+        let validation = |schema_path: &str| Diagnostic {
+            code: Some(NumberOrString::String("schema-validation".into())),
+            severity: Some(DiagnosticSeverity::ERROR),
+            data: Some(serde_json::json!({ "schemaPath": schema_path })),
+            ..Default::default()
+        };
+        let settings: DiagnosticsConfig = serde_json::from_value(serde_json::json!({
+            "severity": { "additionalProperties": "warning", "format": "hint", "required": "error" },
+            "rules": { "schema-validation": "info", "schema-validation/format": "off" }
+        }))
+        .unwrap();
+
+        let out = apply_rules(
+            vec![
+                validation("/additionalProperties"),
+                validation("/properties/email/format"),
+                validation("/required"),
+                validation("/properties/n/type"),
+            ],
+            &settings,
+        );
+        let severities: Vec<_> = out.iter().map(|d| d.severity).collect();
+        assert_eq!(
+            severities,
+            vec![
+                Some(DiagnosticSeverity::WARNING),
+                Some(DiagnosticSeverity::ERROR),
+                Some(DiagnosticSeverity::INFORMATION)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rules_override_by_code_and_keyword() {
+        let validation = |schema_path: &str| Diagnostic {
+            code: Some(NumberOrString::String("schema-validation".into())),
+            severity: Some(DiagnosticSeverity::ERROR),
+            data: Some(serde_json::json!({ "schemaPath": schema_path })),
+            ..Default::default()
+        };
+        let syntax = Diagnostic {
+            code: Some(NumberOrString::String("json-syntax".into())),
+            severity: Some(DiagnosticSeverity::ERROR),
+            ..Default::default()
+        };
+        let rules: HashMap<String, RuleLevel> = serde_json::from_value(serde_json::json!({
+            "schema-validation/format": "off",
+            "schema-validation": "warning",
+            "json-syntax": "hint"
+        }))
+        .unwrap();
+
+        let settings = DiagnosticsConfig {
+            rules,
+            ..DiagnosticsConfig::default()
+        };
+        let out = apply_rules(
+            vec![
+                validation("/properties/email/format"),
+                validation("/required"),
+                syntax,
+            ],
+            &settings,
+        );
+        let severities: Vec<_> = out.iter().map(|d| d.severity).collect();
+        assert_eq!(
+            severities,
+            vec![
+                Some(DiagnosticSeverity::WARNING),
+                Some(DiagnosticSeverity::HINT)
+            ]
+        );
+    }
 }
@@ -1,28 +1,146 @@
+use crate::config::{SchemaAssociation, SchemaKeyPrecedence};
+use crate::json_lines::focus_line;
+use crate::offsets::lsp_pos_to_char_idx;
+use crate::schema::catalog::{entry_for, file_matches, match_path, CatalogEntry};
+use crate::tolerant::Dialect;
 use anyhow::{anyhow, Result};
 use dashmap::DashMap;
 use ropey::Rope;
-use tower_lsp::lsp_types::{TextDocumentContentChangeEvent, Url};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use tower_lsp::lsp_types::{
+    ClientCapabilities, TextDocumentContentChangeEvent, TextDocumentSyncKind, Url,
+};
 
 pub struct DocumentState {
     pub rope: Rope,
     pub version: i32,
     pub schema_url: Option<String>,
+    /// Schema from the external `schema_resolver`, the last resort when no other
+    /// association matches.
+    pub associated_schema_url: Option<String>,
+    /// Schema configured for the document's languageId (`language_schemas`), used when
+    /// the text has no `$schema` and no `fileMatch` matches.
+    pub language_schema_url: Option<String>,
+    /// Schema of the first `schemas` setting whose `fileMatch` matches the document's path,
+    /// ahead of `language_schema_url`.
+    pub configured_schema_url: Option<String>,
+    /// Schema of the catalog entry whose `fileMatch` matches the document's path, ahead of
+    /// `associated_schema_url` only.
+    pub catalog_schema_url: Option<String>,
+    /// JSON5 or JSON Lines by the languageId the document was opened with, else by its
+    /// extension (see [`Dialect::detect`]).
+    pub dialect: Dialect,
     pub text: String,
+    /// `text` and `schema_url` lag behind incremental edits to `rope`. A burst of small
+    /// changes (a paste arriving as many events) then costs one rebuild, on the next read.
+    stale: bool,
+    /// Times `refresh` rebuilt `text` from `rope`.
+    #[cfg(test)]
+    rebuilds: usize,
+}
+
+impl DocumentState {
+    /// Bring `text` and `schema_url` up to date with `rope`.
+    fn refresh(&mut self, precedence: SchemaKeyPrecedence) {
+        if self.stale {
+            self.text = self.rope.to_string();
+            self.schema_url = extract_schema_url_with(&self.text, precedence);
+            self.stale = false;
+            #[cfg(test)]
+            {
+                self.rebuilds += 1;
+            }
+        }
+    }
 }
 
 pub struct DocumentStore {
     inner: DashMap<Url, DocumentState>,
+    /// `schema_key_precedence` is `last`.
+    last_schema_key_wins: AtomicBool,
+    /// The `schemas` setting, associating documents by file name.
+    associations: RwLock<Arc<Vec<SchemaAssociation>>>,
+    /// Catalog entries documents are associated by file name with.
+    catalog: RwLock<Arc<Vec<CatalogEntry>>>,
 }
 
 impl DocumentStore {
     pub fn new() -> Self {
         Self {
             inner: DashMap::new(),
+            last_schema_key_wins: AtomicBool::new(false),
+            associations: RwLock::default(),
+            catalog: RwLock::default(),
+        }
+    }
+
+    /// Choose which of several top-level `$schema` keys documents, open and future, are
+    /// validated against.
+    pub fn set_schema_key_precedence(&self, precedence: SchemaKeyPrecedence) {
+        let last = precedence == SchemaKeyPrecedence::Last;
+        if self.last_schema_key_wins.swap(last, Ordering::Relaxed) == last {
+            return;
+        }
+        for mut state in self.inner.iter_mut() {
+            // Stale text is re-read, with the new precedence, on next access.
+            if !state.stale {
+                state.schema_url = extract_schema_url_with(&state.text, precedence);
+            }
+        }
+    }
+
+    fn schema_key_precedence(&self) -> SchemaKeyPrecedence {
+        if self.last_schema_key_wins.load(Ordering::Relaxed) {
+            SchemaKeyPrecedence::Last
+        } else {
+            SchemaKeyPrecedence::First
+        }
+    }
+
+    /// Associate documents, open and future, by the `schemas` setting.
+    pub fn set_schema_associations(&self, associations: Vec<SchemaAssociation>) {
+        let associations = Arc::new(associations);
+        *self
+            .associations
+            .write()
+            .expect("associations lock poisoned") = associations.clone();
+        for mut state in self.inner.iter_mut() {
+            let url = configured_schema_url(&associations, state.key());
+            state.configured_schema_url = url;
         }
     }
 
+    /// Associate documents, open and future, with schemas from these catalog entries.
+    /// Returns `false` if they were already in use.
+    pub fn set_catalog(&self, entries: Arc<Vec<CatalogEntry>>) -> bool {
+        {
+            let mut catalog = self.catalog.write().expect("catalog lock poisoned");
+            if Arc::ptr_eq(&catalog, &entries) {
+                return false;
+            }
+            *catalog = entries.clone();
+        }
+        for mut state in self.inner.iter_mut() {
+            let url = catalog_schema_url(&entries, state.key());
+            state.catalog_schema_url = url;
+        }
+        true
+    }
+
     pub fn open(&self, uri: Url, version: i32, text: String) {
-        let schema_url = extract_schema_url(&text);
+        let schema_url = extract_schema_url_with(&text, self.schema_key_precedence());
+        let configured_schema_url = {
+            let associations = self
+                .associations
+                .read()
+                .expect("associations lock poisoned");
+            configured_schema_url(&associations, &uri)
+        };
+        let catalog_schema_url = {
+            let catalog = self.catalog.read().expect("catalog lock poisoned");
+            catalog_schema_url(&catalog, &uri)
+        };
         let rope = Rope::from_str(&text);
         self.inner.insert(
             uri,
@@ -30,7 +148,15 @@ impl DocumentStore {
                 rope,
                 version,
                 schema_url,
+                associated_schema_url: None,
+                language_schema_url: None,
+                configured_schema_url,
+                catalog_schema_url,
+                dialect: Dialect::Json,
                 text,
+                stale: false,
+                #[cfg(test)]
+                rebuilds: 0,
             },
         );
     }
@@ -47,12 +173,17 @@ impl DocumentStore {
             .get_mut(uri)
             .ok_or_else(|| anyhow!("Document not found: {uri}"))?;
 
+        let precedence = self.schema_key_precedence();
         for change in changes {
+            // A range is honoured whichever sync kind was negotiated: clients that were
+            // offered FULL may still send incremental changes.
             match change.range {
                 None => {
                     // Full replacement
                     state.rope = Rope::from_str(&change.text);
+                    state.schema_url = extract_schema_url_with(&change.text, precedence);
                     state.text = change.text;
+                    state.stale = false;
                 }
                 Some(range) => {
                     // Incremental update — convert LSP range to rope char indices
@@ -60,34 +191,128 @@ impl DocumentStore {
                     let end = lsp_pos_to_char_idx(&state.rope, range.end)?;
                     state.rope.remove(start..end);
                     state.rope.insert(start, &change.text);
-                    // Rebuild text from rope for diagnostics
-                    state.text = state.rope.to_string();
+                    state.stale = true;
                 }
             }
         }
 
         state.version = version;
-        state.schema_url = extract_schema_url(&state.text);
         Ok(())
     }
 
+    /// Open a document the client edits without having opened it (e.g. after a server
+    /// restart), if `changes` carry its full text. Returns `false` when they are all
+    /// incremental, leaving the store untouched.
+    pub fn open_from_changes(
+        &self,
+        uri: Url,
+        version: i32,
+        mut changes: Vec<TextDocumentContentChangeEvent>,
+    ) -> Result<bool> {
+        let Some(last_full) = changes.iter().rposition(|change| change.range.is_none()) else {
+            return Ok(false);
+        };
+        let rest = changes.split_off(last_full + 1);
+        let text = changes.pop().map(|change| change.text).unwrap_or_default();
+        self.open(uri.clone(), version, text);
+        self.update(&uri, version, rest)?;
+        Ok(true)
+    }
+
+    pub fn contains(&self, uri: &Url) -> bool {
+        self.inner.contains_key(uri)
+    }
+
     pub fn close(&self, uri: &Url) {
         self.inner.remove(uri);
     }
 
+    /// The schema in effect: the document's own `$schema`, else its association — `schemas`
+    /// `fileMatch`, `language_schemas`, the catalog, then the external resolver.
     pub fn get_schema_url(&self, uri: &Url) -> Option<String> {
-        self.inner.get(uri)?.schema_url.clone()
+        let state = self.fresh(uri)?;
+        state
+            .schema_url
+            .clone()
+            .or_else(|| state.configured_schema_url.clone())
+            .or_else(|| state.language_schema_url.clone())
+            .or_else(|| state.catalog_schema_url.clone())
+            .or_else(|| state.associated_schema_url.clone())
+    }
+
+    /// The `$schema` declared in the document text.
+    pub fn get_inline_schema_url(&self, uri: &Url) -> Option<String> {
+        self.fresh(uri)?.schema_url.clone()
+    }
+
+    pub fn get_associated_schema_url(&self, uri: &Url) -> Option<String> {
+        self.inner.get(uri)?.associated_schema_url.clone()
+    }
+
+    pub fn set_associated_schema_url(&self, uri: &Url, schema_url: Option<String>) {
+        if let Some(mut state) = self.inner.get_mut(uri) {
+            state.associated_schema_url = schema_url;
+        }
+    }
+
+    pub fn get_language_schema_url(&self, uri: &Url) -> Option<String> {
+        self.inner.get(uri)?.language_schema_url.clone()
+    }
+
+    pub fn set_language_schema_url(&self, uri: &Url, schema_url: Option<String>) {
+        if let Some(mut state) = self.inner.get_mut(uri) {
+            state.language_schema_url = schema_url;
+        }
+    }
+
+    pub fn get_dialect(&self, uri: &Url) -> Dialect {
+        self.inner
+            .get(uri)
+            .map_or(Dialect::Json, |state| state.dialect)
+    }
+
+    pub fn set_dialect(&self, uri: &Url, dialect: Dialect) {
+        if let Some(mut state) = self.inner.get_mut(uri) {
+            state.dialect = dialect;
+        }
+    }
+
+    pub fn get_configured_schema_url(&self, uri: &Url) -> Option<String> {
+        self.inner.get(uri)?.configured_schema_url.clone()
+    }
+
+    pub fn get_catalog_schema_url(&self, uri: &Url) -> Option<String> {
+        self.inner.get(uri)?.catalog_schema_url.clone()
+    }
+
+    /// URIs of all open documents.
+    pub fn uris(&self) -> Vec<Url> {
+        self.inner.iter().map(|entry| entry.key().clone()).collect()
+    }
+
+    pub fn get_version(&self, uri: &Url) -> Option<i32> {
+        self.inner.get(uri).map(|s| s.version)
     }
 
     pub fn get_text(&self, uri: &Url) -> Option<String> {
-        self.inner.get(uri).map(|s| s.text.clone())
+        self.fresh(uri).map(|s| s.text.clone())
     }
 
-    // TODO: use this in a future `textDocument/formatting` handler — a rope reference is
-    // needed to efficiently apply formatter edits back as incremental LSP text edits.
-    #[allow(dead_code)]
-    pub fn get_rope(&self, uri: &Url) -> Option<Rope> {
-        self.inner.get(uri).map(|s| s.rope.clone())
+    /// The text position-based features (hover, completion) read at `line`: the whole
+    /// document, or for JSON Lines only that line, at its place.
+    pub fn get_instance_text(&self, uri: &Url, line: u32) -> Option<String> {
+        let state = self.fresh(uri)?;
+        Some(match state.dialect {
+            Dialect::JsonLines => focus_line(&state.text, line),
+            Dialect::Json | Dialect::Json5 => state.text.clone(),
+        })
+    }
+
+    /// The document's state with pending incremental edits folded into its text.
+    fn fresh(&self, uri: &Url) -> Option<dashmap::mapref::one::Ref<'_, Url, DocumentState>> {
+        let mut state = self.inner.get_mut(uri)?;
+        state.refresh(self.schema_key_precedence());
+        Some(state.downgrade())
     }
 }
 
@@ -97,67 +322,177 @@ impl Default for DocumentStore {
     }
 }
 
-/// Convert an LSP `Position` (0-based line + UTF-16 character) to a ropey char index.
-pub fn lsp_pos_to_char_idx(rope: &Rope, pos: tower_lsp::lsp_types::Position) -> Result<usize> {
-    let line = pos.line as usize;
-    if line >= rope.len_lines() {
-        return Err(anyhow!(
-            "Line {line} out of range (doc has {} lines)",
-            rope.len_lines()
-        ));
+fn configured_schema_url(associations: &[SchemaAssociation], uri: &Url) -> Option<String> {
+    let path = match_path(uri);
+    associations
+        .iter()
+        .filter(|association| file_matches(&association.file_match, &path))
+        .find_map(SchemaAssociation::schema_url)
+}
+
+fn catalog_schema_url(entries: &[CatalogEntry], uri: &Url) -> Option<String> {
+    entry_for(entries, &match_path(uri)).map(|entry| entry.url.clone())
+}
+
+/// Pick the sync kind to advertise: an explicit preference wins; otherwise clients that
+/// declare no `textDocument` capabilities at all are treated as minimal and get FULL.
+pub fn negotiate_sync_kind(
+    preference: Option<TextDocumentSyncKind>,
+    capabilities: &ClientCapabilities,
+) -> TextDocumentSyncKind {
+    if let Some(kind) = preference {
+        return kind;
     }
+    match &capabilities.text_document {
+        None => TextDocumentSyncKind::FULL,
+        Some(_) => TextDocumentSyncKind::INCREMENTAL,
+    }
+}
 
-    let line_char_start = rope.line_to_char(line);
-    let line_slice = rope.line(line);
+/// The document's `$schema` URL, taking the first if the root object declares several.
+pub fn extract_schema_url(text: &str) -> Option<String> {
+    extract_schema_url_with(text, SchemaKeyPrecedence::First)
+}
 
-    // Count UTF-16 code units to find the correct char offset within the line
-    let col_utf16 = pos.character as usize;
-    let mut utf16_remaining = col_utf16;
-    let mut char_offset = 0;
+pub fn extract_schema_url_with(text: &str, precedence: SchemaKeyPrecedence) -> Option<String> {
+    effective_schema_key(text, precedence)?.url
+}
 
-    for ch in line_slice.chars() {
-        if utf16_remaining == 0 {
-            break;
-        }
-        let utf16_len = ch.len_utf16();
-        if utf16_remaining < utf16_len {
-            // Cursor is in the middle of a surrogate pair — snap to start
-            break;
-        }
-        utf16_remaining -= utf16_len;
-        char_offset += 1;
+/// The `$schema` member that applies under `precedence`.
+pub fn effective_schema_key(text: &str, precedence: SchemaKeyPrecedence) -> Option<SchemaKey> {
+    match precedence {
+        SchemaKeyPrecedence::First => schema_keys(text).next(),
+        SchemaKeyPrecedence::Last => schema_keys(text).last(),
     }
+}
 
-    Ok(line_char_start + char_offset)
+/// A `"$schema"` member of the root object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaKey {
+    /// Byte offset of the key's opening quote.
+    pub key_start: usize,
+    /// Byte range of the value.
+    pub value_start: usize,
+    pub value_end: usize,
+    /// The value, when it is a non-empty string.
+    pub url: Option<String>,
 }
 
-/// Scan the first ~2 KiB of the document for a `"$schema"` key.
-pub fn extract_schema_url(text: &str) -> Option<String> {
-    // We only need to look near the top of the file
-    let scan = &text[..text.len().min(2048)];
+/// The `"$schema"` members of the root object, in document order. Nested values are
+/// skipped without being parsed; the scan ends at the first malformed member, so a
+/// document being typed still yields the keys before the cursor.
+pub fn schema_keys(text: &str) -> SchemaKeys<'_> {
+    let bytes = text.as_bytes();
+    let mut pos = skip_trivia(bytes, 0);
+    let in_object = bytes.get(pos) == Some(&b'{');
+    pos += 1;
+    SchemaKeys {
+        text,
+        pos,
+        done: !in_object,
+    }
+}
 
-    // Find "$schema" key
-    let key_pos = scan.find("\"$schema\"")?;
-    let after_key = &scan[key_pos + 9..]; // skip `"$schema"`
+pub struct SchemaKeys<'a> {
+    text: &'a str,
+    /// Where the next member may start.
+    pos: usize,
+    done: bool,
+}
+
+impl Iterator for SchemaKeys<'_> {
+    type Item = SchemaKey;
 
-    // Find ':'
-    let colon = after_key.find(':')? + 1;
-    let after_colon = after_key[colon..].trim_start();
+    fn next(&mut self) -> Option<SchemaKey> {
+        let bytes = self.text.as_bytes();
+        while !self.done {
+            self.done = true;
+            let key_start = skip_trivia(bytes, self.pos);
+            if bytes.get(key_start) != Some(&b'"') {
+                return None;
+            }
+            let key_end = value_end(bytes, key_start)?;
+            let colon = skip_trivia(bytes, key_end);
+            if bytes.get(colon) != Some(&b':') {
+                return None;
+            }
+            let value_start = skip_trivia(bytes, colon + 1);
+            let value_end = value_end(bytes, value_start)?;
+            let after = skip_trivia(bytes, value_end);
+            if bytes.get(after) == Some(&b',') {
+                self.pos = after + 1;
+                self.done = false;
+            }
 
-    // Expect a quoted string value
-    if !after_colon.starts_with('"') {
-        return None;
+            if &self.text[key_start..key_end] == "\"$schema\"" {
+                let url = serde_json::from_str::<String>(&self.text[value_start..value_end])
+                    .ok()
+                    .filter(|url| !url.is_empty());
+                return Some(SchemaKey {
+                    key_start,
+                    value_start,
+                    value_end,
+                    url,
+                });
+            }
+        }
+        None
     }
+}
 
-    let inner = &after_colon[1..];
-    let end = inner.find('"')?;
-    let url = &inner[..end];
+/// Skip whitespace, a byte-order mark and `//` / `/* */` comments.
+fn skip_trivia(bytes: &[u8], mut pos: usize) -> usize {
+    loop {
+        match bytes.get(pos..) {
+            Some([b' ' | b'\t' | b'\r' | b'\n', ..]) => pos += 1,
+            Some([0xEF, 0xBB, 0xBF, ..]) => pos += 3,
+            Some([b'/', b'/', ..]) => {
+                pos = bytes[pos..]
+                    .iter()
+                    .position(|&b| b == b'\n')
+                    .map_or(bytes.len(), |idx| pos + idx);
+            }
+            Some([b'/', b'*', ..]) => {
+                pos = bytes[pos + 2..]
+                    .windows(2)
+                    .position(|w| w == b"*/")
+                    .map_or(bytes.len(), |idx| pos + 2 + idx + 2);
+            }
+            _ => return pos,
+        }
+    }
+}
 
-    if url.is_empty() {
-        None
-    } else {
-        Some(url.to_owned())
+/// End of the value starting at `start`: past the closing quote or bracket, or at the
+/// delimiter ending a literal. `None` if a string or container is left open.
+fn value_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut pos = start;
+    let mut depth = 0usize;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'"' => {
+                pos += 1;
+                loop {
+                    match bytes.get(pos)? {
+                        b'\\' => pos += 2,
+                        b'"' => break,
+                        _ => pos += 1,
+                    }
+                }
+            }
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' if depth > 0 => depth -= 1,
+            b',' | b'}' | b']' | b' ' | b'\t' | b'\r' | b'\n' | b'/' if depth == 0 => {
+                return Some(pos);
+            }
+            _ => {}
+        }
+        pos += 1;
+        if depth == 0 && matches!(bytes[start], b'"' | b'{' | b'[') {
+            return Some(pos);
+        }
     }
+    (depth == 0 && !matches!(bytes.get(start), Some(b'"' | b'{' | b'['))).then_some(pos)
 }
 
 #[cfg(test)]
@@ -177,12 +512,127 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_open_from_changes() {
+        let store = DocumentStore::new();
+        let uri = Url::parse("file:///tmp/unknown.json").unwrap();
+        let incremental = |text: &str| TextDocumentContentChangeEvent {
+            range: Some(tower_lsp::lsp_types::Range::default()),
+            range_length: None,
+            text: text.into(),
+        };
+
+        assert!(!store
+            .open_from_changes(uri.clone(), 2, vec![incremental("x")])
+            .unwrap());
+        assert!(!store.contains(&uri));
+
+        let full = TextDocumentContentChangeEvent {
+            range: None,
+            range_length: None,
+            text: "{}".into(),
+        };
+        assert!(store
+            .open_from_changes(
+                uri.clone(),
+                3,
+                vec![incremental("x"), full, incremental(" ")]
+            )
+            .unwrap());
+        assert_eq!(store.get_text(&uri).as_deref(), Some(" {}"));
+    }
+
+    #[test]
+    fn test_incremental_burst_rebuilds_text_once_on_read() {
+        let store = DocumentStore::new();
+        let uri = Url::parse("file:///tmp/burst.json").unwrap();
+        let padding = format!("{{\"pad\": \"{}\"}}", "x".repeat(1 << 20));
+        store.open(uri.clone(), 1, padding.clone());
+
+        // A paste delivered one character per event at the start of the document.
+        let pasted = "{\"$schema\": \"https://example.com/s.json\", \"p\": ";
+        for (i, ch) in pasted.chars().rev().enumerate() {
+            let change = TextDocumentContentChangeEvent {
+                range: Some(tower_lsp::lsp_types::Range::default()),
+                range_length: None,
+                text: ch.to_string(),
+            };
+            store.update(&uri, 2 + i as i32, vec![change]).unwrap();
+        }
+        assert_eq!(store.inner.get(&uri).unwrap().rebuilds, 0);
+
+        assert_eq!(store.get_text(&uri), Some(format!("{pasted}{padding}")));
+        assert_eq!(
+            store.get_schema_url(&uri).as_deref(),
+            Some("https://example.com/s.json")
+        );
+        assert_eq!(store.inner.get(&uri).unwrap().rebuilds, 1);
+    }
+
     #[test]
     fn test_extract_schema_url_missing() {
         let text = r#"{ "name": "test" }"#;
         assert!(extract_schema_url(text).is_none());
     }
 
+    #[test]
+    fn test_duplicate_schema_keys_follow_precedence() {
+        let text = r#"{
+  // leading comment
+  "$schema": "https://example.com/a.json",
+  "nested": { "$schema": "https://example.com/nested.json", "s": "}\"," },
+  "$schema": "https://example.com/b.json"
+}"#;
+        let keys: Vec<_> = schema_keys(text).collect();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(
+            &text[keys[1].value_start..keys[1].value_end],
+            r#""https://example.com/b.json""#
+        );
+        assert_eq!(
+            extract_schema_url_with(text, SchemaKeyPrecedence::First).as_deref(),
+            Some("https://example.com/a.json")
+        );
+        assert_eq!(
+            extract_schema_url_with(text, SchemaKeyPrecedence::Last).as_deref(),
+            Some("https://example.com/b.json")
+        );
+
+        // Open documents follow a precedence change.
+        let store = DocumentStore::new();
+        let uri = Url::parse("file:///tmp/dup.json").unwrap();
+        store.open(uri.clone(), 1, text.into());
+        store.set_schema_key_precedence(SchemaKeyPrecedence::Last);
+        assert_eq!(
+            store.get_inline_schema_url(&uri).as_deref(),
+            Some("https://example.com/b.json")
+        );
+        store.set_schema_key_precedence(SchemaKeyPrecedence::First);
+        assert_eq!(
+            store.get_inline_schema_url(&uri).as_deref(),
+            Some("https://example.com/a.json")
+        );
+
+        // Only the root object's members count, and a half-typed tail is ignored.
+        assert!(extract_schema_url(r#"[{ "$schema": "https://example.com/a.json" }]"#).is_none());
+        assert_eq!(
+            extract_schema_url(r#"{ "$schema": "https://example.com/a.json", "na"#).as_deref(),
+            Some("https://example.com/a.json")
+        );
+    }
+
+    #[test]
+    fn test_extract_schema_url_after_long_multibyte_prefix() {
+        let text = format!(
+            r#"{{ "title": "{}", "$schema": "https://example.com/s.json" }}"#,
+            "é".repeat(2048)
+        );
+        assert_eq!(
+            extract_schema_url(&text).as_deref(),
+            Some("https://example.com/s.json")
+        );
+    }
+
     #[test]
     fn test_lsp_pos_to_char_ascii() {
         let rope = Rope::from_str("hello\nworld\n");
@@ -206,4 +656,127 @@ mod tests {
         let idx = lsp_pos_to_char_idx(&rope, pos).unwrap();
         assert_eq!(idx, 2); // 'a' + '😀' = 2 chars
     }
+
+    #[test]
+    fn test_ranged_changes_apply_under_any_sync_kind() {
+        let store = DocumentStore::new();
+        let uri = Url::parse("file:///tmp/full.json").unwrap();
+        store.open(uri.clone(), 1, r#"{"a": 1}"#.into());
+
+        // A client offered FULL sync that still sends a ranged edit.
+        let change = TextDocumentContentChangeEvent {
+            range: Some(tower_lsp::lsp_types::Range::new(
+                tower_lsp::lsp_types::Position::new(0, 6),
+                tower_lsp::lsp_types::Position::new(0, 7),
+            )),
+            range_length: None,
+            text: "2".into(),
+        };
+        store.update(&uri, 2, vec![change]).unwrap();
+        assert_eq!(store.get_text(&uri).as_deref(), Some(r#"{"a": 2}"#));
+    }
+
+    #[test]
+    fn test_negotiate_sync_kind() {
+        let minimal = ClientCapabilities::default();
+        assert_eq!(
+            negotiate_sync_kind(None, &minimal),
+            TextDocumentSyncKind::FULL
+        );
+
+        let editor = ClientCapabilities {
+            text_document: Some(Default::default()),
+            ..Default::default()
+        };
+        assert_eq!(
+            negotiate_sync_kind(None, &editor),
+            TextDocumentSyncKind::INCREMENTAL
+        );
+        assert_eq!(
+            negotiate_sync_kind(Some(TextDocumentSyncKind::FULL), &editor),
+            TextDocumentSyncKind::FULL
+        );
+    }
+
+    #[test]
+    fn test_catalog_association_by_file_name() {
+        let entry = CatalogEntry {
+            name: "package.json".into(),
+            url: "https://example.com/package.json".into(),
+            file_match: vec!["package.json".into()],
+        };
+        let store = DocumentStore::new();
+        let package = Url::parse("file:///app/package.json").unwrap();
+        let inline = Url::parse("file:///lib/package.json").unwrap();
+        store.open(package.clone(), 1, "{}".into());
+        store.open(
+            inline.clone(),
+            1,
+            r#"{ "$schema": "https://example.com/own.json" }"#.into(),
+        );
+        assert_eq!(store.get_schema_url(&package), None);
+
+        // Open documents are re-associated, later ones associated on open.
+        store.set_catalog(Arc::new(vec![entry]));
+        let other = Url::parse("file:///other.json").unwrap();
+        store.open(other.clone(), 1, "{}".into());
+        assert_eq!(
+            store.get_schema_url(&package).as_deref(),
+            Some("https://example.com/package.json")
+        );
+        assert_eq!(
+            store.get_schema_url(&inline).as_deref(),
+            Some("https://example.com/own.json")
+        );
+        assert_eq!(store.get_schema_url(&other), None);
+    }
+
+    #[test]
+    fn test_file_match_settings_precede_language_and_catalog() {
+        let store = DocumentStore::new();
+        store.set_catalog(Arc::new(vec![CatalogEntry {
+            name: "any".into(),
+            url: "https://example.com/catalog.json".into(),
+            file_match: vec!["*.json".into()],
+        }]));
+        store.set_schema_associations(vec![SchemaAssociation {
+            file_match: vec!["configs/**.json".into(), "!configs/legacy/*".into()],
+            url: "https://example.com/config.json".into(),
+            schema: None,
+        }]);
+
+        let config = Url::parse("file:///repo/configs/app/prod.json").unwrap();
+        let legacy = Url::parse("file:///repo/configs/legacy/old.json").unwrap();
+        for uri in [&config, &legacy] {
+            store.open(uri.clone(), 1, "{}".into());
+            store.set_language_schema_url(uri, Some("https://example.com/lang.json".into()));
+        }
+        assert_eq!(
+            store.get_schema_url(&config).as_deref(),
+            Some("https://example.com/config.json")
+        );
+        assert_eq!(
+            store.get_schema_url(&legacy).as_deref(),
+            Some("https://example.com/lang.json")
+        );
+
+        store.set_schema_associations(Vec::new());
+        assert_eq!(
+            store.get_schema_url(&config).as_deref(),
+            Some("https://example.com/lang.json")
+        );
+
+        // The external resolver's answer ranks below every other association.
+        store.set_associated_schema_url(&config, Some("https://example.com/resolved.json".into()));
+        store.set_language_schema_url(&config, None);
+        assert_eq!(
+            store.get_schema_url(&config).as_deref(),
+            Some("https://example.com/catalog.json")
+        );
+        store.set_catalog(Arc::new(Vec::new()));
+        assert_eq!(
+            store.get_schema_url(&config).as_deref(),
+            Some("https://example.com/resolved.json")
+        );
+    }
 }
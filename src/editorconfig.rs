@@ -0,0 +1,187 @@
+//! Minimal [EditorConfig](https://editorconfig.org) reader: the properties that affect
+//! JSON formatting, resolved for one file path.
+
+use std::path::Path;
+
+/// Formatting-relevant properties. `None` means "not specified by any .editorconfig".
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EditorConfig {
+    pub indent_style: Option<IndentStyle>,
+    pub indent_size: Option<usize>,
+    pub tab_width: Option<usize>,
+    pub insert_final_newline: Option<bool>,
+    pub end_of_line: Option<&'static str>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentStyle {
+    Space,
+    Tab,
+}
+
+/// Resolve the properties applying to `file` by reading `.editorconfig` files from its
+/// directory upwards until one declares `root = true`.
+pub fn editorconfig_for(file: &Path) -> EditorConfig {
+    let mut files = Vec::new();
+    for dir in file.ancestors().skip(1) {
+        let Ok(text) = std::fs::read_to_string(dir.join(".editorconfig")) else {
+            continue;
+        };
+        let is_root = is_root_file(&text);
+        files.push((dir.to_path_buf(), text));
+        if is_root {
+            break;
+        }
+    }
+
+    // Outermost first, so nearer files override.
+    let mut config = EditorConfig::default();
+    for (dir, text) in files.iter().rev() {
+        let Ok(relative) = file.strip_prefix(dir) else {
+            continue;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        apply_file(&mut config, text, &relative);
+    }
+    config
+}
+
+fn is_root_file(text: &str) -> bool {
+    text.lines()
+        .map(str::trim)
+        .take_while(|line| !line.starts_with('['))
+        .filter_map(|line| line.split_once('='))
+        .any(|(key, value)| {
+            key.trim().eq_ignore_ascii_case("root") && value.trim().eq_ignore_ascii_case("true")
+        })
+}
+
+/// Apply every section of one .editorconfig whose glob matches `relative`.
+fn apply_file(config: &mut EditorConfig, text: &str, relative: &str) {
+    let mut matches = false;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            matches = section_matches(section, relative);
+            continue;
+        }
+        if !matches {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim().to_ascii_lowercase();
+        match key.trim().to_ascii_lowercase().as_str() {
+            "indent_style" => {
+                config.indent_style = match value.as_str() {
+                    "space" => Some(IndentStyle::Space),
+                    "tab" => Some(IndentStyle::Tab),
+                    _ => None,
+                }
+            }
+            "indent_size" => config.indent_size = value.parse().ok(),
+            "tab_width" => config.tab_width = value.parse().ok(),
+            "insert_final_newline" => config.insert_final_newline = value.parse().ok(),
+            "end_of_line" => {
+                config.end_of_line = match value.as_str() {
+                    "lf" => Some("\n"),
+                    "crlf" => Some("\r\n"),
+                    "cr" => Some("\r"),
+                    _ => None,
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Section globs without a `/` match the file name in any directory.
+pub(crate) fn section_matches(section: &str, relative: &str) -> bool {
+    let pattern = match section.strip_prefix('/') {
+        Some(anchored) => anchored.to_owned(),
+        None if section.contains('/') => section.to_owned(),
+        None => format!("**/{section}"),
+    };
+    expand_braces(&pattern)
+        .iter()
+        .any(|p| glob_match(p.as_bytes(), relative.as_bytes()))
+}
+
+/// Expand `{a,b}` alternatives into separate patterns.
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let Some(open) = pattern.find('{') else {
+        return vec![pattern.to_owned()];
+    };
+    let Some(close) = pattern[open..].find('}').map(|i| open + i) else {
+        return vec![pattern.to_owned()];
+    };
+    let (head, tail) = (&pattern[..open], &pattern[close + 1..]);
+    pattern[open + 1..close]
+        .split(',')
+        .flat_map(|alt| expand_braces(&format!("{head}{alt}{tail}")))
+        .collect()
+}
+
+/// `*` matches within a path segment, `**` across segments, `?` one character.
+fn glob_match(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        [b'*', b'*', b'/', rest @ ..] => {
+            // `**/` may also match zero directories.
+            glob_match(rest, path)
+                || (0..path.len())
+                    .filter(|&i| path[i] == b'/')
+                    .any(|i| glob_match(rest, &path[i + 1..]))
+        }
+        [b'*', b'*', rest @ ..] => (0..=path.len()).any(|i| glob_match(rest, &path[i..])),
+        [b'*', rest @ ..] => (0..=path.len())
+            .take_while(|&i| i == 0 || path[i - 1] != b'/')
+            .any(|i| glob_match(rest, &path[i..])),
+        [b'?', rest @ ..] => !path.is_empty() && path[0] != b'/' && glob_match(rest, &path[1..]),
+        [c, rest @ ..] => path.first() == Some(c) && glob_match(rest, &path[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_section_matches() {
+        assert!(section_matches("*", "a/b/c.json"));
+        assert!(section_matches("*.json", "config/app.json"));
+        assert!(section_matches("*.{json,jsonc}", "x.jsonc"));
+        assert!(!section_matches("*.md", "x.json"));
+        assert!(section_matches("config/*.json", "config/app.json"));
+        assert!(!section_matches("config/*.json", "config/nested/app.json"));
+        assert!(section_matches("config/**.json", "config/nested/app.json"));
+    }
+
+    #[test]
+    fn test_editorconfig_for_nearest_wins() {
+        let root = std::env::temp_dir().join(format!("json-ls-ec-{}", std::process::id()));
+        let nested = root.join("nested");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(
+            root.join(".editorconfig"),
+            "root = true\n\n[*]\nindent_style = space\nindent_size = 4\nend_of_line = crlf\n",
+        )
+        .unwrap();
+        std::fs::write(
+            nested.join(".editorconfig"),
+            "[*.json]\nindent_size = 2\ninsert_final_newline = true\n",
+        )
+        .unwrap();
+
+        let config = editorconfig_for(&nested.join("a.json"));
+        assert_eq!(config.indent_style, Some(IndentStyle::Space));
+        assert_eq!(config.indent_size, Some(2));
+        assert_eq!(config.insert_final_newline, Some(true));
+        assert_eq!(config.end_of_line, Some("\r\n"));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,280 @@
+use crate::document::DocumentStore;
+use crate::editorconfig::{editorconfig_for, EditorConfig, IndentStyle};
+use crate::offsets::byte_range_to_lsp_range;
+use crate::tolerant::Dialect;
+use serde::de::IgnoredAny;
+use std::sync::Arc;
+use tower_lsp::lsp_types::{DocumentFormattingParams, FormattingOptions, TextEdit};
+use tracing::debug;
+
+/// Resolved output style for one formatting request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatStyle {
+    pub indent: String,
+    pub insert_final_newline: bool,
+    pub end_of_line: &'static str,
+}
+
+impl FormatStyle {
+    /// Merge client options with `.editorconfig`. EditorConfig indentation wins when the
+    /// client only sent the generic defaults (4 spaces, which most editors send unasked);
+    /// EditorConfig fills in the newline settings whenever the client leaves them unset.
+    pub fn resolve(options: &FormattingOptions, editorconfig: &EditorConfig, text: &str) -> Self {
+        let generic = options.tab_size == 0 || (options.tab_size == 4 && options.insert_spaces);
+        let client_tabs = !options.insert_spaces;
+        let client_size = options.tab_size.max(1) as usize;
+
+        let (tabs, size) = if generic {
+            let tabs = editorconfig
+                .indent_style
+                .map_or(client_tabs, |style| style == IndentStyle::Tab);
+            let size = editorconfig
+                .indent_size
+                .or(editorconfig.tab_width)
+                .unwrap_or(client_size);
+            (tabs, size)
+        } else {
+            (client_tabs, client_size)
+        };
+
+        let insert_final_newline = options
+            .insert_final_newline
+            .or(editorconfig.insert_final_newline)
+            .unwrap_or_else(|| text.ends_with('\n'));
+        let end_of_line = editorconfig.end_of_line.unwrap_or_else(|| {
+            if text.contains("\r\n") {
+                "\r\n"
+            } else {
+                "\n"
+            }
+        });
+
+        Self {
+            indent: if tabs { "\t".into() } else { " ".repeat(size) },
+            insert_final_newline,
+            end_of_line,
+        }
+    }
+}
+
+/// Re-indent the whole document. Syntactically invalid documents are left alone, and so
+/// are JSON Lines, whose records must stay on one line each.
+pub fn handle_formatting(
+    documents: &Arc<DocumentStore>,
+    params: DocumentFormattingParams,
+) -> Option<Vec<TextEdit>> {
+    let uri = &params.text_document.uri;
+    if documents.get_dialect(uri) == Dialect::JsonLines {
+        return None;
+    }
+    let text = documents.get_text(uri)?;
+    let editorconfig = uri
+        .to_file_path()
+        .map(|path| editorconfig_for(&path))
+        .unwrap_or_default();
+    let style = FormatStyle::resolve(&params.options, &editorconfig, &text);
+
+    let formatted = format_json(&text, &style)?;
+    if formatted == text {
+        return Some(Vec::new());
+    }
+    debug!("Formatting {uri} with {style:?}");
+
+    Some(vec![TextEdit {
+        range: byte_range_to_lsp_range(&text, 0, text.len()),
+        new_text: formatted,
+    }])
+}
+
+/// Pretty-print `text` in `style`, preserving key order and literal spelling.
+pub fn format_json(text: &str, style: &FormatStyle) -> Option<String> {
+    serde_json::from_str::<IgnoredAny>(text).ok()?;
+
+    let bytes = text.as_bytes();
+    let mut out = String::with_capacity(text.len());
+    let mut depth = 0usize;
+    let mut i = 0;
+    let newline = |out: &mut String, depth: usize| {
+        out.push_str(style.end_of_line);
+        for _ in 0..depth {
+            out.push_str(&style.indent);
+        }
+    };
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b' ' | b'\t' | b'\r' | b'\n' => i += 1,
+            open @ (b'{' | b'[') => {
+                let close = if open == b'{' { b'}' } else { b']' };
+                let next = next_non_ws(bytes, i + 1);
+                if bytes.get(next) == Some(&close) {
+                    out.push(open as char);
+                    out.push(close as char);
+                    i = next + 1;
+                } else {
+                    out.push(open as char);
+                    depth += 1;
+                    newline(&mut out, depth);
+                    i += 1;
+                }
+            }
+            close @ (b'}' | b']') => {
+                depth -= 1;
+                newline(&mut out, depth);
+                out.push(close as char);
+                i += 1;
+            }
+            b',' => {
+                out.push(',');
+                newline(&mut out, depth);
+                i += 1;
+            }
+            b':' => {
+                out.push_str(": ");
+                i += 1;
+            }
+            b'"' => {
+                let end = string_end(bytes, i);
+                out.push_str(&text[i..end]);
+                i = end;
+            }
+            _ => {
+                let end = bytes[i..]
+                    .iter()
+                    .position(|b| b" \t\r\n,:{}[]".contains(b))
+                    .map_or(bytes.len(), |n| i + n);
+                out.push_str(&text[i..end]);
+                i = end;
+            }
+        }
+    }
+
+    if style.insert_final_newline {
+        out.push_str(style.end_of_line);
+    }
+    Some(out)
+}
+
+/// Pretty-print the JSON `json` for insertion at byte `at` of `text`: the document's indent
+/// unit and line endings, continuation lines aligned with the line containing `at`.
+pub fn reindent_for(json: &str, text: &str, at: usize) -> String {
+    let end_of_line = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    let style = FormatStyle {
+        indent: indent_unit(text).to_owned(),
+        insert_final_newline: false,
+        end_of_line,
+    };
+    let pretty = format_json(json, &style).unwrap_or_else(|| json.to_owned());
+    pretty.replace(
+        end_of_line,
+        &format!("{end_of_line}{}", line_indent(text, at)),
+    )
+}
+
+/// `value` serialized for insertion at byte `at` of `text`: compact in a single-line
+/// document (or for a scalar or empty container), else pretty-printed to fit in at `at`.
+pub fn render_at(value: &serde_json::Value, text: &str, at: usize) -> String {
+    render_compact_at(&value.to_string(), text, at)
+}
+
+/// [`render_at`] for JSON already serialized compactly, e.g. in its source key order.
+pub fn render_compact_at(compact: &str, text: &str, at: usize) -> String {
+    let nested = compact.starts_with(['{', '[']) && compact.len() > 2;
+    if !nested || !text.contains('\n') {
+        return compact.to_owned();
+    }
+    reindent_for(compact, text, at)
+}
+
+/// `s` as a JSON string literal, quoted and escaped.
+pub fn json_string(s: &str) -> String {
+    serde_json::Value::String(s.to_owned()).to_string()
+}
+
+/// Leading whitespace of the first indented line, else two spaces.
+pub fn indent_unit(text: &str) -> &str {
+    text.lines()
+        .skip(1)
+        .map(|line| &line[..line.len() - line.trim_start_matches([' ', '\t']).len()])
+        .find(|indent| !indent.is_empty())
+        .unwrap_or("  ")
+}
+
+/// Leading whitespace of the line containing byte `at`.
+pub fn line_indent(text: &str, at: usize) -> &str {
+    let line_start = text[..at].rfind('\n').map_or(0, |i| i + 1);
+    let line = &text[line_start..];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+fn next_non_ws(bytes: &[u8], mut i: usize) -> usize {
+    while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+        i += 1;
+    }
+    i
+}
+
+/// Byte offset just past the string starting at `start` (which must be a `"`).
+fn string_end(bytes: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(tab_size: u32, insert_spaces: bool) -> FormattingOptions {
+        FormattingOptions {
+            tab_size,
+            insert_spaces,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_format_json_preserves_order_and_literals() {
+        let style = FormatStyle {
+            indent: "  ".into(),
+            insert_final_newline: true,
+            end_of_line: "\n",
+        };
+        let formatted =
+            format_json(r#"{"z":1.0,"a":[],"s":"x, \"y\"","o":{"k":[1,2]}}"#, &style).unwrap();
+        assert_eq!(
+            formatted,
+            "{\n  \"z\": 1.0,\n  \"a\": [],\n  \"s\": \"x, \\\"y\\\"\",\n  \"o\": {\n    \"k\": [\n      1,\n      2\n    ]\n  }\n}\n"
+        );
+        assert!(format_json("{\"a\":", &style).is_none());
+    }
+
+    #[test]
+    fn test_editorconfig_overrides_generic_options_only() {
+        let editorconfig = EditorConfig {
+            indent_style: Some(IndentStyle::Tab),
+            insert_final_newline: Some(false),
+            end_of_line: Some("\r\n"),
+            ..Default::default()
+        };
+
+        let style = FormatStyle::resolve(&options(4, true), &editorconfig, "{}\n");
+        assert_eq!(style.indent, "\t");
+        assert!(!style.insert_final_newline);
+        assert_eq!(style.end_of_line, "\r\n");
+
+        let style = FormatStyle::resolve(&options(2, true), &editorconfig, "{}\n");
+        assert_eq!(style.indent, "  ");
+
+        let style = FormatStyle::resolve(&options(4, true), &EditorConfig::default(), "{}\n");
+        assert_eq!(style.indent, "    ");
+        assert!(style.insert_final_newline);
+        assert_eq!(style.end_of_line, "\n");
+    }
+}
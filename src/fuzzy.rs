@@ -0,0 +1,128 @@
+//! Case-insensitive subsequence matching for symbol and path search, and edit-distance
+//! matching for "did you mean" suggestions.
+
+/// Score `candidate` against `query`: `None` unless every query character appears in
+/// order. Higher is better — matches at word starts (after `_ - . / ` or at a lowercase →
+/// uppercase step), runs of consecutive matches and a match of the whole candidate score
+/// extra; longer candidates score slightly less. Whitespace in the query is ignored.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let mut query = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .peekable();
+    if query.peek().is_none() {
+        return Some(0);
+    }
+
+    let mut score = 0i64;
+    let mut previous: Option<char> = None;
+    let mut previous_matched = false;
+    let mut matched_count = 0i64;
+    for c in candidate.chars() {
+        let Some(&wanted) = query.peek() else {
+            break;
+        };
+        let matched = c.to_lowercase().eq(std::iter::once(wanted));
+        if matched {
+            query.next();
+            matched_count += 1;
+            score += 1;
+            if is_word_start(previous, c) {
+                score += 8;
+            }
+            if previous_matched {
+                score += 4;
+            }
+        }
+        previous_matched = matched;
+        previous = Some(c);
+    }
+
+    if query.peek().is_some() {
+        return None;
+    }
+    let len = candidate.chars().count() as i64;
+    if matched_count == len {
+        score += 16;
+    }
+    Some(score * 16 - len)
+}
+
+fn is_word_start(previous: Option<char>, c: char) -> bool {
+    match previous {
+        None => true,
+        Some(p) => {
+            matches!(p, '_' | '-' | '.' | '/' | ' ' | '$')
+                || (p.is_lowercase() && c.is_uppercase())
+                || (!p.is_ascii_digit() && c.is_ascii_digit())
+        }
+    }
+}
+
+/// Case-insensitive Levenshtein distance between `a` and `b`, in characters.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().flat_map(char::to_lowercase).collect();
+    let b: Vec<char> = b.chars().flat_map(char::to_lowercase).collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// The candidate closest to `target`, if within a third of its length (at least one edit);
+/// the first candidate wins ties.
+pub fn closest_match<'a>(
+    target: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let max_distance = target.chars().count().div_ceil(3).max(1);
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(target, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subsequence_required() {
+        assert!(fuzzy_score("rtp", "retryPolicy").is_some());
+        assert!(fuzzy_score("RETRY", "retryPolicy").is_some());
+        assert_eq!(fuzzy_score("ptr", "retryPolicy"), None);
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn test_word_starts_and_runs_rank_higher() {
+        let exact = fuzzy_score("retryPolicy", "retryPolicy").unwrap();
+        let camel = fuzzy_score("rp", "retryPolicy").unwrap();
+        let scattered = fuzzy_score("rp", "errorProp").unwrap();
+        let buried = fuzzy_score("rp", "carpet").unwrap();
+        assert!(exact > camel);
+        assert!(camel > buried);
+        assert!(scattered > buried);
+    }
+
+    #[test]
+    fn test_closest_match_within_a_third_of_the_length() {
+        assert_eq!(edit_distance("colour", "color"), 1);
+        assert_eq!(edit_distance("Debug", "debug"), 0);
+        assert_eq!(edit_distance("nmae", "name"), 2);
+        let levels = ["debug", "info", "warn", "error"];
+        assert_eq!(closest_match("wran", levels), Some("warn"));
+        assert_eq!(closest_match("INFO", levels), Some("info"));
+        assert_eq!(closest_match("verbose", levels), None);
+        assert_eq!(closest_match("x", ["y", "zz"]), Some("y"));
+    }
+}
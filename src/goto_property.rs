@@ -0,0 +1,137 @@
+use crate::document::DocumentStore;
+use crate::fuzzy::fuzzy_score;
+use crate::offsets::byte_range_to_lsp_range;
+use crate::span::{parse_spans, SpanKind, SpanMember, SpanNode};
+use tower_lsp::lsp_types::{Location, Url};
+use tracing::debug;
+
+pub const GOTO_PROPERTY_COMMAND: &str = "json-ls.gotoProperty";
+
+/// Score lost per path segment the query skips over, so `srv cert` prefers
+/// `server.cert` to `server.tls.cert`.
+const SKIPPED_SEGMENT_PENALTY: i64 = 8;
+
+/// Find the key in the document at `uri` whose path best matches `query`: whitespace- (or
+/// `.` / `/`-) separated fuzzy terms matched in order against the path's keys, the last term
+/// against the key itself. E.g. `srv tls cert` finds `server.tls.certificateFile`.
+pub fn goto_property(documents: &DocumentStore, uri: &Url, query: &str) -> Option<Location> {
+    let terms: Vec<&str> = query
+        .split(|c: char| c.is_whitespace() || c == '.' || c == '/')
+        .filter(|term| !term.is_empty())
+        .collect();
+    if terms.is_empty() {
+        return None;
+    }
+
+    let text = documents.get_text(uri)?;
+    let spans = parse_spans(&text)?;
+    let mut best: Option<(i64, &SpanMember)> = None;
+    let mut path = Vec::new();
+    visit_members(&spans, &mut path, &mut |path, member| {
+        if let Some(score) = path_score(&terms, path) {
+            // Strictly greater: ties go to the member earliest in the document.
+            if best.is_none_or(|(best_score, _)| score > best_score) {
+                best = Some((score, member));
+            }
+        }
+    });
+
+    let (score, member) = best?;
+    debug!("gotoProperty {query:?}: {:?} (score {score})", member.key);
+    Some(Location {
+        uri: uri.clone(),
+        range: byte_range_to_lsp_range(&text, member.key_start, member.key_end),
+    })
+}
+
+/// Call `visit` for every member, with the keys leading to it (its own key last).
+fn visit_members<'a>(
+    node: &'a SpanNode,
+    path: &mut Vec<&'a str>,
+    visit: &mut impl FnMut(&[&'a str], &'a SpanMember),
+) {
+    match &node.kind {
+        SpanKind::Object(members) => {
+            for member in members {
+                path.push(&member.key);
+                visit(path, member);
+                visit_members(&member.value, path, visit);
+                path.pop();
+            }
+        }
+        SpanKind::Array(items) => {
+            for item in items {
+                visit_members(item, path, visit);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Best in-order alignment of `terms` to `path`, the last term on the last key. `None` if
+/// some term cannot be placed.
+fn path_score(terms: &[&str], path: &[&str]) -> Option<i64> {
+    let (last_term, leading_terms) = terms.split_last()?;
+    let (last_key, ancestors) = path.split_last()?;
+    let last = fuzzy_score(last_term, last_key)?;
+
+    // best[j + 1]: best score placing the terms so far with the latest one on ancestor j;
+    // best[0] is the start, before any ancestor.
+    let mut best: Vec<Option<i64>> = vec![None; ancestors.len() + 1];
+    best[0] = Some(0);
+    for term in leading_terms {
+        let mut next = vec![None; ancestors.len() + 1];
+        let mut running: Option<i64> = None;
+        for (j, key) in ancestors.iter().enumerate() {
+            // Best placement of the previous terms strictly before ancestor j.
+            if let Some(previous) = best[j] {
+                running = Some(running.map_or(previous, |r| r.max(previous)));
+            }
+            if let (Some(before), Some(score)) = (running, fuzzy_score(term, key)) {
+                next[j + 1] = Some(before + score);
+            }
+        }
+        best = next;
+    }
+
+    let placed = best.iter().flatten().max()?;
+    let skipped = (ancestors.len() - leading_terms.len()) as i64;
+    Some(placed + last - skipped * SKIPPED_SEGMENT_PENALTY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_lsp::lsp_types::Position;
+
+    const CONFIG: &str = r#"{
+  "server": {
+    "port": 8080,
+    "tls": { "certificateFile": "a.pem", "keyFile": "a.key" }
+  },
+  "client": { "tls": { "certificateFile": "b.pem" } },
+  "cert": true
+}"#;
+
+    fn goto(query: &str) -> Option<Position> {
+        let documents = DocumentStore::new();
+        let uri = Url::parse("file:///tmp/config.json").unwrap();
+        documents.open(uri.clone(), 1, CONFIG.into());
+        goto_property(&documents, &uri, query).map(|location| location.range.start)
+    }
+
+    #[test]
+    fn test_terms_match_path_in_order() {
+        assert_eq!(goto("srv tls cert"), Some(Position::new(3, 13)));
+        assert_eq!(goto("client.cert"), Some(Position::new(5, 23)));
+        assert_eq!(goto("srv key"), Some(Position::new(3, 41)));
+    }
+
+    #[test]
+    fn test_shallower_and_earlier_matches_win() {
+        assert_eq!(goto("cert"), Some(Position::new(6, 2)));
+        assert_eq!(goto("tls"), Some(Position::new(3, 4)));
+        assert_eq!(goto("tls server"), None);
+        assert_eq!(goto("  "), None);
+    }
+}
@@ -1,5 +1,9 @@
+use crate::authoring::keywords::keyword_doc;
+use crate::authoring::{is_meta_schema_url, keyword_at};
+use crate::config::ServerConfig;
 use crate::document::DocumentStore;
-use crate::position::{position_to_context, PositionContext};
+use crate::markdown::{code_span, render_description};
+use crate::position::{position_to_context, PathSegment, PositionContext};
 use crate::schema::{SchemaCache, SchemaNode};
 use std::sync::Arc;
 use tower_lsp::lsp_types::{Hover, HoverContents, HoverParams, MarkupContent, MarkupKind};
@@ -8,38 +12,84 @@ use tracing::debug;
 pub async fn handle_hover(
     documents: &Arc<DocumentStore>,
     schema_cache: &Arc<SchemaCache>,
+    config: &ServerConfig,
     params: HoverParams,
 ) -> Option<Hover> {
     let uri = &params.text_document_position_params.text_document.uri;
     let pos = params.text_document_position_params.position;
 
-    let text = documents.get_text(uri)?;
+    let text = documents.get_instance_text(uri, pos.line)?;
     let schema_url = documents.get_schema_url(uri)?;
 
     let context = position_to_context(&text, pos.line, pos.character);
     debug!("Hover context: {context:?}");
 
     let path = match &context {
-        PositionContext::Value { path } | PositionContext::Key { path } => path.clone(),
+        PositionContext::Value { path } | PositionContext::Key { path, .. } => path.clone(),
         _ => return None,
     };
 
+    // Schema-authoring path: hovering a keyword in a schema document shows the built-in
+    // keyword reference instead of the meta-schema's description.
+    if let PositionContext::Key { path, .. } = &context {
+        if is_meta_schema_url(&schema_url) {
+            if let Some(doc) = keyword_at(path).and_then(keyword_doc) {
+                return Some(markdown_hover(doc.to_markdown()));
+            }
+        }
+    }
+
     let schema_value = schema_cache.get_or_fetch(&schema_url).await.ok()?;
-    let root_node = SchemaNode::new(&schema_value, &schema_value);
+    let root_node = SchemaNode::new(&schema_value, &schema_value).with_limits(config.schema_limits);
     let node = root_node.navigate(&path)?;
 
-    let info = node.hover_info();
-    let markdown = info.to_markdown();
+    let mut info = node.hover_info();
+    // `deprecated` often sits beside the `$ref` in `properties`, which navigation follows.
+    let declaration = root_node.declaration(&path);
+    let declaration = declaration.as_ref().unwrap_or(&node);
+    if declaration.is_deprecated() {
+        let note = declaration.deprecation_message().unwrap_or_default();
+        info.deprecated = Some(render_description(note, &schema_url, &config.markdown));
+    }
+    if let Some((PathSegment::Key(key), parent_path)) = path.split_last() {
+        info.required = root_node
+            .navigate(parent_path)
+            .is_some_and(|parent| parent.required_names().contains(key.as_str()));
+    }
+    info.description = info
+        .description
+        .map(|d| render_description(&d, &schema_url, &config.markdown));
+    let mut markdown = info.to_markdown();
 
     if markdown.is_empty() {
         return None;
     }
 
-    Some(Hover {
+    // Composed and catalog-associated schemas leave it unclear where the text came from.
+    if let Some(pointer) = node.resolved_pointer() {
+        markdown.push_str("\n\n");
+        markdown.push_str(&provenance(&schema_url, &pointer));
+    }
+
+    Some(markdown_hover(markdown))
+}
+
+/// "from `https://example.com/schema.json#/properties/port`"; "from inline schema `#/…`"
+/// for `data:` URLs, which are mostly noise.
+fn provenance(schema_url: &str, pointer: &str) -> String {
+    if schema_url.starts_with("data:") {
+        return format!("from inline schema {}", code_span(&format!("#{pointer}")));
+    }
+    let document = schema_url.split('#').next().unwrap_or(schema_url);
+    format!("from {}", code_span(&format!("{document}#{pointer}")))
+}
+
+fn markdown_hover(markdown: String) -> Hover {
+    Hover {
         contents: HoverContents::Markup(MarkupContent {
             kind: MarkupKind::Markdown,
             value: markdown,
         }),
         range: None,
-    })
+    }
 }
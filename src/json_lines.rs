@@ -0,0 +1,61 @@
+//! JSON Lines (`.jsonl` / `.ndjson`): every line is an instance of its own. Validation
+//! reads the lines one by one; position-based features see the line under the cursor alone.
+
+use tower_lsp::lsp_types::{Diagnostic, Range, Url};
+
+/// The non-blank lines of `text`, with their 0-based line numbers.
+pub fn instance_lines(text: &str) -> impl Iterator<Item = (u32, &str)> {
+    text.split('\n')
+        .enumerate()
+        .map(|(index, line)| (index as u32, line.strip_suffix('\r').unwrap_or(line)))
+        .filter(|(_, line)| !line.trim().is_empty())
+}
+
+/// `text` with every line but `line` emptied: positions and ranges on `line` are unchanged,
+/// and the only value left is the instance there.
+pub fn focus_line(text: &str, line: u32) -> String {
+    let mut focused = String::with_capacity(text.len());
+    for (index, content) in text.split('\n').enumerate() {
+        if index > 0 {
+            focused.push('\n');
+        }
+        if index as u32 == line {
+            focused.push_str(content);
+        }
+    }
+    focused
+}
+
+/// Move `diagnostic`, computed on a line's text alone, to `line` of the document at `uri`.
+pub fn shift_diagnostic(mut diagnostic: Diagnostic, uri: &Url, line: u32) -> Diagnostic {
+    let shift = |range: &mut Range| {
+        range.start.line += line;
+        range.end.line += line;
+    };
+    shift(&mut diagnostic.range);
+    for related in diagnostic.related_information.iter_mut().flatten() {
+        if related.location.uri == *uri {
+            shift(&mut related.location.range);
+        }
+    }
+    diagnostic
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instance_lines_skip_blank_lines() {
+        let text = "{\"a\": 1}\r\n\n  \n[2]";
+        let lines: Vec<(u32, &str)> = instance_lines(text).collect();
+        assert_eq!(lines, [(0, "{\"a\": 1}"), (3, "[2]")]);
+    }
+
+    #[test]
+    fn test_focus_line_keeps_positions() {
+        let text = "{\"a\": 1}\n{\"b\": 2}\n{\"c\": 3}";
+        assert_eq!(focus_line(text, 1), "\n{\"b\": 2}\n");
+        assert_eq!(focus_line(text, 5), "\n\n");
+    }
+}
@@ -0,0 +1,421 @@
+//! RFC 6902 JSON Patch applied as text edits: only the members and items an operation touches
+//! are rewritten, so the rest of the document keeps its formatting and comments.
+
+use crate::formatting::{json_string, render_at};
+use crate::span::{parse_spans, ByteEdit, SpanKind, SpanNode};
+use serde_json::Value;
+
+pub const APPLY_PATCH_COMMAND: &str = "json-ls.applyPatch";
+
+/// Apply `patch` (an array of RFC 6902 operations) to `text` and return the edits against
+/// `text` that produce the result. Operations apply in order; the first failing one
+/// (including a failed `test`) aborts the whole patch.
+pub fn apply_patch(text: &str, patch: &Value) -> Result<Vec<ByteEdit>, String> {
+    let operations = patch
+        .as_array()
+        .ok_or("patch must be an array of operations")?;
+    let mut doc = PatchedText {
+        original: text,
+        current: text.to_owned(),
+        edits: Vec::new(),
+    };
+    for (idx, operation) in operations.iter().enumerate() {
+        apply_operation(&mut doc, operation).map_err(|e| format!("operation {idx}: {e}"))?;
+    }
+
+    let mut edits = doc.edits;
+    edits.retain(|edit| text[edit.start..edit.end] != edit.new_text);
+    Ok(edits)
+}
+
+/// The patched text so far, plus the edits against the original text that produce it.
+struct PatchedText<'a> {
+    original: &'a str,
+    current: String,
+    /// Sorted, non-overlapping, in original-text offsets.
+    edits: Vec<ByteEdit>,
+}
+
+impl PatchedText<'_> {
+    /// Replace `start..end` of the current text, folding the change into `edits`. Earlier
+    /// edits that overlap or touch the range merge with it into one.
+    fn replace(&mut self, start: usize, end: usize, new_text: String) {
+        let shift =
+            |edit: &ByteEdit| edit.new_text.len() as isize - (edit.end - edit.start) as isize;
+
+        // Offset shift (current − original) in front of the edit being looked at.
+        let mut delta = 0isize;
+        let mut first = 0;
+        for edit in &self.edits {
+            if edit.start.saturating_add_signed(delta) + edit.new_text.len() >= start {
+                break;
+            }
+            delta += shift(edit);
+            first += 1;
+        }
+        let delta_before = delta;
+        let (mut merged_start, mut merged_end) = (start, end);
+        let mut stop = first;
+        for edit in &self.edits[first..] {
+            let current_start = edit.start.saturating_add_signed(delta);
+            if current_start > end {
+                break;
+            }
+            merged_start = merged_start.min(current_start);
+            merged_end = merged_end.max(current_start + edit.new_text.len());
+            delta += shift(edit);
+            stop += 1;
+        }
+
+        let merged = ByteEdit {
+            start: merged_start.saturating_add_signed(-delta_before),
+            end: merged_end.saturating_add_signed(-delta),
+            new_text: format!(
+                "{}{new_text}{}",
+                &self.current[merged_start..start],
+                &self.current[end..merged_end]
+            ),
+        };
+        debug_assert!(merged.end <= self.original.len());
+        self.edits.splice(first..stop, [merged]);
+        self.current.replace_range(start..end, &new_text);
+    }
+}
+
+fn apply_operation(doc: &mut PatchedText, operation: &Value) -> Result<(), String> {
+    let field = |name: &str| {
+        operation
+            .get(name)
+            .and_then(Value::as_str)
+            .ok_or_else(|| format!("missing string field `{name}`"))
+    };
+    let value = || {
+        operation
+            .get("value")
+            .cloned()
+            .ok_or_else(|| "missing field `value`".to_owned())
+    };
+    let path = split_pointer(field("path")?)?;
+
+    match field("op")? {
+        "add" => add(doc, &path, &value()?),
+        "remove" => remove(doc, &path),
+        "replace" => {
+            let root = parse_spans(&doc.current).ok_or("document is empty")?;
+            let node = resolve(&root, &path).ok_or("path does not exist")?;
+            let new_text = render_at(&value()?, &doc.current, node.start);
+            doc.replace(node.start, node.end, new_text);
+            Ok(())
+        }
+        "move" => {
+            let from = split_pointer(field("from")?)?;
+            if path.len() > from.len() && path.starts_with(&from) {
+                return Err("cannot move a value into one of its children".into());
+            }
+            let moved = value_at(doc, &from)?;
+            remove(doc, &from)?;
+            add(doc, &path, &moved)
+        }
+        "copy" => {
+            let copied = value_at(doc, &split_pointer(field("from")?)?)?;
+            add(doc, &path, &copied)
+        }
+        "test" => {
+            if json_equal(&value_at(doc, &path)?, &value()?) {
+                Ok(())
+            } else {
+                Err(format!("test failed at `{}`", field("path")?))
+            }
+        }
+        other => Err(format!("unknown op `{other}`")),
+    }
+}
+
+fn add(doc: &mut PatchedText, path: &[String], value: &Value) -> Result<(), String> {
+    let Some((last, parent_path)) = path.split_last() else {
+        let (start, end) =
+            parse_spans(&doc.current).map_or((0, doc.current.len()), |root| (root.start, root.end));
+        let new_text = render_at(value, &doc.current, start);
+        doc.replace(start, end, new_text);
+        return Ok(());
+    };
+    let root = parse_spans(&doc.current).ok_or("document is empty")?;
+    let parent = resolve(&root, parent_path).ok_or("parent path does not exist")?;
+    let text = &doc.current;
+
+    let (idx, entry) = match &parent.kind {
+        SpanKind::Object(members) => {
+            if let Some(member) = members.iter().find(|m| m.key == *last) {
+                let new_text = render_at(value, text, member.value.start);
+                doc.replace(member.value.start, member.value.end, new_text);
+                return Ok(());
+            }
+            let colon = members
+                .first()
+                .map(|m| &text[m.key_end..m.value.start])
+                .filter(|sep| sep.chars().all(|c| c == ':' || c.is_whitespace()))
+                .unwrap_or(": ");
+            let at = members.last().map_or(parent.start, |m| m.value.end);
+            let entry = format!("{}{colon}{}", json_string(last), render_at(value, text, at));
+            (members.len(), entry)
+        }
+        SpanKind::Array(items) => {
+            let idx = if last == "-" {
+                items.len()
+            } else {
+                parse_index(last)
+                    .filter(|&idx| idx <= items.len())
+                    .ok_or("index out of bounds")?
+            };
+            let at = items
+                .get(idx)
+                .or(items.last())
+                .map_or(parent.start, |item| item.start);
+            (idx, render_at(value, text, at))
+        }
+        _ => return Err("parent is not an object or array".into()),
+    };
+
+    let entries = entry_ranges(parent);
+    let separator = separator(text, parent, &entries);
+    match (entries.get(idx), entries.last()) {
+        (Some(&(start, _)), _) => doc.replace(start, start, format!("{entry}{separator}")),
+        (None, Some(&(_, end))) => doc.replace(end, end, format!("{separator}{entry}")),
+        (None, None) => doc.replace(parent.start + 1, parent.end - 1, entry),
+    }
+    Ok(())
+}
+
+fn remove(doc: &mut PatchedText, path: &[String]) -> Result<(), String> {
+    let (last, parent_path) = path
+        .split_last()
+        .ok_or("cannot remove the whole document")?;
+    let root = parse_spans(&doc.current).ok_or("document is empty")?;
+    let parent = resolve(&root, parent_path).ok_or("parent path does not exist")?;
+    let idx = match &parent.kind {
+        SpanKind::Object(members) => members.iter().position(|m| m.key == *last),
+        SpanKind::Array(items) => parse_index(last).filter(|&idx| idx < items.len()),
+        _ => None,
+    }
+    .ok_or("path does not exist")?;
+
+    let entries = entry_ranges(parent);
+    let (start, end) = if entries.len() == 1 {
+        (parent.start + 1, parent.end - 1)
+    } else if idx + 1 < entries.len() {
+        (entries[idx].0, entries[idx + 1].0)
+    } else {
+        (entries[idx - 1].1, entries[idx].1)
+    };
+    doc.replace(start, end, String::new());
+    Ok(())
+}
+
+fn value_at(doc: &PatchedText, path: &[String]) -> Result<Value, String> {
+    let root = parse_spans(&doc.current).ok_or("document is empty")?;
+    let node = resolve(&root, path).ok_or("path does not exist")?;
+    serde_json::from_str(&doc.current[node.start..node.end])
+        .map_err(|e| format!("value is not valid JSON: {e}"))
+}
+
+/// Equality as RFC 6902 `test` defines it: numbers compare by value (`1` equals `1.0`),
+/// arrays item by item and objects member by member.
+fn json_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => match (a.as_i64(), b.as_i64()) {
+            (Some(a), Some(b)) => a == b,
+            _ => match (a.as_u64(), b.as_u64()) {
+                (Some(a), Some(b)) => a == b,
+                _ => a.as_f64() == b.as_f64(),
+            },
+        },
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| json_equal(a, b))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| b.get(key).is_some_and(|b| json_equal(a, b)))
+        }
+        (a, b) => a == b,
+    }
+}
+
+/// Split a JSON Pointer into unescaped reference tokens.
+fn split_pointer(pointer: &str) -> Result<Vec<String>, String> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let tokens = pointer
+        .strip_prefix('/')
+        .ok_or_else(|| format!("`{pointer}` is not a JSON Pointer"))?;
+    Ok(tokens
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+fn resolve<'a>(root: &'a SpanNode, path: &[String]) -> Option<&'a SpanNode> {
+    let mut node = root;
+    for token in path {
+        node = match &node.kind {
+            SpanKind::Object(_) => &node.member(token)?.value,
+            SpanKind::Array(items) => items.get(parse_index(token)?)?,
+            _ => return None,
+        };
+    }
+    Some(node)
+}
+
+/// An RFC 6902 array index: digits without leading zeros.
+fn parse_index(token: &str) -> Option<usize> {
+    if token.len() > 1 && token.starts_with('0') {
+        return None;
+    }
+    token.parse().ok()
+}
+
+/// Byte ranges of a container's members (key through value) or items.
+fn entry_ranges(container: &SpanNode) -> Vec<(usize, usize)> {
+    match &container.kind {
+        SpanKind::Object(members) => members.iter().map(|m| (m.key_start, m.value.end)).collect(),
+        SpanKind::Array(items) => items.iter().map(|item| (item.start, item.end)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The `,` and whitespace the container already puts between entries, so a new entry lands
+/// on its own line in a multi-line container and inline in a compact one.
+fn separator(text: &str, container: &SpanNode, entries: &[(usize, usize)]) -> String {
+    let existing = match entries {
+        [.., (_, previous_end), (last_start, _)] => text[*previous_end..*last_start].to_owned(),
+        [(first_start, _)] => format!(",{}", &text[container.start + 1..*first_start]),
+        [] => String::new(),
+    };
+    if existing.chars().all(|c| c == ',' || c.is_whitespace()) && existing.contains(',') {
+        return existing;
+    }
+    // Something else (a comment) between entries: keep only the line break and indentation.
+    match existing.rfind('\n') {
+        Some(newline) if existing[..newline].ends_with('\r') => {
+            format!(",\r{}", &existing[newline..])
+        }
+        Some(newline) => format!(",{}", &existing[newline..]),
+        None => ", ".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn patched(text: &str, patch: Value) -> Result<String, String> {
+        let mut edits = apply_patch(text, &patch)?;
+        edits.sort_by_key(|e| std::cmp::Reverse(e.start));
+        let mut out = text.to_owned();
+        for edit in edits {
+            out.replace_range(edit.start..edit.end, &edit.new_text);
+        }
+        Ok(out)
+    }
+
+    #[test]
+    fn test_edits_keep_surrounding_formatting() {
+        let text = "{\n    \"name\": \"app\", // primary\n    \"ports\": [80,443],\n    \"debug\": false\n}\n";
+        let result = patched(
+            text,
+            json!([
+                { "op": "replace", "path": "/name", "value": "api" },
+                { "op": "add", "path": "/ports/1", "value": 8080 },
+                { "op": "remove", "path": "/debug" },
+                { "op": "add", "path": "/tls", "value": { "cert": "a.pem" } }
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            result,
+            "{\n    \"name\": \"api\", // primary\n    \"ports\": [80,8080,443],\n    \"tls\": {\n        \"cert\": \"a.pem\"\n    }\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_edits_are_minimal() {
+        let text = r#"{"a": 1, "b": [1, 2], "c": {}}"#;
+        let edits = apply_patch(
+            text,
+            &json!([
+                { "op": "replace", "path": "/a", "value": 2 },
+                { "op": "add", "path": "/c/d", "value": true },
+                { "op": "replace", "path": "/c/d", "value": null }
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            edits,
+            vec![
+                ByteEdit {
+                    start: 6,
+                    end: 7,
+                    new_text: "2".into()
+                },
+                ByteEdit {
+                    start: 28,
+                    end: 28,
+                    new_text: "\"d\": null".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_move_copy_and_array_append() {
+        let text = r#"{"from": {"x": 1}, "list": ["a"]}"#;
+        let result = patched(
+            text,
+            json!([
+                { "op": "copy", "from": "/list/0", "path": "/list/-" },
+                { "op": "move", "from": "/from/x", "path": "/to" },
+                { "op": "remove", "path": "/list/0" }
+            ]),
+        )
+        .unwrap();
+        assert_eq!(result, r#"{"from": {}, "list": ["a"], "to": 1}"#);
+        assert_eq!(
+            serde_json::from_str::<Value>(&result).unwrap(),
+            json!({ "from": {}, "list": ["a"], "to": 1 })
+        );
+    }
+
+    #[test]
+    fn test_failures_abort_the_patch() {
+        let text = r#"{"a": 1}"#;
+        let patch = json!([
+            { "op": "replace", "path": "/a", "value": 2 },
+            { "op": "test", "path": "/a", "value": 1 }
+        ]);
+        assert_eq!(
+            patched(text, patch).unwrap_err(),
+            "operation 1: test failed at `/a`"
+        );
+        assert!(patched(text, json!([{ "op": "remove", "path": "/b" }])).is_err());
+        assert!(patched(text, json!([{ "op": "add", "path": "/a/b/c", "value": 1 }])).is_err());
+        assert!(patched(text, json!([{ "op": "move", "from": "", "path": "/a" }])).is_err());
+        assert!(patched(text, json!({ "op": "remove" })).is_err());
+        assert_eq!(
+            patched(text, json!([{ "op": "test", "path": "/a", "value": 1 }])).unwrap(),
+            text
+        );
+    }
+
+    #[test]
+    fn test_numbers_compare_by_value() {
+        let text = r#"{"a": 1, "b": [1.0, {"c": 2}]}"#;
+        let patch = json!([
+            { "op": "test", "path": "/a", "value": 1.0 },
+            { "op": "test", "path": "/b", "value": [1, { "c": 2.0 }] }
+        ]);
+        assert_eq!(patched(text, patch).unwrap(), text);
+        assert!(patched(text, json!([{ "op": "test", "path": "/a", "value": 1.5 }])).is_err());
+    }
+}
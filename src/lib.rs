@@ -0,0 +1,61 @@
+//! json-ls as a library: the LSP [`Backend`] plus the pieces it is built from, for tools
+//! that want schema-driven validation, hover and completion without spawning the binary.
+
+pub mod add_schema;
+pub mod association;
+pub mod authoring;
+#[cfg(feature = "native")]
+pub mod backend;
+pub mod cleanup;
+pub mod code_action;
+pub mod code_lens;
+pub mod completion;
+pub mod config;
+pub mod content;
+pub mod definition;
+pub mod deprecated;
+pub mod diagnostics;
+pub mod document;
+pub mod editorconfig;
+pub mod formatting;
+pub mod fuzzy;
+pub mod goto_property;
+pub mod hover;
+pub mod json_lines;
+pub mod json_patch;
+pub mod markdown;
+pub mod metrics;
+pub mod notebook;
+pub mod notifications;
+pub mod numbers;
+pub mod offsets;
+pub mod on_type;
+pub mod paste;
+pub mod path_completion;
+pub mod percent;
+pub mod position;
+pub mod references;
+#[cfg(feature = "native")]
+pub mod resolver;
+pub mod schema;
+pub mod span;
+pub mod suggest;
+pub mod symbols;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod time_box;
+pub mod tolerant;
+pub mod unknown_properties;
+pub mod usage;
+pub mod workspace;
+pub mod workspace_symbol;
+pub mod workspace_validation;
+
+#[cfg(feature = "native")]
+pub use backend::Backend;
+pub use completion::handle_completion;
+pub use config::ServerConfig;
+pub use diagnostics::validate_document;
+pub use document::DocumentStore;
+pub use hover::handle_hover;
+pub use schema::{SchemaCache, SchemaLoader};
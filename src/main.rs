@@ -1,16 +1,16 @@
-use tower_lsp::{LspService, Server};
+use json_ls::{Backend, ServerConfig};
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tower_lsp::Server;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::{fmt, EnvFilter};
 
-mod backend;
-mod completion;
-mod config;
-mod diagnostics;
-mod document;
-mod hover;
-mod position;
-mod schema;
-
-use backend::Backend;
+/// Log file under the cache dir; it is moved to `json-ls.log.1` once it reaches
+/// [`LOG_ROTATE_BYTES`] at startup.
+const LOG_FILE: &str = "json-ls.log";
+const LOG_ROTATE_BYTES: u64 = 10 * 1024 * 1024;
 
 #[tokio::main]
 async fn main() {
@@ -19,14 +19,45 @@ async fn main() {
         std::process::exit(0);
     }
 
-    fmt()
-        .with_env_filter(EnvFilter::from_default_env())
-        .with_writer(std::io::stderr)
+    let cache_dir = cache_dir_arg(std::env::args().skip(1));
+    // `cache_dir` from initializationOptions arrives too late for the log file; only the
+    // flag and the default location apply to it.
+    let log_file = cache_dir
+        .clone()
+        .or_else(|| ServerConfig::default().cache_dir())
+        .and_then(|dir| open_log_file(&dir));
+    tracing_subscriber::registry()
+        .with(EnvFilter::from_default_env())
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .with(log_file.map(|file| fmt::layer().with_ansi(false).with_writer(Mutex::new(file))))
         .init();
 
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
-    let (service, socket) = LspService::new(Backend::new);
+    let (service, socket) = Backend::service(cache_dir);
     Server::new(stdin, stdout, socket).serve(service).await;
 }
+
+/// `--cache-dir <path>` or `--cache-dir=<path>`.
+fn cache_dir_arg(mut args: impl Iterator<Item = String>) -> Option<PathBuf> {
+    while let Some(arg) = args.next() {
+        if arg == "--cache-dir" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(dir) = arg.strip_prefix("--cache-dir=") {
+            return Some(PathBuf::from(dir));
+        }
+    }
+    None
+}
+
+/// `<dir>/json-ls.log`, appended to; `None` (logging to stderr only) if it cannot be opened.
+fn open_log_file(dir: &Path) -> Option<File> {
+    let path = dir.join(LOG_FILE);
+    if std::fs::metadata(&path).is_ok_and(|meta| meta.len() >= LOG_ROTATE_BYTES) {
+        let _ = std::fs::rename(&path, path.with_extension("log.1"));
+    }
+    std::fs::create_dir_all(dir).ok()?;
+    OpenOptions::new().create(true).append(true).open(path).ok()
+}
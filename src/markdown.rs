@@ -0,0 +1,279 @@
+//! Rendering of schema-supplied text into the Markdown shown by hover and completion.
+//!
+//! Descriptions come from (often remote) schemas, so by default they are treated as
+//! untrusted: raw HTML is escaped and script-capable link targets are dropped. Code spans
+//! and fenced code blocks are left alone — the editor never interprets markup inside them.
+
+use crate::config::MarkdownConfig;
+
+/// Link destinations that can run code when clicked in a Markdown preview.
+const UNSAFE_SCHEMES: &[&str] = &["javascript:", "vbscript:", "data:"];
+
+/// Autolink schemes kept intact (`<https://…>`) instead of being escaped as HTML.
+const AUTOLINK_SCHEMES: &[&str] = &["http://", "https://", "mailto:"];
+
+/// A schema `description` for display: clamped to `max_description_chars`, and sanitized
+/// unless `schema_url` is listed in `trusted_schemas`.
+pub fn render_description(text: &str, schema_url: &str, config: &MarkdownConfig) -> String {
+    let text = clamp(text, config.max_description_chars);
+    if config.is_trusted(schema_url) {
+        text
+    } else {
+        sanitize(&text)
+    }
+}
+
+/// Inline code span showing `text` verbatim, fenced with more backticks than any run inside.
+pub fn code_span(text: &str) -> String {
+    let longest_run = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run + 1);
+    if text.starts_with('`') || text.ends_with('`') {
+        format!("{fence} {text} {fence}")
+    } else {
+        format!("{fence}{text}{fence}")
+    }
+}
+
+/// Cut `text` after `max_chars` characters, marking the cut with an ellipsis.
+fn clamp(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((cut, _)) => format!("{}…", text[..cut].trim_end()),
+        None => text.to_owned(),
+    }
+}
+
+/// Escape raw HTML and neutralize unsafe link targets outside code.
+pub fn sanitize(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut prose = String::new();
+    let mut fence: Option<(char, usize)> = None;
+
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_start_matches(' ');
+        if let Some(open) = fence {
+            out.push_str(line);
+            if fence_marker(trimmed).is_some_and(|(c, n)| c == open.0 && n >= open.1) {
+                fence = None;
+            }
+            continue;
+        }
+        if let Some(open) = fence_marker(trimmed) {
+            sanitize_inline(&prose, &mut out);
+            prose.clear();
+            out.push_str(line);
+            fence = Some(open);
+            continue;
+        }
+        prose.push_str(&sanitize_reference_definition(line));
+    }
+    sanitize_inline(&prose, &mut out);
+    out
+}
+
+/// The fence character and length if `line` opens or closes a fenced code block.
+fn fence_marker(line: &str) -> Option<(char, usize)> {
+    let c = line.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+    let len = line.chars().take_while(|&ch| ch == c).count();
+    (len >= 3).then_some((c, len))
+}
+
+/// `[label]: javascript:…` → `[label]: #`.
+fn sanitize_reference_definition(line: &str) -> String {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 || !trimmed.starts_with('[') {
+        return line.to_owned();
+    }
+    let Some(close) = trimmed.find("]:") else {
+        return line.to_owned();
+    };
+    let rest = &trimmed[close + 2..];
+    let destination = rest.trim_start();
+    if !is_unsafe_destination(destination) {
+        return line.to_owned();
+    }
+    let end = destination
+        .find(char::is_whitespace)
+        .unwrap_or(destination.len());
+    let prefix_len = line.len() - destination.len();
+    format!("{}#{}", &line[..prefix_len], &destination[end..])
+}
+
+fn sanitize_inline(text: &str, out: &mut String) {
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '\\' => {
+                // A backslash escape is copied as a pair so the escaped char is not re-read.
+                let len = rest[1..]
+                    .chars()
+                    .next()
+                    .map_or(1, |next| 1 + next.len_utf8());
+                out.push_str(&rest[..len]);
+                rest = &rest[len..];
+            }
+            '`' => {
+                let run = rest.len() - rest.trim_start_matches('`').len();
+                let span_len = closing_backticks(&rest[run..], run).map_or(run, |end| run + end);
+                out.push_str(&rest[..span_len]);
+                rest = &rest[span_len..];
+            }
+            '<' => match autolink_len(rest) {
+                Some(len) => {
+                    out.push_str(&rest[..len]);
+                    rest = &rest[len..];
+                }
+                None => {
+                    out.push_str("&lt;");
+                    rest = &rest[1..];
+                }
+            },
+            ']' if rest[1..].starts_with('(') => {
+                out.push_str("](");
+                rest = &rest[2..];
+                let destination = rest.trim_start().trim_start_matches('<');
+                if is_unsafe_destination(destination) {
+                    let skipped = rest.len() - destination.len();
+                    let end = destination_len(destination);
+                    out.push('#');
+                    rest = &rest[skipped + end..];
+                }
+            }
+            _ => {
+                out.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+}
+
+/// Length of an inline link destination: up to whitespace, `>`, or the `)` closing the
+/// link (parentheses inside it must balance).
+fn destination_len(destination: &str) -> usize {
+    let mut depth = 0usize;
+    for (idx, c) in destination.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return idx,
+            ')' => depth -= 1,
+            c if c.is_whitespace() || c == '>' => return idx,
+            _ => {}
+        }
+    }
+    destination.len()
+}
+
+/// Byte length up to and including the run of exactly `run` backticks that closes a code
+/// span, if there is one.
+fn closing_backticks(text: &str, run: usize) -> Option<usize> {
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find('`') {
+        let start = offset + start;
+        let len = text[start..].len() - text[start..].trim_start_matches('`').len();
+        if len == run {
+            return Some(start + len);
+        }
+        offset = start + len;
+    }
+    None
+}
+
+/// Length of an autolink such as `<https://example.com>` at the start of `text`.
+fn autolink_len(text: &str) -> Option<usize> {
+    let inner = &text[1..];
+    if !AUTOLINK_SCHEMES
+        .iter()
+        .any(|scheme| starts_with_ignore_case(inner, scheme))
+    {
+        return None;
+    }
+    let end = inner.find(|c: char| c == '>' || c == '<' || c.is_whitespace())?;
+    inner[end..].starts_with('>').then_some(end + 2)
+}
+
+fn is_unsafe_destination(destination: &str) -> bool {
+    // Browsers ignore whitespace and control characters inside a scheme.
+    let scheme: String = destination
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .take(16)
+        .collect();
+    UNSAFE_SCHEMES
+        .iter()
+        .any(|unsafe_scheme| starts_with_ignore_case(&scheme, unsafe_scheme))
+}
+
+fn starts_with_ignore_case(text: &str, prefix: &str) -> bool {
+    text.len() >= prefix.len()
+        && text.is_char_boundary(prefix.len())
+        && text[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_is_escaped_outside_code() {
+        assert_eq!(
+            sanitize("Use <b>bold</b> or `<b>` <img src=x onerror=alert(1)>"),
+            "Use &lt;b>bold&lt;/b> or `<b>` &lt;img src=x onerror=alert(1)>"
+        );
+        let fenced = "Example:\n```html\n<script>alert(1)</script>\n```\n<script>";
+        assert_eq!(
+            sanitize(fenced),
+            "Example:\n```html\n<script>alert(1)</script>\n```\n&lt;script>"
+        );
+    }
+
+    #[test]
+    fn test_unsafe_link_targets_are_dropped() {
+        assert_eq!(
+            sanitize(
+                "[docs](https://example.com) [x](JavaScript:alert(1)) [y]( data:text/html,hi)"
+            ),
+            "[docs](https://example.com) [x](#) [y](#)"
+        );
+        assert_eq!(
+            sanitize("see [x]\n\n[x]: javascript:alert(1) \"t\"\n"),
+            "see [x]\n\n[x]: # \"t\"\n"
+        );
+        assert_eq!(
+            sanitize("<https://example.com/a?b> and \\<b>"),
+            "<https://example.com/a?b> and \\<b>"
+        );
+    }
+
+    #[test]
+    fn test_long_descriptions_are_clamped() {
+        let config = MarkdownConfig {
+            max_description_chars: 5,
+            ..MarkdownConfig::default()
+        };
+        assert_eq!(render_description("héllo world", "x", &config), "héllo…");
+        assert_eq!(render_description("short", "x", &config), "short");
+    }
+
+    #[test]
+    fn test_trusted_schemas_keep_raw_markdown() {
+        let config = MarkdownConfig {
+            trusted_schemas: vec!["file:///work/".into()],
+            ..MarkdownConfig::default()
+        };
+        let text = "<details>more</details>";
+        assert_eq!(
+            render_description(text, "file:///work/schema.json", &config),
+            text
+        );
+        assert_eq!(
+            render_description(text, "https://example.com/schema.json", &config),
+            "&lt;details>more&lt;/details>"
+        );
+    }
+
+    #[test]
+    fn test_code_span_fences_backticks() {
+        assert_eq!(code_span("a"), "`a`");
+        assert_eq!(code_span("a`b"), "``a`b``");
+        assert_eq!(code_span("`x`"), "`` `x` ``");
+    }
+}
@@ -0,0 +1,292 @@
+//! Process-wide request latency and schema cache counters.
+//!
+//! Recording is always on (a few relaxed atomic adds per request); exporting them in the
+//! Prometheus text format over HTTP needs the `metrics` feature and `metrics_addr`.
+
+use serde_json::{json, Map, Value};
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Upper bounds (seconds) of the latency histogram buckets.
+const BUCKETS_SECS: [f64; 8] = [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.5, 1.0];
+
+pub static METRICS: Metrics = Metrics::new();
+
+/// LSP features whose invocations are counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    Hover,
+    Completion,
+    Definition,
+    References,
+    DocumentSymbol,
+    WorkspaceSymbol,
+    Formatting,
+    CodeAction,
+    CodeLens,
+    ExecuteCommand,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 10] = [
+        Feature::Hover,
+        Feature::Completion,
+        Feature::Definition,
+        Feature::References,
+        Feature::DocumentSymbol,
+        Feature::WorkspaceSymbol,
+        Feature::Formatting,
+        Feature::CodeAction,
+        Feature::CodeLens,
+        Feature::ExecuteCommand,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Feature::Hover => "hover",
+            Feature::Completion => "completion",
+            Feature::Definition => "definition",
+            Feature::References => "references",
+            Feature::DocumentSymbol => "documentSymbol",
+            Feature::WorkspaceSymbol => "workspaceSymbol",
+            Feature::Formatting => "formatting",
+            Feature::CodeAction => "codeAction",
+            Feature::CodeLens => "codeLens",
+            Feature::ExecuteCommand => "executeCommand",
+        }
+    }
+}
+
+pub struct Metrics {
+    invocations: [AtomicU64; Feature::ALL.len()],
+    pub hover: Timer,
+    pub completion: Timer,
+    pub diagnostics: Timer,
+    pub cache_hits: AtomicU64,
+    pub cache_misses: AtomicU64,
+    pub fetch_failures: AtomicU64,
+}
+
+/// Latency histogram: cumulative bucket counts plus total count and sum.
+pub struct Timer {
+    buckets: [AtomicU64; BUCKETS_SECS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Timer {
+    const fn new() -> Self {
+        Self {
+            buckets: [const { AtomicU64::new(0) }; BUCKETS_SECS.len()],
+            count: AtomicU64::new(0),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        for (bucket, bound) in self.buckets.iter().zip(BUCKETS_SECS) {
+            if secs <= bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Mean observed duration in milliseconds; `None` before the first observation.
+    pub fn mean_ms(&self) -> Option<f64> {
+        let count = self.count();
+        (count > 0).then(|| self.sum_micros.load(Ordering::Relaxed) as f64 / 1e3 / count as f64)
+    }
+
+    /// `{ count, sumSecs, buckets: { "<le>": n } }`, buckets cumulative as in `render`.
+    fn snapshot(&self) -> Value {
+        let buckets: Map<String, Value> = self
+            .buckets
+            .iter()
+            .zip(BUCKETS_SECS)
+            .map(|(bucket, bound)| (bound.to_string(), bucket.load(Ordering::Relaxed).into()))
+            .collect();
+        json!({
+            "count": self.count(),
+            "sumSecs": self.sum_micros.load(Ordering::Relaxed) as f64 / 1e6,
+            "buckets": buckets,
+        })
+    }
+
+    fn render(&self, out: &mut String, request: &str) {
+        for (bucket, bound) in self.buckets.iter().zip(BUCKETS_SECS) {
+            let _ = writeln!(
+                out,
+                "json_ls_request_duration_seconds_bucket{{request=\"{request}\",le=\"{bound}\"}} {}",
+                bucket.load(Ordering::Relaxed)
+            );
+        }
+        let count = self.count();
+        let _ = writeln!(
+            out,
+            "json_ls_request_duration_seconds_bucket{{request=\"{request}\",le=\"+Inf\"}} {count}"
+        );
+        let _ = writeln!(
+            out,
+            "json_ls_request_duration_seconds_sum{{request=\"{request}\"}} {}",
+            self.sum_micros.load(Ordering::Relaxed) as f64 / 1e6
+        );
+        let _ = writeln!(
+            out,
+            "json_ls_request_duration_seconds_count{{request=\"{request}\"}} {count}"
+        );
+    }
+}
+
+impl Metrics {
+    const fn new() -> Self {
+        Self {
+            invocations: [const { AtomicU64::new(0) }; Feature::ALL.len()],
+            hover: Timer::new(),
+            completion: Timer::new(),
+            diagnostics: Timer::new(),
+            cache_hits: AtomicU64::new(0),
+            cache_misses: AtomicU64::new(0),
+            fetch_failures: AtomicU64::new(0),
+        }
+    }
+
+    /// Count one invocation of `feature`.
+    pub fn record(&self, feature: Feature) {
+        self.invocations[feature as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn invocations(&self, feature: Feature) -> u64 {
+        self.invocations[feature as usize].load(Ordering::Relaxed)
+    }
+
+    /// All counters as JSON: invocations per feature, schema cache counters and latency
+    /// histograms. Contains no document or schema names.
+    pub fn snapshot(&self) -> Value {
+        let features: Map<String, Value> = Feature::ALL
+            .iter()
+            .map(|feature| (feature.name().to_owned(), self.invocations(*feature).into()))
+            .collect();
+        json!({
+            "features": features,
+            "schemaCache": {
+                "hits": self.cache_hits.load(Ordering::Relaxed),
+                "misses": self.cache_misses.load(Ordering::Relaxed),
+                "fetchFailures": self.fetch_failures.load(Ordering::Relaxed),
+            },
+            "latency": {
+                "hover": self.hover.snapshot(),
+                "completion": self.completion.snapshot(),
+                "diagnostics": self.diagnostics.snapshot(),
+            },
+        })
+    }
+
+    /// Render all metrics in the Prometheus text exposition format.
+    pub fn render_prometheus(&self) -> String {
+        let mut out = String::new();
+        out.push_str("# TYPE json_ls_request_duration_seconds histogram\n");
+        self.hover.render(&mut out, "hover");
+        self.completion.render(&mut out, "completion");
+        self.diagnostics.render(&mut out, "diagnostics");
+
+        out.push_str("# HELP json_ls_requests_total LSP feature invocations\n");
+        out.push_str("# TYPE json_ls_requests_total counter\n");
+        for feature in Feature::ALL {
+            let _ = writeln!(
+                out,
+                "json_ls_requests_total{{feature=\"{}\"}} {}",
+                feature.name(),
+                self.invocations(feature)
+            );
+        }
+
+        for (name, help, counter) in [
+            (
+                "json_ls_schema_cache_hits_total",
+                "Schema lookups served from the cache",
+                &self.cache_hits,
+            ),
+            (
+                "json_ls_schema_cache_misses_total",
+                "Schema lookups that required a fetch",
+                &self.cache_misses,
+            ),
+            (
+                "json_ls_schema_fetch_failures_total",
+                "Schema fetches that failed",
+                &self.fetch_failures,
+            ),
+        ] {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} counter");
+            let _ = writeln!(out, "{name} {}", counter.load(Ordering::Relaxed));
+        }
+        out
+    }
+}
+
+/// Serve `GET /metrics` (any path, really) in the Prometheus text format on `addr`. Only
+/// binding fails; connection errors are logged and the endpoint keeps serving.
+#[cfg(feature = "metrics")]
+pub async fn serve_prometheus(addr: &str) -> std::io::Result<()> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tracing::info!("Serving Prometheus metrics on {}", listener.local_addr()?);
+    loop {
+        let mut stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                // Transient (EMFILE, ECONNABORTED, …): keep serving. The pause keeps a
+                // persistent error such as fd exhaustion from spinning the loop.
+                tracing::warn!("Metrics endpoint failed to accept a connection: {e}");
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        tokio::spawn(async move {
+            // The request itself is irrelevant; read what's there and answer.
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf).await;
+            let body = METRICS.render_prometheus();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timer_buckets_are_cumulative() {
+        let timer = Timer::new();
+        timer.observe(Duration::from_millis(3));
+        timer.observe(Duration::from_millis(200));
+
+        let mut out = String::new();
+        timer.render(&mut out, "hover");
+        assert!(out.contains(r#"{request="hover",le="0.001"} 0"#));
+        assert!(out.contains(r#"{request="hover",le="0.005"} 1"#));
+        assert!(out.contains(r#"{request="hover",le="0.5"} 2"#));
+        assert!(out.contains(r#"json_ls_request_duration_seconds_count{request="hover"} 2"#));
+
+        let snapshot = timer.snapshot();
+        assert_eq!(snapshot["count"], 2);
+        assert_eq!(snapshot["buckets"]["0.005"], 1);
+    }
+}
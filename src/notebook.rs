@@ -0,0 +1,103 @@
+//! LSP 3.17 notebook document synchronization (`notebookDocument/*`).
+//!
+//! lsp-types 0.94 predates notebooks, so the wire types live here. Each JSON cell is kept
+//! in the [`DocumentStore`](crate::document::DocumentStore) under its own cell URI, which
+//! is also the URI clients use for hover / completion inside the cell — positions are
+//! already cell-relative, so no further mapping is needed.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tower_lsp::lsp_types::{
+    TextDocumentContentChangeEvent, TextDocumentIdentifier, TextDocumentItem, Url,
+    VersionedTextDocumentIdentifier,
+};
+
+/// Method name for dynamically registering notebook sync.
+pub const NOTEBOOK_SYNC_METHOD: &str = "notebookDocument/sync";
+
+/// Cell languages json-ls handles.
+const CELL_LANGUAGES: &[&str] = &["json", "jsonc"];
+
+/// Registration options: sync notebooks of any type, JSON cells only.
+pub fn notebook_sync_options() -> Value {
+    json!({
+        "notebookSelector": CELL_LANGUAGES
+            .iter()
+            .map(|language| json!({ "cells": [{ "language": language }] }))
+            .collect::<Vec<_>>()
+    })
+}
+
+/// True if `cell` is a JSON cell we should track.
+pub fn is_json_cell(cell: &TextDocumentItem) -> bool {
+    CELL_LANGUAGES.contains(&cell.language_id.as_str())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidOpenNotebookDocumentParams {
+    pub notebook_document: NotebookDocument,
+    pub cell_text_documents: Vec<TextDocumentItem>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotebookDocument {
+    pub uri: Url,
+    pub notebook_type: String,
+    pub version: i32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidChangeNotebookDocumentParams {
+    pub notebook_document: NotebookDocumentIdentifier,
+    pub change: NotebookDocumentChangeEvent,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotebookDocumentChangeEvent {
+    #[serde(default)]
+    pub cells: Option<NotebookCellChanges>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotebookCellChanges {
+    /// Cells added or removed.
+    #[serde(default)]
+    pub structure: Option<NotebookCellStructure>,
+    /// Text edits inside existing cells.
+    #[serde(default)]
+    pub text_content: Vec<NotebookCellTextContent>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotebookCellStructure {
+    #[serde(default)]
+    pub did_open: Vec<TextDocumentItem>,
+    #[serde(default)]
+    pub did_close: Vec<TextDocumentIdentifier>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotebookCellTextContent {
+    pub document: VersionedTextDocumentIdentifier,
+    pub changes: Vec<TextDocumentContentChangeEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidCloseNotebookDocumentParams {
+    pub notebook_document: NotebookDocumentIdentifier,
+    pub cell_text_documents: Vec<TextDocumentIdentifier>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotebookDocumentIdentifier {
+    pub uri: Url,
+}
@@ -0,0 +1,218 @@
+use crate::association::{schema_candidates, SchemaCandidate, SchemaSource};
+use crate::document::DocumentStore;
+use crate::metrics::METRICS;
+use crate::schema::SchemaCache;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use tower_lsp::lsp_types::notification::Notification;
+use tower_lsp::lsp_types::{ProgressToken, Url, WorkspaceDiagnosticReportPartialResult};
+
+/// `json-ls/schemaStatus` — pushed whenever a document's effective schema association is
+/// resolved, changes, or fails to load, so editor plugins can show a statusline indicator.
+pub enum SchemaStatus {}
+
+impl Notification for SchemaStatus {
+    type Params = SchemaStatusParams;
+    const METHOD: &'static str = "json-ls/schemaStatus";
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaStatusParams {
+    pub uri: Url,
+    pub state: SchemaState,
+    /// The schema URL in effect, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema_url: Option<String>,
+    /// Which association supplied `schema_url`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<SchemaSource>,
+    /// Why the schema could not be loaded (only for `error`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Lower-precedence associations that also matched but were not used.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overridden: Vec<SchemaCandidate>,
+    /// Why `schema_url` is the one in effect, e.g. "inline `$schema` takes precedence over
+    /// `schemas` fileMatch `https://…`".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub why: Option<String>,
+}
+
+impl SchemaStatusParams {
+    /// True if `other` describes a different association outcome (ignoring reason text).
+    pub fn differs_from(&self, other: &SchemaStatusParams) -> bool {
+        self.state != other.state
+            || self.schema_url != other.schema_url
+            || self.source != other.source
+            || self.overridden != other.overridden
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SchemaState {
+    /// No schema is associated with the document.
+    None,
+    /// The schema was fetched and is in use.
+    Loaded,
+    /// A schema is associated but could not be fetched or parsed.
+    Error,
+}
+
+/// `$/progress` carrying a batch of `workspace/diagnostic` reports to the request's partial
+/// result token (lsp-types' `Progress` only models work-done progress).
+pub enum WorkspaceDiagnosticProgress {}
+
+impl Notification for WorkspaceDiagnosticProgress {
+    type Params = WorkspaceDiagnosticProgressParams;
+    const METHOD: &'static str = "$/progress";
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkspaceDiagnosticProgressParams {
+    pub token: ProgressToken,
+    pub value: WorkspaceDiagnosticReportPartialResult,
+}
+
+/// `json-ls/metrics` — a compact health summary pushed every `metrics_notification_secs`,
+/// for statusline plugins that would rather not poll `json-ls/status`.
+pub enum MetricsNotification {}
+
+impl Notification for MetricsNotification {
+    type Params = MetricsParams;
+    const METHOD: &'static str = "json-ls/metrics";
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricsParams {
+    pub open_documents: usize,
+    /// Validations run since the server started.
+    pub validations: u64,
+    /// Mean validation time in milliseconds, once one has run.
+    pub average_validation_ms: Option<f64>,
+    /// Share of schema lookups served from the cache, once one has happened.
+    pub cache_hit_rate: Option<f64>,
+}
+
+impl MetricsParams {
+    /// The process counters in [`METRICS`], plus the store's open document count.
+    pub fn current(documents: &DocumentStore) -> Self {
+        let hits = METRICS.cache_hits.load(Ordering::Relaxed);
+        let lookups = hits + METRICS.cache_misses.load(Ordering::Relaxed);
+        Self {
+            open_documents: documents.uris().len(),
+            validations: METRICS.diagnostics.count(),
+            average_validation_ms: METRICS.diagnostics.mean_ms(),
+            cache_hit_rate: (lookups > 0).then(|| hits as f64 / lookups as f64),
+        }
+    }
+}
+
+/// Resolve the current schema status for `uri`: the highest-precedence candidate, which is
+/// the schema validation uses, whether it loads through the cache, and why it won.
+pub async fn resolve_schema_status(
+    uri: &Url,
+    documents: &Arc<DocumentStore>,
+    schema_cache: &Arc<SchemaCache>,
+) -> SchemaStatusParams {
+    let mut candidates = schema_candidates(uri, documents).into_iter();
+    let mut status = SchemaStatusParams {
+        uri: uri.clone(),
+        state: SchemaState::None,
+        schema_url: None,
+        source: None,
+        reason: None,
+        overridden: Vec::new(),
+        why: None,
+    };
+
+    if let Some(candidate) = candidates.next() {
+        match schema_cache.get_or_fetch(&candidate.schema_url).await {
+            Ok(_) => status.state = SchemaState::Loaded,
+            Err(e) => {
+                status.state = SchemaState::Error;
+                status.reason = Some(e.to_string());
+            }
+        }
+        status.overridden = candidates.collect();
+        status.why = Some(precedence_reason(
+            candidate.source,
+            &status.overridden,
+            status.state,
+        ));
+        status.schema_url = Some(candidate.schema_url);
+        status.source = Some(candidate.source);
+    }
+
+    status
+}
+
+/// How an association is named in [`SchemaStatusParams::why`].
+fn source_label(source: SchemaSource) -> &'static str {
+    match source {
+        SchemaSource::Inline => "inline `$schema`",
+        SchemaSource::FileMatch => "`schemas` fileMatch",
+        SchemaSource::Language => "`language_schemas`",
+        SchemaSource::Catalog => "catalog fileMatch",
+        SchemaSource::Resolver => "`schema_resolver`",
+    }
+}
+
+/// Explain why the `winner` association is in effect over `overridden`. A winner that fails
+/// to load is still the one in effect: lower-precedence associations are not a fallback.
+fn precedence_reason(
+    winner: SchemaSource,
+    overridden: &[SchemaCandidate],
+    state: SchemaState,
+) -> String {
+    let winner = source_label(winner);
+    if overridden.is_empty() {
+        return format!("{winner} is the only association");
+    }
+    let losers: Vec<String> = overridden
+        .iter()
+        .map(|candidate| {
+            format!(
+                "{} `{}`",
+                source_label(candidate.source),
+                candidate.schema_url
+            )
+        })
+        .collect();
+    let mut why = format!("{winner} takes precedence over {}", losers.join(", "));
+    if state == SchemaState::Error {
+        why.push_str(
+            "; it is used although it failed to load — fix or remove it to use the next one",
+        );
+    }
+    why
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_precedence_reason_names_winner_and_overridden() {
+        let overridden = vec![SchemaCandidate {
+            schema_url: "https://example.com/catalog.json".into(),
+            source: SchemaSource::Catalog,
+        }];
+        assert_eq!(
+            precedence_reason(SchemaSource::Inline, &[], SchemaState::Loaded),
+            "inline `$schema` is the only association"
+        );
+        assert_eq!(
+            precedence_reason(SchemaSource::Inline, &overridden, SchemaState::Loaded),
+            "inline `$schema` takes precedence over catalog fileMatch \
+             `https://example.com/catalog.json`"
+        );
+        assert!(
+            precedence_reason(SchemaSource::Inline, &overridden, SchemaState::Error)
+                .ends_with("fix or remove it to use the next one")
+        );
+    }
+}
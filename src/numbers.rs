@@ -0,0 +1,215 @@
+//! Opt-in numeric checks beyond JSON Schema: strict integers (`1.0` is not an integer) and
+//! warnings for literals that downstream parsers cannot represent faithfully.
+
+use crate::config::ServerConfig;
+use crate::offsets::byte_range_to_lsp_range;
+use crate::position::PathSegment;
+use crate::schema::SchemaNode;
+use crate::span::{SpanKind, SpanNode};
+use serde_json::Value;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString};
+
+/// Largest integer magnitude an IEEE 754 double represents exactly (2^53).
+const MAX_SAFE_INTEGER: u64 = 1 << 53;
+
+/// Check every number literal in the document according to the numeric settings in
+/// `config`. Returns nothing when both checks are disabled.
+pub fn number_diagnostics(
+    text: &str,
+    spans: &SpanNode,
+    schema: &Value,
+    config: &ServerConfig,
+) -> Vec<Diagnostic> {
+    if !config.strict_integers && !config.number_precision_warnings {
+        return vec![];
+    }
+
+    let root = SchemaNode::new(schema, schema).with_limits(config.schema_limits);
+    let mut diagnostics = Vec::new();
+    let mut path = Vec::new();
+    walk_numbers(text, spans, &mut path, &mut |path, node, literal| {
+        let problem = if config.strict_integers && is_integral_decimal(literal) {
+            root.navigate(path)
+                .filter(|node| requires_integer(node))
+                .map(|_| NumberProblem {
+                    code: "integer-strict",
+                    severity: DiagnosticSeverity::ERROR,
+                    message: format!("{literal} is not an integer (strict integer checking)"),
+                })
+        } else {
+            None
+        };
+        let problem = problem.or_else(|| {
+            config
+                .number_precision_warnings
+                .then(|| precision_problem(literal))
+                .flatten()
+        });
+
+        if let Some(problem) = problem {
+            diagnostics.push(Diagnostic {
+                range: byte_range_to_lsp_range(text, node.start, node.end),
+                severity: Some(problem.severity),
+                code: Some(NumberOrString::String(problem.code.into())),
+                source: Some("json-ls".into()),
+                message: problem.message,
+                ..Default::default()
+            });
+        }
+    });
+    diagnostics
+}
+
+struct NumberProblem {
+    code: &'static str,
+    severity: DiagnosticSeverity,
+    message: String,
+}
+
+/// Visit every number literal in `node` with its path and source text.
+fn walk_numbers(
+    text: &str,
+    node: &SpanNode,
+    path: &mut Vec<PathSegment>,
+    visit: &mut dyn FnMut(&[PathSegment], &SpanNode, &str),
+) {
+    match &node.kind {
+        SpanKind::Object(members) => {
+            for member in members {
+                path.push(PathSegment::Key(member.key.clone()));
+                walk_numbers(text, &member.value, path, visit);
+                path.pop();
+            }
+        }
+        SpanKind::Array(items) => {
+            for (idx, item) in items.iter().enumerate() {
+                path.push(PathSegment::Index(idx));
+                walk_numbers(text, item, path, visit);
+                path.pop();
+            }
+        }
+        SpanKind::Literal => {
+            let literal = &text[node.start..node.end];
+            if literal.starts_with(|c: char| c == '-' || c.is_ascii_digit()) {
+                visit(path, node, literal);
+            }
+        }
+        SpanKind::String(_) => {}
+    }
+}
+
+/// True for literals like `1.0` or `2e3` whose value is integral but whose spelling is not.
+fn is_integral_decimal(literal: &str) -> bool {
+    literal.contains(['.', 'e', 'E'])
+        && literal
+            .parse::<f64>()
+            .is_ok_and(|n| n.is_finite() && n.fract() == 0.0)
+}
+
+/// True if the schema's `type` admits integers but not other numbers.
+fn requires_integer(node: &SchemaNode) -> bool {
+    match node.keyword("type") {
+        Some(Value::String(ty)) => ty == "integer",
+        Some(Value::Array(types)) => {
+            types.iter().any(|t| t == "integer") && !types.iter().any(|t| t == "number")
+        }
+        _ => false,
+    }
+}
+
+/// Warn about integers outside the 64-bit range or beyond exact double precision, and
+/// numbers that overflow a double altogether.
+fn precision_problem(literal: &str) -> Option<NumberProblem> {
+    let warning = |message: String| NumberProblem {
+        code: "number-precision",
+        severity: DiagnosticSeverity::WARNING,
+        message,
+    };
+
+    if literal.contains(['.', 'e', 'E']) {
+        let value = literal.parse::<f64>().ok()?;
+        return value
+            .is_infinite()
+            .then(|| warning(format!("{literal} overflows a 64-bit float")));
+    }
+
+    let magnitude = literal.trim_start_matches('-');
+    let fits_64_bit = if literal.starts_with('-') {
+        literal.parse::<i64>().is_ok()
+    } else {
+        literal.parse::<u64>().is_ok()
+    };
+    if !fits_64_bit {
+        return Some(warning(format!(
+            "{literal} is outside the 64-bit integer range"
+        )));
+    }
+    match magnitude.parse::<u64>() {
+        Ok(n) if n > MAX_SAFE_INTEGER => Some(warning(format!(
+            "{literal} cannot be represented exactly by a 64-bit float"
+        ))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::parse_spans;
+    use serde_json::json;
+
+    fn codes(text: &str, schema: &Value, config: &ServerConfig) -> Vec<String> {
+        let spans = parse_spans(text).unwrap();
+        number_diagnostics(text, &spans, schema, config)
+            .into_iter()
+            .map(|d| match d.code {
+                Some(NumberOrString::String(code)) => code,
+                _ => String::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_strict_integers() {
+        let schema = json!({
+            "properties": {
+                "count": { "type": "integer" },
+                "ratio": { "type": ["integer", "number"] }
+            }
+        });
+        let text = r#"{"count": 1.0, "ratio": 2.0}"#;
+
+        assert!(codes(text, &schema, &ServerConfig::default()).is_empty());
+
+        let config = ServerConfig {
+            strict_integers: true,
+            ..Default::default()
+        };
+        assert_eq!(codes(text, &schema, &config), ["integer-strict"]);
+        assert!(codes(r#"{"count": 1}"#, &schema, &config).is_empty());
+    }
+
+    #[test]
+    fn test_number_precision_warnings() {
+        let config = ServerConfig {
+            number_precision_warnings: true,
+            ..Default::default()
+        };
+        let schema = json!({});
+
+        assert!(codes("[1, -9007199254740992, 1.5]", &schema, &config).is_empty());
+        assert_eq!(
+            codes(
+                "[9007199254740993, 18446744073709551616, -9223372036854775809, 1e400]",
+                &schema,
+                &config
+            ),
+            [
+                "number-precision",
+                "number-precision",
+                "number-precision",
+                "number-precision"
+            ]
+        );
+    }
+}
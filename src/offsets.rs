@@ -0,0 +1,229 @@
+//! Conversions between LSP positions (line + UTF-16 code unit) and offsets into the
+//! document, shared by every handler so both directions agree.
+//!
+//! Rules, in both directions:
+//! - a position inside a surrogate pair (or an offset inside a multi-byte character) snaps
+//!   back to the start of that character;
+//! - a character past the end of a line clamps to the end of the line, before its `\n` /
+//!   `\r\n`;
+//! - only `\n` and `\r\n` break lines (ropey is built without CR / Unicode line breaks).
+
+use anyhow::{anyhow, Result};
+use ropey::Rope;
+use tower_lsp::lsp_types::{Position, Range};
+
+/// Convert an LSP `Position` to a byte offset in `text`. `None` if `line` is past the end.
+pub fn lsp_position_to_byte_offset(text: &str, line: u32, character: u32) -> Option<usize> {
+    let line_start = match line {
+        0 => 0,
+        n => text.match_indices('\n').nth(n as usize - 1)?.0 + 1,
+    };
+
+    let line_text = &text[line_start..];
+    let line_text = match line_text.find('\n') {
+        Some(end) => line_text[..end]
+            .strip_suffix('\r')
+            .unwrap_or(&line_text[..end]),
+        None => line_text,
+    };
+
+    // Fast path: an ASCII prefix has one UTF-16 unit per byte.
+    let ascii_len = (character as usize).min(line_text.len());
+    if line_text.as_bytes()[..ascii_len].is_ascii() {
+        return Some(line_start + ascii_len);
+    }
+
+    // Skip whole chunks while the target lies beyond them, then walk char by char.
+    const CHUNK: usize = 256;
+    let mut chunk_start = 0;
+    let mut utf16_count = 0u32;
+    while chunk_start + CHUNK < line_text.len() {
+        let mut chunk_end = chunk_start + CHUNK;
+        while !line_text.is_char_boundary(chunk_end) {
+            chunk_end += 1;
+        }
+        let chunk = &line_text.as_bytes()[chunk_start..chunk_end];
+        let units = if chunk.is_ascii() {
+            chunk.len() as u32
+        } else {
+            utf16_len(chunk)
+        };
+        if utf16_count + units > character {
+            break;
+        }
+        chunk_start = chunk_end;
+        utf16_count += units;
+    }
+    for (byte_off, ch) in line_text[chunk_start..].char_indices() {
+        utf16_count += ch.len_utf16() as u32;
+        if utf16_count > character {
+            return Some(line_start + chunk_start + byte_off);
+        }
+    }
+
+    // Cursor at (or past) end of line
+    Some(line_start + line_text.len())
+}
+
+/// Convert a byte offset in `text` to an LSP position as `(line, character)`.
+pub fn byte_offset_to_lsp_pos(text: &str, byte_offset: usize) -> (u32, u32) {
+    let mut byte_offset = byte_offset.min(text.len());
+    while !text.is_char_boundary(byte_offset) {
+        byte_offset -= 1;
+    }
+    let before = &text[..byte_offset];
+    let line = before.bytes().filter(|&b| b == b'\n').count() as u32;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+    // Count UTF-16 units from line_start to byte_offset
+    let character = utf16_len(&before.as_bytes()[line_start..]);
+
+    (line, character)
+}
+
+/// Convert a byte range in `text` to an LSP Range.
+pub fn byte_range_to_lsp_range(text: &str, start: usize, end: usize) -> Range {
+    let (start_line, start_char) = byte_offset_to_lsp_pos(text, start);
+    let (end_line, end_char) = byte_offset_to_lsp_pos(text, end);
+    Range {
+        start: Position {
+            line: start_line,
+            character: start_char,
+        },
+        end: Position {
+            line: end_line,
+            character: end_char,
+        },
+    }
+}
+
+/// Convert an LSP `Position` to a char index into `rope`, for applying edits.
+pub fn lsp_pos_to_char_idx(rope: &Rope, pos: Position) -> Result<usize> {
+    let line = pos.line as usize;
+    if line >= rope.len_lines() {
+        return Err(anyhow!(
+            "Line {line} out of range (doc has {} lines)",
+            rope.len_lines()
+        ));
+    }
+
+    let line_slice = rope.line(line);
+    let line_end = line_slice.len_chars() - line_ending_chars(line_slice);
+
+    // ropey snaps a unit inside a surrogate pair back to the pair's char.
+    let line_utf16_start = line_slice.char_to_utf16_cu(0);
+    let target = (pos.character as usize).min(line_slice.len_utf16_cu() - line_utf16_start);
+    let char_offset = line_slice.utf16_cu_to_char(target).min(line_end);
+
+    Ok(rope.line_to_char(line) + char_offset)
+}
+
+fn line_ending_chars(line: ropey::RopeSlice) -> usize {
+    let len = line.len_chars();
+    match (
+        len.checked_sub(2).map(|i| line.char(i)),
+        len.checked_sub(1).map(|i| line.char(i)),
+    ) {
+        (Some('\r'), Some('\n')) => 2,
+        (_, Some('\n')) => 1,
+        _ => 0,
+    }
+}
+
+/// UTF-16 length of complete UTF-8 sequences: one unit per leading byte, two for 4-byte
+/// sequences (surrogate pairs).
+fn utf16_len(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .map(|&b| u32::from(b & 0xC0 != 0x80) + u32::from(b >= 0xF0))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn pos(line: u32, character: u32) -> Position {
+        Position { line, character }
+    }
+
+    /// Char index of a byte offset, for comparing against the rope conversion.
+    fn char_idx(text: &str, byte_offset: usize) -> usize {
+        text[..byte_offset].chars().count()
+    }
+
+    #[test]
+    fn test_mid_surrogate_snaps_back_in_both_conversions() {
+        let text = "{\"k\": \"😀x\"}\n";
+        let rope = Rope::from_str(text);
+        // `😀` occupies UTF-16 units 7..9; unit 8 is inside the pair.
+        let byte = lsp_position_to_byte_offset(text, 0, 8).unwrap();
+        assert_eq!(byte, text.find('😀').unwrap());
+        assert_eq!(
+            lsp_pos_to_char_idx(&rope, pos(0, 8)).unwrap(),
+            char_idx(text, byte)
+        );
+        assert_eq!(byte_offset_to_lsp_pos(text, byte + 2), (0, 7));
+    }
+
+    #[test]
+    fn test_character_past_line_end_clamps_before_newline() {
+        let text = "ab\r\ncd";
+        let rope = Rope::from_str(text);
+        assert_eq!(lsp_position_to_byte_offset(text, 0, 10), Some(2));
+        assert_eq!(lsp_pos_to_char_idx(&rope, pos(0, 10)).unwrap(), 2);
+        assert_eq!(lsp_position_to_byte_offset(text, 1, 10), Some(6));
+        assert_eq!(lsp_position_to_byte_offset(text, 2, 0), None);
+    }
+
+    fn document() -> impl Strategy<Value = String> {
+        prop::collection::vec(
+            prop::sample::select(vec![
+                "a",
+                "{",
+                "\"",
+                "é",
+                "中",
+                "😀",
+                "\u{1F469}\u{200D}\u{1F4BB}",
+                "\n",
+                "\r\n",
+                "\r",
+                "\u{2028}",
+                " ",
+            ]),
+            0..40,
+        )
+        .prop_map(|parts| parts.concat())
+    }
+
+    proptest! {
+        #[test]
+        fn prop_offset_position_round_trip(text in document(), cut in 0usize..200) {
+            let mut offset = cut.min(text.len());
+            while !text.is_char_boundary(offset) {
+                offset -= 1;
+            }
+            if text[..offset].ends_with('\r') && text[offset..].starts_with('\n') {
+                offset -= 1;
+            }
+            let (line, character) = byte_offset_to_lsp_pos(&text, offset);
+            prop_assert_eq!(lsp_position_to_byte_offset(&text, line, character), Some(offset));
+
+            let rope = Rope::from_str(&text);
+            prop_assert_eq!(
+                lsp_pos_to_char_idx(&rope, pos(line, character)).unwrap(),
+                char_idx(&text, offset)
+            );
+        }
+
+        #[test]
+        fn prop_text_and_rope_conversions_agree(text in document(), line in 0u32..6, character in 0u32..30) {
+            let rope = Rope::from_str(&text);
+            let byte = lsp_position_to_byte_offset(&text, line, character);
+            let char = lsp_pos_to_char_idx(&rope, pos(line, character)).ok();
+            prop_assert_eq!(byte.map(|b| char_idx(&text, b)), char);
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//! Comma fix-ups between members: on Enter (`textDocument/onTypeFormatting`) and for
+//! property-name completions, so inserting a property between siblings keeps the
+//! document valid.
+
+use crate::document::DocumentStore;
+use crate::offsets::{byte_range_to_lsp_range, lsp_position_to_byte_offset};
+use crate::span::{parse_spans, skip_whitespace, ByteEdit, SpanKind, SpanMember, SpanNode};
+use std::sync::Arc;
+use tower_lsp::lsp_types::{DocumentOnTypeFormattingParams, TextEdit};
+use tracing::debug;
+
+/// Characters that trigger on-type formatting.
+pub const ON_TYPE_TRIGGER: &str = "\n";
+
+/// On Enter, add the comma missing between the member or item that ends before the new
+/// line and the one that starts after it.
+pub fn handle_on_type_formatting(
+    documents: &Arc<DocumentStore>,
+    params: DocumentOnTypeFormattingParams,
+) -> Option<Vec<TextEdit>> {
+    let uri = &params.text_document_position.text_document.uri;
+    let pos = params.text_document_position.position;
+    if params.ch != ON_TYPE_TRIGGER {
+        return None;
+    }
+
+    let text = documents.get_text(uri)?;
+    let offset = lsp_position_to_byte_offset(&text, pos.line, pos.character)?;
+    let edit = missing_comma(&text, offset)?;
+    debug!("On-type comma for {uri} at byte {}", edit.start);
+    Some(vec![TextEdit {
+        range: byte_range_to_lsp_range(&text, edit.start, edit.end),
+        new_text: edit.new_text,
+    }])
+}
+
+/// A `,` insertion if `offset` lies between two adjacent entries of a container with no
+/// comma between them.
+pub fn missing_comma(text: &str, offset: usize) -> Option<ByteEdit> {
+    let root = parse_spans(text)?;
+    let (previous_end, next_start) = adjacent_entries(&root, offset)?;
+    if text[previous_end..next_start].contains(',') {
+        return None;
+    }
+    Some(ByteEdit {
+        start: previous_end,
+        end: previous_end,
+        new_text: ",".into(),
+    })
+}
+
+/// End of the entry before `offset` and start of the entry after it, in the innermost
+/// container where `offset` falls between two entries.
+fn adjacent_entries(node: &SpanNode, offset: usize) -> Option<(usize, usize)> {
+    let entries: Vec<(usize, usize, &SpanNode)> = match &node.kind {
+        SpanKind::Object(members) => members
+            .iter()
+            .map(|m| (m.key_start, m.value.end, &m.value))
+            .collect(),
+        SpanKind::Array(items) => items.iter().map(|i| (i.start, i.end, i)).collect(),
+        _ => return None,
+    };
+    for (idx, &(start, end, value)) in entries.iter().enumerate() {
+        if (start..end).contains(&offset) {
+            return adjacent_entries(value, offset);
+        }
+        if let Some(&(next_start, _, _)) = entries.get(idx + 1) {
+            // A missing value ends where it starts; it is not an entry to separate.
+            if end > start && (end..=next_start).contains(&offset) {
+                return Some((end, next_start));
+            }
+        }
+    }
+    None
+}
+
+/// Commas a property-name completion needs, given the byte range of the key being typed
+/// (`key_start` at its opening quote or the cursor, `key_end` past any closing quote).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CompletionCommas {
+    /// Another member follows: end the inserted member with `,`.
+    pub trailing: bool,
+    /// The previous member's value ends here without a `,` after it.
+    pub after_previous: Option<usize>,
+}
+
+pub fn completion_commas(text: &str, key_start: usize, key_end: usize) -> CompletionCommas {
+    let mut next = key_end;
+    skip_whitespace(text.as_bytes(), &mut next);
+    if text.as_bytes().get(next) == Some(&b':') {
+        // Renaming an existing key: its member already has whatever comma it needs.
+        return CompletionCommas::default();
+    }
+
+    // Without the key being typed, which would take the next member's key for its value.
+    let rest = format!("{}{}", &text[..key_start], &text[key_end..]);
+    let root = parse_spans(&rest);
+    let Some(members) = root
+        .as_ref()
+        .and_then(|root| enclosing_members(root, key_start, rest.len()))
+    else {
+        return CompletionCommas::default();
+    };
+    // Whether a `,` is the first thing after `offset` other than whitespace and comments.
+    let comma_after = |mut offset: usize| {
+        skip_whitespace(rest.as_bytes(), &mut offset);
+        rest.as_bytes().get(offset) == Some(&b',')
+    };
+
+    let previous = members.iter().rfind(|m| m.key_start < key_start);
+    CompletionCommas {
+        trailing: members.iter().any(|m| m.key_start >= key_start) && !comma_after(key_start),
+        after_previous: previous
+            .map(|m| &m.value)
+            // A missing value is not a member to separate.
+            .filter(|value| value.end > value.start && !comma_after(value.end))
+            .map(|value| value.end),
+    }
+}
+
+/// The members of the innermost object whose braces enclose `offset`, in a document of
+/// `len` bytes (an unclosed container runs to its end).
+fn enclosing_members(node: &SpanNode, offset: usize, len: usize) -> Option<&[SpanMember]> {
+    let encloses = |n: &SpanNode| n.start < offset && (offset < n.end || n.end == len);
+    if !encloses(node) {
+        return None;
+    }
+    match &node.kind {
+        SpanKind::Object(members) => match members.iter().find(|m| encloses(&m.value)) {
+            Some(member) => enclosing_members(&member.value, offset, len),
+            None => Some(members),
+        },
+        SpanKind::Array(items) => {
+            let item = items.iter().find(|item| encloses(item))?;
+            enclosing_members(item, offset, len)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixed(text: &str, offset: usize) -> Option<String> {
+        let edit = missing_comma(text, offset)?;
+        let mut out = text.to_owned();
+        out.replace_range(edit.start..edit.end, &edit.new_text);
+        Some(out)
+    }
+
+    #[test]
+    fn test_enter_between_members_adds_comma() {
+        let text = "{\n  \"new\": 1\n  \"old\": { \"a\": [1\n 2] }\n}";
+        let after_new = text.find("1\n").unwrap() + 2;
+        assert_eq!(
+            fixed(text, after_new).as_deref(),
+            Some("{\n  \"new\": 1,\n  \"old\": { \"a\": [1\n 2] }\n}")
+        );
+        let in_array = text.find("[1\n").unwrap() + 3;
+        assert_eq!(
+            fixed(text, in_array).as_deref(),
+            Some("{\n  \"new\": 1\n  \"old\": { \"a\": [1,\n 2] }\n}")
+        );
+    }
+
+    #[test]
+    fn test_no_comma_before_close_or_when_present() {
+        let text = "{\n  \"a\": 1,\n  \"b\": 2\n}";
+        assert_eq!(fixed(text, text.find("1,").unwrap() + 3), None);
+        assert_eq!(fixed(text, text.find("2\n").unwrap() + 2), None);
+        assert_eq!(fixed("{\n  \"a\": \n  \"b\": 2\n}", 9), None);
+    }
+
+    #[test]
+    fn test_completion_commas_follow_siblings() {
+        let text = "{\n  \"a\": 1\n  \"\"\n  \"b\": 2\n}";
+        let key = text.find("\"\"").unwrap();
+        assert_eq!(
+            completion_commas(text, key, key + 2),
+            CompletionCommas {
+                trailing: true,
+                after_previous: Some(text.find("1\n").unwrap() + 1),
+            }
+        );
+
+        let last = "{\n  \"a\": 1,\n  \"\"\n}";
+        let key = last.find("\"\"").unwrap();
+        assert_eq!(
+            completion_commas(last, key, key + 2),
+            CompletionCommas::default()
+        );
+
+        let rename = "{ \"na\": 1 }";
+        assert_eq!(completion_commas(rename, 2, 6), CompletionCommas::default());
+    }
+
+    #[test]
+    fn test_completion_commas_skip_comments() {
+        let text = "{\n  \"a\": 1 // port, host\n  \"\"\n}";
+        let key = text.find("\"\"").unwrap();
+        assert_eq!(
+            completion_commas(text, key, key + 2),
+            CompletionCommas {
+                trailing: false,
+                after_previous: Some(text.find("1 ").unwrap() + 1),
+            }
+        );
+
+        let text = "{\n  \"a\": 1, /* was b */\n  \"\" // next:\n  \"c\": 3\n}";
+        let key = text.find("\"\"").unwrap();
+        assert_eq!(
+            completion_commas(text, key, key + 2),
+            CompletionCommas {
+                trailing: true,
+                after_previous: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_completion_commas_with_unquoted_keys() {
+        let text = "{\n  a: 1\n  na\n  b: 2\n}";
+        let key = text.find("na").unwrap();
+        assert_eq!(
+            completion_commas(text, key, key + 2),
+            CompletionCommas {
+                trailing: true,
+                after_previous: Some(text.find("1\n").unwrap() + 1),
+            }
+        );
+
+        let text = "{\n  a: 1,\n  '', // last\n  b: 2\n}";
+        let key = text.find("''").unwrap();
+        assert_eq!(
+            completion_commas(text, key, key + 2),
+            CompletionCommas::default()
+        );
+    }
+}
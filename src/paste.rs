@@ -0,0 +1,229 @@
+//! `json-ls.normalizePasted`: turn a pasted, JavaScript-object-ish snippet (single quotes,
+//! unquoted keys, trailing commas, comments) into strict JSON formatted for where it landed.
+//!
+//! The snippet is read as [`Dialect::Json5`]; the span tree supplies the key order (and the
+//! spelling of numbers that are already valid JSON), which `serde_json::Value` drops.
+
+use crate::formatting::{json_string, line_indent, render_compact_at};
+use crate::span::{parse_spans, ByteEdit, SpanKind, SpanNode};
+use crate::tolerant::{parse_tolerant_with, Dialect};
+use serde_json::Value;
+
+pub const NORMALIZE_PASTED_COMMAND: &str = "json-ls.normalizePasted";
+
+/// Replace `start..end` of `text` with its strict-JSON equivalent. The range may hold one
+/// value or a bare member list (`a: 1, b: 2`) pasted into an object. Objects keep their key
+/// order, and a repeated key keeps its first position and its last value, as in
+/// JavaScript. With `sort_keys`, object keys are sorted instead.
+pub fn normalize_pasted(
+    text: &str,
+    start: usize,
+    end: usize,
+    sort_keys: bool,
+) -> Result<ByteEdit, String> {
+    let pasted = text.get(start..end).ok_or("range is out of bounds")?;
+    let leading = pasted.len() - pasted.trim_start().len();
+    let trailing = pasted.len() - pasted.trim_end().len();
+    let at = start + leading;
+
+    let new_text = match Snippet::parse(pasted, 0) {
+        Ok(snippet) => render_compact_at(&snippet.compact(sort_keys), text, at),
+        Err(value_error) => {
+            // A bare member list reads as an object once wrapped in braces; the line break
+            // keeps a trailing `//` comment from swallowing the closing one.
+            let wrapped = format!("{{{pasted}\n}}");
+            let snippet = Snippet::parse(&wrapped, 1).map_err(|_| value_error)?;
+            let separator = format!(
+                ",{}{}",
+                if text.contains("\r\n") { "\r\n" } else { "\n" },
+                line_indent(text, at)
+            );
+            snippet
+                .members(sort_keys)
+                .into_iter()
+                .map(|(key, value, node)| {
+                    let mut compact = String::new();
+                    snippet.write(value, node, sort_keys, &mut compact);
+                    format!(
+                        "{}: {}",
+                        json_string(key),
+                        render_compact_at(&compact, text, at)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(&separator)
+        }
+    };
+
+    Ok(ByteEdit {
+        start: at,
+        end: end - trailing,
+        new_text,
+    })
+}
+
+/// Pasted text read as JSON5, with the span tree of the same text.
+struct Snippet<'a> {
+    source: &'a str,
+    value: Value,
+    spans: SpanNode,
+}
+
+impl<'a> Snippet<'a> {
+    /// Read `source`, failing on its first syntax error. `offset` is the number of bytes
+    /// `source` has in front of the pasted text, so error lines count from the paste.
+    fn parse(source: &'a str, offset: usize) -> Result<Self, String> {
+        let parsed = parse_tolerant_with(source, Dialect::Json5);
+        if let Some(error) = parsed.errors.first() {
+            let line = source[offset.min(error.start)..error.start]
+                .matches('\n')
+                .count()
+                + 1;
+            return Err(format!("{} (pasted line {line})", error.message));
+        }
+        match (parsed.value, parse_spans(source)) {
+            (Some(value), Some(spans)) => Ok(Self {
+                source,
+                value,
+                spans,
+            }),
+            _ => Err("expected a value (pasted line 1)".into()),
+        }
+    }
+
+    fn compact(&self, sort_keys: bool) -> String {
+        let mut out = String::new();
+        self.write(&self.value, &self.spans, sort_keys, &mut out);
+        out
+    }
+
+    /// The root object's members in output order.
+    fn members(&self, sort_keys: bool) -> Vec<(&String, &Value, &SpanNode)> {
+        match &self.value {
+            Value::Object(map) => ordered_members(map, &self.spans, sort_keys),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Serialize `value`, whose source is `node`, as compact JSON.
+    fn write(&self, value: &Value, node: &SpanNode, sort_keys: bool, out: &mut String) {
+        match (value, &node.kind) {
+            (Value::Object(map), _) => {
+                out.push('{');
+                for (idx, (key, value, node)) in ordered_members(map, node, sort_keys)
+                    .into_iter()
+                    .enumerate()
+                {
+                    if idx > 0 {
+                        out.push(',');
+                    }
+                    out.push_str(&json_string(key));
+                    out.push(':');
+                    self.write(value, node, sort_keys, out);
+                }
+                out.push('}');
+            }
+            (Value::Array(items), SpanKind::Array(nodes)) => {
+                out.push('[');
+                for (idx, (item, node)) in items.iter().zip(nodes).enumerate() {
+                    if idx > 0 {
+                        out.push(',');
+                    }
+                    self.write(item, node, sort_keys, out);
+                }
+                out.push(']');
+            }
+            (Value::Number(number), _) => {
+                // Keep the pasted spelling (`1e3`) unless it is JSON5-only (`0x1F`, `.5`).
+                let spelled = &self.source[node.start..node.end];
+                match serde_json::from_str::<serde_json::Number>(spelled) {
+                    Ok(_) => out.push_str(spelled),
+                    Err(_) => out.push_str(&number.to_string()),
+                }
+            }
+            (value, _) => out.push_str(&value.to_string()),
+        }
+    }
+}
+
+/// The members of `map` with the span of each one's value: in source order (the first
+/// occurrence of a repeated key, whose value is the last one's), or sorted.
+fn ordered_members<'v, 's>(
+    map: &'v serde_json::Map<String, Value>,
+    node: &'s SpanNode,
+    sort_keys: bool,
+) -> Vec<(&'v String, &'v Value, &'s SpanNode)> {
+    let mut members: Vec<(&String, &Value, &SpanNode)> = Vec::with_capacity(map.len());
+    for member in node.members() {
+        let Some((key, value)) = map.get_key_value(&member.key) else {
+            continue;
+        };
+        match members.iter_mut().find(|(existing, ..)| *existing == key) {
+            Some(existing) => existing.2 = &member.value,
+            None => members.push((key, value, &member.value)),
+        }
+    }
+    if sort_keys {
+        members.sort_by_key(|(key, ..)| *key);
+    }
+    members
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalized(text: &str, pasted: &str, sort_keys: bool) -> Result<String, String> {
+        let start = text.find(pasted).unwrap();
+        let edit = normalize_pasted(text, start, start + pasted.len(), sort_keys)?;
+        let mut out = text.to_owned();
+        out.replace_range(edit.start..edit.end, &edit.new_text);
+        Ok(out)
+    }
+
+    #[test]
+    fn test_js_object_becomes_indented_json() {
+        let pasted =
+            "{ name: 'api', // the service\n ports: [80, 0x1BB,], ratio: .5, name: \"web\", }";
+        let text = format!("{{\n    \"service\": {pasted}\n}}");
+        assert_eq!(
+            normalized(&text, pasted, false).unwrap(),
+            "{\n    \"service\": {\n        \"name\": \"web\",\n        \"ports\": [\n            80,\n            443\n        ],\n        \"ratio\": 0.5\n    }\n}"
+        );
+    }
+
+    #[test]
+    fn test_member_list_and_sorting() {
+        let pasted = "b: true, 'a': { y: null, x: -1e3 },";
+        let text = format!("{{\n  \"z\": 0,\n  {pasted}\n}}");
+        assert_eq!(
+            normalized(&text, pasted, true).unwrap(),
+            "{\n  \"z\": 0,\n  \"a\": {\n    \"x\": -1e3,\n    \"y\": null\n  },\n  \"b\": true\n}"
+        );
+    }
+
+    #[test]
+    fn test_rejects_non_json_values() {
+        let err = normalized("[ NaN ]", "NaN", false).unwrap_err();
+        assert_eq!(
+            err,
+            "`NaN` has no JSON equivalent and cannot be validated (pasted line 1)"
+        );
+        assert!(normalized("{ a: 'open }", "a: 'open", false).is_err());
+        assert_eq!(
+            normalized("[ 'it\\'s' ]", "'it\\'s'", false).unwrap(),
+            "[ \"it's\" ]"
+        );
+    }
+
+    #[test]
+    fn test_surrogate_pair_escape_is_one_character() {
+        let pasted = "'\\ud83d\\ude00'";
+        let text = format!("[ {pasted} ]");
+        assert_eq!(
+            normalized(&text, pasted, false).unwrap(),
+            "[ \"\u{1F600}\" ]"
+        );
+        assert!(normalized("[ '\\ud83d' ]", "'\\ud83d'", false).is_err());
+    }
+}
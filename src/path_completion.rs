@@ -0,0 +1,248 @@
+//! File and directory completion inside string values meant to hold a path: properties with
+//! `format: "uri-reference"` or a path-like name (`path`, `configFile`, `out_dir`, ...),
+//! resolved against the document's folder.
+
+use crate::config::PathCompletionConfig;
+use crate::editorconfig::section_matches;
+use crate::offsets::{byte_range_to_lsp_range, lsp_position_to_byte_offset};
+use crate::position::PathSegment;
+use crate::schema::SchemaNode;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use tower_lsp::lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionTextEdit, Range, TextEdit, Url,
+};
+
+/// Stop listing after this many entries, so a huge directory cannot stall completion.
+const MAX_PATH_ITEMS: usize = 200;
+
+const PATH_FORMATS: &[&str] = &["uri-reference", "iri-reference"];
+
+/// Last word of a property name marking it as path-like (`configFile`, `out_dir`).
+const PATH_WORDS: &[&str] = &[
+    "path",
+    "paths",
+    "file",
+    "files",
+    "dir",
+    "dirs",
+    "directory",
+    "directories",
+    "folder",
+    "folders",
+];
+
+/// The part of a string value typed so far, and where it is resolved from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathInput {
+    /// The document's folder.
+    pub base: PathBuf,
+    /// String content from the opening quote to the cursor.
+    pub typed: String,
+    /// Where `typed` sits; completions replace it with the whole path.
+    pub range: Range,
+}
+
+/// The path typed inside the string value at an LSP position, for `file:` documents.
+pub fn path_input(uri: &Url, text: &str, line: u32, character: u32) -> Option<PathInput> {
+    let base = uri.to_file_path().ok()?.parent()?.to_path_buf();
+    let offset = lsp_position_to_byte_offset(text, line, character)?;
+    let start = text[..offset].rfind('"')? + 1;
+    let typed = &text[start..offset];
+    // Escapes would make the replaced text differ from the path it spells.
+    if typed.contains(['\n', '\\']) {
+        return None;
+    }
+    Some(PathInput {
+        base,
+        typed: typed.to_owned(),
+        range: byte_range_to_lsp_range(text, start, offset),
+    })
+}
+
+/// Whether the string value at `path` (with schema `node`) holds a file path.
+pub fn wants_paths(node: &SchemaNode, path: &[PathSegment]) -> bool {
+    if node.schema_type().is_some_and(|ty| ty != "string") {
+        return false;
+    }
+    if node
+        .keyword("format")
+        .and_then(Value::as_str)
+        .is_some_and(|format| PATH_FORMATS.contains(&format))
+    {
+        return true;
+    }
+    // Array items go by the name of their array.
+    path.iter()
+        .rev()
+        .find_map(|segment| match segment {
+            PathSegment::Key(key) => Some(key),
+            PathSegment::Index(_) => None,
+        })
+        .is_some_and(|name| PATH_WORDS.contains(&last_word(name).as_str()))
+}
+
+/// Lowercased last word of a camelCase, snake_case or kebab-case name.
+fn last_word(name: &str) -> String {
+    let segment = name.rsplit(['_', '-', '.']).next().unwrap_or(name);
+    let word = if segment.chars().any(char::is_lowercase) {
+        segment
+            .char_indices()
+            .rev()
+            .find(|(_, ch)| ch.is_uppercase())
+            .map_or(segment, |(idx, _)| &segment[idx..])
+    } else {
+        segment
+    };
+    word.to_lowercase()
+}
+
+/// Entries below the directory typed so far, depth first, up to `max_depth` levels and
+/// [`MAX_PATH_ITEMS`] entries. Directories end with `/`.
+pub fn path_completions(input: &PathInput, config: &PathCompletionConfig) -> Vec<CompletionItem> {
+    let (dir_part, name_part) = match input.typed.rfind('/') {
+        Some(idx) => input.typed.split_at(idx + 1),
+        None => ("", input.typed.as_str()),
+    };
+    let dir = if dir_part.starts_with('/') {
+        PathBuf::from(dir_part)
+    } else {
+        input.base.join(dir_part)
+    };
+    let mut listing = Listing {
+        config,
+        dir_part,
+        show_hidden: name_part.starts_with('.'),
+        range: input.range,
+        items: Vec::new(),
+    };
+    listing.walk(&dir, "", 1);
+    listing.items
+}
+
+struct Listing<'a> {
+    config: &'a PathCompletionConfig,
+    /// Typed directory prefix, kept verbatim in the inserted path (`./`, `../conf/`).
+    dir_part: &'a str,
+    show_hidden: bool,
+    range: Range,
+    items: Vec<CompletionItem>,
+}
+
+impl Listing<'_> {
+    fn walk(&mut self, dir: &Path, relative: &str, depth: usize) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        let mut entries: Vec<_> = entries.filter_map(|entry| entry.ok()).collect();
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            if self.items.len() >= MAX_PATH_ITEMS {
+                return;
+            }
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with('.') && !(self.show_hidden && depth == 1) {
+                continue;
+            }
+            let label = format!("{relative}{name}");
+            let ignore_path = format!("{}{label}", self.dir_part.trim_start_matches("./"));
+            if self
+                .config
+                .ignore
+                .iter()
+                .any(|glob| section_matches(glob, &ignore_path))
+            {
+                continue;
+            }
+            let is_dir = entry.path().is_dir();
+            let label = if is_dir { format!("{label}/") } else { label };
+            let new_text = format!("{}{label}", self.dir_part);
+            self.items.push(CompletionItem {
+                label: label.clone(),
+                kind: Some(if is_dir {
+                    CompletionItemKind::FOLDER
+                } else {
+                    CompletionItemKind::FILE
+                }),
+                filter_text: Some(new_text.clone()),
+                text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                    range: self.range,
+                    new_text,
+                })),
+                ..Default::default()
+            });
+            if is_dir && depth < self.config.max_depth {
+                self.walk(&entry.path(), &label, depth + 1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_path_like_properties() {
+        let schema = json!({ "type": "string" });
+        let node = SchemaNode::new(&schema, &schema);
+        let wants = |path: &[PathSegment]| wants_paths(&node, path);
+        for name in ["path", "configFile", "out_dir", "include-paths", "PATH"] {
+            assert!(wants(&[PathSegment::Key(name.into())]), "{name}");
+        }
+        for name in ["profile", "name"] {
+            assert!(!wants(&[PathSegment::Key(name.into())]), "{name}");
+        }
+        assert!(wants(&[
+            PathSegment::Key("files".into()),
+            PathSegment::Index(0)
+        ]));
+
+        let reference = json!({ "type": "string", "format": "uri-reference" });
+        let node = SchemaNode::new(&reference, &reference);
+        assert!(wants_paths(&node, &[PathSegment::Key("extends".into())]));
+    }
+
+    #[test]
+    fn test_lists_entries_relative_to_document() {
+        let root = std::env::temp_dir().join(format!("json-ls-paths-{}", std::process::id()));
+        for dir in ["conf/nested/deep", ".hidden", "node_modules/pkg"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in ["conf/app.json", "conf/nested/deep/x.json", "main.json"] {
+            std::fs::write(root.join(file), "{}").unwrap();
+        }
+
+        let uri = Url::from_file_path(root.join("main.json")).unwrap();
+        let text = r#"{ "configFile": "./" }"#;
+        let cursor = text.find("./").unwrap() as u32 + 2;
+        let input = path_input(&uri, text, 0, cursor).unwrap();
+        assert_eq!(input.typed, "./");
+
+        let config = PathCompletionConfig::default();
+        let items = path_completions(&input, &config);
+        let hidden = path_completions(
+            &PathInput {
+                typed: "./.".into(),
+                ..input.clone()
+            },
+            &config,
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let labels: Vec<_> = items.iter().map(|item| item.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            vec!["conf/", "conf/app.json", "conf/nested/", "main.json"]
+        );
+        let Some(CompletionTextEdit::Edit(edit)) = &items[1].text_edit else {
+            panic!("expected a text edit");
+        };
+        assert_eq!(edit.new_text, "./conf/app.json");
+        assert_eq!(edit.range, input.range);
+        assert!(hidden.iter().any(|item| item.label == ".hidden/"));
+    }
+}
@@ -0,0 +1,43 @@
+//! Percent-decoding for URI fragments and `data:` URL payloads.
+
+/// Decode `%XX` escapes in `text` to bytes. A `%` not followed by two hex digits is kept.
+pub fn percent_decode_bytes(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    decoded
+}
+
+/// [`percent_decode_bytes`] as a string, e.g. a JSON Pointer from a `$ref` fragment.
+/// Invalid UTF-8 is replaced.
+pub fn percent_decode(text: &str) -> String {
+    String::from_utf8_lossy(&percent_decode_bytes(text)).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("/%24defs/a%2Fb/100%25"), "/$defs/a/b/100%");
+        assert_eq!(percent_decode("/caf%C3%A9"), "/café");
+        assert_eq!(percent_decode("50%-off/%zz/%4"), "50%-off/%zz/%4");
+    }
+}
@@ -1,3 +1,6 @@
+use crate::offsets::lsp_position_to_byte_offset;
+use crate::span::{decode_unicode_escape, is_identifier_byte};
+
 /// A segment in a JSON path.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PathSegment {
@@ -9,7 +12,11 @@ pub enum PathSegment {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PositionContext {
     /// Cursor is on/in a key string.  `path` is the full path TO this key (same semantics as `Value`).
-    Key { path: Vec<PathSegment> },
+    /// `quote` is the key's quote character, `None` for an unquoted JSON5 key.
+    Key {
+        path: Vec<PathSegment>,
+        quote: Option<char>,
+    },
     /// Cursor is just at the start of a key (e.g., at `"`).
     KeyStart { path: Vec<PathSegment> },
     /// Cursor is inside a value at `path`.
@@ -22,12 +29,9 @@ pub enum PositionContext {
 
 impl PositionContext {
     /// Return the JSON path this context refers to.
-    // TODO: expose to future handlers (code actions, go-to-definition) that need
-    // to extract the path from an already-computed PositionContext without re-scanning.
-    #[allow(dead_code)]
     pub fn path(&self) -> &[PathSegment] {
         match self {
-            PositionContext::Key { path }
+            PositionContext::Key { path, .. }
             | PositionContext::KeyStart { path }
             | PositionContext::Value { path }
             | PositionContext::ValueStart { path } => path,
@@ -36,47 +40,6 @@ impl PositionContext {
     }
 }
 
-/// Convert an LSP `Position` (0-based line + UTF-16 char) to a byte offset in `text`.
-fn lsp_position_to_byte_offset(text: &str, line: u32, character: u32) -> Option<usize> {
-    let mut current_line = 0u32;
-    let mut line_start = 0;
-
-    for (i, ch) in text.char_indices() {
-        if current_line == line {
-            line_start = i;
-            break;
-        }
-        if ch == '\n' {
-            current_line += 1;
-        }
-        if current_line > line {
-            return None;
-        }
-    }
-
-    // Edge case: cursor is on the last line with no trailing newline
-    if current_line != line {
-        if current_line + 1 == line && !text.is_empty() {
-            line_start = text.len();
-        } else {
-            return None;
-        }
-    }
-
-    // Walk UTF-16 units from line_start
-    let line_text = &text[line_start..];
-    let mut utf16_count = 0u32;
-    for (byte_off, ch) in line_text.char_indices() {
-        if utf16_count >= character {
-            return Some(line_start + byte_off);
-        }
-        utf16_count += ch.len_utf16() as u32;
-    }
-
-    // Cursor at end of line
-    Some(line_start + line_text.len())
-}
-
 /// Scan `text` and determine the JSON context at the given byte target offset.
 pub fn position_to_context(text: &str, line: u32, character: u32) -> PositionContext {
     let target = match lsp_position_to_byte_offset(text, line, character) {
@@ -85,77 +48,125 @@ pub fn position_to_context(text: &str, line: u32, character: u32) -> PositionCon
     };
 
     let bytes = text.as_bytes();
-    let mut pos = 0;
+    // A byte-order mark is not part of the root value.
+    let mut pos = if text.starts_with('\u{feff}') { 3 } else { 0 };
 
-    // Skip leading whitespace and look for '{'
+    // Any value may be the root: object, array or a lone scalar. Before the root's opening
+    // bracket there is nothing to complete or describe.
     skip_whitespace(bytes, &mut pos);
-    if pos >= bytes.len() || bytes[pos] != b'{' {
+    if pos >= bytes.len() || (target <= pos && matches!(bytes[pos], b'{' | b'[')) {
         return PositionContext::Unknown;
     }
 
-    let mut path: Vec<PathSegment> = Vec::new();
-    let mut result = PositionContext::Unknown;
-
-    scan_object(bytes, &mut pos, &mut path, target, &mut result);
-    result
+    let mut scanner = Scanner {
+        text,
+        bytes,
+        target,
+        path: Vec::new(),
+    };
+    scanner
+        .scan_value(&mut pos)
+        .unwrap_or(PositionContext::Unknown)
 }
 
 // ────────────────────────────────────────────────────────────
 // Recursive-descent scanner
 // ────────────────────────────────────────────────────────────
 
-fn scan_object(
-    bytes: &[u8],
-    pos: &mut usize,
-    path: &mut Vec<PathSegment>,
+/// A path segment as scanned. Keys stay undecoded (offset of their opening quote, or first
+/// character when unquoted) until a result is built, so skipping over a large document
+/// allocates nothing.
+#[derive(Debug, Clone, Copy)]
+enum RawSegment {
+    Key(usize),
+    Index(usize),
+}
+
+/// Single pass over the document that stops as soon as the target is classified or
+/// passed. `None` means "keep scanning".
+struct Scanner<'a> {
+    text: &'a str,
+    bytes: &'a [u8],
     target: usize,
-    result: &mut PositionContext,
-) {
-    // Consume '{'
-    *pos += 1;
+    path: Vec<RawSegment>,
+}
 
-    loop {
-        skip_whitespace(bytes, pos);
-        if *pos >= bytes.len() {
-            break;
-        }
+impl Scanner<'_> {
+    /// The current path plus `last`, with keys decoded.
+    fn path_with(&self, last: Option<RawSegment>) -> Vec<PathSegment> {
+        self.path
+            .iter()
+            .chain(last.as_ref())
+            .map(|segment| match *segment {
+                RawSegment::Key(start) => PathSegment::Key(scan_string(self.text, &mut { start })),
+                RawSegment::Index(index) => PathSegment::Index(index),
+            })
+            .collect()
+    }
 
-        let ch = bytes[*pos];
+    fn scan_object(&mut self, pos: &mut usize) -> Option<PositionContext> {
+        let (bytes, target) = (self.bytes, self.target);
+        // Consume '{'
+        *pos += 1;
 
-        if ch == b'}' {
-            *pos += 1;
-            break;
-        }
+        loop {
+            skip_whitespace(bytes, pos);
+            if *pos >= bytes.len() {
+                return None;
+            }
 
-        if ch == b',' {
-            *pos += 1;
-            continue;
-        }
+            let ch = bytes[*pos];
 
-        // At a key
-        if ch == b'"' {
-            // Check if target is at the opening quote (KeyStart)
-            if target == *pos {
-                *result = PositionContext::KeyStart { path: path.clone() };
-                return;
+            if ch == b'}' {
+                *pos += 1;
+                return None;
+            }
+
+            if ch == b',' {
+                *pos += 1;
+                continue;
+            }
+
+            // The target lies in whitespace or punctuation already passed.
+            if target < *pos {
+                return Some(PositionContext::Unknown);
+            }
+
+            let bare = is_identifier_byte(ch);
+            if ch != b'"' && ch != b'\'' && !bare {
+                // Malformed — skip until next ',' or '}'
+                *pos += 1;
+                continue;
             }
 
-            let key_start = *pos;
-            let key = scan_string(bytes, pos);
+            // Check if target is at the opening quote (KeyStart). An unquoted JSON5 key has
+            // none, so there the cursor is already in the key.
+            if target == *pos && !bare {
+                return Some(PositionContext::KeyStart {
+                    path: self.path_with(None),
+                });
+            }
+
+            let key = RawSegment::Key(*pos);
+            if bare {
+                skip_identifier(bytes, pos);
+            } else {
+                skip_string(bytes, pos);
+            }
 
             // Check if target is inside the key string.
             // Include the key itself in the path so hover navigates to this field's schema.
-            if target > key_start && target <= *pos {
-                let mut key_path = path.clone();
-                key_path.push(PathSegment::Key(key.clone()));
-                *result = PositionContext::Key { path: key_path };
-                return;
+            if target <= *pos {
+                return Some(PositionContext::Key {
+                    path: self.path_with(Some(key)),
+                    quote: (!bare).then_some(ch as char),
+                });
             }
 
             // After key, skip whitespace and ':'
             skip_whitespace(bytes, pos);
             if *pos >= bytes.len() {
-                break;
+                return None;
             }
             if bytes[*pos] == b':' {
                 *pos += 1;
@@ -163,126 +174,100 @@ fn scan_object(
             skip_whitespace(bytes, pos);
 
             if *pos >= bytes.len() {
-                break;
+                return None;
             }
 
             // Check if target is between ':' and the value, or exactly at value start
-            if target > key_start && target <= *pos {
-                let mut value_path = path.clone();
-                value_path.push(PathSegment::Key(key.clone()));
-                *result = PositionContext::ValueStart { path: value_path };
-                return;
+            if target <= *pos {
+                return Some(PositionContext::ValueStart {
+                    path: self.path_with(Some(key)),
+                });
             }
 
-            path.push(PathSegment::Key(key));
-            scan_value(bytes, pos, path, target, result);
-
-            if *result != PositionContext::Unknown {
-                path.pop();
-                return;
+            self.path.push(key);
+            let result = self.scan_value(pos);
+            self.path.pop();
+            if result.is_some() {
+                return result;
             }
-
-            path.pop();
-        } else {
-            // Malformed — skip until next ',' or '}'
-            *pos += 1;
         }
     }
-}
 
-fn scan_array(
-    bytes: &[u8],
-    pos: &mut usize,
-    path: &mut Vec<PathSegment>,
-    target: usize,
-    result: &mut PositionContext,
-) {
-    // Consume '['
-    *pos += 1;
+    fn scan_array(&mut self, pos: &mut usize) -> Option<PositionContext> {
+        let (bytes, target) = (self.bytes, self.target);
+        // Consume '['
+        *pos += 1;
 
-    let mut index = 0usize;
+        let mut index = 0usize;
 
-    loop {
-        skip_whitespace(bytes, pos);
-        if *pos >= bytes.len() {
-            break;
-        }
-
-        let ch = bytes[*pos];
+        loop {
+            skip_whitespace(bytes, pos);
+            if *pos >= bytes.len() {
+                return None;
+            }
 
-        if ch == b']' {
-            *pos += 1;
-            break;
-        }
+            let ch = bytes[*pos];
 
-        if ch == b',' {
-            *pos += 1;
-            index += 1;
-            continue;
-        }
+            if ch == b']' {
+                *pos += 1;
+                return None;
+            }
 
-        if target == *pos {
-            let mut value_path = path.clone();
-            value_path.push(PathSegment::Index(index));
-            *result = PositionContext::ValueStart { path: value_path };
-            return;
-        }
+            if ch == b',' {
+                *pos += 1;
+                index += 1;
+                continue;
+            }
 
-        path.push(PathSegment::Index(index));
-        scan_value(bytes, pos, path, target, result);
-        if *result != PositionContext::Unknown {
-            path.pop();
-            return;
-        }
-        path.pop();
-    }
-}
+            if target < *pos {
+                return Some(PositionContext::Unknown);
+            }
 
-fn scan_value(
-    bytes: &[u8],
-    pos: &mut usize,
-    path: &mut Vec<PathSegment>,
-    target: usize,
-    result: &mut PositionContext,
-) {
-    if *pos >= bytes.len() {
-        return;
-    }
+            if target == *pos {
+                return Some(PositionContext::ValueStart {
+                    path: self.path_with(Some(RawSegment::Index(index))),
+                });
+            }
 
-    match bytes[*pos] {
-        b'{' => {
-            let brace_pos = *pos;
-            if target == brace_pos {
-                *result = PositionContext::ValueStart { path: path.clone() };
-                return;
+            let start = *pos;
+            self.path.push(RawSegment::Index(index));
+            let result = self.scan_value(pos);
+            self.path.pop();
+            if result.is_some() {
+                return result;
             }
-            scan_object(bytes, pos, path, target, result);
-        }
-        b'[' => {
-            let bracket_pos = *pos;
-            if target == bracket_pos {
-                *result = PositionContext::ValueStart { path: path.clone() };
-                return;
+            // A stray `}` is not consumed as a value; stop rather than loop on it.
+            if *pos == start {
+                return Some(PositionContext::Unknown);
             }
-            scan_array(bytes, pos, path, target, result);
         }
-        b'"' => {
-            let str_start = *pos;
-            let _ = scan_string(bytes, pos);
-            let str_end = *pos;
+    }
 
-            if target >= str_start && target <= str_end {
-                *result = PositionContext::Value { path: path.clone() };
-            }
+    fn scan_value(&mut self, pos: &mut usize) -> Option<PositionContext> {
+        let (bytes, target) = (self.bytes, self.target);
+        if *pos >= bytes.len() {
+            return None;
         }
-        _ => {
-            // number, true, false, null
-            let lit_start = *pos;
-            skip_literal(bytes, pos);
-            let lit_end = *pos;
 
-            if target >= lit_start && target <= lit_end {
-                *result = PositionContext::Value { path: path.clone() };
+        let start = *pos;
+        match bytes[start] {
+            b'{' | b'[' if target == start => Some(PositionContext::ValueStart {
+                path: self.path_with(None),
+            }),
+            b'{' => self.scan_object(pos),
+            b'[' => self.scan_array(pos),
+            b'"' | b'\'' => {
+                skip_string(bytes, pos);
+                (target >= start && target <= *pos).then(|| PositionContext::Value {
+                    path: self.path_with(None),
+                })
+            }
+            _ => {
+                // number, true, false, null
+                skip_literal(bytes, pos);
+                (target >= start && target <= *pos).then(|| PositionContext::Value {
+                    path: self.path_with(None),
+                })
             }
         }
     }
@@ -292,61 +277,119 @@ fn scan_value(
 // Helpers
 // ────────────────────────────────────────────────────────────
 
+/// Skip whitespace and `//` / `/* */` comments.
 fn skip_whitespace(bytes: &[u8], pos: &mut usize) {
-    while *pos < bytes.len() && matches!(bytes[*pos], b' ' | b'\t' | b'\r' | b'\n') {
-        *pos += 1;
+    loop {
+        match bytes.get(*pos..) {
+            Some([b' ' | b'\t' | b'\r' | b'\n', ..]) => *pos += 1,
+            Some([b'/', b'/', ..]) => {
+                *pos = bytes[*pos..]
+                    .iter()
+                    .position(|&b| b == b'\n')
+                    .map_or(bytes.len(), |idx| *pos + idx);
+            }
+            Some([b'/', b'*', ..]) => {
+                *pos = bytes[*pos + 2..]
+                    .windows(2)
+                    .position(|w| w == b"*/")
+                    .map_or(bytes.len(), |idx| *pos + 2 + idx + 2);
+            }
+            _ => return,
+        }
     }
 }
 
-/// Consume a JSON string (including surrounding quotes), returning the unescaped content.
-fn scan_string(bytes: &[u8], pos: &mut usize) -> String {
+/// Consume a key, quoted or (JSON5) bare, returning its unescaped content. Text between
+/// escapes is copied as whole `str` slices: quotes and backslashes are ASCII, so every cut
+/// falls on a character boundary and multibyte characters come through intact.
+fn scan_string(text: &str, pos: &mut usize) -> String {
+    let bytes = text.as_bytes();
     let mut s = String::new();
 
-    if *pos >= bytes.len() || bytes[*pos] != b'"' {
+    let Some(&quote) = bytes.get(*pos) else {
         return s;
+    };
+    if quote != b'"' && quote != b'\'' {
+        let start = *pos;
+        skip_identifier(bytes, pos);
+        return text[start..*pos].to_owned();
     }
-    *pos += 1; // skip opening '"'
+    *pos += 1; // skip opening quote
 
+    // Start of the text not yet copied into `s`.
+    let mut run = *pos;
     while *pos < bytes.len() {
         let ch = bytes[*pos];
-        if ch == b'"' {
-            *pos += 1; // skip closing '"'
-            break;
+        if ch == quote {
+            s.push_str(&text[run..*pos]);
+            *pos += 1; // skip closing quote
+            return s;
+        }
+        if ch != b'\\' {
+            *pos += 1;
+            continue;
         }
-        if ch == b'\\' {
-            *pos += 1; // skip backslash
-            if *pos < bytes.len() {
-                match bytes[*pos] {
-                    b'"' => s.push('"'),
-                    b'\\' => s.push('\\'),
-                    b'/' => s.push('/'),
-                    b'n' => s.push('\n'),
-                    b'r' => s.push('\r'),
-                    b't' => s.push('\t'),
-                    b'u' => {
-                        // Skip 4 hex digits; we don't fully decode escapes for key matching
-                        s.push('?');
-                        *pos += 1;
-                        for _ in 0..3 {
-                            if *pos < bytes.len() {
-                                *pos += 1;
-                            }
-                        }
-                        continue;
-                    }
-                    other => s.push(other as char),
-                }
+        s.push_str(&text[run..*pos]);
+        *pos += 1; // skip backslash
+        let Some(&esc) = bytes.get(*pos) else {
+            return s;
+        };
+        match esc {
+            b'"' => s.push('"'),
+            b'\\' => s.push('\\'),
+            b'/' => s.push('/'),
+            b'n' => s.push('\n'),
+            b'r' => s.push('\r'),
+            b't' => s.push('\t'),
+            b'b' => s.push('\u{8}'),
+            b'f' => s.push('\u{c}'),
+            b'u' => {
+                // Decoded like the span parser, so escaped keys match schema names.
                 *pos += 1;
+                s.push(decode_unicode_escape(bytes, pos));
+                run = *pos;
+                continue;
+            }
+            // Any other escaped character stands for itself (`\'` in JSON5 keys). A
+            // multibyte one is left to the next run.
+            other if other.is_ascii() => s.push(other as char),
+            _ => {
+                run = *pos;
+                continue;
             }
-        } else {
-            s.push(ch as char);
-            *pos += 1;
         }
+        *pos += 1;
+        run = *pos;
     }
-
+    s.push_str(&text[run..]);
     s
 }
 
+/// Consume a JSON string (including surrounding quotes, double or single) without
+/// decoding it.
+fn skip_string(bytes: &[u8], pos: &mut usize) {
+    let quote = bytes[*pos];
+    *pos += 1; // skip opening quote
+    while *pos < bytes.len() {
+        match bytes[*pos] {
+            ch if ch == quote => {
+                *pos += 1;
+                return;
+            }
+            b'\\' => *pos += 2,
+            _ => *pos += 1,
+        }
+    }
+    *pos = bytes.len();
+}
+
+/// Consume an unquoted JSON5 key.
+fn skip_identifier(bytes: &[u8], pos: &mut usize) {
+    while *pos < bytes.len() && is_identifier_byte(bytes[*pos]) {
+        *pos += 1;
+    }
+}
+
 /// Skip over a literal (number, true, false, null).
 fn skip_literal(bytes: &[u8], pos: &mut usize) {
     while *pos < bytes.len()
@@ -397,7 +440,7 @@ mod tests {
         // Key { path } must include "name" so hover navigates to the field's schema.
         let result = ctx(DOC, 2, 4);
         assert!(
-            matches!(result, PositionContext::Key { ref path } if *path == vec![PathSegment::Key("name".into())]),
+            matches!(result, PositionContext::Key { ref path, .. } if *path == vec![PathSegment::Key("name".into())]),
             "Expected Key with path [name], got {result:?}"
         );
     }
@@ -408,7 +451,7 @@ mod tests {
         // Key { path } must be [nested, inner] — the full path to the field.
         let result = ctx(DOC, 6, 6);
         assert!(
-            matches!(result, PositionContext::Key { ref path } if *path == vec![
+            matches!(result, PositionContext::Key { ref path, .. } if *path == vec![
                 PathSegment::Key("nested".into()),
                 PathSegment::Key("inner".into())
             ]),
@@ -508,6 +551,109 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_scalar_root() {
+        let result = ctx("  42\n", 0, 3);
+        assert_eq!(result, PositionContext::Value { path: vec![] });
+    }
+
+    #[test]
+    fn test_array_root() {
+        let text = "[\"a\", {\"k\": true}]";
+        let result = ctx(text, 0, 2);
+        assert_eq!(
+            result,
+            PositionContext::Value {
+                path: vec![PathSegment::Index(0)]
+            }
+        );
+        let result = ctx(text, 0, 8);
+        assert_eq!(
+            result,
+            PositionContext::Key {
+                path: vec![PathSegment::Index(1), PathSegment::Key("k".into())],
+                quote: Some('"'),
+            }
+        );
+        assert_eq!(ctx(text, 0, 0), PositionContext::Unknown);
+    }
+
+    #[test]
+    fn test_nested_array_root_and_byte_order_mark() {
+        let text = "[[1, 2], [30]]";
+        assert_eq!(
+            ctx(text, 0, 11),
+            PositionContext::Value {
+                path: vec![PathSegment::Index(1), PathSegment::Index(0)]
+            }
+        );
+
+        // The mark is one UTF-16 unit: `[` is at 1, the key's quote at 3.
+        let text = "\u{feff}[{\"k\": 1}]";
+        assert_eq!(
+            ctx(text, 0, 3),
+            PositionContext::KeyStart {
+                path: vec![PathSegment::Index(0)]
+            }
+        );
+        assert_eq!(ctx(text, 0, 1), PositionContext::Unknown);
+    }
+
+    #[test]
+    fn test_unicode_escapes_in_keys_are_decoded() {
+        let text = r#"{"na\u006de": {"\ud83d\ude00": 1, "\u00e9t\u00e9": 2}}"#;
+        assert_eq!(
+            ctx(text, 0, 3),
+            PositionContext::Key {
+                path: vec![PathSegment::Key("name".into())],
+                quote: Some('"'),
+            }
+        );
+        assert_eq!(
+            ctx(text, 0, 32),
+            PositionContext::Value {
+                path: vec![
+                    PathSegment::Key("name".into()),
+                    PathSegment::Key("😀".into())
+                ]
+            }
+        );
+        assert_eq!(
+            ctx(text, 0, 37),
+            PositionContext::Key {
+                path: vec![
+                    PathSegment::Key("name".into()),
+                    PathSegment::Key("été".into())
+                ],
+                quote: Some('"'),
+            }
+        );
+    }
+
+    #[test]
+    fn test_non_ascii_keys_are_not_mangled() {
+        let text = "{\"ключ\": {\"名前\": 1, 'it\\'s é\\é': 2}}";
+        let key = |byte: usize| {
+            let character = text[..byte].encode_utf16().count() as u32;
+            ctx(text, 0, character)
+        };
+        assert_eq!(key(4).path(), [PathSegment::Key("ключ".into())]);
+        assert_eq!(
+            key(text.find("名").unwrap()).path(),
+            [
+                PathSegment::Key("ключ".into()),
+                PathSegment::Key("名前".into())
+            ]
+        );
+        assert_eq!(
+            key(text.find("it").unwrap()).path(),
+            [
+                PathSegment::Key("ключ".into()),
+                PathSegment::Key("it's éé".into())
+            ]
+        );
+    }
+
     #[test]
     fn test_empty_object() {
         let text = "{}";
@@ -515,4 +661,85 @@ mod tests {
         // Inside empty object — Unknown or ValueStart is fine
         let _ = result; // just shouldn't panic
     }
+
+    #[test]
+    fn test_json5_unquoted_and_single_quoted_keys() {
+        let text = "{\n  // comment with \"quotes\"\n  name: 'v',\n  'other': { inner: 1 }\n}";
+        assert_eq!(
+            ctx(text, 2, 2),
+            PositionContext::Key {
+                path: vec![PathSegment::Key("name".into())],
+                quote: None,
+            }
+        );
+        assert_eq!(
+            ctx(text, 2, 9),
+            PositionContext::Value {
+                path: vec![PathSegment::Key("name".into())]
+            }
+        );
+        assert_eq!(
+            ctx(text, 3, 4),
+            PositionContext::Key {
+                path: vec![PathSegment::Key("other".into())],
+                quote: Some('\''),
+            }
+        );
+        assert_eq!(
+            ctx(text, 3, 16),
+            PositionContext::Key {
+                path: vec![
+                    PathSegment::Key("other".into()),
+                    PathSegment::Key("inner".into())
+                ],
+                quote: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_stray_brace_in_array_terminates() {
+        assert_eq!(ctx(r#"{"a": [}"#, 0, 8), PositionContext::Unknown);
+        assert_eq!(ctx("{\"a\": [1, }\n}", 1, 1), PositionContext::Unknown);
+        assert_eq!(ctx("[}, 1]", 0, 5), PositionContext::Unknown);
+    }
+
+    /// ~5 MB of minified JSON on one line, with the probe key as the last member.
+    fn minified_document() -> String {
+        let mut text = String::from(r#"{"items":["#);
+        for i in 0..60_000 {
+            if i > 0 {
+                text.push(',');
+            }
+            text.push_str(&format!(
+                r#"{{"id":{i},"name":"item-{i}","tags":["a","b","ü"],"meta":{{"ok":true}}}}"#
+            ));
+        }
+        text.push_str(r#"],"last":"end"}"#);
+        text
+    }
+
+    #[test]
+    fn test_minified_document_context() {
+        let text = minified_document();
+        assert!(text.len() > 4_000_000 && !text.contains('\n'));
+
+        let utf16_len = text.encode_utf16().count() as u32;
+        let probes = [
+            (
+                12,
+                vec![
+                    PathSegment::Key("items".into()),
+                    PathSegment::Index(0),
+                    PathSegment::Key("id".into()),
+                ],
+            ),
+            (utf16_len - 4, vec![PathSegment::Key("last".into())]),
+        ];
+
+        for (character, expected) in &probes {
+            let result = position_to_context(&text, 0, *character);
+            assert_eq!(result.path(), &expected[..], "at {character}: {result:?}");
+        }
+    }
 }
@@ -0,0 +1,230 @@
+use crate::authoring::is_meta_schema_url;
+use crate::authoring::refs::ref_sites;
+use crate::document::DocumentStore;
+use crate::offsets::byte_range_to_lsp_range;
+use crate::percent::percent_decode;
+use crate::position::{position_to_context, PathSegment, PositionContext};
+use crate::schema::loader::local_path;
+use crate::schema::SchemaCache;
+use crate::span::{parse_spans, path_to_pointer, SpanKind, SpanNode};
+use crate::workspace::Workspace;
+use std::sync::Arc;
+use tower_lsp::lsp_types::{Location, ReferenceParams, Url};
+use tracing::debug;
+
+/// Containers whose entries are reusable definitions.
+const DEFINITION_CONTAINERS: &[&str] = &["$defs", "definitions"];
+
+/// From a `$defs` / `definitions` entry in a schema document, find every `$ref` to it —
+/// in the same document, other open documents, locally cached schema files and the `.json`
+/// files of the workspace folders.
+pub async fn handle_references(
+    documents: &Arc<DocumentStore>,
+    schema_cache: &Arc<SchemaCache>,
+    workspace: &Arc<Workspace>,
+    params: ReferenceParams,
+) -> Option<Vec<Location>> {
+    let uri = &params.text_document_position.text_document.uri;
+    let pos = params.text_document_position.position;
+
+    let text = documents.get_text(uri)?;
+    if !is_meta_schema_url(&documents.get_schema_url(uri)?) {
+        return None;
+    }
+
+    let PositionContext::Key { path, .. } = position_to_context(&text, pos.line, pos.character)
+    else {
+        return None;
+    };
+    let [.., PathSegment::Key(container), PathSegment::Key(_)] = path.as_slice() else {
+        return None;
+    };
+    if !DEFINITION_CONTAINERS.contains(&container.as_str()) {
+        return None;
+    }
+
+    let spans = parse_spans(&text)?;
+    let pointer = path_to_pointer(&path);
+    let target_ids = document_ids(uri, &spans);
+    debug!("References to {pointer} in {target_ids:?}");
+
+    let mut locations = Vec::new();
+
+    if params.context.include_declaration {
+        let (parent, last) = path.split_at(path.len() - 1);
+        if let (Some(parent), [PathSegment::Key(name)]) = (spans.find(parent), last) {
+            if let Some(member) = parent.member(name) {
+                locations.push(Location {
+                    uri: uri.clone(),
+                    range: byte_range_to_lsp_range(&text, member.key_start, member.key_end),
+                });
+            }
+        }
+    }
+
+    let open = documents.uris();
+    for doc_uri in &open {
+        if let Some(doc_text) = documents.get_text(doc_uri) {
+            collect_references(doc_uri, &doc_text, &target_ids, &pointer, &mut locations);
+        }
+    }
+
+    // Files on disk are walked, read and parsed off the async runtime.
+    let cached_urls = schema_cache.cached_urls();
+    let workspace = workspace.clone();
+    let on_disk = tokio::task::spawn_blocking(move || {
+        let mut locations = Vec::new();
+        for (file_uri, file_text) in disk_documents(&cached_urls, &workspace, &open) {
+            collect_references(&file_uri, &file_text, &target_ids, &pointer, &mut locations);
+        }
+        locations
+    })
+    .await;
+    match on_disk {
+        Ok(found) => locations.extend(found),
+        Err(e) => debug!("Reference scan of files on disk failed: {e}"),
+    }
+
+    Some(locations)
+}
+
+/// Push the location of every `$ref` in `text` that targets `pointer` in `target_ids`.
+fn collect_references(
+    uri: &Url,
+    text: &str,
+    target_ids: &[Url],
+    pointer: &str,
+    locations: &mut Vec<Location>,
+) {
+    let Some(spans) = parse_spans(text) else {
+        return;
+    };
+    let base = document_base(uri, &spans);
+    for site in ref_sites(&spans) {
+        if ref_targets(&base, site.reference, target_ids, pointer) {
+            locations.push(Location {
+                uri: uri.clone(),
+                range: byte_range_to_lsp_range(text, site.start, site.end),
+            });
+        }
+    }
+}
+
+/// Cached `file://` schemas and workspace `.json` files that are not open, each read once.
+/// Files without a `$ref` are skipped before parsing.
+fn disk_documents(
+    cached_urls: &[String],
+    workspace: &Workspace,
+    open: &[Url],
+) -> Vec<(Url, String)> {
+    let cached = cached_urls
+        .iter()
+        .filter_map(|url| local_path(url))
+        .filter_map(|path| std::fs::canonicalize(path).ok());
+    let paths = cached.chain(workspace.json_files());
+
+    let mut seen: Vec<Url> = open.to_vec();
+    let mut documents = Vec::new();
+    for path in paths {
+        let Ok(file_uri) = Url::from_file_path(&path) else {
+            continue;
+        };
+        if seen.contains(&file_uri) {
+            continue;
+        }
+        seen.push(file_uri.clone());
+        if let Ok(text) = std::fs::read_to_string(&path) {
+            if text.contains("\"$ref\"") {
+                documents.push((file_uri, text));
+            }
+        }
+    }
+    documents
+}
+
+/// Base URI for resolving `$ref`s in a document: its root `$id`, else its own URI.
+fn document_base(uri: &Url, spans: &SpanNode) -> Url {
+    root_id(spans)
+        .and_then(|id| uri.join(id).ok())
+        .unwrap_or_else(|| uri.clone())
+}
+
+/// All URIs a document can be referenced by (its own URI and root `$id`), fragment-free.
+fn document_ids(uri: &Url, spans: &SpanNode) -> Vec<Url> {
+    let mut ids = vec![without_fragment(uri.clone())];
+    if let Some(id) = root_id(spans).and_then(|id| uri.join(id).ok()) {
+        ids.push(without_fragment(id));
+    }
+    ids
+}
+
+fn root_id(spans: &SpanNode) -> Option<&str> {
+    match &spans.member("$id")?.value.kind {
+        SpanKind::String(id) => Some(id),
+        _ => None,
+    }
+}
+
+fn without_fragment(mut url: Url) -> Url {
+    url.set_fragment(None);
+    url
+}
+
+/// True if `reference`, resolved against `base`, points at `pointer` (or below it) in a
+/// document identified by one of `target_ids`.
+fn ref_targets(base: &Url, reference: &str, target_ids: &[Url], pointer: &str) -> bool {
+    let Ok(resolved) = base.join(reference) else {
+        return false;
+    };
+    let fragment = percent_decode(resolved.fragment().unwrap_or(""));
+    if !target_ids.contains(&without_fragment(resolved)) {
+        return false;
+    }
+    fragment == pointer
+        || fragment
+            .strip_prefix(pointer)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ref_targets_local_and_external() {
+        let target = Url::parse("file:///schemas/common.json").unwrap();
+        let ids = vec![
+            target.clone(),
+            Url::parse("https://example.com/common").unwrap(),
+        ];
+
+        // Local ref within the same document
+        assert!(ref_targets(&target, "#/$defs/Name", &ids, "/$defs/Name"));
+        // Relative file ref from a sibling schema
+        let sibling = Url::parse("file:///schemas/app.json").unwrap();
+        assert!(ref_targets(
+            &sibling,
+            "common.json#/$defs/Name",
+            &ids,
+            "/$defs/Name"
+        ));
+        // Ref via the $id, pointing below the definition
+        assert!(ref_targets(
+            &sibling,
+            "https://example.com/common#/$defs/Name/properties/x",
+            &ids,
+            "/$defs/Name"
+        ));
+        // Different definition with a shared prefix
+        assert!(!ref_targets(&target, "#/$defs/NameX", &ids, "/$defs/Name"));
+        // Different document
+        assert!(!ref_targets(&sibling, "#/$defs/Name", &ids, "/$defs/Name"));
+        // Percent-encoded fragment
+        assert!(ref_targets(
+            &target,
+            "#/%24defs/Full%20Name",
+            &ids,
+            "/$defs/Full Name"
+        ));
+    }
+}
@@ -0,0 +1,139 @@
+use crate::config::SchemaResolver;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+use tower_lsp::lsp_types::request::Request;
+use tower_lsp::lsp_types::Url;
+use tower_lsp::Client;
+use tracing::{debug, warn};
+
+const RESOLVER_TIMEOUT_SECS: u64 = 5;
+
+/// `json-ls/resolveSchema` — server → client request for the schema of a document.
+pub enum ResolveSchema {}
+
+impl Request for ResolveSchema {
+    type Params = ResolveSchemaParams;
+    type Result = Option<ResolvedSchema>;
+    const METHOD: &'static str = "json-ls/resolveSchema";
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolveSchemaParams {
+    pub uri: Url,
+}
+
+/// A resolver's answer: a schema URL to fetch, or the schema itself.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ResolvedSchema {
+    SchemaUrl(String),
+    Schema(Value),
+}
+
+/// Cache key for a schema handed over inline for `uri`.
+pub fn inline_schema_url(uri: &Url) -> String {
+    format!("json-ls-resolved:{uri}")
+}
+
+/// Ask `resolver` for the schema of `uri`. Failures and timeouts are logged and yield
+/// `None`, so a broken resolver never blocks the document.
+pub async fn resolve_schema(
+    resolver: &SchemaResolver,
+    client: &Client,
+    uri: &Url,
+) -> Option<ResolvedSchema> {
+    let timeout = Duration::from_secs(RESOLVER_TIMEOUT_SECS);
+    let resolved = match resolver {
+        SchemaResolver::Command(argv) => {
+            tokio::time::timeout(timeout, run_command(argv, uri)).await
+        }
+        SchemaResolver::Client => {
+            let params = ResolveSchemaParams { uri: uri.clone() };
+            tokio::time::timeout(timeout, async {
+                client
+                    .send_request::<ResolveSchema>(params)
+                    .await
+                    .inspect_err(|e| warn!("json-ls/resolveSchema failed for {uri}: {e}"))
+                    .ok()
+                    .flatten()
+            })
+            .await
+        }
+    };
+    resolved.unwrap_or_else(|_| {
+        warn!("Schema resolver timed out for {uri}");
+        None
+    })
+}
+
+async fn run_command(argv: &[String], uri: &Url) -> Option<ResolvedSchema> {
+    let (program, args) = argv.split_first()?;
+    let output = Command::new(program)
+        .args(args)
+        .arg(uri.as_str())
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .inspect_err(|e| warn!("Could not run schema resolver {program}: {e}"))
+        .ok()?;
+    if !output.status.success() {
+        debug!("Schema resolver {program} exited with {}", output.status);
+        return None;
+    }
+    parse_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn parse_output(output: &str) -> Option<ResolvedSchema> {
+    let output = output.trim();
+    if output.is_empty() {
+        return None;
+    }
+    if output.starts_with('{') {
+        return serde_json::from_str(output)
+            .inspect_err(|e| warn!("Schema resolver printed invalid JSON: {e}"))
+            .ok()
+            .map(ResolvedSchema::Schema);
+    }
+    Some(ResolvedSchema::SchemaUrl(output.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_output() {
+        assert_eq!(parse_output("  \n"), None);
+        assert_eq!(
+            parse_output("https://example.com/s.json\n"),
+            Some(ResolvedSchema::SchemaUrl(
+                "https://example.com/s.json".into()
+            ))
+        );
+        assert_eq!(
+            parse_output(r#"{"type": "object"}"#),
+            Some(ResolvedSchema::Schema(
+                serde_json::json!({ "type": "object" })
+            ))
+        );
+        assert_eq!(parse_output("{not json"), None);
+    }
+
+    #[tokio::test]
+    async fn test_run_command_appends_uri() {
+        let argv = vec!["echo".to_owned(), "file:///schemas/for".to_owned()];
+        let uri = Url::parse("file:///work/out.json").unwrap();
+        assert_eq!(
+            run_command(&argv, &uri).await,
+            Some(ResolvedSchema::SchemaUrl(
+                "file:///schemas/for file:///work/out.json".into()
+            ))
+        );
+    }
+}
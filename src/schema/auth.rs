@@ -0,0 +1,241 @@
+//! Credentials for authenticated schema hosts, looked up per request so tokens stay out
+//! of editor settings: a git-credential-style helper command, then `~/.netrc`.
+//!
+//! Schema URLs come from the documents being edited, so credentials only go out over
+//! https, and only to hosts listed in `auth.hosts` or named by a netrc `machine`.
+
+use crate::config::AuthConfig;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::{debug, warn};
+
+/// Longest wait for a credential helper; one that prompts or hangs must not hold up the
+/// schema fetch (and every validation waiting on it).
+const HELPER_TIMEOUT_SECS: u64 = 5;
+
+/// Username and password for HTTP basic auth.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+/// Credentials for `url` from the configured sources, in order of precedence. The helper
+/// and the netrc `default` entry are only consulted for hosts listed in `auth.hosts`.
+pub async fn credentials_for(url: &reqwest::Url, auth: &AuthConfig) -> Option<Credentials> {
+    if url.scheme() != "https" {
+        return None;
+    }
+    let host = url.host_str()?;
+    let listed = auth
+        .hosts
+        .iter()
+        .any(|listed| listed.eq_ignore_ascii_case(host));
+    if listed {
+        if let Some(helper) = &auth.credential_helper {
+            let timeout = Duration::from_secs(HELPER_TIMEOUT_SECS);
+            if let Some(credentials) = run_helper(helper, url, timeout).await {
+                return Some(credentials);
+            }
+        }
+    }
+    if auth.netrc {
+        let text = tokio::fs::read_to_string(netrc_path()?).await.ok()?;
+        return netrc_lookup(&text, host, listed);
+    }
+    None
+}
+
+/// `$NETRC`, else `~/.netrc` (`~/_netrc` on Windows).
+fn netrc_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("NETRC") {
+        return Some(path.into());
+    }
+    let home = directories::BaseDirs::new()?.home_dir().to_path_buf();
+    let name = if cfg!(windows) { "_netrc" } else { ".netrc" };
+    Some(home.join(name))
+}
+
+/// The `machine` entry for `host`, else the `default` entry if `use_default`.
+fn netrc_lookup(text: &str, host: &str, use_default: bool) -> Option<Credentials> {
+    // (machine, login, password); `None` machine is the `default` entry.
+    let mut entries: Vec<(Option<&str>, Option<&str>, Option<&str>)> = Vec::new();
+    let mut tokens = text.split_whitespace();
+    while let Some(token) = tokens.next() {
+        match token {
+            "machine" => entries.push((Some(tokens.next()?), None, None)),
+            "default" => entries.push((None, None, None)),
+            "login" => {
+                let login = tokens.next();
+                if let Some(entry) = entries.last_mut() {
+                    entry.1 = login;
+                }
+            }
+            "password" => {
+                let password = tokens.next();
+                if let Some(entry) = entries.last_mut() {
+                    entry.2 = password;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let (_, username, password) = entries
+        .iter()
+        .find(|(machine, ..)| machine.is_some_and(|m| m.eq_ignore_ascii_case(host)))
+        .or_else(|| {
+            entries
+                .iter()
+                .find(|(machine, ..)| use_default && machine.is_none())
+        })?;
+    Some(Credentials {
+        username: (*username)?.to_owned(),
+        password: (*password)?.to_owned(),
+    })
+}
+
+/// Invoke `helper get` with the git-credential protocol on stdin and parse its answer. A
+/// helper still running after `timeout` is killed and yields `None`.
+async fn run_helper(
+    helper: &[String],
+    url: &reqwest::Url,
+    timeout: Duration,
+) -> Option<Credentials> {
+    let (program, args) = helper.split_first()?;
+    let mut child = Command::new(program)
+        .args(args)
+        .arg("get")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .inspect_err(|e| warn!("Could not run credential helper {program}: {e}"))
+        .ok()?;
+
+    let request = format!(
+        "protocol={}\nhost={}\npath={}\n\n",
+        url.scheme(),
+        url.host_str()?,
+        url.path().trim_start_matches('/'),
+    );
+    let exchange = async {
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(request.as_bytes()).await.ok()?;
+        }
+        child.wait_with_output().await.ok()
+    };
+    // On timeout the child is dropped, and with it killed.
+    let Ok(output) = tokio::time::timeout(timeout, exchange).await else {
+        warn!(
+            "Credential helper {program} timed out for {}",
+            url.host_str()?
+        );
+        return None;
+    };
+    let output = output?;
+    if !output.status.success() {
+        debug!("Credential helper {program} exited with {}", output.status);
+        return None;
+    }
+    parse_helper_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn parse_helper_output(output: &str) -> Option<Credentials> {
+    let field = |name: &str| {
+        output
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix('='))
+            .map(str::to_owned)
+    };
+    Some(Credentials {
+        username: field("username")?,
+        password: field("password")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn credentials(username: &str, password: &str) -> Option<Credentials> {
+        Some(Credentials {
+            username: username.into(),
+            password: password.into(),
+        })
+    }
+
+    #[test]
+    fn test_netrc_lookup() {
+        let netrc = "machine other.example login x password y\n\
+                     default login anon password guest\n\
+                     machine schemas.example.com\n  login alice\n  password s3cret\n";
+        assert_eq!(
+            netrc_lookup(netrc, "schemas.example.com", false),
+            credentials("alice", "s3cret")
+        );
+        assert_eq!(netrc_lookup(netrc, "unknown.example", false), None);
+        assert_eq!(
+            netrc_lookup(netrc, "unknown.example", true),
+            credentials("anon", "guest")
+        );
+        assert_eq!(netrc_lookup("machine a login b", "a", true), None);
+    }
+
+    #[tokio::test]
+    async fn test_credentials_only_for_listed_https_hosts() {
+        let auth = AuthConfig {
+            credential_helper: Some(vec![
+                "sh".to_owned(),
+                "-c".to_owned(),
+                "cat >/dev/null; printf 'username=bot\\npassword=token\\n'".to_owned(),
+            ]),
+            hosts: vec!["schemas.example.com".to_owned()],
+            ..AuthConfig::default()
+        };
+        let url = |s: &str| reqwest::Url::parse(s).unwrap();
+        assert_eq!(
+            credentials_for(&url("https://schemas.example.com/a.json"), &auth).await,
+            credentials("bot", "token")
+        );
+        assert_eq!(
+            credentials_for(&url("http://schemas.example.com/a.json"), &auth).await,
+            None
+        );
+        assert_eq!(
+            credentials_for(&url("https://attacker.example/a.json"), &auth).await,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_credential_helper() {
+        let helper = vec![
+            "sh".to_owned(),
+            "-c".to_owned(),
+            "cat >/dev/null; printf 'username=bot\\npassword=token\\n'".to_owned(),
+        ];
+        let url = reqwest::Url::parse("https://schemas.example.com/a.json").unwrap();
+        let timeout = Duration::from_secs(5);
+        assert_eq!(
+            run_helper(&helper, &url, timeout).await,
+            credentials("bot", "token")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hanging_credential_helper_times_out() {
+        let helper = vec!["sh".to_owned(), "-c".to_owned(), "sleep 30".to_owned()];
+        let url = reqwest::Url::parse("https://schemas.example.com/a.json").unwrap();
+        let started = std::time::Instant::now();
+        assert_eq!(
+            run_helper(&helper, &url, Duration::from_millis(100)).await,
+            None
+        );
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
@@ -1,82 +1,318 @@
-use crate::config::ServerConfig;
-use crate::schema::loader::load_schema;
+use crate::config::{SchemaLimits, ServerConfig};
+use crate::metrics::METRICS;
+use crate::schema::disk_cache::DiskCache;
+#[cfg(feature = "native")]
+use crate::schema::loader::NativeLoader;
+use crate::schema::loader::{load_data_url, SchemaLoader};
+use crate::schema::navigator::PropertyMemo;
+use crate::schema::refs::inline_external_refs;
 use anyhow::{anyhow, Result};
 use dashmap::DashMap;
 use moka::future::Cache;
 use serde_json::Value;
-use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use tracing::{debug, warn};
-
-const ERROR_RETRY_SECS: u64 = 60;
+use web_time::Instant;
 
 pub struct SchemaCache {
-    inner: Cache<String, Arc<Value>>,
-    errors: DashMap<String, Instant>,
+    caches: RwLock<Caches>,
+    /// Shared with the fetch futures, which record failures: when, and the error.
+    errors: Arc<DashMap<String, (Instant, String)>>,
+    loader: Arc<dyn SchemaLoader>,
+}
+
+/// The moka caches, rebuilt when their capacity or TTL setting changes. Clones share entries.
+#[derive(Clone)]
+struct Caches {
+    /// Schema documents as loaded.
+    documents: Cache<String, Arc<Value>>,
+    /// Documents with their external `$ref`s inlined, as served by `get_or_fetch`.
+    bundles: Cache<String, Arc<Value>>,
+    /// Completion's property enumerations, per cached schema.
+    property_memos: Cache<String, Arc<PropertyMemo>>,
+    capacity: u64,
+    ttl_secs: u64,
+    /// `error_retry_secs`: how long a failed fetch is not retried; `0` never skips a fetch.
+    error_retry_secs: u64,
+    /// Remote schemas persisted under `cache_dir`; `None` without one.
+    disk: Option<DiskCache>,
+}
+
+impl Caches {
+    fn new(config: &ServerConfig) -> Self {
+        let (capacity, ttl_secs) = (config.schema_cache_capacity, config.schema_ttl_secs);
+        Self {
+            documents: Cache::builder()
+                .max_capacity(capacity)
+                .time_to_live(Duration::from_secs(ttl_secs))
+                .build(),
+            bundles: Cache::builder()
+                .max_capacity(capacity)
+                .time_to_live(Duration::from_secs(ttl_secs))
+                .build(),
+            property_memos: Cache::builder()
+                .max_capacity(capacity)
+                .time_to_live(Duration::from_secs(ttl_secs))
+                .build(),
+            capacity,
+            ttl_secs,
+            error_retry_secs: config.error_retry_secs,
+            disk: config.cache_dir().map(|dir| DiskCache::new(&dir)),
+        }
+    }
 }
 
 impl SchemaCache {
+    /// Cache backed by the native HTTP / filesystem loader.
+    #[cfg(feature = "native")]
     pub fn new(config: &ServerConfig) -> Self {
-        let inner = Cache::builder()
-            .max_capacity(config.schema_cache_capacity)
-            .time_to_live(Duration::from_secs(config.schema_ttl_secs))
-            .build();
+        Self::with_loader(config, Arc::new(NativeLoader::default()))
+    }
 
+    /// Cache fetching schemas through `loader`.
+    pub fn with_loader(config: &ServerConfig, loader: Arc<dyn SchemaLoader>) -> Self {
         Self {
-            inner,
-            errors: DashMap::new(),
+            caches: RwLock::new(Caches::new(config)),
+            errors: Arc::default(),
+            loader,
+        }
+    }
+
+    /// Apply changed settings: a new capacity or TTL starts over with empty caches, and
+    /// failed fetches may be retried at once (credentials or timeouts may have changed).
+    pub fn reconfigure(&self, config: &ServerConfig) {
+        self.errors.clear();
+        let mut caches = self.caches.write().expect("schema cache lock poisoned");
+        caches.disk = config.cache_dir().map(|dir| DiskCache::new(&dir));
+        caches.error_retry_secs = config.error_retry_secs;
+        if (caches.capacity, caches.ttl_secs)
+            != (config.schema_cache_capacity, config.schema_ttl_secs)
+        {
+            debug!(
+                "Schema cache rebuilt: capacity {}, TTL {} s",
+                config.schema_cache_capacity, config.schema_ttl_secs
+            );
+            *caches = Caches::new(config);
         }
     }
 
-    /// Return a cached schema, fetching it if not present.
+    fn caches(&self) -> Caches {
+        self.caches
+            .read()
+            .expect("schema cache lock poisoned")
+            .clone()
+    }
+
+    /// Return a cached schema, fetching it if not present. Documents its external `$ref`s
+    /// point into are fetched too and embedded (see [`inline_external_refs`]).
+    pub async fn get_or_fetch(&self, url: &str) -> Result<Arc<Value>> {
+        let bundles = self.caches().bundles;
+        if bundles.contains_key(url) {
+            METRICS.cache_hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            METRICS.cache_misses.fetch_add(1, Ordering::Relaxed);
+        }
+        bundles
+            .try_get_with(url.to_owned(), async {
+                let document = self.get_document(url).await?;
+                let bundle =
+                    inline_external_refs(url, (*document).clone(), &CachedDocuments(self)).await;
+                Ok::<_, anyhow::Error>(Arc::new(bundle))
+            })
+            .await
+            .map_err(|e| anyhow!("{e}"))
+    }
+
+    /// Return the schema document at `url` as loaded, without resolving its `$ref`s.
     ///
     /// Failed fetches are NOT cached in moka; instead we store an error timestamp
-    /// and refuse to retry for `ERROR_RETRY_SECS` seconds.
-    pub async fn get_or_fetch(&self, url: &str) -> Result<Arc<Value>> {
+    /// and refuse to retry for `error_retry_secs` seconds.
+    pub async fn get_document(&self, url: &str) -> Result<Arc<Value>> {
+        let Caches {
+            documents,
+            ttl_secs,
+            error_retry_secs,
+            disk,
+            ..
+        } = self.caches();
+
         // Check error cooldown
-        if let Some(failed_at) = self.errors.get(url) {
-            if failed_at.elapsed() < Duration::from_secs(ERROR_RETRY_SECS) {
+        if let Some(failed) = self.errors.get(url) {
+            let (failed_at, error) = failed.value();
+            if failed_at.elapsed() < Duration::from_secs(error_retry_secs) {
                 debug!("Schema fetch on cooldown: {url}");
-                return Err(anyhow!("Schema fetch on cooldown for: {url}"));
+                // The failure that started the cooldown, so callers report the same cause.
+                return Err(anyhow!("{error}"));
             }
             // Cooldown expired — allow retry
-            drop(failed_at);
+            drop(failed);
             self.errors.remove(url);
         }
 
         let url_owned = url.to_owned();
         let errors = self.errors.clone();
+        let loader = self.loader.clone();
+        let disk = disk.filter(|_| DiskCache::is_cacheable(url));
 
         // get_with coalesces concurrent fetches for the same URL
-        let result = self
-            .inner
+        let result = documents
             .try_get_with(url_owned.clone(), async move {
-                match load_schema(&url_owned).await {
+                let on_disk = disk.as_ref().and_then(|disk| disk.get(&url_owned));
+                if let Some(cached) = on_disk.as_ref().filter(|cached| cached.fresh) {
+                    debug!("Schema read from disk cache: {url_owned}");
+                    return Ok(Arc::new(cached.schema.clone()));
+                }
+                let loaded = if url_owned.starts_with("data:") {
+                    load_data_url(&url_owned)
+                } else {
+                    loader.load(&url_owned).await
+                };
+                match loaded {
                     Ok(schema) => {
                         debug!("Schema loaded and cached: {url_owned}");
+                        if let Some(disk) = &disk {
+                            disk.put(&url_owned, &schema, ttl_secs);
+                        }
                         Ok(Arc::new(schema))
                     }
                     Err(e) => {
-                        warn!("Failed to fetch schema {url_owned}: {e}");
-                        errors.insert(url_owned, Instant::now());
+                        METRICS.fetch_failures.fetch_add(1, Ordering::Relaxed);
+                        // Offline (or the host is down): an expired copy beats no schema.
+                        if let Some(cached) = on_disk {
+                            warn!("Failed to fetch schema {url_owned}, using the disk copy: {e}");
+                            return Ok(Arc::new(cached.schema));
+                        }
+                        warn!("Failed to fetch schema {url_owned}: {e:#}");
+                        if error_retry_secs > 0 {
+                            errors.insert(url_owned, (Instant::now(), format!("{e:#}")));
+                        }
                         Err(e)
                     }
                 }
             })
             .await;
 
-        result.map_err(|e| anyhow!("{e}"))
+        result.map_err(|e| anyhow!("{e:#}"))
     }
 
-    // TODO: wire up to a `workspace/executeCommand` handler so editors can force-refresh
-    // a specific schema URL without restarting the server (e.g. after editing a local schema).
-    #[allow(dead_code)]
-    pub fn invalidate(&self, url: &str) {
-        let cache = self.inner.clone();
-        let url_owned = url.to_owned();
-        self.errors.remove(&url_owned);
-        tokio::spawn(async move {
-            cache.invalidate(&url_owned).await;
-        });
+    /// Cache a schema that was handed over directly instead of fetched.
+    pub async fn insert(&self, url: &str, schema: Value) {
+        self.errors.remove(url);
+        let caches = self.caches();
+        caches
+            .documents
+            .insert(url.to_owned(), Arc::new(schema))
+            .await;
+        // Any bundle may embed the document.
+        caches.bundles.invalidate_all();
+    }
+
+    /// Property-name memo for `schema` as fetched from `url`. A new one replaces it once the
+    /// schema is refetched or `limits` change.
+    pub async fn property_memo(
+        &self,
+        url: &str,
+        schema: &Arc<Value>,
+        limits: SchemaLimits,
+    ) -> Arc<PropertyMemo> {
+        let property_memos = self.caches().property_memos;
+        if let Some(memo) = property_memos.get(url).await {
+            if memo.is_for(schema, limits) {
+                return memo;
+            }
+        }
+        let memo = Arc::new(PropertyMemo::new(schema.clone(), limits));
+        property_memos.insert(url.to_owned(), memo.clone()).await;
+        memo
+    }
+
+    /// URLs of all schema documents currently held in the cache, including those only
+    /// reached through another schema's `$ref`s.
+    pub fn cached_urls(&self) -> Vec<String> {
+        self.caches()
+            .documents
+            .iter()
+            .map(|(url, _)| (*url).clone())
+            .collect()
+    }
+}
+
+/// Serves a bundle's referenced documents from the cache, so they share its TTL, disk copies
+/// and error cooldown.
+struct CachedDocuments<'a>(&'a SchemaCache);
+
+#[tower_lsp::async_trait]
+impl SchemaLoader for CachedDocuments<'_> {
+    async fn load(&self, url: &str) -> Result<Value> {
+        Ok((*self.0.get_document(url).await?).clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::EmbeddedLoader;
+    use serde_json::json;
+
+    /// Fails until a schema is registered.
+    #[derive(Default)]
+    struct FlakyLoader(std::sync::Mutex<Option<Value>>);
+
+    #[tower_lsp::async_trait]
+    impl SchemaLoader for FlakyLoader {
+        async fn load(&self, url: &str) -> Result<Value> {
+            self.0
+                .lock()
+                .unwrap()
+                .clone()
+                .ok_or_else(|| anyhow!("unreachable: {url}"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_error_cooldown_is_configurable() {
+        let url = "mem://flaky.json";
+        let loader = Arc::new(FlakyLoader::default());
+        let cache = SchemaCache::with_loader(&ServerConfig::default(), loader.clone());
+        assert!(cache.get_or_fetch(url).await.is_err());
+        *loader.0.lock().unwrap() = Some(json!({ "type": "object" }));
+        let cooling_down = cache.get_or_fetch(url).await.unwrap_err();
+        // Still failing, with the cause of the failure that started the cooldown.
+        assert_eq!(cooling_down.to_string(), "unreachable: mem://flaky.json");
+
+        *loader.0.lock().unwrap() = None;
+        let config = ServerConfig {
+            error_retry_secs: 0,
+            ..ServerConfig::default()
+        };
+        let cache = SchemaCache::with_loader(&config, loader.clone());
+        assert!(cache.get_or_fetch(url).await.is_err());
+        *loader.0.lock().unwrap() = Some(json!({ "type": "object" }));
+        assert!(cache.get_or_fetch(url).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_remote_schemas_survive_restart_on_disk() {
+        let dir = std::env::temp_dir().join(format!("json-ls-schema-cache-{}", std::process::id()));
+        let config = ServerConfig {
+            cache_dir: Some(dir.clone()),
+            ..ServerConfig::default()
+        };
+        let url = "https://schemas.example.com/app.json";
+        let schema = json!({ "type": "object" });
+
+        let online = SchemaCache::with_loader(
+            &config,
+            Arc::new(EmbeddedLoader::new().with_schema(url, schema.clone())),
+        );
+        online.get_or_fetch(url).await.unwrap();
+
+        // A new process without network access still has it.
+        let offline = SchemaCache::with_loader(&config, Arc::new(EmbeddedLoader::new()));
+        let restored = offline.get_or_fetch(url).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(*restored.unwrap(), schema);
     }
 }
@@ -0,0 +1,334 @@
+//! Schema catalogs in the schemastore.org `catalog.json` format: lists of schemas and the
+//! file names they apply to. Sources are configurable, so an organisation can serve its own
+//! registry next to (or instead of) schemastore.org.
+
+use crate::config::{CatalogConfig, ServerConfig};
+use crate::editorconfig::section_matches;
+use crate::schema::disk_cache::DiskCache;
+use crate::schema::loader::SchemaLoader;
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tower_lsp::lsp_types::Url;
+use tracing::{debug, warn};
+use web_time::Instant;
+
+/// `workspace/executeCommand` reloading every catalog source now, regardless of the TTL.
+pub const REFRESH_CATALOG_COMMAND: &str = "json-ls.refreshCatalog";
+
+/// One schema listed in a catalog.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CatalogEntry {
+    pub name: String,
+    pub url: String,
+    /// Globs of the files the schema applies to, e.g. `package.json`, `*.eslintrc.json`.
+    #[serde(default, rename = "fileMatch")]
+    pub file_match: Vec<String>,
+}
+
+impl CatalogEntry {
+    /// Whether `path` (absolute, `/`-separated) is one of the entry's files.
+    pub fn matches(&self, path: &str) -> bool {
+        file_matches(&self.file_match, path)
+    }
+}
+
+/// Whether `path` (absolute, `/`-separated) matches `fileMatch` globs. A glob matches the
+/// end of the path (`*.eslintrc.json`, `.github/workflows/*.yml`); `!` globs exclude.
+pub fn file_matches(globs: &[String], path: &str) -> bool {
+    let path = path.trim_start_matches('/');
+    let glob_matches = |glob: &str| {
+        let glob = glob.trim_start_matches("./").trim_start_matches('/');
+        match glob.strip_prefix("**/") {
+            Some(_) => section_matches(glob, path),
+            None => section_matches(&format!("**/{glob}"), path),
+        }
+    };
+    let (excludes, includes): (Vec<&String>, Vec<&String>) =
+        globs.iter().partition(|glob| glob.starts_with('!'));
+    includes.iter().any(|glob| glob_matches(glob))
+        && !excludes.iter().any(|glob| glob_matches(&glob[1..]))
+}
+
+/// The first entry listing `path` among its files.
+pub fn entry_for<'a>(entries: &'a [CatalogEntry], path: &str) -> Option<&'a CatalogEntry> {
+    entries.iter().find(|entry| entry.matches(path))
+}
+
+/// The `/`-separated path `fileMatch` globs are matched against: the file path of `file:`
+/// URIs, else the URI's path.
+pub fn match_path(uri: &Url) -> String {
+    uri.to_file_path()
+        .map(|path| path.to_string_lossy().replace('\\', "/"))
+        .unwrap_or_else(|_| uri.path().to_owned())
+}
+
+/// Outcome of loading one source.
+#[derive(Debug, Clone)]
+struct SourceState {
+    url: String,
+    entries: Vec<CatalogEntry>,
+    error: Option<String>,
+}
+
+#[derive(Debug)]
+struct Loaded {
+    sources: Vec<SourceState>,
+    loaded_at: Instant,
+    /// Every source's entries, earlier sources first.
+    entries: Arc<Vec<CatalogEntry>>,
+}
+
+pub struct Catalog {
+    loader: Arc<dyn SchemaLoader>,
+    /// Catalogs persisted under the cache dir, so a restart needn't fetch them again.
+    disk: RwLock<Option<DiskCache>>,
+    loaded: RwLock<Option<Loaded>>,
+    /// Held while sources are fetched, so concurrent callers share one load and readers
+    /// are never blocked on the network.
+    loading: Mutex<()>,
+}
+
+impl Catalog {
+    /// Catalog fetching its sources through `loader`, so `auth` and the fetch timeouts apply.
+    pub fn new(loader: Arc<dyn SchemaLoader>) -> Self {
+        Self {
+            loader,
+            disk: RwLock::new(None),
+            loaded: RwLock::new(None),
+            loading: Mutex::new(()),
+        }
+    }
+
+    /// Persist catalogs under `config`'s cache dir.
+    pub fn configure(&self, config: &ServerConfig) {
+        *self.disk.write().expect("catalog disk lock poisoned") =
+            config.cache_dir().map(|dir| DiskCache::new(&dir));
+    }
+
+    /// The entries loaded so far, without fetching anything (empty before the first load),
+    /// and whether they are still current under `config`.
+    pub fn current(&self, config: &CatalogConfig) -> (Arc<Vec<CatalogEntry>>, bool) {
+        match self.loaded.read().expect("catalog lock poisoned").as_ref() {
+            Some(loaded) => (loaded.entries.clone(), is_current(loaded, config)),
+            None => (Arc::default(), false),
+        }
+    }
+
+    /// All entries, earlier sources first. Loads the sources on first use, when the
+    /// configured list changed, and once `ttl_secs` have passed.
+    pub async fn entries(&self, config: &CatalogConfig) -> Arc<Vec<CatalogEntry>> {
+        match self.current(config) {
+            (entries, true) => entries,
+            _ => self.load(config, false).await,
+        }
+    }
+
+    /// Reload every source now. Returns `{ entries, sources: [{ url, entries, error? }] }`.
+    pub async fn refresh(&self, config: &CatalogConfig) -> Value {
+        self.load(config, true).await;
+        let loaded = self.loaded.read().expect("catalog lock poisoned");
+        let Some(loaded) = loaded.as_ref() else {
+            return Value::Null;
+        };
+        let sources: Vec<Value> = loaded
+            .sources
+            .iter()
+            .map(|source| {
+                let mut report = json!({ "url": source.url, "entries": source.entries.len() });
+                if let Some(error) = &source.error {
+                    report["error"] = error.as_str().into();
+                }
+                report
+            })
+            .collect();
+        json!({ "entries": loaded.entries.len(), "sources": sources })
+    }
+
+    async fn load(&self, config: &CatalogConfig, force: bool) -> Arc<Vec<CatalogEntry>> {
+        let _loading = self.loading.lock().await;
+        // Another caller may have reloaded while we waited for the lock.
+        if let (entries, true) = self.current(config) {
+            if !force {
+                return entries;
+            }
+        }
+        let disk = self
+            .disk
+            .read()
+            .expect("catalog disk lock poisoned")
+            .clone();
+
+        let mut sources = Vec::with_capacity(config.sources.len());
+        for url in &config.sources {
+            let disk = disk.as_ref().filter(|_| DiskCache::is_cacheable(url));
+            let on_disk = disk.and_then(|disk| disk.get(url));
+            let fetched = match on_disk.as_ref().filter(|cached| cached.fresh && !force) {
+                Some(cached) => {
+                    debug!("Catalog read from disk cache: {url}");
+                    parse_catalog(cached.schema.clone())
+                }
+                None => self.loader.load(url).await.and_then(|catalog| {
+                    let entries = parse_catalog(catalog.clone())?;
+                    if let Some(disk) = disk {
+                        disk.put(url, &catalog, config.ttl_secs);
+                    }
+                    Ok(entries)
+                }),
+            };
+            let state = match fetched {
+                Ok(entries) => {
+                    debug!("Catalog {url}: {} schemas", entries.len());
+                    SourceState {
+                        url: url.clone(),
+                        entries,
+                        error: None,
+                    }
+                }
+                Err(e) => {
+                    warn!("Could not load catalog {url}: {e}");
+                    // Keep serving what this source listed before, else the disk copy.
+                    let entries = self
+                        .loaded
+                        .read()
+                        .expect("catalog lock poisoned")
+                        .as_ref()
+                        .and_then(|loaded| loaded.sources.iter().find(|s| s.url == *url))
+                        .map(|s| s.entries.clone())
+                        .or_else(|| on_disk.and_then(|cached| parse_catalog(cached.schema).ok()))
+                        .unwrap_or_default();
+                    SourceState {
+                        url: url.clone(),
+                        entries,
+                        error: Some(e.to_string()),
+                    }
+                }
+            };
+            sources.push(state);
+        }
+        let entries: Arc<Vec<CatalogEntry>> = Arc::new(
+            sources
+                .iter()
+                .flat_map(|source| source.entries.iter().cloned())
+                .collect(),
+        );
+        debug!("Catalog loaded: {} schemas", entries.len());
+        *self.loaded.write().expect("catalog lock poisoned") = Some(Loaded {
+            sources,
+            loaded_at: Instant::now(),
+            entries: entries.clone(),
+        });
+        entries
+    }
+}
+
+fn is_current(loaded: &Loaded, config: &CatalogConfig) -> bool {
+    loaded.loaded_at.elapsed() < Duration::from_secs(config.ttl_secs)
+        && loaded
+            .sources
+            .iter()
+            .map(|s| &s.url)
+            .eq(config.sources.iter())
+}
+
+/// The `schemas` of a catalog document; malformed entries are skipped.
+fn parse_catalog(catalog: Value) -> Result<Vec<CatalogEntry>> {
+    let Some(Value::Array(schemas)) = catalog.get("schemas") else {
+        bail!("not a schema catalog (no `schemas` array)");
+    };
+    Ok(schemas
+        .iter()
+        .filter_map(|entry| serde_json::from_value(entry.clone()).ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::EmbeddedLoader;
+
+    const INTERNAL: &str = "https://schemas.example.com/catalog.json";
+    const PUBLIC: &str = "https://public.example.com/catalog.json";
+
+    fn config(sources: &[&str]) -> CatalogConfig {
+        CatalogConfig {
+            sources: sources.iter().map(|s| (*s).to_owned()).collect(),
+            ..CatalogConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sources_merge_in_order_and_report_failures() {
+        let loader = EmbeddedLoader::new()
+            .with_schema(
+                INTERNAL,
+                json!({ "schemas": [
+                    { "name": "Service", "url": "https://schemas.example.com/service.json", "fileMatch": ["service.json"] },
+                    { "name": "broken" }
+                ] }),
+            )
+            .with_schema(
+                PUBLIC,
+                json!({ "schemas": [
+                    { "name": "package.json", "url": "https://public.example.com/package.json", "fileMatch": ["package.json"] }
+                ] }),
+            );
+        let catalog = Catalog::new(Arc::new(loader));
+
+        let entries = catalog.entries(&config(&[INTERNAL, PUBLIC])).await;
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["Service", "package.json"]);
+        assert_eq!(entries[0].file_match, vec!["service.json"]);
+
+        let missing = "https://missing.example.com/catalog.json";
+        let report = catalog.refresh(&config(&[missing, INTERNAL])).await;
+        assert_eq!(report["entries"], 1);
+        assert_eq!(report["sources"][0]["entries"], 0);
+        assert!(report["sources"][0]["error"].is_string());
+        assert!(report["sources"][1].get("error").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_catalog_survives_restart_on_disk() {
+        let dir = std::env::temp_dir().join(format!("json-ls-catalog-{}", std::process::id()));
+        let config_with_dir = ServerConfig {
+            cache_dir: Some(dir.clone()),
+            ..ServerConfig::default()
+        };
+        let loader = EmbeddedLoader::new().with_schema(
+            PUBLIC,
+            json!({ "schemas": [
+                { "name": "package.json", "url": "https://public.example.com/package.json", "fileMatch": ["package.json"] }
+            ] }),
+        );
+        let catalog = Catalog::new(Arc::new(loader));
+        catalog.configure(&config_with_dir);
+        assert_eq!(catalog.entries(&config(&[PUBLIC])).await.len(), 1);
+
+        // A restarted server that cannot reach the source reads it back from disk.
+        let offline = Catalog::new(Arc::new(EmbeddedLoader::new()));
+        assert!(!offline.current(&config(&[PUBLIC])).1);
+        offline.configure(&config_with_dir);
+        assert_eq!(offline.entries(&config(&[PUBLIC])).await.len(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_file_match_globs() {
+        let entry = |globs: &[&str]| CatalogEntry {
+            name: "x".into(),
+            url: "https://example.com/x.json".into(),
+            file_match: globs.iter().map(|g| (*g).to_owned()).collect(),
+        };
+        assert!(entry(&["package.json"]).matches("/home/me/app/package.json"));
+        assert!(!entry(&["package.json"]).matches("/home/me/app/package.json.bak"));
+        assert!(entry(&["*.eslintrc.json"]).matches("/src/.eslintrc.json"));
+        assert!(entry(&[".github/workflows/*.json"]).matches("/r/.github/workflows/ci.json"));
+        assert!(entry(&["**/tsconfig.*.json"]).matches("/r/tsconfig.base.json"));
+        assert!(!entry(&["*.json", "!package.json"]).matches("/r/package.json"));
+        assert!(entry(&["*.json", "!package.json"]).matches("/r/other.json"));
+    }
+}
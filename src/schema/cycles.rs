@@ -0,0 +1,234 @@
+//! Detection of `$ref` chains that loop back on themselves across schema documents.
+//!
+//! Only the *resolution chain* matters here: a `$ref` whose target is itself just another
+//! `$ref`, and so on. A recursive schema (a property of `a.json` referencing `b.json`, which
+//! references `a.json` again) is fine — every step reaches a concrete subschema — but a chain
+//! of refs that never does would make any resolver spin.
+
+use crate::authoring::refs::ref_sites;
+use crate::percent::percent_decode;
+use crate::schema::SchemaCache;
+use crate::span::SpanNode;
+use serde_json::Value;
+use tower_lsp::lsp_types::Url;
+
+/// A `$ref` resolution chain that came back to a target already on it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefCycle {
+    /// Every target visited, ending with the repeated one.
+    pub chain: Vec<Url>,
+}
+
+impl RefCycle {
+    /// `a.json → b.json → a.json`, naming each step by its file name and fragment.
+    pub fn describe(&self) -> String {
+        self.chain
+            .iter()
+            .map(short_name)
+            .collect::<Vec<_>>()
+            .join(" → ")
+    }
+}
+
+/// A `$ref` in a schema document whose resolution chain is cyclic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CyclicRef {
+    /// Byte range of the `$ref` string value (including quotes).
+    pub start: usize,
+    pub end: usize,
+    pub cycle: RefCycle,
+}
+
+/// Follow the `$ref` chain starting at the root of the schema at `schema_url`.
+/// `None` if the chain reaches a concrete subschema, fails to load, or runs past
+/// `max_depth` steps.
+pub async fn schema_ref_cycle(
+    cache: &SchemaCache,
+    schema_url: &str,
+    max_depth: usize,
+) -> Option<RefCycle> {
+    follow_chain(cache, parse_url(schema_url)?, max_depth).await
+}
+
+/// Check every external `$ref` in the schema document at `uri` for a cyclic resolution chain.
+pub async fn cyclic_refs(
+    cache: &SchemaCache,
+    uri: &Url,
+    spans: &SpanNode,
+    root: &Value,
+    max_depth: usize,
+) -> Vec<CyclicRef> {
+    let base = document_base(uri, root);
+    let mut found = Vec::new();
+    for site in ref_sites(spans) {
+        if site.reference.starts_with('#') {
+            continue;
+        }
+        let Ok(target) = base.join(site.reference) else {
+            continue;
+        };
+        if let Some(cycle) = follow_chain(cache, target, max_depth).await {
+            found.push(CyclicRef {
+                start: site.start,
+                end: site.end,
+                cycle,
+            });
+        }
+    }
+    found
+}
+
+async fn follow_chain(cache: &SchemaCache, start: Url, max_depth: usize) -> Option<RefCycle> {
+    let mut chain: Vec<Url> = Vec::new();
+    let mut target = normalize(start);
+    loop {
+        if chain.contains(&target) {
+            chain.push(target);
+            return Some(RefCycle { chain });
+        }
+        if chain.len() >= max_depth {
+            return None;
+        }
+
+        let mut document_url = target.clone();
+        document_url.set_fragment(None);
+        let document = cache.get_document(document_url.as_str()).await.ok()?;
+        let node = match target.fragment() {
+            None => &*document,
+            Some(fragment) => document.pointer(&percent_decode(fragment))?,
+        };
+        let reference = node.get("$ref")?.as_str()?;
+        let next = document_base(&document_url, &document)
+            .join(reference)
+            .ok()?;
+
+        chain.push(target);
+        target = normalize(next);
+    }
+}
+
+/// Base URI for `$ref`s in a schema: its root `$id`, else its own URL.
+fn document_base(url: &Url, root: &Value) -> Url {
+    root.get("$id")
+        .and_then(Value::as_str)
+        .and_then(|id| url.join(id).ok())
+        .unwrap_or_else(|| url.clone())
+}
+
+/// `$schema` values are URLs, or bare filesystem paths for local schemas.
+fn parse_url(url: &str) -> Option<Url> {
+    Url::parse(url)
+        .ok()
+        .or_else(|| Url::from_file_path(url).ok())
+}
+
+/// `a.json#` and `a.json` name the same target.
+fn normalize(mut url: Url) -> Url {
+    if url.fragment() == Some("") {
+        url.set_fragment(None);
+    }
+    url
+}
+
+fn short_name(url: &Url) -> String {
+    let name = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|name| !name.is_empty())
+        .unwrap_or(url.as_str());
+    match url.fragment() {
+        Some(fragment) => format!("{name}#{fragment}"),
+        None => name.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ServerConfig;
+    use crate::schema::EmbeddedLoader;
+    use crate::span::parse_spans;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn cache(loader: EmbeddedLoader) -> SchemaCache {
+        SchemaCache::with_loader(&ServerConfig::default(), Arc::new(loader))
+    }
+
+    #[tokio::test]
+    async fn test_cross_document_ref_cycle_is_reported() {
+        let cache = cache(
+            EmbeddedLoader::new()
+                .with_schema("mem://schemas/a.json", json!({ "$ref": "b.json" }))
+                .with_schema("mem://schemas/b.json", json!({ "$ref": "a.json#" })),
+        );
+        let cycle = schema_ref_cycle(&cache, "mem://schemas/a.json", 64)
+            .await
+            .unwrap();
+        assert_eq!(cycle.describe(), "a.json → b.json → a.json");
+    }
+
+    #[tokio::test]
+    async fn test_cycle_through_percent_encoded_pointer() {
+        let cache = cache(
+            EmbeddedLoader::new()
+                .with_schema(
+                    "mem://schemas/a.json",
+                    json!({ "$ref": "b.json#/%24defs/50%25%20off" }),
+                )
+                .with_schema(
+                    "mem://schemas/b.json",
+                    json!({ "$defs": { "50% off": { "$ref": "a.json" } } }),
+                ),
+        );
+        let cycle = schema_ref_cycle(&cache, "mem://schemas/a.json", 64)
+            .await
+            .unwrap();
+        assert_eq!(cycle.chain.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_recursive_schema_is_not_a_cycle() {
+        let cache = cache(
+            EmbeddedLoader::new()
+                .with_schema(
+                    "mem://schemas/a.json",
+                    json!({ "type": "object", "properties": { "b": { "$ref": "b.json" } } }),
+                )
+                .with_schema("mem://schemas/b.json", json!({ "$ref": "a.json" })),
+        );
+        assert_eq!(
+            schema_ref_cycle(&cache, "mem://schemas/a.json", 64).await,
+            None
+        );
+        assert_eq!(
+            schema_ref_cycle(&cache, "mem://schemas/b.json", 64).await,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cyclic_ref_site_in_schema_document() {
+        let cache = cache(
+            EmbeddedLoader::new()
+                .with_schema("mem://schemas/b.json", json!({ "$ref": "c.json#/$defs/x" }))
+                .with_schema(
+                    "mem://schemas/c.json",
+                    json!({ "$defs": { "x": { "$ref": "b.json" } } }),
+                ),
+        );
+        let text =
+            r##"{ "properties": { "ok": { "$ref": "#/$defs/y" }, "bad": { "$ref": "b.json" } } }"##;
+        let spans = parse_spans(text).unwrap();
+        let root: Value = serde_json::from_str(text).unwrap();
+        let uri = Url::parse("mem://schemas/main.json").unwrap();
+
+        let found = cyclic_refs(&cache, &uri, &spans, &root, 64).await;
+        assert_eq!(found.len(), 1);
+        assert_eq!(&text[found[0].start..found[0].end], "\"b.json\"");
+        assert_eq!(
+            found[0].cycle.describe(),
+            "b.json → c.json#/$defs/x → b.json"
+        );
+    }
+}
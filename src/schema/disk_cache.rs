@@ -0,0 +1,151 @@
+//! Fetched schemas (and schema catalogs) persisted under the cache dir, so they survive
+//! server restarts and remain available offline. Only remote (`http(s)://`) documents are
+//! kept; local files are read directly anyway.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+use web_time::{SystemTime, UNIX_EPOCH};
+
+/// Subdirectory of the cache dir holding the entries.
+const DISK_CACHE_DIR: &str = "schema-cache";
+
+/// One file per schema URL, named by [`url_file_name`].
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    url: String,
+    /// Seconds since the Unix epoch.
+    fetched_at: u64,
+    ttl_secs: u64,
+    schema: Value,
+}
+
+/// A schema read back from disk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiskSchema {
+    pub schema: Value,
+    /// Still within the TTL it was stored with; a stale copy is only a fallback for when
+    /// the schema cannot be fetched.
+    pub fresh: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+impl DiskCache {
+    /// Entries under `<cache_dir>/schema-cache`.
+    pub fn new(cache_dir: &Path) -> Self {
+        Self {
+            dir: cache_dir.join(DISK_CACHE_DIR),
+        }
+    }
+
+    /// Whether schemas from `url` are persisted.
+    pub fn is_cacheable(url: &str) -> bool {
+        url.starts_with("https://") || url.starts_with("http://")
+    }
+
+    pub fn get(&self, url: &str) -> Option<DiskSchema> {
+        let text = std::fs::read_to_string(self.path(url)).ok()?;
+        let entry: Entry = serde_json::from_str(&text).ok()?;
+        // A hash collision: the file belongs to another URL.
+        if entry.url != url {
+            return None;
+        }
+        let age = now_secs().saturating_sub(entry.fetched_at);
+        Some(DiskSchema {
+            schema: entry.schema,
+            fresh: age < entry.ttl_secs,
+        })
+    }
+
+    /// Store `schema` as fetched now, valid for `ttl_secs`. Failures are logged, not fatal.
+    pub fn put(&self, url: &str, schema: &Value, ttl_secs: u64) {
+        let entry = Entry {
+            url: url.to_owned(),
+            fetched_at: now_secs(),
+            ttl_secs,
+            schema: schema.clone(),
+        };
+        let path = self.path(url);
+        let tmp = path.with_extension("json.tmp");
+        let write = std::fs::create_dir_all(&self.dir)
+            .and_then(|_| std::fs::write(&tmp, serde_json::to_vec(&entry)?))
+            .and_then(|_| std::fs::rename(&tmp, &path));
+        match write {
+            Ok(()) => debug!("Schema persisted: {url} -> {}", path.display()),
+            Err(e) => warn!("Could not persist schema {url}: {e}"),
+        }
+    }
+
+    pub fn remove(&self, url: &str) {
+        let _ = std::fs::remove_file(self.path(url));
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        self.dir.join(url_file_name(url))
+    }
+}
+
+/// `<hash>.json` for a schema URL. The hash is 64-bit FNV-1a, which unlike std's
+/// `DefaultHasher` is fixed across Rust releases, so files written by one build are found
+/// by the next.
+pub fn url_file_name(url: &str) -> String {
+    let hash = url.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!("{hash:016x}.json")
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_entries_round_trip_with_freshness() {
+        let dir = std::env::temp_dir().join(format!("json-ls-disk-{}", std::process::id()));
+        let cache = DiskCache::new(&dir);
+        let url = "https://example.com/schema.json";
+        assert_eq!(cache.get(url), None);
+
+        cache.put(url, &json!({ "type": "object" }), 60);
+        let fresh = cache.get(url);
+        cache.put(url, &json!({ "type": "array" }), 0);
+        let stale = cache.get(url);
+        cache.remove(url);
+        let removed = cache.get(url);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            fresh,
+            Some(DiskSchema {
+                schema: json!({ "type": "object" }),
+                fresh: true
+            })
+        );
+        assert_eq!(
+            stale.map(|s| (s.schema, s.fresh)),
+            Some((json!({ "type": "array" }), false))
+        );
+        assert_eq!(removed, None);
+        assert!(!DiskCache::is_cacheable("file:///tmp/schema.json"));
+    }
+
+    #[test]
+    fn test_file_names_are_stable() {
+        assert_eq!(
+            url_file_name("https://example.com/schema.json"),
+            "737ee54577542161.json"
+        );
+    }
+}
@@ -1,43 +1,332 @@
-use anyhow::{bail, Context, Result};
+#[cfg(feature = "native")]
+use crate::config::{AuthConfig, ServerConfig, TlsConfig};
+use crate::percent::percent_decode_bytes;
+#[cfg(feature = "native")]
+use crate::schema::auth::credentials_for;
+use anyhow::{anyhow, Result};
+#[cfg(feature = "native")]
+use anyhow::{bail, Context};
+use base64::Engine;
 use serde_json::Value;
+use std::collections::HashMap;
+#[cfg(feature = "native")]
+use std::sync::{Arc, RwLock};
+#[cfg(feature = "native")]
 use std::time::Duration;
+#[cfg(feature = "native")]
 use tracing::{debug, instrument};
 
+#[cfg(feature = "native")]
 const USER_AGENT: &str = "json-ls.nvim/0.1";
-const TIMEOUT_SECS: u64 = 10;
+
+/// Source of schema documents for the [`SchemaCache`](super::SchemaCache).
+///
+/// The native build fetches over HTTP(S) and from the filesystem ([`NativeLoader`]);
+/// embedders without network or filesystem access (e.g. a browser build) supply their own,
+/// or use [`EmbeddedLoader`] with schemas bundled ahead of time.
+#[tower_lsp::async_trait]
+pub trait SchemaLoader: Send + Sync {
+    async fn load(&self, url: &str) -> Result<Value>;
+}
+
+/// HTTP(S) + `file://` / bare-path loader, dispatching on the URL scheme.
+///
+/// Owns one HTTP client, so fetches share its connection pool (and TLS sessions); the
+/// client is rebuilt only when the fetch settings change. Other schemes are served by
+/// handlers registered with [`register_scheme`](Self::register_scheme), or rewritten to
+/// another URL by the `url_schemes` templates.
+#[cfg(feature = "native")]
+#[derive(Default)]
+pub struct NativeLoader {
+    client: RwLock<Arc<HttpClient>>,
+    handlers: RwLock<HashMap<String, Arc<dyn SchemaLoader>>>,
+    templates: RwLock<HashMap<String, String>>,
+}
+
+#[cfg(feature = "native")]
+impl NativeLoader {
+    /// Apply the fetch-related settings from `config`.
+    pub fn configure(&self, config: &ServerConfig) {
+        let options = FetchOptions::from(config);
+        let mut client = self.client.write().expect("HTTP client lock poisoned");
+        if client.options != options {
+            *client = Arc::new(HttpClient::new(options));
+        }
+        *self.templates.write().expect("URL template lock poisoned") = config
+            .url_schemes
+            .iter()
+            .map(|(scheme, template)| (scheme.to_ascii_lowercase(), template.clone()))
+            .collect();
+    }
+
+    /// Serve `scheme:` URLs (e.g. `vscode`, `k8s`) through `handler`. A handler registered
+    /// for `http`, `https` or `file` replaces the built-in one.
+    pub fn register_scheme(&self, scheme: &str, handler: Arc<dyn SchemaLoader>) {
+        self.handlers
+            .write()
+            .expect("scheme handler lock poisoned")
+            .insert(scheme.to_ascii_lowercase(), handler);
+    }
+
+    /// `url` rewritten by the `url_schemes` template for its scheme, if any.
+    fn expand(&self, url: &str) -> Option<String> {
+        let scheme = url_scheme(url)?;
+        let templates = self.templates.read().expect("URL template lock poisoned");
+        let template = templates.get(&scheme)?;
+        let path = url[scheme.len() + 1..].trim_start_matches("//");
+        Some(template.replace("{path}", path).replace("{url}", url))
+    }
+}
+
+/// The subset of [`ServerConfig`] that affects HTTP fetches.
+#[cfg(feature = "native")]
+#[derive(Debug, Clone, PartialEq)]
+struct FetchOptions {
+    auth: AuthConfig,
+    timeout: Duration,
+    connect_timeout: Duration,
+    read_timeout: Option<Duration>,
+    proxy_url: Option<String>,
+    tls: TlsConfig,
+}
+
+#[cfg(feature = "native")]
+impl From<&ServerConfig> for FetchOptions {
+    fn from(config: &ServerConfig) -> Self {
+        Self {
+            auth: config.auth.clone(),
+            timeout: Duration::from_secs(config.fetch_timeout_secs),
+            connect_timeout: Duration::from_secs(config.connect_timeout_secs),
+            read_timeout: config.read_timeout_secs.map(Duration::from_secs),
+            proxy_url: config.proxy_url.clone(),
+            tls: config.tls.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl Default for FetchOptions {
+    fn default() -> Self {
+        Self::from(&ServerConfig::default())
+    }
+}
+
+/// An HTTP client built from [`FetchOptions`], with the options it was built from.
+#[cfg(feature = "native")]
+#[derive(Debug)]
+struct HttpClient {
+    options: FetchOptions,
+    /// The build error (e.g. an unreadable `tls.ca_file`) is reported by each HTTP fetch.
+    client: std::result::Result<reqwest::Client, String>,
+}
+
+#[cfg(feature = "native")]
+impl HttpClient {
+    fn new(options: FetchOptions) -> Self {
+        let client = build_client(&options).map_err(|e| format!("{e:#}"));
+        Self { options, client }
+    }
+}
+
+#[cfg(feature = "native")]
+impl Default for HttpClient {
+    fn default() -> Self {
+        Self::new(FetchOptions::default())
+    }
+}
+
+#[cfg(feature = "native")]
+#[tower_lsp::async_trait]
+impl SchemaLoader for NativeLoader {
+    async fn load(&self, url: &str) -> Result<Value> {
+        // Templates are expanded once, so a template may not map onto another template.
+        let url = self.expand(url).unwrap_or_else(|| url.to_owned());
+        let handler = url_scheme(&url).and_then(|scheme| {
+            self.handlers
+                .read()
+                .expect("scheme handler lock poisoned")
+                .get(&scheme)
+                .cloned()
+        });
+        if let Some(handler) = handler {
+            return handler.load(&url).await;
+        }
+        let client = self
+            .client
+            .read()
+            .expect("HTTP client lock poisoned")
+            .clone();
+        fetch(&url, &client).await
+    }
+}
+
+/// In-memory loader serving a fixed set of schemas by URL.
+#[derive(Debug, Default)]
+pub struct EmbeddedLoader {
+    schemas: HashMap<String, Value>,
+}
+
+impl EmbeddedLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve `schema` for `url`.
+    pub fn with_schema(mut self, url: impl Into<String>, schema: Value) -> Self {
+        self.schemas.insert(url.into(), schema);
+        self
+    }
+}
+
+#[tower_lsp::async_trait]
+impl SchemaLoader for EmbeddedLoader {
+    async fn load(&self, url: &str) -> Result<Value> {
+        self.schemas
+            .get(url)
+            .cloned()
+            .ok_or_else(|| anyhow!("No embedded schema for: {url}"))
+    }
+}
 
 /// Fetch a JSON schema from an HTTP(S) URL or a `file://` / bare path.
-#[instrument(skip_all, fields(url = %url))]
+#[cfg(feature = "native")]
 pub async fn load_schema(url: &str) -> Result<Value> {
-    if url.starts_with("http://") || url.starts_with("https://") {
-        load_http(url).await
+    fetch(url, &HttpClient::default()).await
+}
+
+#[cfg(feature = "native")]
+#[instrument(skip_all, fields(url = %url))]
+async fn fetch(url: &str, client: &HttpClient) -> Result<Value> {
+    match url_scheme(url).as_deref() {
+        Some("http" | "https") => load_http(url, client).await,
+        Some("data") => load_data_url(url),
+        Some("file") | None => load_file(local_path(url).unwrap_or(url)),
+        Some(scheme) => bail!("No handler for `{scheme}:` URLs: {url}"),
+    }
+}
+
+/// The lowercased scheme of `url`, or `None` for a bare path. Single letters are Windows
+/// drive letters (`C:\schemas\a.json`), not schemes.
+pub fn url_scheme(url: &str) -> Option<String> {
+    let (scheme, _) = url.split_once(':')?;
+    let valid = scheme.len() > 1
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    valid.then(|| scheme.to_ascii_lowercase())
+}
+
+/// Media types YAML schemas are served as, besides a `.yaml` / `.yml` URL.
+const YAML_MEDIA_TYPES: [&str; 4] = [
+    "application/yaml",
+    "application/x-yaml",
+    "text/yaml",
+    "text/x-yaml",
+];
+
+/// Whether the schema at `url`, served as `content_type` if known, is YAML rather than JSON.
+pub fn is_yaml(url: &str, content_type: Option<&str>) -> bool {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let media_type = content_type
+        .and_then(|content_type| content_type.split(';').next())
+        .map(|media_type| media_type.trim().to_ascii_lowercase());
+    path.ends_with(".yaml")
+        || path.ends_with(".yml")
+        || media_type.is_some_and(|media_type| YAML_MEDIA_TYPES.contains(&media_type.as_str()))
+}
+
+/// Parse a schema document, as YAML if `yaml`, else as JSON.
+pub fn parse_schema(text: &str, yaml: bool) -> Result<Value> {
+    if yaml {
+        Ok(serde_yaml_ng::from_str(text)?)
     } else {
-        let path = url
-            .strip_prefix("file://")
-            .or_else(|| url.strip_prefix("file:"))
-            .unwrap_or(url);
-        load_file(path)
+        Ok(serde_json::from_str(text)?)
     }
 }
 
+/// `schema` as a `data:` URL.
+pub fn data_url(schema: &Value) -> String {
+    format!(
+        "data:application/json;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(schema.to_string())
+    )
+}
+
+/// Decode a `data:` URL (RFC 2397) holding a JSON or YAML schema, base64- or
+/// percent-encoded: `data:application/json;base64,eyJ0eXBlIjoib2JqZWN0In0=`.
+pub fn load_data_url(url: &str) -> Result<Value> {
+    let (header, data) = url
+        .strip_prefix("data:")
+        .and_then(|rest| rest.split_once(','))
+        .ok_or_else(|| anyhow!("Malformed data: URL"))?;
+    let (media_type, bytes) = match header.strip_suffix(";base64") {
+        Some(media_type) => (
+            media_type,
+            base64::engine::general_purpose::STANDARD
+                .decode(data)
+                .map_err(|e| anyhow!("Invalid base64 in data: URL: {e}"))?,
+        ),
+        None => (header, percent_decode_bytes(data)),
+    };
+    let text = String::from_utf8(bytes).map_err(|_| anyhow!("data: URL is not UTF-8"))?;
+    parse_schema(&text, is_yaml("", Some(media_type)))
+}
+
+/// Filesystem path for a `file://` / bare-path schema URL, or `None` for URLs of any other
+/// scheme (HTTP(S), `data:`, …).
+pub fn local_path(url: &str) -> Option<&str> {
+    if url_scheme(url).is_some_and(|scheme| scheme != "file") {
+        return None;
+    }
+    Some(
+        url.strip_prefix("file://")
+            .or_else(|| url.strip_prefix("file:"))
+            .unwrap_or(url),
+    )
+}
+
+#[cfg(feature = "native")]
 fn load_file(path: &str) -> Result<Value> {
     debug!("Loading schema from file: {path}");
     let contents = std::fs::read_to_string(path)
         .with_context(|| format!("Failed to read schema file: {path}"))?;
-    serde_json::from_str(&contents)
-        .with_context(|| format!("Failed to parse schema JSON from: {path}"))
+    parse_schema(&contents, is_yaml(path, None))
+        .with_context(|| format!("Failed to parse schema from: {path}"))
+}
+
+#[cfg(feature = "native")]
+fn build_client(options: &FetchOptions) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder()
+        .timeout(options.timeout)
+        .connect_timeout(options.connect_timeout)
+        .user_agent(USER_AGENT);
+    if let Some(read_timeout) = options.read_timeout {
+        builder = builder.read_timeout(read_timeout);
+    }
+    // Without `proxy_url`, reqwest picks up the `*_PROXY` environment variables itself.
+    if let Some(proxy_url) = &options.proxy_url {
+        let proxy = reqwest::Proxy::all(proxy_url)
+            .with_context(|| format!("Invalid proxy_url: {proxy_url}"))?
+            .no_proxy(reqwest::NoProxy::from_env());
+        builder = builder.proxy(proxy);
+    }
+    builder = with_tls(builder, &options.tls)?;
+    builder.build().context("Failed to build HTTP client")
 }
 
-async fn load_http(url: &str) -> Result<Value> {
+#[cfg(feature = "native")]
+async fn load_http(url: &str, client: &HttpClient) -> Result<Value> {
     debug!("Fetching schema over HTTP: {url}");
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(TIMEOUT_SECS))
-        .user_agent(USER_AGENT)
-        .build()
-        .context("Failed to build HTTP client")?;
-
-    let response = client
-        .get(url)
+    let http = client.client.as_ref().map_err(|e| anyhow!("{e}"))?;
+    let mut request = http.get(url);
+    let auth = &client.options.auth;
+    if let Some(credentials) = credentials_for(&reqwest::Url::parse(url)?, auth).await {
+        debug!("Using stored credentials for {url}");
+        request = request.basic_auth(credentials.username, Some(credentials.password));
+    }
+
+    let response = request
         .send()
         .await
         .with_context(|| format!("HTTP request failed for: {url}"))?;
@@ -49,16 +338,60 @@ async fn load_http(url: &str) -> Result<Value> {
         );
     }
 
-    response
-        .json::<Value>()
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned);
+    let text = response
+        .text()
         .await
-        .with_context(|| format!("Failed to parse JSON schema from: {url}"))
+        .with_context(|| format!("Failed to read schema from: {url}"))?;
+    parse_schema(&text, is_yaml(url, content_type.as_deref()))
+        .with_context(|| format!("Failed to parse schema from: {url}"))
+}
+
+/// Apply the `tls` settings to an HTTP client under construction.
+#[cfg(feature = "native")]
+fn with_tls(
+    mut builder: reqwest::ClientBuilder,
+    tls: &TlsConfig,
+) -> Result<reqwest::ClientBuilder> {
+    if let Some(ca_file) = &tls.ca_file {
+        let pem = std::fs::read(ca_file)
+            .with_context(|| format!("Failed to read tls.ca_file: {}", ca_file.display()))?;
+        let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+            .with_context(|| format!("Invalid PEM in tls.ca_file: {}", ca_file.display()))?;
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+    if let Some(cert_file) = &tls.client_cert {
+        let mut pem = std::fs::read(cert_file)
+            .with_context(|| format!("Failed to read tls.client_cert: {}", cert_file.display()))?;
+        if let Some(key_file) = &tls.client_key {
+            let key = std::fs::read(key_file).with_context(|| {
+                format!("Failed to read tls.client_key: {}", key_file.display())
+            })?;
+            pem.push(b'\n');
+            pem.extend(key);
+        }
+        let identity = reqwest::Identity::from_pem(&pem).context(
+            "Invalid tls.client_cert / tls.client_key: expected PEM certificate and key",
+        )?;
+        builder = builder.identity(identity);
+    }
+    if tls.insecure_skip_verify {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    Ok(builder)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[cfg(feature = "native")]
     #[tokio::test]
     async fn test_load_file_schema() {
         let schema_path = concat!(
@@ -78,4 +411,262 @@ mod tests {
                 || schema.get("$schema").is_some()
         );
     }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn test_fetch_timeout_is_configurable() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        // Accept the connection but never answer.
+        tokio::spawn(async move {
+            let (_socket, _) = listener.accept().await.unwrap();
+            std::future::pending::<()>().await;
+        });
+
+        let config = ServerConfig {
+            fetch_timeout_secs: 1,
+            ..ServerConfig::default()
+        };
+        let started = std::time::Instant::now();
+        let result = fetch(
+            &format!("http://{addr}/schema.json"),
+            &HttpClient::new(FetchOptions::from(&config)),
+        )
+        .await;
+        assert!(result.is_err());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn test_fetch_goes_through_proxy_url() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let proxy = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut head = vec![0; 1024];
+            let n = socket.read(&mut head).await.unwrap();
+            let body = r#"{ "type": "object" }"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{body}",
+                body.len()
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&head[..n]).into_owned()
+        });
+
+        let config = ServerConfig {
+            proxy_url: Some(format!("http://{addr}")),
+            ..ServerConfig::default()
+        };
+        let schema = fetch(
+            "http://schemas.example.invalid/app.json",
+            &HttpClient::new(FetchOptions::from(&config)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(schema["type"], "object");
+        let request = proxy.await.unwrap();
+        assert!(
+            request.starts_with("GET http://schemas.example.invalid/app.json "),
+            "proxy saw: {request}"
+        );
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn test_fetches_reuse_one_connection() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let accepted = connections.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                accepted.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut head = vec![0; 1024];
+                    while socket.read(&mut head).await.unwrap_or(0) > 0 {
+                        let body = r#"{ "type": "object" }"#;
+                        let response = format!(
+                            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{body}",
+                            body.len()
+                        );
+                        socket.write_all(response.as_bytes()).await.unwrap();
+                    }
+                });
+            }
+        });
+
+        let loader = NativeLoader::default();
+        loader.configure(&ServerConfig::default());
+        for name in ["a", "b", "c"] {
+            let schema = loader.load(&format!("http://{addr}/{name}.json")).await;
+            assert_eq!(schema.unwrap()["type"], "object");
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+        // Unchanged settings keep the client; changed ones replace it.
+        let client = loader.client.read().unwrap().clone();
+        loader.configure(&ServerConfig::default());
+        assert!(Arc::ptr_eq(&client, &loader.client.read().unwrap()));
+        loader.configure(&ServerConfig {
+            fetch_timeout_secs: 1,
+            ..ServerConfig::default()
+        });
+        assert!(!Arc::ptr_eq(&client, &loader.client.read().unwrap()));
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn test_unreadable_tls_files_fail_the_fetch() {
+        let config = ServerConfig {
+            tls: TlsConfig {
+                ca_file: Some("/nonexistent/ca.pem".into()),
+                ..TlsConfig::default()
+            },
+            ..ServerConfig::default()
+        };
+        let error = fetch(
+            "https://schemas.example.invalid/a.json",
+            &HttpClient::new(FetchOptions::from(&config)),
+        )
+        .await
+        .unwrap_err();
+        assert!(format!("{error:#}").contains("tls.ca_file"), "{error:#}");
+
+        let not_pem = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/simple-schema.json"
+        );
+        let config = ServerConfig {
+            tls: TlsConfig {
+                client_cert: Some(not_pem.into()),
+                ..TlsConfig::default()
+            },
+            ..ServerConfig::default()
+        };
+        let error = fetch(
+            "https://schemas.example.invalid/a.json",
+            &HttpClient::new(FetchOptions::from(&config)),
+        )
+        .await
+        .unwrap_err();
+        assert!(
+            format!("{error:#}").contains("tls.client_cert"),
+            "{error:#}"
+        );
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn test_yaml_schemas_by_extension_and_content_type() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/simple-schema.yaml"
+        );
+        let schema = load_schema(&format!("file://{path}")).await.unwrap();
+        assert_eq!(schema["required"], serde_json::json!(["name"]));
+        assert_eq!(schema["properties"]["count"]["minimum"], 0);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut head = vec![0; 1024];
+            let _ = socket.read(&mut head).await.unwrap();
+            let body = "type: object\nproperties:\n  port: { type: integer }\n";
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/yaml; charset=utf-8\r\ncontent-length: {}\r\n\r\n{body}",
+                body.len()
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+        let schema = load_schema(&format!("http://{addr}/schema")).await.unwrap();
+        assert_eq!(schema["properties"]["port"]["type"], "integer");
+    }
+
+    #[test]
+    fn test_yaml_detection() {
+        assert!(is_yaml("https://example.com/openapi.yaml", None));
+        assert!(is_yaml("file:///ci/pipeline.yml#/x", None));
+        assert!(is_yaml("https://example.com/schema", Some("text/yaml")));
+        assert!(!is_yaml(
+            "https://example.com/schema.json",
+            Some("application/json")
+        ));
+        assert!(!is_yaml("https://example.com/yaml.json", None));
+    }
+
+    #[test]
+    fn test_data_urls() {
+        let schema = serde_json::json!({ "type": "object", "required": ["name"] });
+        assert_eq!(load_data_url(&data_url(&schema)).unwrap(), schema);
+        assert_eq!(
+            load_data_url("data:application/json,%7B%22type%22%3A%22string%22%7D").unwrap(),
+            serde_json::json!({ "type": "string" })
+        );
+        assert_eq!(
+            load_data_url("data:application/yaml,type: integer").unwrap()["type"],
+            "integer"
+        );
+        assert!(load_data_url("data:application/json;base64,!!!").is_err());
+        assert!(load_data_url("data:no-comma").is_err());
+        assert_eq!(local_path("data:application/json,{}"), None);
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn test_scheme_handlers_and_templates() {
+        let loader = NativeLoader::default();
+        loader.configure(&ServerConfig {
+            url_schemes: HashMap::from([(
+                "k8s".to_owned(),
+                concat!(
+                    "file://",
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/tests/fixtures/{path}"
+                )
+                .to_owned(),
+            )]),
+            ..ServerConfig::default()
+        });
+        loader.register_scheme(
+            "vscode",
+            Arc::new(EmbeddedLoader::new().with_schema(
+                "vscode://schemas/settings",
+                serde_json::json!({ "type": "object" }),
+            )),
+        );
+
+        let templated = loader.load("k8s://simple-schema.json").await.unwrap();
+        assert!(templated.get("properties").is_some(), "got: {templated}");
+        assert_eq!(
+            loader.load("vscode://schemas/settings").await.unwrap()["type"],
+            "object"
+        );
+        let unknown = loader.load("unknown://schema.json").await.unwrap_err();
+        assert!(unknown.to_string().contains("`unknown:`"), "got: {unknown}");
+
+        assert_eq!(url_scheme("HTTPS://example.com").as_deref(), Some("https"));
+        assert_eq!(url_scheme("C:\\schemas\\a.json"), None);
+        assert_eq!(url_scheme("/tmp/a.json"), None);
+        assert_eq!(local_path("vscode://schemas/settings"), None);
+        assert_eq!(local_path("file:///tmp/a.json"), Some("/tmp/a.json"));
+    }
+
+    #[tokio::test]
+    async fn test_embedded_loader() {
+        let loader = EmbeddedLoader::new()
+            .with_schema("mem://a.json", serde_json::json!({ "type": "string" }));
+        assert_eq!(loader.load("mem://a.json").await.unwrap()["type"], "string");
+        assert!(loader.load("mem://missing.json").await.is_err());
+    }
 }
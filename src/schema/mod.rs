@@ -1,6 +1,15 @@
+#[cfg(feature = "native")]
+pub mod auth;
 pub mod cache;
+pub mod catalog;
+pub mod cycles;
+pub mod disk_cache;
 pub mod loader;
 pub mod navigator;
+pub mod refs;
+pub mod source;
 
 pub use cache::SchemaCache;
-pub use navigator::SchemaNode;
+pub use catalog::Catalog;
+pub use loader::{EmbeddedLoader, SchemaLoader};
+pub use navigator::{PropertyMemo, SchemaNode};
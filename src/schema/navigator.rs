@@ -1,6 +1,12 @@
+use crate::config::SchemaLimits;
+use crate::markdown::code_span;
 use crate::position::PathSegment;
+use crate::schema::refs::DATA_KEYWORDS;
+use dashmap::DashMap;
 use serde_json::Value;
 use std::collections::HashSet;
+use std::sync::Arc;
+use tracing::warn;
 
 /// Information extracted from a schema node for hover display.
 #[derive(Debug, Default)]
@@ -10,29 +16,54 @@ pub struct HoverInfo {
     pub default: Option<String>,
     pub examples: Vec<String>,
     pub enum_values: Vec<String>,
+    pub pattern: Option<String>,
+    /// Summary of `minLength` / `maxLength`, e.g. "3 to 20 characters".
+    pub length: Option<String>,
+    /// Set for `"deprecated": true`: the `deprecationMessage`, or empty.
+    pub deprecated: Option<String>,
+    /// The property is listed in its object's `required`.
+    pub required: bool,
 }
 
 impl HoverInfo {
     pub fn to_markdown(&self) -> String {
         let mut parts = Vec::new();
 
+        match self.deprecated.as_deref() {
+            Some("") => parts.push("**Deprecated**".to_owned()),
+            Some(note) => parts.push(format!("**Deprecated:** {note}")),
+            None => {}
+        }
+
+        if self.required {
+            parts.push("**Required**".to_owned());
+        }
+
         if let Some(desc) = &self.description {
             parts.push(desc.clone());
         }
 
         if let Some(ty) = &self.type_info {
-            parts.push(format!("**Type:** `{ty}`"));
+            parts.push(format!("**Type:** {}", code_span(ty)));
         }
 
         if let Some(default) = &self.default {
-            parts.push(format!("**Default:** `{default}`"));
+            parts.push(format!("**Default:** {}", code_span(default)));
+        }
+
+        if let Some(pattern) = &self.pattern {
+            parts.push(format!("**Pattern:** {}", code_span(pattern)));
+        }
+
+        if let Some(length) = &self.length {
+            parts.push(format!("**Length:** {length}"));
         }
 
         if !self.enum_values.is_empty() {
             let vals = self
                 .enum_values
                 .iter()
-                .map(|v| format!("`{v}`"))
+                .map(|v| code_span(v))
                 .collect::<Vec<_>>()
                 .join(", ");
             parts.push(format!("**Allowed values:** {vals}"));
@@ -42,7 +73,7 @@ impl HoverInfo {
             let exs = self
                 .examples
                 .iter()
-                .map(|e| format!("`{e}`"))
+                .map(|e| code_span(e))
                 .collect::<Vec<_>>()
                 .join(", ");
             parts.push(format!("**Examples:** {exs}"));
@@ -50,6 +81,87 @@ impl HoverInfo {
 
         parts.join("\n\n")
     }
+
+    /// Fill what this info lacks from `other`, describing another `allOf` member of the
+    /// same location.
+    fn merge(&mut self, other: HoverInfo) {
+        self.description = self.description.take().or(other.description);
+        self.type_info = self.type_info.take().or(other.type_info);
+        self.default = self.default.take().or(other.default);
+        self.pattern = self.pattern.take().or(other.pattern);
+        if self.enum_values.is_empty() {
+            self.enum_values = other.enum_values;
+        }
+        for example in other.examples {
+            if !self.examples.contains(&example) {
+                self.examples.push(example);
+            }
+        }
+    }
+}
+
+/// The `pattern`, `minLength` and `maxLength` of a string schema.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StringConstraints<'a> {
+    pub pattern: Option<&'a str>,
+    pub min_length: Option<u64>,
+    pub max_length: Option<u64>,
+}
+
+impl<'a> StringConstraints<'a> {
+    fn of(schema: &'a Value) -> Self {
+        Self {
+            pattern: schema.get("pattern").and_then(Value::as_str),
+            min_length: schema.get("minLength").and_then(Value::as_u64),
+            max_length: schema.get("maxLength").and_then(Value::as_u64),
+        }
+    }
+
+    /// Constraints satisfied only by strings satisfying both: `self`'s pattern, else
+    /// `other`'s, and the tighter of each length bound.
+    fn intersect(self, other: Self) -> Self {
+        Self {
+            pattern: self.pattern.or(other.pattern),
+            min_length: self.min_length.max(other.min_length),
+            max_length: match (self.max_length, other.max_length) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+        }
+    }
+
+    /// "3 to 20 characters", "at least 3 characters", ... `None` without either bound.
+    pub fn length(&self) -> Option<String> {
+        let plural = |n: u64| if n == 1 { "" } else { "s" };
+        Some(match (self.min_length, self.max_length) {
+            (Some(min), Some(max)) if min == max => {
+                format!("exactly {min} character{}", plural(min))
+            }
+            (Some(min), Some(max)) => format!("{min} to {max} characters"),
+            (Some(min), None) => format!("at least {min} character{}", plural(min)),
+            (None, Some(max)) => format!("at most {max} character{}", plural(max)),
+            (None, None) => return None,
+        })
+    }
+
+    /// Whether `value` satisfies the constraints, using the validator's regex dialect. A
+    /// pattern the validator cannot compile constrains nothing.
+    pub fn accepts(&self, value: &str) -> bool {
+        let mut schema = serde_json::Map::new();
+        if let Some(pattern) = self.pattern {
+            schema.insert("pattern".into(), pattern.into());
+        }
+        if let Some(min) = self.min_length {
+            schema.insert("minLength".into(), min.into());
+        }
+        if let Some(max) = self.max_length {
+            schema.insert("maxLength".into(), max.into());
+        }
+        match jsonschema::validator_for(&Value::Object(schema)) {
+            Ok(validator) => validator.is_valid(&Value::String(value.to_owned())),
+            Err(_) => true,
+        }
+    }
 }
 
 /// A reference into a JSON Schema document that supports navigation.
@@ -58,49 +170,189 @@ pub struct SchemaNode<'a> {
     pub schema: &'a Value,
     /// The document root (for resolving `$ref`).
     pub root: &'a Value,
+    /// Traversal bounds applied to navigation from this node.
+    pub limits: SchemaLimits,
+    /// Further declarations of the same location in sibling `allOf` branches. The instance
+    /// must satisfy them too, so hover and property names merge them in.
+    pub merged: Vec<&'a Value>,
 }
 
 impl<'a> SchemaNode<'a> {
     pub fn new(schema: &'a Value, root: &'a Value) -> Self {
-        Self { schema, root }
+        Self {
+            schema,
+            root,
+            limits: SchemaLimits::default(),
+            merged: Vec::new(),
+        }
+    }
+
+    /// Use `limits` instead of the defaults for traversals from this node.
+    pub fn with_limits(mut self, limits: SchemaLimits) -> Self {
+        self.limits = limits;
+        self
     }
 
     fn resolved(&self) -> &'a Value {
-        resolve_ref(self.schema, self.root, &mut HashSet::new()).unwrap_or(self.schema)
+        resolve_ref(self.schema, self.root).unwrap_or(self.schema)
+    }
+
+    /// This node's schema followed by the [`merged`](Self::merged) declarations.
+    fn schemas(&self) -> Vec<&'a Value> {
+        std::iter::once(self.schema)
+            .chain(self.merged.iter().copied())
+            .collect()
     }
 
     /// Navigate to the schema node at the given JSON path.
+    ///
+    /// The node is the first declaration found; declarations of the same last segment in
+    /// the parent's other `allOf` branches are kept in [`merged`](Self::merged).
     pub fn navigate(&self, path: &[PathSegment]) -> Option<SchemaNode<'a>> {
-        let mut visited: HashSet<usize> = HashSet::new();
-        navigate_inner(self.schema, self.root, path, &mut visited)
+        let mut walk = Walk::new(self.limits);
+        let node = navigate_inner(self.schema, self.root, path, &mut walk, 0);
+        walk.report("navigate");
+        let mut node = node?;
+        if let Some((last, parent_path)) = path.split_last() {
+            let mut walk = Walk::new(self.limits);
+            if let Some(parent) = navigate_inner(self.schema, self.root, parent_path, &mut walk, 0)
+            {
+                let mut declarations = Vec::new();
+                for schema in all_of_members(&parent.schemas(), self.root, &mut walk) {
+                    if let Some(found) = try_navigate_segment(schema, self.root, last, &walk) {
+                        declarations.push(found.schema);
+                    }
+                }
+                node.merged = declarations
+                    .into_iter()
+                    .filter(|schema| {
+                        let schema = resolve_ref(schema, self.root).unwrap_or(schema);
+                        !std::ptr::eq(schema, node.schema)
+                    })
+                    .collect();
+            }
+            walk.report("navigate");
+        }
+        Some(node)
+    }
+
+    /// Navigate to where the last segment of `path` is *declared* — e.g. the
+    /// `properties/<key>` entry itself rather than the target of its `$ref`.
+    pub fn declaration(&self, path: &[PathSegment]) -> Option<SchemaNode<'a>> {
+        let (last, parent_path) = path.split_last()?;
+        let parent = self.navigate(parent_path)?;
+        let mut walk = Walk::new(self.limits);
+        let node = declaration_in(parent.resolved(), self.root, last, &mut walk, 0);
+        walk.report("declaration");
+        node
+    }
+
+    /// JSON Pointer of this node within its root document, if it is part of it.
+    pub fn pointer(&self) -> Option<String> {
+        pointer_to(self.root, self.schema, String::new())
+    }
+
+    /// Like [`pointer`](Self::pointer), for the schema hover and completion read keywords
+    /// from: the target of this node's `$ref`, if it has one.
+    pub fn resolved_pointer(&self) -> Option<String> {
+        pointer_to(self.root, self.resolved(), String::new())
+    }
+
+    /// JSON Pointers of this node's schema and its [`merged`](Self::merged) declarations
+    /// within the root document, or `None` if one of them is not part of it.
+    pub fn declaration_pointers(&self) -> Option<Vec<String>> {
+        self.schemas()
+            .into_iter()
+            .map(|schema| pointer_to(self.root, schema, String::new()))
+            .collect()
     }
 
     /// Return the names of all directly defined properties (for completion).
+    ///
+    /// Beyond `max_properties` names the result is truncated: own properties come first,
+    /// then composition branches in order, so the same schema always yields the same subset.
     pub fn property_names(&self) -> Vec<String> {
-        let schema = self.resolved();
+        let mut walk = Walk::new(self.limits);
         let mut names = Vec::new();
-
-        if let Some(props) = schema.get("properties").and_then(|p| p.as_object()) {
-            names.extend(props.keys().cloned());
-        }
-
-        for key in &["allOf", "anyOf", "oneOf"] {
-            if let Some(arr) = schema.get(key).and_then(|v| v.as_array()) {
-                for sub in arr {
-                    let node = SchemaNode::new(sub, self.root);
-                    names.extend(node.property_names());
-                }
-            }
+        let mut seen = HashSet::new();
+        for schema in self.schemas() {
+            collect_property_names(schema, self.root, &mut walk, 0, &mut names, &mut seen);
         }
+        walk.report("property_names");
 
+        let mut names: Vec<String> = names.into_iter().map(str::to_owned).collect();
         names.sort();
-        names.dedup();
         names
     }
 
-    /// Extract hover information from this schema node.
+    /// The `properties` and `patternProperties` this node and its composition branches
+    /// declare, or `None` when the traversal was truncated and the answer would be partial.
+    pub fn declared_properties(&self) -> Option<DeclaredProperties<'a>> {
+        let mut walk = Walk::new(self.limits);
+        let mut declared = DeclaredProperties::default();
+        for schema in self.schemas() {
+            collect_declared(schema, self.root, &mut walk, 0, &mut declared);
+        }
+        walk.report("declared_properties");
+        (!walk.truncated).then_some(declared)
+    }
+
+    /// [`property_names`](Self::property_names), reusing the result for this node from
+    /// `memo` when it was computed before.
+    pub fn property_names_memoized(&self, memo: &PropertyMemo) -> Arc<Vec<String>> {
+        if !std::ptr::eq(self.root, &*memo.root)
+            || self.limits != memo.limits
+            || !self.merged.is_empty()
+        {
+            return Arc::new(self.property_names());
+        }
+        memo.names
+            .entry(self.schema as *const Value as usize)
+            .or_insert_with(|| Arc::new(self.property_names()))
+            .clone()
+    }
+
+    /// Extract hover information from this schema node, merged with its `allOf` members and
+    /// the [`merged`](Self::merged) declarations: the first description, type, default,
+    /// pattern and enum found, every example, and the tightest length bounds.
     pub fn hover_info(&self) -> HoverInfo {
-        extract_hover_info(self.resolved())
+        let mut walk = Walk::new(self.limits);
+        let members = all_of_members(&self.schemas(), self.root, &mut walk);
+        walk.report("hover_info");
+        let mut infos = members.iter().map(|schema| extract_hover_info(schema));
+        let mut info = infos.next().unwrap_or_default();
+        for other in infos {
+            info.merge(other);
+        }
+        info.length = self.string_constraints().length();
+        info
+    }
+
+    /// Names in the `required` lists of this object node and its `allOf` members.
+    pub fn required_names(&self) -> HashSet<&'a str> {
+        let mut walk = Walk::new(self.limits);
+        let members = all_of_members(&self.schemas(), self.root, &mut walk);
+        walk.report("required_names");
+        members
+            .iter()
+            .filter_map(|schema| schema.get("required").and_then(Value::as_array))
+            .flatten()
+            .filter_map(Value::as_str)
+            .collect()
+    }
+
+    /// True for `"deprecated": true` on this node or on the target of its `$ref`.
+    pub fn is_deprecated(&self) -> bool {
+        [self.schema, self.resolved()]
+            .iter()
+            .any(|schema| schema.get("deprecated") == Some(&Value::Bool(true)))
+    }
+
+    /// The `deprecationMessage` (the VS Code extension keyword) of a deprecated node.
+    pub fn deprecation_message(&self) -> Option<&'a str> {
+        [self.schema, self.resolved()]
+            .into_iter()
+            .find_map(|schema| schema.get("deprecationMessage").and_then(Value::as_str))
     }
 
     /// Return enum values if the schema has an `enum` keyword.
@@ -119,44 +371,192 @@ impl<'a> SchemaNode<'a> {
             .unwrap_or_default()
     }
 
+    /// Return the value of `keyword` on this node (after following `$ref`).
+    pub fn keyword(&self, keyword: &str) -> Option<&'a Value> {
+        self.resolved().get(keyword)
+    }
+
+    /// String examples from `examples` that satisfy the node's string constraints.
+    pub fn matching_examples(&self) -> Vec<&'a str> {
+        let constraints = self.string_constraints();
+        self.keyword("examples")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .filter(|example| constraints.accepts(example))
+            .collect()
+    }
+
+    /// String constraints of this node and its `allOf` members: the first `pattern` and the
+    /// tightest length bounds.
+    pub fn string_constraints(&self) -> StringConstraints<'a> {
+        let mut walk = Walk::new(self.limits);
+        let members = all_of_members(&self.schemas(), self.root, &mut walk);
+        walk.report("string_constraints");
+        members
+            .iter()
+            .map(|schema| StringConstraints::of(schema))
+            .reduce(StringConstraints::intersect)
+            .unwrap_or_default()
+    }
+
     /// Return the `type` field if present.
     pub fn schema_type(&self) -> Option<&str> {
         self.resolved().get("type").and_then(|t| t.as_str())
     }
 }
 
+/// Property names and patterns an object schema declares, across composition branches.
+#[derive(Debug, Default)]
+pub struct DeclaredProperties<'a> {
+    pub names: HashSet<&'a str>,
+    pub patterns: Vec<&'a str>,
+}
+
+impl DeclaredProperties<'_> {
+    /// True if nothing is declared: a free-form object or a map.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty() && self.patterns.is_empty()
+    }
+
+    /// Whether `key` is a declared name or matches a declared pattern (in the validator's
+    /// regex dialect).
+    pub fn covers(&self, key: &str) -> bool {
+        self.names.contains(key)
+            || self.patterns.iter().any(|pattern| {
+                let constraints = StringConstraints {
+                    pattern: Some(pattern),
+                    ..StringConstraints::default()
+                };
+                constraints.accepts(key)
+            })
+    }
+}
+
+/// Property names already enumerated for nodes of one schema document, keyed by node
+/// address. Holds the document, so the addresses stay valid for the memo's lifetime.
+pub struct PropertyMemo {
+    root: Arc<Value>,
+    limits: SchemaLimits,
+    names: DashMap<usize, Arc<Vec<String>>>,
+}
+
+impl PropertyMemo {
+    pub fn new(root: Arc<Value>, limits: SchemaLimits) -> Self {
+        Self {
+            root,
+            limits,
+            names: DashMap::new(),
+        }
+    }
+
+    /// True if this memo was built for `root` under `limits`.
+    pub fn is_for(&self, root: &Arc<Value>, limits: SchemaLimits) -> bool {
+        Arc::ptr_eq(&self.root, root) && self.limits == limits
+    }
+}
+
+/// Traversal state shared across one navigation: cycle guard plus depth/branch budget.
+struct Walk {
+    limits: SchemaLimits,
+    visited: HashSet<usize>,
+    /// Nodes entered by `navigate`, with the number of path segments left.
+    positions: HashSet<(usize, usize)>,
+    branches: usize,
+    truncated: bool,
+}
+
+impl Walk {
+    fn new(limits: SchemaLimits) -> Self {
+        Self {
+            limits,
+            visited: HashSet::new(),
+            positions: HashSet::new(),
+            branches: 0,
+            truncated: false,
+        }
+    }
+
+    /// False (and mark truncated) once `depth` exceeds the limit.
+    fn within_depth(&mut self, depth: usize) -> bool {
+        if depth > self.limits.max_depth {
+            self.truncated = true;
+            return false;
+        }
+        true
+    }
+
+    /// Count one more composition branch; false (and mark truncated) over budget.
+    fn enter_branch(&mut self) -> bool {
+        self.branches += 1;
+        if self.branches > self.limits.max_branches {
+            self.truncated = true;
+            return false;
+        }
+        true
+    }
+
+    fn node<'a>(&self, schema: &'a Value, root: &'a Value) -> SchemaNode<'a> {
+        SchemaNode {
+            schema,
+            root,
+            limits: self.limits,
+            merged: Vec::new(),
+        }
+    }
+
+    fn report(&self, operation: &str) {
+        if self.truncated {
+            warn!(
+                "Schema {operation} truncated: exceeded max_depth {}, max_branches {} or \
+                 max_properties {}",
+                self.limits.max_depth, self.limits.max_branches, self.limits.max_properties
+            );
+        }
+    }
+}
+
 fn navigate_inner<'a>(
     schema: &'a Value,
     root: &'a Value,
     path: &[PathSegment],
-    visited: &mut HashSet<usize>,
+    walk: &mut Walk,
+    depth: usize,
 ) -> Option<SchemaNode<'a>> {
-    // Cycle guard
+    if !walk.within_depth(depth) {
+        return None;
+    }
+
+    // Cycle guard: back at a node with the same path left to go. Recursive schemas revisit
+    // nodes, but each visit consumes a segment.
     let ptr = schema as *const Value as usize;
-    if visited.contains(&ptr) {
+    if !walk.positions.insert((ptr, path.len())) {
         return None;
     }
-    visited.insert(ptr);
 
-    let schema = resolve_ref(schema, root, visited).unwrap_or(schema);
+    let schema = resolve_ref(schema, root).unwrap_or(schema);
 
     if path.is_empty() {
-        return Some(SchemaNode { schema, root });
+        return Some(walk.node(schema, root));
     }
 
     let segment = &path[0];
     let rest = &path[1..];
 
     // Try direct resolution for current segment
-    if let Some(node) = try_navigate_segment(schema, root, segment, visited) {
-        return navigate_inner(node.schema, root, rest, visited);
+    if let Some(node) = try_navigate_segment(schema, root, segment, walk) {
+        return navigate_inner(node.schema, root, rest, walk, depth + 1);
     }
 
     // Try allOf / anyOf / oneOf sub-schemas
     for key in &["allOf", "anyOf", "oneOf"] {
         if let Some(arr) = schema.get(key).and_then(|v| v.as_array()) {
             for sub in arr {
-                if let Some(node) = navigate_inner(sub, root, path, visited) {
+                if !walk.enter_branch() {
+                    return None;
+                }
+                if let Some(node) = navigate_inner(sub, root, path, walk, depth + 1) {
                     return Some(node);
                 }
             }
@@ -170,13 +570,13 @@ fn try_navigate_segment<'a>(
     schema: &'a Value,
     root: &'a Value,
     segment: &PathSegment,
-    _visited: &mut HashSet<usize>,
+    walk: &Walk,
 ) -> Option<SchemaNode<'a>> {
     match segment {
         PathSegment::Key(key) => {
             // Check properties
             if let Some(prop) = schema.get("properties").and_then(|p| p.get(key.as_str())) {
-                return Some(SchemaNode { schema: prop, root });
+                return Some(walk.node(prop, root));
             }
 
             // Check patternProperties (find first matching pattern)
@@ -185,7 +585,7 @@ fn try_navigate_segment<'a>(
                 for (pattern, sub) in pattern_props {
                     if let Ok(re) = regex_lite_match(pattern, key) {
                         if re {
-                            return Some(SchemaNode { schema: sub, root });
+                            return Some(walk.node(sub, root));
                         }
                     }
                 }
@@ -194,7 +594,7 @@ fn try_navigate_segment<'a>(
             // Fall back to additionalProperties
             if let Some(ap) = schema.get("additionalProperties") {
                 if ap.is_object() {
-                    return Some(SchemaNode { schema: ap, root });
+                    return Some(walk.node(ap, root));
                 }
             }
 
@@ -205,14 +605,11 @@ fn try_navigate_segment<'a>(
             // items as object (applies to all)
             if let Some(items) = schema.get("items") {
                 if items.is_object() || items.get("$ref").is_some() {
-                    return Some(SchemaNode {
-                        schema: items,
-                        root,
-                    });
+                    return Some(walk.node(items, root));
                 }
                 // items as array (tuple validation — deprecated in draft 2020-12)
                 if let Some(item) = items.as_array().and_then(|a| a.get(*idx)) {
-                    return Some(SchemaNode { schema: item, root });
+                    return Some(walk.node(item, root));
                 }
             }
 
@@ -222,7 +619,7 @@ fn try_navigate_segment<'a>(
                 .and_then(|pi| pi.as_array())
                 .and_then(|a| a.get(*idx))
             {
-                return Some(SchemaNode { schema: item, root });
+                return Some(walk.node(item, root));
             }
 
             None
@@ -230,25 +627,216 @@ fn try_navigate_segment<'a>(
     }
 }
 
-/// Resolve a `$ref` JSON Pointer fragment within the root document.
-/// Returns `None` if no `$ref` is present or resolution fails.
-fn resolve_ref<'a>(
+/// Find the node declaring `segment` in `schema`, searching composition branches.
+fn declaration_in<'a>(
     schema: &'a Value,
     root: &'a Value,
-    visited: &mut HashSet<usize>,
-) -> Option<&'a Value> {
-    let ref_str = schema.get("$ref")?.as_str()?;
+    segment: &PathSegment,
+    walk: &mut Walk,
+    depth: usize,
+) -> Option<SchemaNode<'a>> {
+    if !walk.within_depth(depth) {
+        return None;
+    }
+    let ptr = schema as *const Value as usize;
+    if !walk.visited.insert(ptr) {
+        return None;
+    }
 
-    // Only support fragment-only JSON Pointers: "#/path/to/def"
-    let pointer = ref_str.strip_prefix('#')?;
+    if let Some(node) = try_navigate_segment(schema, root, segment, walk) {
+        return Some(node);
+    }
 
-    let ptr = root as *const Value as usize;
-    if visited.contains(&ptr) {
-        return None;
+    for key in &["allOf", "anyOf", "oneOf"] {
+        if let Some(arr) = schema.get(key).and_then(|v| v.as_array()) {
+            for sub in arr {
+                if !walk.enter_branch() {
+                    return None;
+                }
+                let sub = resolve_ref(sub, root).unwrap_or(sub);
+                if let Some(node) = declaration_in(sub, root, segment, walk, depth + 1) {
+                    return Some(node);
+                }
+            }
+        }
     }
-    visited.insert(ptr);
 
-    root.pointer(pointer)
+    None
+}
+
+/// `schemas` and their `allOf` members, recursively, each with its `$ref` followed and each
+/// once. Unlike `anyOf` / `oneOf` branches, every member applies to the instance.
+fn all_of_members<'a>(schemas: &[&'a Value], root: &'a Value, walk: &mut Walk) -> Vec<&'a Value> {
+    let mut members = Vec::new();
+    let mut pending: Vec<(&'a Value, usize)> = schemas.iter().rev().map(|s| (*s, 0)).collect();
+    while let Some((schema, depth)) = pending.pop() {
+        if !walk.within_depth(depth) {
+            continue;
+        }
+        let schema = resolve_ref(schema, root).unwrap_or(schema);
+        if !walk.visited.insert(schema as *const Value as usize) {
+            continue;
+        }
+        members.push(schema);
+        if let Some(arr) = schema.get("allOf").and_then(Value::as_array) {
+            let subs: Vec<&Value> = arr.iter().take_while(|_| walk.enter_branch()).collect();
+            pending.extend(subs.into_iter().rev().map(|sub| (sub, depth + 1)));
+        }
+    }
+    members
+}
+
+/// Collect distinct `properties` names from `schema` and its composition branches, own
+/// properties first, stopping once `max_properties` names are found.
+fn collect_property_names<'a>(
+    schema: &'a Value,
+    root: &'a Value,
+    walk: &mut Walk,
+    depth: usize,
+    names: &mut Vec<&'a str>,
+    seen: &mut HashSet<&'a str>,
+) {
+    if !walk.within_depth(depth) {
+        return;
+    }
+    let schema = resolve_ref(schema, root).unwrap_or(schema);
+    if !walk.visited.insert(schema as *const Value as usize) {
+        return;
+    }
+
+    if let Some(props) = schema.get("properties").and_then(|p| p.as_object()) {
+        for name in props.keys() {
+            if seen.contains(name.as_str()) {
+                continue;
+            }
+            if names.len() >= walk.limits.max_properties {
+                walk.truncated = true;
+                return;
+            }
+            seen.insert(name);
+            names.push(name);
+        }
+    }
+
+    for key in &["allOf", "anyOf", "oneOf"] {
+        if let Some(arr) = schema.get(key).and_then(|v| v.as_array()) {
+            for sub in arr {
+                if walk.truncated || !walk.enter_branch() {
+                    return;
+                }
+                collect_property_names(sub, root, walk, depth + 1, names, seen);
+            }
+        }
+    }
+}
+
+fn collect_declared<'a>(
+    schema: &'a Value,
+    root: &'a Value,
+    walk: &mut Walk,
+    depth: usize,
+    declared: &mut DeclaredProperties<'a>,
+) {
+    if !walk.within_depth(depth) {
+        return;
+    }
+    let schema = resolve_ref(schema, root).unwrap_or(schema);
+    if !walk.visited.insert(schema as *const Value as usize) {
+        return;
+    }
+
+    if let Some(props) = schema.get("properties").and_then(Value::as_object) {
+        declared.names.extend(props.keys().map(String::as_str));
+    }
+    if let Some(patterns) = schema.get("patternProperties").and_then(Value::as_object) {
+        declared
+            .patterns
+            .extend(patterns.keys().map(String::as_str));
+    }
+
+    for key in &["allOf", "anyOf", "oneOf"] {
+        if let Some(arr) = schema.get(key).and_then(|v| v.as_array()) {
+            for sub in arr {
+                if !walk.enter_branch() {
+                    return;
+                }
+                collect_declared(sub, root, walk, depth + 1, declared);
+            }
+        }
+    }
+}
+
+/// Depth-first search for `target` (by identity) inside `node`, returning its JSON Pointer.
+fn pointer_to(node: &Value, target: &Value, prefix: String) -> Option<String> {
+    if std::ptr::eq(node, target) {
+        return Some(prefix);
+    }
+    match node {
+        Value::Object(map) => map.iter().find_map(|(key, child)| {
+            let token = key.replace('~', "~0").replace('/', "~1");
+            pointer_to(child, target, format!("{prefix}/{token}"))
+        }),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .find_map(|(idx, child)| pointer_to(child, target, format!("{prefix}/{idx}"))),
+        _ => None,
+    }
+}
+
+/// Resolve a `$ref` (or `$dynamicRef`) fragment within the root document, following chains
+/// of refs to refs. Returns `None` if no reference is present, resolution fails or the chain
+/// loops. External and `$anchor` refs were rewritten to JSON Pointers when the schema was
+/// loaded (see `schema::refs`).
+fn resolve_ref<'a>(schema: &'a Value, root: &'a Value) -> Option<&'a Value> {
+    let mut visited = HashSet::new();
+    let mut target = schema;
+    while let Some(ref_str) = reference(target) {
+        if !visited.insert(target as *const Value as usize) {
+            return None;
+        }
+        target = match ref_str.strip_prefix('#')? {
+            pointer if pointer.is_empty() || pointer.starts_with('/') => root.pointer(pointer)?,
+            anchor => dynamic_anchor(root, anchor)?,
+        };
+    }
+    (!std::ptr::eq(target, schema)).then_some(target)
+}
+
+fn reference(schema: &Value) -> Option<&str> {
+    schema
+        .get("$ref")
+        .or_else(|| schema.get("$dynamicRef"))
+        .and_then(Value::as_str)
+}
+
+/// The subschema a `$dynamicRef: "#<name>"` binds to. Dynamic scope depends on the
+/// instance's evaluation path, which navigation doesn't track; this takes the outermost
+/// `$dynamicAnchor` (the one evaluation starting at the root would find first), else an
+/// `$anchor` of that name.
+fn dynamic_anchor<'a>(root: &'a Value, name: &str) -> Option<&'a Value> {
+    let mut queue = std::collections::VecDeque::from([root]);
+    let mut fallback = None;
+    while let Some(schema) = queue.pop_front() {
+        match schema {
+            Value::Object(map) => {
+                if map.get("$dynamicAnchor").and_then(Value::as_str) == Some(name) {
+                    return Some(schema);
+                }
+                if fallback.is_none() && map.get("$anchor").and_then(Value::as_str) == Some(name) {
+                    fallback = Some(schema);
+                }
+                queue.extend(
+                    map.iter()
+                        .filter(|(key, _)| !DATA_KEYWORDS.contains(&key.as_str()))
+                        .map(|(_, child)| child),
+                );
+            }
+            Value::Array(items) => queue.extend(items),
+            _ => {}
+        }
+    }
+    fallback
 }
 
 /// Minimal pattern matching — just literal string containment for patternProperties.
@@ -310,12 +898,18 @@ fn extract_hover_info(schema: &Value) -> HoverInfo {
         })
         .unwrap_or_default();
 
+    let constraints = StringConstraints::of(schema);
+
     HoverInfo {
         description,
         type_info,
         default,
         examples,
         enum_values,
+        pattern: constraints.pattern.map(str::to_owned),
+        length: constraints.length(),
+        deprecated: None,
+        required: false,
     }
 }
 
@@ -427,6 +1021,35 @@ mod tests {
         assert_eq!(info.default.as_deref(), Some("0"));
     }
 
+    #[test]
+    fn test_string_constraints_filter_examples() {
+        let schema = json!({
+            "type": "string",
+            "pattern": "^[a-z]+-[0-9]+$",
+            "minLength": 5,
+            "maxLength": 8,
+            "examples": ["api-1", "API-1", "a-1", "service-10", "web-42", 7]
+        });
+        let node = SchemaNode::new(&schema, &schema);
+        assert_eq!(node.matching_examples(), vec!["api-1", "web-42"]);
+
+        let info = node.hover_info();
+        assert_eq!(info.pattern.as_deref(), Some("^[a-z]+-[0-9]+$"));
+        assert_eq!(info.length.as_deref(), Some("5 to 8 characters"));
+        assert!(info
+            .to_markdown()
+            .contains("**Pattern:** `^[a-z]+-[0-9]+$`"));
+
+        let exact = json!({ "minLength": 1, "maxLength": 1 });
+        assert_eq!(
+            SchemaNode::new(&exact, &exact)
+                .string_constraints()
+                .length()
+                .as_deref(),
+            Some("exactly 1 character")
+        );
+    }
+
     #[test]
     fn test_ref_resolution() {
         let schema = json!({
@@ -472,6 +1095,22 @@ mod tests {
         assert_eq!(vals, vec!["\"active\"", "\"inactive\"", "\"pending\""]);
     }
 
+    #[test]
+    fn test_declaration_stops_before_ref() {
+        let schema = json!({
+            "$defs": { "Name": { "type": "string" } },
+            "properties": {
+                "value": { "$ref": "#/$defs/Name" }
+            }
+        });
+
+        let node = SchemaNode::new(&schema, &schema);
+        let path = vec![PathSegment::Key("value".into())];
+        let decl = node.declaration(&path).unwrap();
+        assert_eq!(decl.pointer().as_deref(), Some("/properties/value"));
+        assert_eq!(decl.resolved_pointer().as_deref(), Some("/$defs/Name"));
+    }
+
     #[test]
     fn test_cycle_detection() {
         // A schema with a $ref that points to itself — should not infinite-loop
@@ -493,4 +1132,187 @@ mod tests {
         // Should return Some or None, but NOT panic/stack-overflow
         let _ = node.navigate(&path);
     }
+
+    #[test]
+    fn test_dynamic_ref_binds_to_outermost_dynamic_anchor() {
+        let schema = json!({
+            "$defs": {
+                "tree": {
+                    "$dynamicAnchor": "node",
+                    "properties": {
+                        "children": { "type": "array", "items": { "$dynamicRef": "#node" } }
+                    }
+                },
+                "stringTree": {
+                    "$ref": "#/$defs/tree",
+                    "properties": { "value": { "description": "Leaf text" } }
+                }
+            },
+            "$dynamicAnchor": "node",
+            "allOf": [{ "$ref": "#/$defs/stringTree" }],
+            "properties": { "value": { "description": "Root value" } }
+        });
+        let node = SchemaNode::new(&schema, &schema);
+        let path = [
+            PathSegment::Key("children".into()),
+            PathSegment::Index(0),
+            PathSegment::Key("value".into()),
+        ];
+        let value = node.navigate(&path).unwrap();
+        assert_eq!(
+            value.hover_info().description.as_deref(),
+            Some("Root value")
+        );
+    }
+
+    #[test]
+    fn test_navigate_through_refs_and_recursion() {
+        let schema = json!({
+            "$defs": {
+                "Node": {
+                    "properties": {
+                        "label": { "$ref": "#/$defs/Label" },
+                        "children": { "type": "array", "items": { "$ref": "#/$defs/Node" } }
+                    }
+                },
+                "Label": { "$ref": "#/$defs/Text" },
+                "Text": { "type": "string", "description": "Display text" },
+                "Loop": { "$ref": "#/$defs/Loop" }
+            },
+            "properties": {
+                "root": { "$ref": "#/$defs/Node" },
+                "loop": { "$ref": "#/$defs/Loop" }
+            }
+        });
+        let node = SchemaNode::new(&schema, &schema);
+        let path = [
+            PathSegment::Key("root".into()),
+            PathSegment::Key("children".into()),
+            PathSegment::Index(0),
+            PathSegment::Key("children".into()),
+            PathSegment::Index(1),
+            PathSegment::Key("label".into()),
+        ];
+        let label = node.navigate(&path).unwrap();
+        assert_eq!(
+            label.hover_info().description.as_deref(),
+            Some("Display text")
+        );
+
+        let path = [
+            PathSegment::Key("loop".into()),
+            PathSegment::Key("x".into()),
+        ];
+        assert!(node.navigate(&path).is_none());
+    }
+
+    #[test]
+    fn test_all_of_branches_merge_for_hover_and_property_names() {
+        let schema = json!({
+            "allOf": [
+                {
+                    "properties": {
+                        "name": { "description": "Display name", "minLength": 3 },
+                        "server": { "properties": { "host": {} } }
+                    },
+                    "required": ["name"]
+                },
+                { "$ref": "#/$defs/Limits" }
+            ],
+            "$defs": {
+                "Limits": {
+                    "properties": {
+                        "name": {
+                            "allOf": [{ "type": "string", "maxLength": 20, "examples": ["api"] }],
+                            "minLength": 1
+                        },
+                        "server": { "properties": { "port": {} } }
+                    },
+                    "required": ["server"]
+                }
+            }
+        });
+        let node = SchemaNode::new(&schema, &schema);
+        let name = node.navigate(&[PathSegment::Key("name".into())]).unwrap();
+        let info = name.hover_info();
+        assert_eq!(info.description.as_deref(), Some("Display name"));
+        assert_eq!(info.type_info.as_deref(), Some("string"));
+        assert_eq!(info.length.as_deref(), Some("3 to 20 characters"));
+        assert_eq!(info.examples, ["\"api\""]);
+
+        let server = node.navigate(&[PathSegment::Key("server".into())]).unwrap();
+        assert_eq!(server.property_names(), vec!["host", "port"]);
+        assert!(server.declared_properties().unwrap().covers("port"));
+
+        let mut required: Vec<&str> = node.required_names().into_iter().collect();
+        required.sort_unstable();
+        assert_eq!(required, ["name", "server"]);
+    }
+
+    #[test]
+    fn test_recursive_composition_is_bounded() {
+        // Every branch refers back to the root: property enumeration must terminate.
+        let schema = json!({
+            "properties": { "a": {} },
+            "allOf": [{ "$ref": "#" }, { "anyOf": [{ "$ref": "#" }, { "properties": { "b": {} } }] }]
+        });
+        let node = SchemaNode::new(&schema, &schema);
+        assert_eq!(node.property_names(), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_branch_limit_truncates() {
+        let branches: Vec<Value> = (0..10)
+            .map(|i| json!({ "properties": { format!("p{i}"): {} } }))
+            .collect();
+        let schema = json!({ "anyOf": branches });
+        let limits = SchemaLimits {
+            max_depth: 8,
+            max_branches: 3,
+            ..SchemaLimits::default()
+        };
+        let node = SchemaNode::new(&schema, &schema).with_limits(limits);
+        assert_eq!(node.property_names(), vec!["p0", "p1", "p2"]);
+        assert!(node.navigate(&[PathSegment::Key("p9".into())]).is_none());
+        assert!(node.navigate(&[PathSegment::Key("p1".into())]).is_some());
+    }
+
+    #[test]
+    fn test_property_limit_truncates_deterministically() {
+        let branches: Vec<Value> = (0..200)
+            .map(|i| json!({ "properties": { format!("b{i:03}"): {}, "shared": {} } }))
+            .collect();
+        let schema = json!({ "properties": { "z_own": {} }, "allOf": branches });
+        let limits = SchemaLimits {
+            max_properties: 4,
+            ..SchemaLimits::default()
+        };
+        let node = SchemaNode::new(&schema, &schema).with_limits(limits);
+        let names = node.property_names();
+        assert_eq!(names, vec!["b000", "b001", "shared", "z_own"]);
+        assert_eq!(node.property_names(), names);
+    }
+
+    #[test]
+    fn test_property_names_memoized_per_node() {
+        let schema = Arc::new(json!({
+            "properties": { "a": { "properties": { "x": {} } } },
+            "anyOf": [{ "properties": { "b": {} } }]
+        }));
+        let memo = PropertyMemo::new(schema.clone(), SchemaLimits::default());
+        let root = SchemaNode::new(&schema, &schema);
+        let first = root.property_names_memoized(&memo);
+        assert_eq!(*first, vec!["a", "b"]);
+        assert!(Arc::ptr_eq(&first, &root.property_names_memoized(&memo)));
+
+        let child = root.navigate(&[PathSegment::Key("a".into())]).unwrap();
+        assert_eq!(*child.property_names_memoized(&memo), vec!["x"]);
+        assert_eq!(memo.names.len(), 2);
+
+        // A different document is never served from this memo.
+        let other = json!({ "properties": { "c": {} } });
+        let other_node = SchemaNode::new(&other, &other);
+        assert_eq!(*other_node.property_names_memoized(&memo), vec!["c"]);
+        assert_eq!(memo.names.len(), 2);
+    }
 }
@@ -0,0 +1,491 @@
+//! `$ref` resolution. The navigator and the validator only follow pointers within one
+//! document, so a schema's references are resolved when it is loaded: every document its
+//! external references (`common.json#/$defs/name`, `https://example.com/other.json`) reach is
+//! fetched and embedded under the root's `$defs` keyed by its URL, and every reference is
+//! rewritten to a pointer into the root.
+//!
+//! References resolve against their base URI as JSON Schema defines it: the document's URL,
+//! re-based by each enclosing `$id`. A reference to a `$id` names that subschema, wherever
+//! it is embedded.
+
+use crate::schema::loader::{local_path, SchemaLoader};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use tower_lsp::lsp_types::Url;
+use tracing::{debug, warn};
+
+/// `workspace/executeCommand` taking `[url, { "output": path }?]`: returns
+/// `{ schema, unresolved }`, the schema with every external `$ref` inlined under `$defs` and
+/// the refs that could not be, or writes the schema to `output` and returns
+/// `{ path, unresolved }`.
+pub const BUNDLE_SCHEMA_COMMAND: &str = "json-ls.bundleSchema";
+
+/// Most external documents pulled into one schema.
+const MAX_EXTERNAL_DOCUMENTS: usize = 64;
+
+/// Keywords whose values are instance data rather than schemas; a `$ref` in them is not a
+/// reference.
+pub(crate) const DATA_KEYWORDS: [&str; 4] = ["const", "default", "enum", "examples"];
+
+/// Resource URL (a document's URL or a `$id`, without fragment) or anchor URL
+/// (`<resource>#<name>`) to the pointer of the subschema within the bundled root.
+type Resources = HashMap<String, String>;
+
+/// `schema`, loaded from `url`, with the documents its external references reach embedded
+/// under `$defs` and its references rewritten to pointers within it. References that cannot
+/// be resolved are left as they are.
+pub async fn inline_external_refs(
+    url: &str,
+    mut schema: Value,
+    loader: &dyn SchemaLoader,
+) -> Value {
+    if !schema.is_object() {
+        return schema;
+    }
+    let mut resources = Resources::from([(url.to_owned(), String::new())]);
+    let mut queue = Vec::new();
+    index_resources(&schema, url, "", &mut resources, &mut queue);
+
+    let mut documents = BTreeMap::new();
+    let mut failed = HashSet::new();
+    while let Some(target) = queue.pop() {
+        if resources.contains_key(&target) || failed.contains(&target) {
+            continue;
+        }
+        if documents.len() >= MAX_EXTERNAL_DOCUMENTS {
+            warn!("Schema {url} references more than {MAX_EXTERNAL_DOCUMENTS} documents");
+            break;
+        }
+        match loader.load(&target).await {
+            Ok(document) => {
+                debug!("Referenced schema loaded: {target}");
+                let prefix = defs_pointer(&target);
+                resources.insert(target.clone(), prefix.clone());
+                index_resources(&document, &target, &prefix, &mut resources, &mut queue);
+                documents.insert(target, document);
+            }
+            Err(e) => {
+                warn!("Could not load referenced schema {target}: {e}");
+                failed.insert(target);
+            }
+        }
+    }
+
+    rewrite_refs(&mut schema, url, &resources, false);
+    if documents.is_empty() {
+        return schema;
+    }
+    let mut embedded = Map::new();
+    for (document_url, mut document) in documents {
+        // Embedded, the document's own `$id` / `$schema` would re-base or re-interpret it.
+        rewrite_refs(&mut document, &document_url, &resources, true);
+        if let Some(object) = document.as_object_mut() {
+            object.remove("$schema");
+        }
+        embedded.insert(document_url, document);
+    }
+    let defs = schema
+        .as_object_mut()
+        .expect("checked above")
+        .entry("$defs")
+        .or_insert_with(|| Value::Object(Map::new()));
+    if let Some(defs) = defs.as_object_mut() {
+        defs.extend(embedded);
+    }
+    schema
+}
+
+/// The `$ref`s of a bundled schema still pointing outside it, sorted and deduplicated.
+pub fn unresolved_refs(schema: &Value) -> Vec<String> {
+    fn collect<'a>(schema: &'a Value, found: &mut BTreeSet<&'a str>) {
+        match schema {
+            Value::Object(map) => {
+                for keyword in ["$ref", "$dynamicRef"] {
+                    if let Some(reference) = map.get(keyword).and_then(Value::as_str) {
+                        if !reference.starts_with('#') {
+                            found.insert(reference);
+                        }
+                    }
+                }
+                map.iter()
+                    .filter(|(key, _)| !DATA_KEYWORDS.contains(&key.as_str()))
+                    .for_each(|(_, child)| collect(child, found));
+            }
+            Value::Array(items) => items.iter().for_each(|item| collect(item, found)),
+            _ => {}
+        }
+    }
+    let mut found = BTreeSet::new();
+    collect(schema, &mut found);
+    found.into_iter().map(str::to_owned).collect()
+}
+
+/// The subschema of `root` at a validation error's schema path (`/properties/a/items`).
+/// Follows local `$ref`s met on the way, in case the path runs through them.
+pub fn schema_at_path<'a>(root: &'a Value, path: &str) -> Option<&'a Value> {
+    root.pointer(&schema_pointer(root, path)?)
+}
+
+/// The JSON Pointer within `root` of the subschema at a validation error's schema path:
+/// `$ref` segments are replaced by the pointer they lead to.
+pub fn schema_pointer(root: &Value, path: &str) -> Option<String> {
+    let mut pointer = String::new();
+    for raw in path.split('/').skip(1) {
+        let node = root.pointer(&pointer)?;
+        if raw == "$ref" {
+            if let Some(target) = node.get("$ref").and_then(Value::as_str) {
+                pointer = target.strip_prefix('#')?.to_owned();
+                continue;
+            }
+        }
+        pointer.push('/');
+        pointer.push_str(raw);
+    }
+    root.pointer(&pointer).map(|_| pointer)
+}
+
+/// Absolute URL of `reference` relative to the document at `base`, without its fragment.
+/// Bare-path bases resolve against their directory.
+pub fn resolve_reference(base: &str, reference: &str) -> Option<String> {
+    if let Ok(mut absolute) = Url::parse(reference) {
+        absolute.set_fragment(None);
+        return Some(absolute.into());
+    }
+    if let Ok(base) = Url::parse(base) {
+        let mut absolute = base.join(reference).ok()?;
+        absolute.set_fragment(None);
+        return Some(absolute.into());
+    }
+    let reference = reference.split('#').next().unwrap_or_default();
+    if reference.is_empty() {
+        return Some(base.to_owned());
+    }
+    let directory = Path::new(local_path(base)?).parent()?;
+    let mut path = PathBuf::new();
+    for component in directory.join(reference).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                path.pop();
+            }
+            component => path.push(component),
+        }
+    }
+    Some(path.to_string_lossy().into_owned())
+}
+
+/// Pointer (without `#`) to the embedded copy of the document at `url`.
+fn defs_pointer(url: &str) -> String {
+    format!("/$defs/{}", escape_token(url))
+}
+
+/// The document a pointer into the bundle of `url` falls in, and the pointer within it:
+/// embedded documents live under `$defs`, keyed by their URL (or absolute path).
+pub fn bundle_source(url: &str, pointer: &str) -> (String, String) {
+    let embedded = pointer.strip_prefix("/$defs/").and_then(|rest| {
+        let split = rest.find('/').unwrap_or(rest.len());
+        let document = rest[..split].replace("~1", "/").replace("~0", "~");
+        (document.contains(':') || document.starts_with('/'))
+            .then(|| (document, rest[split..].to_owned()))
+    });
+    embedded.unwrap_or_else(|| (url.to_owned(), pointer.to_owned()))
+}
+
+fn escape_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// The base URI of `schema` given its parent's: re-based by its `$id`, if it has one.
+fn base_of(schema: &Map<String, Value>, parent_base: &str) -> String {
+    schema
+        .get("$id")
+        .and_then(Value::as_str)
+        .and_then(|id| resolve_reference(parent_base, id))
+        .unwrap_or_else(|| parent_base.to_owned())
+}
+
+/// Record the `$id` resources of the schema at `pointer` with base URI `base`, and queue the
+/// documents its `$ref`s point into.
+fn index_resources(
+    schema: &Value,
+    base: &str,
+    pointer: &str,
+    resources: &mut Resources,
+    targets: &mut Vec<String>,
+) {
+    match schema {
+        Value::Object(map) => {
+            let base = base_of(map, base);
+            resources
+                .entry(base.clone())
+                .or_insert_with(|| pointer.to_owned());
+            for name in anchors(map) {
+                resources
+                    .entry(format!("{base}#{name}"))
+                    .or_insert_with(|| pointer.to_owned());
+            }
+            if let Some(reference) = map.get("$ref").and_then(Value::as_str) {
+                let (target, _) = reference.split_once('#').unwrap_or((reference, ""));
+                if !target.is_empty() {
+                    targets.extend(resolve_reference(&base, target));
+                }
+            }
+            for (key, child) in map {
+                if !DATA_KEYWORDS.contains(&key.as_str()) {
+                    let pointer = format!("{pointer}/{}", escape_token(key));
+                    index_resources(child, &base, &pointer, resources, targets);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                index_resources(
+                    item,
+                    base,
+                    &format!("{pointer}/{index}"),
+                    resources,
+                    targets,
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Names `schema` can be referenced by within its resource: `$anchor`, `$dynamicAnchor`,
+/// and draft 6 / 7 `"$id": "#name"`.
+fn anchors(schema: &Map<String, Value>) -> impl Iterator<Item = &str> {
+    let id_anchor = schema
+        .get("$id")
+        .and_then(Value::as_str)
+        .and_then(|id| id.strip_prefix('#'));
+    ["$anchor", "$dynamicAnchor"]
+        .into_iter()
+        .filter_map(|keyword| schema.get(keyword).and_then(Value::as_str))
+        .chain(id_anchor)
+        .filter(|name| !name.is_empty())
+}
+
+/// Rewrite the `$ref`s of the document at `base` into pointers within the bundled root, and
+/// drop the `$id`s that would re-base the rewritten pointers: all but the root's, which
+/// `drop_id` keeps.
+fn rewrite_refs(schema: &mut Value, base: &str, resources: &Resources, drop_id: bool) {
+    match schema {
+        Value::Object(map) => {
+            let base = base_of(map, base);
+            if drop_id {
+                map.remove("$id");
+            }
+            if let Some(Value::String(reference)) = map.get_mut("$ref") {
+                if let Some(local) = local_reference(reference, &base, resources) {
+                    *reference = local;
+                }
+            }
+            for (key, child) in map.iter_mut() {
+                if !DATA_KEYWORDS.contains(&key.as_str()) {
+                    rewrite_refs(child, &base, resources, true);
+                }
+            }
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| rewrite_refs(item, base, resources, true)),
+        _ => {}
+    }
+}
+
+/// `reference` as a pointer within the bundled root, or `None` to leave it as is (it is one
+/// already, or its target is unknown).
+fn local_reference(reference: &str, base: &str, resources: &Resources) -> Option<String> {
+    let (target, fragment) = reference.split_once('#').unwrap_or((reference, ""));
+    let resource = resolve_reference(base, target)?;
+    let local = if fragment.is_empty() || fragment.starts_with('/') {
+        format!("#{}{fragment}", resources.get(&resource)?)
+    } else {
+        format!("#{}", resources.get(&format!("{resource}#{fragment}"))?)
+    };
+    (local != reference).then_some(local)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::PathSegment;
+    use crate::schema::{EmbeddedLoader, SchemaNode};
+    use serde_json::json;
+
+    const ROOT: &str = "mem://schemas/root.json";
+    const COMMON: &str = "mem://schemas/common.json";
+    const OTHER: &str = "https://example.com/other.json";
+
+    fn loader() -> EmbeddedLoader {
+        EmbeddedLoader::new()
+            .with_schema(
+                COMMON,
+                json!({
+                    "$id": COMMON,
+                    "definitions": {
+                        "name": { "type": "string", "description": "A name" },
+                        "person": {
+                            "properties": {
+                                "name": { "$ref": "#/definitions/name" },
+                                "friend": { "$ref": "root.json#/properties/owner" }
+                            }
+                        }
+                    }
+                }),
+            )
+            .with_schema(OTHER, json!({ "type": "integer", "minimum": 1 }))
+    }
+
+    #[tokio::test]
+    async fn test_external_refs_are_embedded_and_followed() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "owner": { "$ref": "./common.json#/definitions/person" },
+                "size": { "$ref": OTHER },
+                "local": { "$ref": "#/properties/size" },
+                "missing": { "$ref": "missing.json" }
+            },
+            "examples": [{ "$ref": "not-a-reference.json" }]
+        });
+        let bundled = inline_external_refs(ROOT, schema, &loader()).await;
+
+        let properties = &bundled["properties"];
+        assert_eq!(
+            properties["owner"]["$ref"],
+            "#/$defs/mem:~1~1schemas~1common.json/definitions/person"
+        );
+        assert_eq!(
+            properties["size"]["$ref"],
+            "#/$defs/https:~1~1example.com~1other.json"
+        );
+        assert_eq!(properties["local"]["$ref"], "#/properties/size");
+        assert_eq!(properties["missing"]["$ref"], "missing.json");
+        assert_eq!(unresolved_refs(&bundled), vec!["missing.json"]);
+        assert_eq!(bundled["examples"][0]["$ref"], "not-a-reference.json");
+        let common = &bundled["$defs"][COMMON];
+        assert!(common.get("$id").is_none());
+        assert_eq!(
+            common["definitions"]["person"]["properties"]["friend"]["$ref"],
+            "#/properties/owner"
+        );
+
+        let node = SchemaNode::new(&bundled, &bundled);
+        let path = [
+            PathSegment::Key("owner".into()),
+            PathSegment::Key("friend".into()),
+            PathSegment::Key("name".into()),
+        ];
+        let name = node.navigate(&path).unwrap();
+        assert_eq!(name.hover_info().description.as_deref(), Some("A name"));
+
+        let mut resolvable = bundled.clone();
+        resolvable["properties"]
+            .as_object_mut()
+            .unwrap()
+            .remove("missing");
+        let validator = jsonschema::validator_for(&resolvable).unwrap();
+        assert!(validator.is_valid(&json!({ "owner": { "name": "a" }, "size": 2 })));
+        assert!(!validator.is_valid(&json!({ "owner": { "friend": { "name": 1 } } })));
+        assert!(!validator.is_valid(&json!({ "size": 0 })));
+    }
+
+    #[tokio::test]
+    async fn test_refs_resolve_against_id_base_uris() {
+        let loader = EmbeddedLoader::new().with_schema(
+            "https://example.com/schemas/common.json",
+            json!({ "definitions": { "port": { "type": "integer", "maximum": 65535 } } }),
+        );
+        let schema = json!({
+            "$id": "https://example.com/schemas/root.json",
+            "properties": {
+                "port": { "$ref": "common.json#/definitions/port" },
+                "item": { "$ref": "item.json" },
+                "self": { "$ref": "root.json#/properties/port" }
+            },
+            "$defs": {
+                "item": {
+                    "$id": "item.json",
+                    "definitions": { "n": { "type": "integer" } },
+                    "properties": { "n": { "$ref": "#/definitions/n" } }
+                }
+            }
+        });
+        let bundled = inline_external_refs("mem://local/root.json", schema, &loader).await;
+
+        let properties = &bundled["properties"];
+        assert_eq!(
+            properties["port"]["$ref"],
+            "#/$defs/https:~1~1example.com~1schemas~1common.json/definitions/port"
+        );
+        assert_eq!(properties["item"]["$ref"], "#/$defs/item");
+        assert_eq!(properties["self"]["$ref"], "#/properties/port");
+        let item = &bundled["$defs"]["item"];
+        assert_eq!(
+            item["properties"]["n"]["$ref"],
+            "#/$defs/item/definitions/n"
+        );
+        assert!(item.get("$id").is_none());
+        assert_eq!(bundled["$id"], "https://example.com/schemas/root.json");
+
+        let validator = jsonschema::validator_for(&bundled).unwrap();
+        assert!(validator.is_valid(&json!({ "port": 80, "item": { "n": 1 } })));
+        assert!(!validator.is_valid(&json!({ "port": 70000 })));
+        assert!(!validator.is_valid(&json!({ "item": { "n": "x" } })));
+    }
+
+    #[tokio::test]
+    async fn test_anchor_refs_become_pointers() {
+        let loader = EmbeddedLoader::new().with_schema(
+            COMMON,
+            json!({ "$defs": { "x": { "$anchor": "name", "type": "string" } } }),
+        );
+        let schema = json!({
+            "properties": {
+                "a": { "$ref": "#address" },
+                "b": { "$ref": "common.json#name" },
+                "c": { "$ref": "#legacy" },
+                "d": { "$ref": "#unknown" },
+                "e": { "$dynamicRef": "#address" }
+            },
+            "definitions": {
+                "address": { "$anchor": "address", "type": "object" },
+                "legacy": { "$id": "#legacy", "type": "number" }
+            }
+        });
+        let bundled = inline_external_refs(ROOT, schema, &loader).await;
+
+        let properties = &bundled["properties"];
+        assert_eq!(properties["a"]["$ref"], "#/definitions/address");
+        assert_eq!(
+            properties["b"]["$ref"],
+            "#/$defs/mem:~1~1schemas~1common.json/$defs/x"
+        );
+        assert_eq!(properties["c"]["$ref"], "#/definitions/legacy");
+        assert_eq!(properties["d"]["$ref"], "#unknown");
+        assert_eq!(properties["e"]["$dynamicRef"], "#address");
+    }
+
+    #[test]
+    fn test_references_resolve_against_the_document() {
+        assert_eq!(
+            resolve_reference("https://example.com/a/b.json", "../c.json#/x").as_deref(),
+            Some("https://example.com/c.json")
+        );
+        assert_eq!(
+            resolve_reference("file:///schemas/a.json", "common.json").as_deref(),
+            Some("file:///schemas/common.json")
+        );
+        assert_eq!(
+            resolve_reference("/schemas/a.json", "./common.json#/x").as_deref(),
+            Some("/schemas/common.json")
+        );
+        assert_eq!(
+            resolve_reference("/schemas/a.json", "https://example.com/x.json").as_deref(),
+            Some("https://example.com/x.json")
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! Source locations of schema keywords in local (`file://` / bare-path) schema files, so a
+//! validation error can point at the keyword that produced it.
+
+use crate::offsets::byte_range_to_lsp_range;
+use crate::schema::loader::local_path;
+use crate::schema::refs::{bundle_source, schema_pointer};
+use crate::span::{parse_spans, SpanNode};
+use serde_json::Value;
+use std::collections::HashMap;
+use tower_lsp::lsp_types::{DiagnosticRelatedInformation, Location, Url};
+
+/// Longest keyword excerpt shown in the related information.
+const MAX_EXCERPT_CHARS: usize = 80;
+
+/// A local schema file, read once per validation run.
+struct SourceFile {
+    uri: Url,
+    text: String,
+    spans: SpanNode,
+}
+
+/// Keyword locations in the files behind one schema bundle; files are read on first use.
+pub struct SchemaSources<'a> {
+    url: &'a str,
+    bundle: &'a Value,
+    files: HashMap<String, Option<SourceFile>>,
+}
+
+impl<'a> SchemaSources<'a> {
+    /// Sources of `bundle`, the schema at `url` with its external documents embedded.
+    pub fn new(url: &'a str, bundle: &'a Value) -> Self {
+        Self {
+            url,
+            bundle,
+            files: HashMap::new(),
+        }
+    }
+
+    /// `schema.json:42 "minimum": 1` for the keyword at a validation error's schema path, or
+    /// `None` when the keyword comes from a remote schema.
+    pub fn related_information(
+        &mut self,
+        schema_path: &str,
+    ) -> Option<DiagnosticRelatedInformation> {
+        let pointer = schema_pointer(self.bundle, schema_path)?;
+        let (document, pointer) = bundle_source(self.url, &pointer);
+        let file = self
+            .files
+            .entry(document)
+            .or_insert_with_key(|document| read_source(document))
+            .as_ref()?;
+        let (parent, token) = pointer.rsplit_once('/')?;
+        let key = token.replace("~1", "/").replace("~0", "~");
+        let member = file.spans.find_pointer(parent)?.member(&key)?;
+        Some(DiagnosticRelatedInformation {
+            location: Location {
+                uri: file.uri.clone(),
+                range: byte_range_to_lsp_range(&file.text, member.key_start, member.key_end),
+            },
+            message: excerpt(&file.text[member.key_start..member.value.end]),
+        })
+    }
+}
+
+fn read_source(document: &str) -> Option<SourceFile> {
+    let path = std::fs::canonicalize(local_path(document)?).ok()?;
+    let text = std::fs::read_to_string(&path).ok()?;
+    Some(SourceFile {
+        uri: Url::from_file_path(&path).ok()?,
+        spans: parse_spans(&text)?,
+        text,
+    })
+}
+
+/// `text` on one line, whitespace runs collapsed, cut at [`MAX_EXCERPT_CHARS`].
+fn excerpt(text: &str) -> String {
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match line.char_indices().nth(MAX_EXCERPT_CHARS) {
+        Some((cut, _)) => format!("{}…", &line[..cut]),
+        None => line,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_keywords_located_in_root_and_embedded_files() {
+        let dir = std::env::temp_dir().join(format!("json-ls-sources-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let root_path = dir.join("root.json");
+        let common_path = dir.join("common.json");
+        std::fs::write(
+            &root_path,
+            "{\n  \"properties\": {\n    \"port\": {\n      \"minimum\": 1\n    }\n  }\n}\n",
+        )
+        .unwrap();
+        std::fs::write(&common_path, "{ \"enum\": [\n  \"a\",\n  \"b\"\n] }").unwrap();
+
+        let root_url = format!("file://{}", root_path.display());
+        let common_url = format!("file://{}", common_path.display());
+        let mut bundle = json!({ "properties": { "port": { "minimum": 1 } } });
+        bundle["$defs"] = json!({ common_url.clone(): { "enum": ["a", "b"] } });
+        let escaped = common_url.replace('/', "~1");
+
+        let mut sources = SchemaSources::new(&root_url, &bundle);
+        let minimum = sources
+            .related_information("/properties/port/minimum")
+            .unwrap();
+        let enumeration = sources
+            .related_information(&format!("/$defs/{escaped}/enum"))
+            .unwrap();
+        let missing = sources.related_information("/properties/port/maximum");
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(minimum.location.uri.path().ends_with("/root.json"));
+        assert_eq!(minimum.location.range.start.line, 3);
+        assert_eq!(minimum.message, "\"minimum\": 1");
+        assert!(enumeration.location.uri.path().ends_with("/common.json"));
+        assert_eq!(enumeration.message, "\"enum\": [ \"a\", \"b\" ]");
+        assert!(missing.is_none());
+    }
+
+    #[test]
+    fn test_remote_schemas_have_no_source() {
+        let bundle = json!({ "minimum": 1 });
+        let mut sources = SchemaSources::new("https://example.com/s.json", &bundle);
+        assert!(sources.related_information("/minimum").is_none());
+    }
+}
@@ -0,0 +1,470 @@
+use crate::position::PathSegment;
+
+/// A parsed JSON value together with the byte range it occupies in the source text.
+///
+/// Unlike `serde_json::Value`, this keeps the offsets needed to map JSON paths back to
+/// document ranges (code lenses, diagnostics, go-to-definition). The parser is tolerant:
+/// malformed input yields a best-effort tree rather than an error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpanNode {
+    pub start: usize,
+    pub end: usize,
+    pub kind: SpanKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpanKind {
+    Object(Vec<SpanMember>),
+    Array(Vec<SpanNode>),
+    String(String),
+    /// Number, `true`, `false`, `null` — or a missing value (empty range).
+    Literal,
+}
+
+/// A `"key": value` member of an object.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpanMember {
+    pub key: String,
+    /// Byte range of the key including its quotes (JSON5 keys may have none).
+    pub key_start: usize,
+    pub key_end: usize,
+    pub value: SpanNode,
+}
+
+impl SpanNode {
+    /// Return the member list if this node is an object.
+    pub fn members(&self) -> &[SpanMember] {
+        match &self.kind {
+            SpanKind::Object(members) => members,
+            _ => &[],
+        }
+    }
+
+    /// Return the first member named `key`.
+    pub fn member(&self, key: &str) -> Option<&SpanMember> {
+        self.members().iter().find(|m| m.key == key)
+    }
+
+    /// Walk `path` from this node.
+    pub fn find(&self, path: &[PathSegment]) -> Option<&SpanNode> {
+        let mut node = self;
+        for segment in path {
+            node = match (segment, &node.kind) {
+                (PathSegment::Key(key), SpanKind::Object(_)) => &node.member(key)?.value,
+                (PathSegment::Index(idx), SpanKind::Array(items)) => items.get(*idx)?,
+                _ => return None,
+            };
+        }
+        Some(node)
+    }
+
+    /// Walk a JSON Pointer (e.g. `/items/3/name`) from this node.
+    pub fn find_pointer(&self, pointer: &str) -> Option<&SpanNode> {
+        self.find(&pointer_to_path(pointer, self)?)
+    }
+}
+
+/// Replace the byte range `start..end` of a document with `new_text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ByteEdit {
+    pub start: usize,
+    pub end: usize,
+    pub new_text: String,
+}
+
+/// Escape a key for use as a JSON Pointer reference token (RFC 6901).
+pub fn escape_pointer_token(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Render `path` as a JSON Pointer (e.g. `/$defs/Name`).
+pub fn path_to_pointer(path: &[PathSegment]) -> String {
+    path.iter()
+        .map(|segment| match segment {
+            PathSegment::Key(key) => format!("/{}", escape_pointer_token(key)),
+            PathSegment::Index(idx) => format!("/{idx}"),
+        })
+        .collect()
+}
+
+/// Split a JSON Pointer into path segments, using `root` to decide whether a numeric
+/// token is an array index or an object key.
+pub fn pointer_to_path(pointer: &str, root: &SpanNode) -> Option<Vec<PathSegment>> {
+    let pointer = pointer.strip_prefix('#').unwrap_or(pointer);
+    if pointer.is_empty() {
+        return Some(vec![]);
+    }
+    let tokens = pointer.strip_prefix('/')?;
+
+    let mut path = Vec::new();
+    let mut node = Some(root);
+    for raw in tokens.split('/') {
+        let token = raw.replace("~1", "/").replace("~0", "~");
+        let segment = match (node.map(|n| &n.kind), token.parse::<usize>()) {
+            (Some(SpanKind::Array(_)), Ok(idx)) => PathSegment::Index(idx),
+            _ => PathSegment::Key(token),
+        };
+        node = node.and_then(|n| n.find(std::slice::from_ref(&segment)));
+        path.push(segment);
+    }
+    Some(path)
+}
+
+/// Visit every object in `node` with its path and members.
+pub fn walk_objects(
+    node: &SpanNode,
+    path: &mut Vec<PathSegment>,
+    visit: &mut dyn FnMut(&[PathSegment], &[SpanMember]),
+) {
+    match &node.kind {
+        SpanKind::Object(members) => {
+            visit(path, members);
+            for member in members {
+                path.push(PathSegment::Key(member.key.clone()));
+                walk_objects(&member.value, path, visit);
+                path.pop();
+            }
+        }
+        SpanKind::Array(items) => {
+            for (idx, item) in items.iter().enumerate() {
+                path.push(PathSegment::Index(idx));
+                walk_objects(item, path, visit);
+                path.pop();
+            }
+        }
+        SpanKind::String(_) | SpanKind::Literal => {}
+    }
+}
+
+/// Parse `text` into a span tree. Returns `None` for a blank document.
+pub fn parse_spans(text: &str) -> Option<SpanNode> {
+    let bytes = text.as_bytes();
+    let mut pos = 0;
+    skip_whitespace(bytes, &mut pos);
+    if pos >= bytes.len() {
+        return None;
+    }
+    Some(parse_value(bytes, &mut pos))
+}
+
+// ────────────────────────────────────────────────────────────
+// Recursive-descent parser
+// ────────────────────────────────────────────────────────────
+
+fn parse_value(bytes: &[u8], pos: &mut usize) -> SpanNode {
+    let start = *pos;
+    if *pos >= bytes.len() {
+        return SpanNode {
+            start,
+            end: start,
+            kind: SpanKind::Literal,
+        };
+    }
+
+    let kind = match bytes[*pos] {
+        b'{' => SpanKind::Object(parse_object(bytes, pos)),
+        b'[' => SpanKind::Array(parse_array(bytes, pos)),
+        b'"' | b'\'' => SpanKind::String(parse_string(bytes, pos)),
+        _ => {
+            skip_literal(bytes, pos);
+            SpanKind::Literal
+        }
+    };
+
+    SpanNode {
+        start,
+        end: *pos,
+        kind,
+    }
+}
+
+fn parse_object(bytes: &[u8], pos: &mut usize) -> Vec<SpanMember> {
+    // Consume '{'
+    *pos += 1;
+    let mut members = Vec::new();
+
+    loop {
+        skip_whitespace(bytes, pos);
+        if *pos >= bytes.len() {
+            break;
+        }
+
+        match bytes[*pos] {
+            b'}' => {
+                *pos += 1;
+                break;
+            }
+            b',' => {
+                *pos += 1;
+            }
+            ch if ch == b'"' || ch == b'\'' || is_identifier_byte(ch) => {
+                let key_start = *pos;
+                let key = if is_identifier_byte(ch) {
+                    // An unquoted JSON5 key.
+                    while *pos < bytes.len() && is_identifier_byte(bytes[*pos]) {
+                        *pos += 1;
+                    }
+                    String::from_utf8_lossy(&bytes[key_start..*pos]).into_owned()
+                } else {
+                    parse_string(bytes, pos)
+                };
+                let key_end = *pos;
+
+                skip_whitespace(bytes, pos);
+                if *pos < bytes.len() && bytes[*pos] == b':' {
+                    *pos += 1;
+                }
+                skip_whitespace(bytes, pos);
+
+                let value = if *pos < bytes.len() && matches!(bytes[*pos], b',' | b'}') {
+                    // Missing value — record an empty literal at the cursor
+                    SpanNode {
+                        start: *pos,
+                        end: *pos,
+                        kind: SpanKind::Literal,
+                    }
+                } else {
+                    parse_value(bytes, pos)
+                };
+
+                members.push(SpanMember {
+                    key,
+                    key_start,
+                    key_end,
+                    value,
+                });
+            }
+            _ => {
+                // Malformed — skip until something recognisable
+                *pos += 1;
+            }
+        }
+    }
+
+    members
+}
+
+fn parse_array(bytes: &[u8], pos: &mut usize) -> Vec<SpanNode> {
+    // Consume '['
+    *pos += 1;
+    let mut items = Vec::new();
+
+    loop {
+        skip_whitespace(bytes, pos);
+        if *pos >= bytes.len() {
+            break;
+        }
+
+        match bytes[*pos] {
+            b']' => {
+                *pos += 1;
+                break;
+            }
+            b',' => {
+                *pos += 1;
+            }
+            b'}' => {
+                // Unbalanced close — let the enclosing object handle it
+                break;
+            }
+            _ => items.push(parse_value(bytes, pos)),
+        }
+    }
+
+    items
+}
+
+/// Consume a JSON string (including quotes, double or single), returning its decoded
+/// content.
+fn parse_string(bytes: &[u8], pos: &mut usize) -> String {
+    let mut out: Vec<u8> = Vec::new();
+
+    // Skip the opening quote
+    let quote = bytes[*pos];
+    *pos += 1;
+
+    while *pos < bytes.len() {
+        let ch = bytes[*pos];
+        if ch == quote {
+            *pos += 1;
+            break;
+        }
+        if ch != b'\\' {
+            out.push(ch);
+            *pos += 1;
+            continue;
+        }
+
+        *pos += 1; // skip backslash
+        let Some(&esc) = bytes.get(*pos) else {
+            break;
+        };
+        *pos += 1;
+        let decoded = match esc {
+            b'n' => '\n',
+            b'r' => '\r',
+            b't' => '\t',
+            b'b' => '\u{8}',
+            b'f' => '\u{c}',
+            b'u' => decode_unicode_escape(bytes, pos),
+            other => other as char,
+        };
+        let mut buf = [0u8; 4];
+        out.extend_from_slice(decoded.encode_utf8(&mut buf).as_bytes());
+    }
+
+    // Only ASCII bytes are ever split off, so the remainder is still valid UTF-8.
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Decode the `XXXX` of a `\uXXXX` escape (already past the `u`), combining surrogate pairs.
+pub(crate) fn decode_unicode_escape(bytes: &[u8], pos: &mut usize) -> char {
+    try_decode_unicode_escape(bytes, pos).unwrap_or(char::REPLACEMENT_CHARACTER)
+}
+
+/// [`decode_unicode_escape`], or `None` for malformed hex digits or a lone surrogate.
+pub(crate) fn try_decode_unicode_escape(bytes: &[u8], pos: &mut usize) -> Option<char> {
+    let high = read_hex4(bytes, *pos)?;
+    *pos += 4;
+
+    if (0xD800..0xDC00).contains(&high) {
+        // Expect a low surrogate as `\uXXXX` immediately after
+        if bytes.get(*pos) == Some(&b'\\') && bytes.get(*pos + 1) == Some(&b'u') {
+            if let Some(low) = read_hex4(bytes, *pos + 2) {
+                if (0xDC00..0xE000).contains(&low) {
+                    *pos += 6;
+                    let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                    return char::from_u32(code);
+                }
+            }
+        }
+        return None;
+    }
+
+    char::from_u32(high)
+}
+
+fn read_hex4(bytes: &[u8], at: usize) -> Option<u32> {
+    let digits = bytes.get(at..at + 4)?;
+    let s = std::str::from_utf8(digits).ok()?;
+    u32::from_str_radix(s, 16).ok()
+}
+
+/// A byte of an unquoted JSON5 property name: ASCII letters, digits, `_`, `$`, or part of
+/// a non-ASCII character.
+pub(crate) fn is_identifier_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'$') || !byte.is_ascii()
+}
+
+/// Whether `word` can be a JSON5 property name without quotes.
+pub(crate) fn is_identifier(word: &str) -> bool {
+    !word.is_empty()
+        && !word.starts_with(|ch: char| ch.is_ascii_digit())
+        && word.bytes().all(is_identifier_byte)
+}
+
+/// Skip whitespace and `//` / `/* */` comments.
+pub(crate) fn skip_whitespace(bytes: &[u8], pos: &mut usize) {
+    loop {
+        match bytes.get(*pos..) {
+            Some([b' ' | b'\t' | b'\r' | b'\n', ..]) => *pos += 1,
+            Some([b'/', b'/', ..]) => {
+                *pos = bytes[*pos..]
+                    .iter()
+                    .position(|&b| b == b'\n')
+                    .map_or(bytes.len(), |idx| *pos + idx);
+            }
+            Some([b'/', b'*', ..]) => {
+                *pos = bytes[*pos + 2..]
+                    .windows(2)
+                    .position(|w| w == b"*/")
+                    .map_or(bytes.len(), |idx| *pos + 2 + idx + 2);
+            }
+            _ => return,
+        }
+    }
+}
+
+fn skip_literal(bytes: &[u8], pos: &mut usize) {
+    let start = *pos;
+    while *pos < bytes.len()
+        && !matches!(
+            bytes[*pos],
+            b',' | b'}' | b']' | b' ' | b'\t' | b'\r' | b'\n'
+        )
+    {
+        *pos += 1;
+    }
+    // Always make progress, even on a stray delimiter
+    if *pos == start {
+        *pos += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = r#"{
+  "name": "hello",
+  "tags": ["a", "b"],
+  "nested": { "inner": true }
+}"#;
+
+    #[test]
+    fn test_member_spans() {
+        let root = parse_spans(DOC).unwrap();
+        let name = root.member("name").unwrap();
+        assert_eq!(&DOC[name.key_start..name.key_end], "\"name\"");
+        assert_eq!(&DOC[name.value.start..name.value.end], "\"hello\"");
+    }
+
+    #[test]
+    fn test_find_nested_path() {
+        let root = parse_spans(DOC).unwrap();
+        let node = root
+            .find(&[
+                PathSegment::Key("nested".into()),
+                PathSegment::Key("inner".into()),
+            ])
+            .unwrap();
+        assert_eq!(&DOC[node.start..node.end], "true");
+    }
+
+    #[test]
+    fn test_find_pointer_array_index() {
+        let root = parse_spans(DOC).unwrap();
+        let node = root.find_pointer("/tags/1").unwrap();
+        assert_eq!(&DOC[node.start..node.end], "\"b\"");
+    }
+
+    #[test]
+    fn test_unicode_escape_decoding() {
+        let text = r#"{"na\u006de": 1, "\ud83d\ude00": 2}"#;
+        let root = parse_spans(text).unwrap();
+        assert!(root.member("name").is_some());
+        assert!(root.member("😀").is_some());
+    }
+
+    #[test]
+    fn test_truncated_document() {
+        let text = r#"{"a": {"b": [1, 2"#;
+        let root = parse_spans(text).unwrap();
+        assert!(root.find_pointer("/a/b/1").is_some());
+    }
+
+    #[test]
+    fn test_json5_keys_and_comments() {
+        let text = "{ // note\n  name: 'a', 'b': [/* none */], \"c\": 1 }";
+        let root = parse_spans(text).unwrap();
+        let name = root.member("name").unwrap();
+        assert_eq!(&text[name.key_start..name.key_end], "name");
+        assert_eq!(&text[name.value.start..name.value.end], "'a'");
+        assert_eq!(name.value.kind, SpanKind::String("a".into()));
+        assert_eq!(
+            root.find_pointer("/b").unwrap().kind,
+            SpanKind::Array(vec![])
+        );
+        assert!(root.member("c").is_some());
+    }
+}
@@ -0,0 +1,173 @@
+//! "Did you mean" suggestions for validation errors: the closest allowed value for a failed
+//! `enum` / `const`, and the closest declared property for one rejected by
+//! `additionalProperties: false`. Attached to the diagnostic's `data` for the quick fix.
+
+use crate::fuzzy::closest_match;
+use crate::schema::refs::schema_at_path;
+use crate::span::escape_pointer_token;
+use jsonschema::error::ValidationErrorKind;
+use jsonschema::ValidationError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Replace the key or the value at `instance_path` with `replacement`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Suggestion {
+    pub instance_path: String,
+    pub target: SuggestionTarget,
+    /// A property name for a key, else the JSON value.
+    pub replacement: Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SuggestionTarget {
+    Key,
+    Value,
+}
+
+/// Suggestions for `error`; `schema` is the root schema it was validated against.
+pub fn suggestions(error: &ValidationError, schema: &Value) -> Vec<Suggestion> {
+    let instance_path = error.instance_path().to_string();
+    let value_suggestion = |allowed: &[Value]| {
+        let Value::String(actual) = error.instance().as_ref() else {
+            return Vec::new();
+        };
+        let strings = allowed.iter().filter_map(Value::as_str);
+        closest_match(actual, strings)
+            .map(|closest| Suggestion {
+                instance_path: instance_path.clone(),
+                target: SuggestionTarget::Value,
+                replacement: closest.into(),
+            })
+            .into_iter()
+            .collect()
+    };
+    match error.kind() {
+        ValidationErrorKind::Enum { options } => {
+            value_suggestion(options.as_array().map_or(&[], Vec::as_slice))
+        }
+        ValidationErrorKind::Constant { expected_value } => {
+            value_suggestion(std::slice::from_ref(expected_value))
+        }
+        ValidationErrorKind::AdditionalProperties { unexpected } => {
+            let schema_path = error.schema_path().to_string();
+            let Some(properties) = schema_path
+                .strip_suffix("/additionalProperties")
+                .and_then(|parent| schema_at_path(schema, parent))
+                .and_then(|parent| parent.get("properties"))
+                .and_then(Value::as_object)
+            else {
+                return Vec::new();
+            };
+            let present = error.instance().as_object();
+            // Declared properties the object does not have yet.
+            let missing: Vec<&str> = properties
+                .keys()
+                .map(String::as_str)
+                .filter(|name| present.is_none_or(|object| !object.contains_key(*name)))
+                .collect();
+            unexpected
+                .iter()
+                .filter_map(|name| {
+                    let closest = closest_match(name, missing.iter().copied())?;
+                    Some(Suggestion {
+                        instance_path: format!("{instance_path}/{}", escape_pointer_token(name)),
+                        target: SuggestionTarget::Key,
+                        replacement: closest.into(),
+                    })
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// ` — did you mean …?` for a diagnostic message, or an empty string.
+pub fn did_you_mean(suggestions: &[Suggestion]) -> String {
+    let render = |suggestion: &Suggestion| match &suggestion.replacement {
+        Value::String(name) if suggestion.target == SuggestionTarget::Key => format!("`{name}`"),
+        replacement => format!("`{replacement}`"),
+    };
+    match suggestions {
+        [] => String::new(),
+        [single] => format!(" — did you mean {}?", render(single)),
+        several => {
+            let each: Vec<String> = several
+                .iter()
+                .map(|suggestion| {
+                    let name = suggestion.instance_path.rsplit('/').next().unwrap_or("");
+                    format!("{} for `{name}`", render(suggestion))
+                })
+                .collect();
+            format!(" — did you mean {}?", each.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn suggest(schema: &Value, instance: &Value) -> Vec<Suggestion> {
+        let validator = jsonschema::validator_for(schema).unwrap();
+        validator
+            .iter_errors(instance)
+            .flat_map(|error| suggestions(&error, schema))
+            .collect()
+    }
+
+    #[test]
+    fn test_enum_and_const_suggestions() {
+        let schema = json!({
+            "properties": {
+                "level": { "enum": ["debug", "info", "warn", 3] },
+                "kind": { "const": "service" }
+            }
+        });
+        let found = suggest(&schema, &json!({ "level": "wran", "kind": "servise" }));
+        assert_eq!(
+            found,
+            vec![
+                Suggestion {
+                    instance_path: "/kind".into(),
+                    target: SuggestionTarget::Value,
+                    replacement: json!("service"),
+                },
+                Suggestion {
+                    instance_path: "/level".into(),
+                    target: SuggestionTarget::Value,
+                    replacement: json!("warn"),
+                },
+            ]
+        );
+        assert_eq!(did_you_mean(&found[1..]), " — did you mean `\"warn\"`?");
+        assert!(suggest(&schema, &json!({ "level": "verbose" })).is_empty());
+    }
+
+    #[test]
+    fn test_unknown_property_suggestions() {
+        let schema = json!({
+            "properties": { "server": { "$ref": "#/$defs/Server" } },
+            "$defs": {
+                "Server": {
+                    "properties": { "host": {}, "port": {}, "timeout": {} },
+                    "additionalProperties": false
+                }
+            }
+        });
+        let instance = json!({ "server": { "host": "a", "hots": "b", "prot": 1, "zzz": 0 } });
+        let found = suggest(&schema, &instance);
+        assert_eq!(
+            found
+                .iter()
+                .map(|s| (s.instance_path.as_str(), s.replacement.as_str().unwrap()))
+                .collect::<Vec<_>>(),
+            // `host` is already there, so `hots` gets no suggestion.
+            vec![("/server/prot", "port")]
+        );
+        assert_eq!(did_you_mean(&found), " — did you mean `port`?");
+    }
+}
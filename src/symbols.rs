@@ -0,0 +1,179 @@
+use crate::authoring::is_meta_schema_url;
+use crate::document::DocumentStore;
+use crate::offsets::byte_range_to_lsp_range;
+use crate::span::{parse_spans, SpanKind, SpanMember, SpanNode};
+use std::sync::Arc;
+use tower_lsp::lsp_types::{
+    DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse, SymbolKind,
+};
+use tracing::debug;
+
+/// Outline of a schema document by its logical structure: `properties` (flagged when
+/// `required`) and `$defs` / `definitions`, nested through subschemas and `items`.
+pub fn handle_document_symbol(
+    documents: &Arc<DocumentStore>,
+    params: DocumentSymbolParams,
+) -> Option<DocumentSymbolResponse> {
+    let uri = &params.text_document.uri;
+    let text = documents.get_text(uri)?;
+    if !is_meta_schema_url(&documents.get_schema_url(uri)?) {
+        return None;
+    }
+
+    let spans = parse_spans(&text)?;
+    let symbols = schema_symbols(&text, &spans);
+    debug!("Document symbols: {} top-level in {uri}", symbols.len());
+
+    Some(DocumentSymbolResponse::Nested(symbols))
+}
+
+/// Symbols for the properties and definitions declared directly by `schema`.
+fn schema_symbols(text: &str, schema: &SpanNode) -> Vec<DocumentSymbol> {
+    let required = required_names(schema);
+    let mut symbols = Vec::new();
+
+    if let Some(properties) = schema.member("properties") {
+        for member in properties.value.members() {
+            let is_required = required.contains(&member.key.as_str());
+            symbols.push(symbol(
+                text,
+                member,
+                SymbolKind::PROPERTY,
+                property_detail(&member.value, is_required),
+            ));
+        }
+    }
+
+    for container in ["$defs", "definitions"] {
+        if let Some(defs) = schema.member(container) {
+            for member in defs.value.members() {
+                symbols.push(symbol(
+                    text,
+                    member,
+                    SymbolKind::STRUCT,
+                    type_label(&member.value),
+                ));
+            }
+        }
+    }
+
+    // Array items are transparent: their properties belong to the enclosing symbol.
+    if let Some(items) = schema.member("items") {
+        symbols.extend(schema_symbols(text, &items.value));
+    }
+
+    symbols
+}
+
+fn symbol(
+    text: &str,
+    member: &SpanMember,
+    kind: SymbolKind,
+    detail: Option<String>,
+) -> DocumentSymbol {
+    let children = schema_symbols(text, &member.value);
+    #[allow(deprecated)]
+    DocumentSymbol {
+        name: member.key.clone(),
+        detail,
+        kind,
+        tags: None,
+        deprecated: None,
+        range: byte_range_to_lsp_range(text, member.key_start, member.value.end),
+        selection_range: byte_range_to_lsp_range(text, member.key_start, member.key_end),
+        children: (!children.is_empty()).then_some(children),
+    }
+}
+
+/// Names listed in the schema's `required` array.
+fn required_names(schema: &SpanNode) -> Vec<&str> {
+    let Some(required) = schema.member("required") else {
+        return vec![];
+    };
+    match &required.value.kind {
+        SpanKind::Array(items) => items
+            .iter()
+            .filter_map(|item| match &item.kind {
+                SpanKind::String(name) => Some(name.as_str()),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// e.g. `string · required`.
+fn property_detail(schema: &SpanNode, required: bool) -> Option<String> {
+    match (type_label(schema), required) {
+        (Some(ty), true) => Some(format!("{ty} · required")),
+        (None, true) => Some("required".into()),
+        (ty, false) => ty,
+    }
+}
+
+/// The schema's `type` (`"a" | "b"` for a type array), or its `$ref` target.
+fn type_label(schema: &SpanNode) -> Option<String> {
+    if let Some(ty) = schema.member("type") {
+        return match &ty.value.kind {
+            SpanKind::String(ty) => Some(ty.clone()),
+            SpanKind::Array(items) => {
+                let names: Vec<&str> = items
+                    .iter()
+                    .filter_map(|item| match &item.kind {
+                        SpanKind::String(name) => Some(name.as_str()),
+                        _ => None,
+                    })
+                    .collect();
+                (!names.is_empty()).then(|| names.join(" | "))
+            }
+            _ => None,
+        };
+    }
+    match &schema.member("$ref")?.value.kind {
+        SpanKind::String(reference) => Some(reference.clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_symbols_flag_required() {
+        let text = r##"{
+  "type": "object",
+  "required": ["name"],
+  "properties": {
+    "name": { "type": "string" },
+    "tags": {
+      "type": "array",
+      "items": { "properties": { "label": { "type": "string" } }, "required": ["label"] }
+    },
+    "owner": { "$ref": "#/$defs/Person" }
+  },
+  "$defs": {
+    "Person": { "type": "object", "properties": { "email": {} } }
+  }
+}"##;
+        let spans = parse_spans(text).unwrap();
+        let symbols = schema_symbols(text, &spans);
+
+        let names: Vec<_> = symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["name", "tags", "owner", "Person"]);
+
+        assert_eq!(symbols[0].detail.as_deref(), Some("string · required"));
+        assert_eq!(symbols[0].kind, SymbolKind::PROPERTY);
+        assert_eq!(symbols[1].detail.as_deref(), Some("array"));
+        assert_eq!(symbols[2].detail.as_deref(), Some("#/$defs/Person"));
+        assert_eq!(symbols[3].kind, SymbolKind::STRUCT);
+
+        let label = &symbols[1].children.as_ref().unwrap()[0];
+        assert_eq!(label.name, "label");
+        assert_eq!(label.detail.as_deref(), Some("string · required"));
+
+        let email = &symbols[3].children.as_ref().unwrap()[0];
+        assert_eq!(email.name, "email");
+        assert_eq!(email.detail, None);
+    }
+}
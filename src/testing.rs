@@ -0,0 +1,282 @@
+//! A JSON-RPC test client for integration tests of json-ls and the editor plugins built on
+//! it. It drives either the `json-ls` binary over stdio ([`LspClient::spawn`]) or a
+//! [`Backend`] running in-process ([`LspClient::in_process`]), which needs no build step and
+//! starts in microseconds.
+//!
+//! ```no_run
+//! # async fn example() {
+//! use json_ls::testing::LspClient;
+//!
+//! let client = LspClient::in_process();
+//! client.initialize().await;
+//! client.open_document("file:///tmp/a.json", None, r#""name": 1"#).await;
+//! let published = client.wait_for_notification("textDocument/publishDiagnostics").await;
+//! client.shutdown().await;
+//! # }
+//! ```
+
+use crate::backend::Backend;
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::{oneshot, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+use tower_lsp::Server;
+
+/// How long [`LspClient::send_request`] waits for the response.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long [`LspClient::wait_for_notification`] waits: the default 300 ms diagnostics debounce plus
+/// schema fetches and headroom.
+pub const NOTIFICATION_TIMEOUT: Duration = Duration::from_secs(6);
+
+type PendingResponses = Arc<Mutex<HashMap<i64, oneshot::Sender<Value>>>>;
+
+/// The server end of an [`LspClient`]; dropping it stops the server.
+enum Peer {
+    Process { _child: Child },
+    InProcess(JoinHandle<()>),
+}
+
+pub struct LspClient {
+    writer: Mutex<Box<dyn AsyncWrite + Send + Unpin>>,
+    next_id: AtomicI64,
+    pending: PendingResponses,
+    notifications: Arc<Mutex<VecDeque<Value>>>,
+    peer: Peer,
+}
+
+impl LspClient {
+    /// Start the `json-ls` binary at `binary` and talk to it over its stdin / stdout.
+    pub fn spawn(binary: impl AsRef<Path>) -> Self {
+        let binary = binary.as_ref();
+        let mut child = Command::new(binary)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap_or_else(|e| panic!("Failed to spawn {}: {e}", binary.display()));
+        let stdin = child.stdin.take().expect("piped stdin");
+        let stdout = child.stdout.take().expect("piped stdout");
+        Self::connect(stdout, Box::new(stdin), Peer::Process { _child: child })
+    }
+
+    /// Run a [`Backend`] on the current Tokio runtime, connected through an in-memory pipe.
+    pub fn in_process() -> Self {
+        let (client_io, server_io) = tokio::io::duplex(1 << 16);
+        let (server_read, server_write) = tokio::io::split(server_io);
+        let (service, socket) = Backend::service(None);
+        let server = tokio::spawn(async move {
+            Server::new(server_read, server_write, socket)
+                .serve(service)
+                .await;
+        });
+        let (client_read, client_write) = tokio::io::split(client_io);
+        Self::connect(client_read, Box::new(client_write), Peer::InProcess(server))
+    }
+
+    fn connect(
+        reader: impl AsyncRead + Send + Unpin + 'static,
+        writer: Box<dyn AsyncWrite + Send + Unpin>,
+        peer: Peer,
+    ) -> Self {
+        let pending = PendingResponses::default();
+        let notifications = Arc::new(Mutex::new(VecDeque::new()));
+        tokio::spawn(read_messages(
+            BufReader::new(reader),
+            pending.clone(),
+            notifications.clone(),
+        ));
+        Self {
+            writer: Mutex::new(writer),
+            next_id: AtomicI64::new(1),
+            pending,
+            notifications,
+            peer,
+        }
+    }
+
+    /// Send a request and wait for its response message (`result` or `error`).
+    pub async fn send_request(&self, method: &str, params: Option<Value>) -> Value {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let mut msg = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+        });
+        if let Some(p) = params {
+            msg["params"] = p;
+        }
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().await.insert(id, tx);
+        self.write_message(&msg).await;
+        timeout(REQUEST_TIMEOUT, rx)
+            .await
+            .unwrap_or_else(|_| panic!("Request timed out: {method}"))
+            .expect("Response channel dropped")
+    }
+
+    pub async fn send_notification(&self, method: &str, params: Option<Value>) {
+        let mut msg = json!({
+            "jsonrpc": "2.0",
+            "method": method,
+        });
+        if let Some(p) = params {
+            msg["params"] = p;
+        }
+        self.write_message(&msg).await;
+    }
+
+    async fn write_message(&self, msg: &Value) {
+        let body = serde_json::to_string(msg).expect("JSON value serializes");
+        let frame = format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+        let mut writer = self.writer.lock().await;
+        writer
+            .write_all(frame.as_bytes())
+            .await
+            .expect("server closed its input");
+        writer.flush().await.expect("server closed its input");
+    }
+
+    /// Take the oldest unseen notification (or server request) with `method`, waiting up to
+    /// [`NOTIFICATION_TIMEOUT`] for one to arrive. Panics on timeout.
+    pub async fn wait_for_notification(&self, method: &str) -> Value {
+        let deadline = tokio::time::Instant::now() + NOTIFICATION_TIMEOUT;
+        loop {
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "Timeout waiting for notification: {method}"
+            );
+            let found = {
+                let mut queue = self.notifications.lock().await;
+                let pos = queue
+                    .iter()
+                    .position(|n| n["method"].as_str() == Some(method));
+                pos.and_then(|i| queue.remove(i))
+            };
+            if let Some(notif) = found {
+                return notif;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    /// `initialize` + `initialized` with a small schema cache and no catalogs.
+    pub async fn initialize(&self) -> Value {
+        self.initialize_with(json!({
+            "schema_ttl_secs": 60,
+            "schema_cache_capacity": 16
+        }))
+        .await
+    }
+
+    /// `initialize` with `options` as `initializationOptions`, then `initialized`. Returns
+    /// the `initialize` response. Unless `options` sets `catalog`, no catalog sources are
+    /// configured, so the server never reaches schemastore.org from a test.
+    pub async fn initialize_with(&self, mut options: Value) -> Value {
+        if let Value::Object(map) = &mut options {
+            map.entry("catalog")
+                .or_insert_with(|| json!({ "sources": [] }));
+        }
+        let resp = self
+            .send_request(
+                "initialize",
+                Some(json!({
+                    "processId": null,
+                    "rootUri": null,
+                    "capabilities": {},
+                    "initializationOptions": options
+                })),
+            )
+            .await;
+        self.send_notification("initialized", Some(json!({}))).await;
+        resp
+    }
+
+    /// Open a document. `schema_url` is injected as the `$schema` value.
+    /// Use `None` to omit the `$schema` key entirely.
+    pub async fn open_document(&self, uri: &str, schema_url: Option<&str>, body_fields: &str) {
+        let text = match schema_url {
+            Some(url) => format!("{{\n  \"$schema\": \"{url}\",\n  {body_fields}\n}}"),
+            None => format!("{{\n  {body_fields}\n}}"),
+        };
+        self.send_notification(
+            "textDocument/didOpen",
+            Some(json!({
+                "textDocument": {
+                    "uri": uri,
+                    "languageId": "json",
+                    "version": 1,
+                    "text": text,
+                }
+            })),
+        )
+        .await;
+    }
+
+    pub async fn shutdown(&self) {
+        self.send_request("shutdown", None).await;
+        self.send_notification("exit", None).await;
+    }
+}
+
+impl Drop for LspClient {
+    fn drop(&mut self) {
+        if let Peer::InProcess(server) = &self.peer {
+            server.abort();
+        }
+    }
+}
+
+/// Route each framed message: responses to their waiting request, everything else (notifications
+/// and server-initiated requests) to the notification queue.
+async fn read_messages(
+    mut reader: BufReader<impl AsyncRead + Unpin>,
+    pending: PendingResponses,
+    notifications: Arc<Mutex<VecDeque<Value>>>,
+) {
+    loop {
+        let mut content_length: Option<usize> = None;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).await.unwrap_or(0) == 0 {
+                return; // EOF
+            }
+            let line = line.trim();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(val) = line.strip_prefix("Content-Length: ") {
+                content_length = val.trim().parse().ok();
+            }
+        }
+        let Some(len) = content_length else {
+            continue;
+        };
+        let mut buf = vec![0u8; len];
+        if reader.read_exact(&mut buf).await.is_err() {
+            return;
+        }
+        let Ok(msg) = serde_json::from_slice::<Value>(&buf) else {
+            continue;
+        };
+
+        if msg.get("method").is_none() {
+            if let Some(id) = msg.get("id").and_then(Value::as_i64) {
+                if let Some(tx) = pending.lock().await.remove(&id) {
+                    let _ = tx.send(msg);
+                }
+                continue;
+            }
+        }
+        notifications.lock().await.push_back(msg);
+    }
+}
@@ -0,0 +1,394 @@
+//! Wall-clock budget for schema validation, and a probe that names the `pattern` keyword
+//! responsible when a validation runs out of time.
+//!
+//! Validation runs on a blocking thread; when the budget expires the diagnostics task stops
+//! waiting for it (the thread finishes in the background — regex evaluation cannot be
+//! interrupted). At most `MAX_ABANDONED_RUNS` such threads are left running at once; while
+//! that many are, new work is skipped without starting.
+
+#[cfg(feature = "native")]
+use crate::authoring::refs::{holds_name_map, is_data_member};
+#[cfg(feature = "native")]
+use crate::span::escape_pointer_token;
+use serde_json::Value;
+use std::time::Duration;
+#[cfg(feature = "native")]
+use tracing::debug;
+
+/// Upper bound on (pattern, string) pairs tried by [`find_slow_pattern`].
+#[cfg(feature = "native")]
+const MAX_PROBES: usize = 10_000;
+
+/// Timed-out runs that may still be going before new work is refused, so that edits to a
+/// document whose validation never finishes cannot pile up blocking threads.
+#[cfg(feature = "native")]
+const MAX_ABANDONED_RUNS: usize = 4;
+
+#[cfg(feature = "native")]
+static ABANDONED_RUNS: AbandonedRuns = AbandonedRuns::new(MAX_ABANDONED_RUNS);
+
+/// Count of timed-out runs whose blocking thread has not finished yet.
+#[cfg(feature = "native")]
+struct AbandonedRuns {
+    running: std::sync::atomic::AtomicUsize,
+    limit: usize,
+}
+
+#[cfg(feature = "native")]
+impl AbandonedRuns {
+    const fn new(limit: usize) -> Self {
+        Self {
+            running: std::sync::atomic::AtomicUsize::new(0),
+            limit,
+        }
+    }
+
+    fn full(&self) -> bool {
+        self.running.load(std::sync::atomic::Ordering::Acquire) >= self.limit
+    }
+}
+
+/// Why a time-boxed run has no result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unfinished {
+    /// The budget ran out; the run finishes in the background.
+    TimedOut,
+    /// Not started, while `MAX_ABANDONED_RUNS` earlier runs are still going.
+    Skipped,
+}
+
+/// Run `work` on a blocking thread and wait at most `budget` for it.
+#[cfg(feature = "native")]
+pub async fn time_boxed<T, F>(budget: Duration, work: F) -> Result<T, Unfinished>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    time_boxed_in(&ABANDONED_RUNS, budget, work).await
+}
+
+#[cfg(feature = "native")]
+async fn time_boxed_in<T, F>(
+    runs: &'static AbandonedRuns,
+    budget: Duration,
+    work: F,
+) -> Result<T, Unfinished>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    use std::sync::atomic::{AtomicU8, Ordering};
+    use std::sync::Arc;
+
+    const RUNNING: u8 = 0;
+    const FINISHED: u8 = 1;
+    const ABANDONED: u8 = 2;
+
+    /// Marks the run finished even if `work` panics; an abandoned run frees its slot.
+    struct Finish(Arc<AtomicU8>, &'static AbandonedRuns);
+    impl Drop for Finish {
+        fn drop(&mut self) {
+            if self.0.swap(FINISHED, Ordering::AcqRel) == ABANDONED {
+                self.1.running.fetch_sub(1, Ordering::AcqRel);
+            }
+        }
+    }
+
+    if runs.full() {
+        debug!(
+            "Not starting a time-boxed run: {} earlier runs are still going",
+            runs.limit
+        );
+        return Err(Unfinished::Skipped);
+    }
+    let state = Arc::new(AtomicU8::new(RUNNING));
+    let finish = Finish(state.clone(), runs);
+    let task = tokio::task::spawn_blocking(move || {
+        let _finish = finish;
+        work()
+    });
+    match tokio::time::timeout(budget, task).await {
+        Ok(Ok(result)) => Ok(result),
+        Ok(Err(e)) => std::panic::resume_unwind(e.into_panic()),
+        Err(_) => {
+            if state
+                .compare_exchange(RUNNING, ABANDONED, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            {
+                runs.running.fetch_add(1, Ordering::AcqRel);
+            }
+            Err(Unfinished::TimedOut)
+        }
+    }
+}
+
+/// Without a blocking thread pool the work runs inline and the budget is not enforced.
+#[cfg(not(feature = "native"))]
+pub async fn time_boxed<T, F>(_budget: Duration, work: F) -> Result<T, Unfinished>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    Ok(work())
+}
+
+/// A `pattern` (or `patternProperties` key) that was slow to match a string in the instance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlowPattern {
+    /// JSON Pointer of the keyword in the schema, e.g. `/properties/name/pattern`.
+    pub schema_pointer: String,
+    /// JSON Pointer of the string in the instance (for a property name: its member).
+    pub instance_pointer: String,
+}
+
+/// Time every schema pattern against the instance's strings (values for `pattern`, property
+/// names for `patternProperties`) and return the one stuck when `budget` ran out, else the
+/// slowest pair if it took at least a tenth of the budget.
+#[cfg(feature = "native")]
+pub async fn find_slow_pattern(
+    schema: std::sync::Arc<Value>,
+    instance: std::sync::Arc<Value>,
+    budget: Duration,
+) -> Option<SlowPattern> {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Instant;
+
+    let current: Arc<Mutex<Option<SlowPattern>>> = Arc::default();
+    let stop = Arc::new(AtomicBool::new(false));
+    let probe = {
+        let current = current.clone();
+        let stop = stop.clone();
+        move || {
+            let mut patterns = Vec::new();
+            collect_patterns(&schema, String::new(), false, &mut patterns);
+            let mut values = Vec::new();
+            let mut keys = Vec::new();
+            collect_strings(&instance, String::new(), &mut values, &mut keys);
+
+            let mut slowest: Option<(Duration, SlowPattern)> = None;
+            let mut probes = 0;
+            for pattern in &patterns {
+                let Ok(validator) = jsonschema::validator_for(&serde_json::json!({
+                    "pattern": pattern.source,
+                })) else {
+                    continue;
+                };
+                let strings = if pattern.on_keys { &keys } else { &values };
+                for (pointer, string) in strings {
+                    probes += 1;
+                    if probes > MAX_PROBES || stop.load(Ordering::Relaxed) {
+                        return slowest.map(|(_, slow)| slow);
+                    }
+                    let candidate = SlowPattern {
+                        schema_pointer: pattern.pointer.clone(),
+                        instance_pointer: pointer.clone(),
+                    };
+                    *current.lock().expect("probe lock poisoned") = Some(candidate.clone());
+                    let started = Instant::now();
+                    let _ = validator.is_valid(&Value::String((*string).to_owned()));
+                    let elapsed = started.elapsed();
+                    if slowest.as_ref().is_none_or(|(worst, _)| elapsed > *worst) {
+                        slowest = Some((elapsed, candidate));
+                    }
+                }
+            }
+            slowest
+                .filter(|(elapsed, _)| *elapsed >= budget / 10)
+                .map(|(_, slow)| slow)
+        }
+    };
+
+    match time_boxed(budget, probe).await {
+        Ok(result) => result,
+        Err(_) => {
+            stop.store(true, Ordering::Relaxed);
+            current.lock().expect("probe lock poisoned").take()
+        }
+    }
+}
+
+/// Without a blocking thread pool validation is never cut short, so there is nothing to probe.
+#[cfg(not(feature = "native"))]
+pub async fn find_slow_pattern(
+    _schema: std::sync::Arc<Value>,
+    _instance: std::sync::Arc<Value>,
+    _budget: Duration,
+) -> Option<SlowPattern> {
+    None
+}
+
+#[cfg(feature = "native")]
+struct PatternSite<'a> {
+    pointer: String,
+    source: &'a str,
+    /// `patternProperties` keys match property names, `pattern` matches string values.
+    on_keys: bool,
+}
+
+#[cfg(feature = "native")]
+fn collect_patterns<'a>(
+    schema: &'a Value,
+    pointer: String,
+    in_name_map: bool,
+    out: &mut Vec<PatternSite<'a>>,
+) {
+    match schema {
+        Value::Object(map) if in_name_map => {
+            for (name, subschema) in map {
+                let child = format!("{pointer}/{}", escape_pointer_token(name));
+                collect_patterns(subschema, child, false, out);
+            }
+        }
+        Value::Object(map) => {
+            for (key, value) in map {
+                if is_data_member(key, false) {
+                    continue;
+                }
+                let child = format!("{pointer}/{}", escape_pointer_token(key));
+                match (key.as_str(), value) {
+                    ("pattern", Value::String(source)) => out.push(PatternSite {
+                        pointer: child,
+                        source,
+                        on_keys: false,
+                    }),
+                    ("patternProperties", Value::Object(properties)) => {
+                        for (source, subschema) in properties {
+                            let site = format!("{child}/{}", escape_pointer_token(source));
+                            out.push(PatternSite {
+                                pointer: site.clone(),
+                                source,
+                                on_keys: true,
+                            });
+                            collect_patterns(subschema, site, false, out);
+                        }
+                    }
+                    _ => collect_patterns(value, child, holds_name_map(key, false), out),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (idx, item) in items.iter().enumerate() {
+                collect_patterns(item, format!("{pointer}/{idx}"), false, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(feature = "native")]
+fn collect_strings<'a>(
+    instance: &'a Value,
+    pointer: String,
+    values: &mut Vec<(String, &'a str)>,
+    keys: &mut Vec<(String, &'a str)>,
+) {
+    match instance {
+        Value::String(s) => values.push((pointer, s)),
+        Value::Object(map) => {
+            for (key, value) in map {
+                let child = format!("{pointer}/{}", escape_pointer_token(key));
+                keys.push((child.clone(), key));
+                collect_strings(value, child, values, keys);
+            }
+        }
+        Value::Array(items) => {
+            for (idx, item) in items.iter().enumerate() {
+                collect_strings(item, format!("{pointer}/{idx}"), values, keys);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[cfg(feature = "native")]
+    #[test]
+    fn test_collects_patterns_outside_data_keywords() {
+        let schema = json!({
+            "properties": { "a~b": { "pattern": "^x$" }, "default": { "pattern": "^z$" } },
+            "patternProperties": { "^k": { "pattern": "^y$" } },
+            "examples": [{ "pattern": "not a keyword" }]
+        });
+        let mut patterns = Vec::new();
+        collect_patterns(&schema, String::new(), false, &mut patterns);
+        let found: Vec<_> = patterns
+            .iter()
+            .map(|p| (p.pointer.as_str(), p.source, p.on_keys))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("/patternProperties/^k", "^k", true),
+                ("/patternProperties/^k/pattern", "^y$", false),
+                ("/properties/a~0b/pattern", "^x$", false),
+                ("/properties/default/pattern", "^z$", false),
+            ]
+        );
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn test_time_boxed_gives_up_after_budget() {
+        let slow = time_boxed(Duration::from_millis(20), || {
+            std::thread::sleep(Duration::from_millis(500));
+        });
+        assert_eq!(slow.await, Err(Unfinished::TimedOut));
+        assert_eq!(time_boxed(Duration::from_secs(5), || 7).await, Ok(7));
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn test_time_boxed_refuses_work_while_abandoned_runs_are_going() {
+        static RUNS: AbandonedRuns = AbandonedRuns::new(1);
+        let (release, blocked) = std::sync::mpsc::channel::<()>();
+        let stuck = time_boxed_in(&RUNS, Duration::from_millis(20), move || {
+            let _ = blocked.recv();
+        });
+        assert_eq!(stuck.await, Err(Unfinished::TimedOut));
+        assert!(RUNS.full());
+        assert_eq!(
+            time_boxed_in(&RUNS, Duration::from_secs(5), || 7).await,
+            Err(Unfinished::Skipped)
+        );
+
+        release.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while RUNS.full() {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .expect("the abandoned run frees its slot when it finishes");
+        assert_eq!(
+            time_boxed_in(&RUNS, Duration::from_secs(5), || 7).await,
+            Ok(7)
+        );
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn test_finds_slow_pattern() {
+        let schema = json!({
+            "properties": {
+                "fast": { "pattern": "^a" },
+                "slow": { "items": { "pattern": "^(\\w+\\s?)*\\1$" } }
+            }
+        });
+        let words = "a".repeat(30) + "!";
+        let instance = json!({ "fast": "abc", "slow": vec![words; 50] });
+        let slow = find_slow_pattern(
+            std::sync::Arc::new(schema),
+            std::sync::Arc::new(instance),
+            Duration::from_millis(100),
+        )
+        .await
+        .unwrap();
+        assert_eq!(slow.schema_pointer, "/properties/slow/items/pattern");
+        assert!(slow.instance_pointer.starts_with("/slow/"));
+    }
+}
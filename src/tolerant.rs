@@ -0,0 +1,615 @@
+//! Error-recovering JSON parser: reports every syntax problem in one pass and still yields a
+//! best-effort value, so schema validation keeps working on a partially broken document.
+//!
+//! Recovery follows the [`span`](crate::span) parser's reading of broken input (a missing
+//! comma starts the next member, an unclosed container ends at end of input), so instance
+//! paths into the value map back onto the span tree.
+//!
+//! Documents opened with languageId `json5` are read as [`Dialect::Json5`]: comments,
+//! trailing commas, unquoted keys, single-quoted strings and hex numbers are accepted.
+//! [`Dialect::JsonLines`] documents are JSON read one line at a time by the caller.
+
+use crate::span::{is_identifier, try_decode_unicode_escape};
+use serde_json::{Map, Number, Value};
+
+/// One syntax problem, as a byte range of the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub start: usize,
+    pub end: usize,
+    pub message: String,
+}
+
+/// The syntax a document is read with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Dialect {
+    #[default]
+    Json,
+    Json5,
+    /// JSON Lines / NDJSON: one JSON value per line.
+    JsonLines,
+}
+
+impl Dialect {
+    /// The dialect of a document opened with `language_id`, falling back to the extension
+    /// of `path` for a plain `json` (or unknown) languageId.
+    pub fn detect(language_id: &str, path: &str) -> Self {
+        match language_id {
+            "json5" => Dialect::Json5,
+            "jsonl" | "jsonlines" | "ndjson" => Dialect::JsonLines,
+            _ => Dialect::from_path(path),
+        }
+    }
+
+    /// The dialect of a file, by extension.
+    pub fn from_path(path: &str) -> Self {
+        match path.rsplit_once('.').map(|(_, extension)| extension) {
+            Some("json5") => Dialect::Json5,
+            Some("jsonl" | "ndjson") => Dialect::JsonLines,
+            _ => Dialect::Json,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TolerantParse {
+    /// `None` when the document holds no value at all (blank, or only garbage).
+    pub value: Option<Value>,
+    pub errors: Vec<SyntaxError>,
+}
+
+/// Parse `text`, recovering after each error. Members and elements whose value cannot be
+/// read are left out of objects and become `null` in arrays (keeping indices aligned).
+pub fn parse_tolerant(text: &str) -> TolerantParse {
+    parse_tolerant_with(text, Dialect::Json)
+}
+
+/// [`parse_tolerant`] in `dialect`.
+pub fn parse_tolerant_with(text: &str, dialect: Dialect) -> TolerantParse {
+    let mut parser = Parser {
+        text,
+        bytes: text.as_bytes(),
+        dialect,
+        pos: 0,
+        errors: Vec::new(),
+        at_eof: false,
+    };
+    parser.skip_whitespace();
+    let value = parser.parse_value();
+    parser.skip_whitespace();
+    if parser.pos < parser.bytes.len() {
+        parser.error_at(parser.pos, "unexpected content after the document's value");
+    }
+    TolerantParse {
+        value,
+        errors: parser.errors,
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    bytes: &'a [u8],
+    dialect: Dialect,
+    pos: usize,
+    errors: Vec<SyntaxError>,
+    /// End of input was reported; every unclosed container follows from it, so no more
+    /// errors are recorded.
+    at_eof: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn parse_value(&mut self) -> Option<Value> {
+        let Some(&byte) = self.bytes.get(self.pos) else {
+            self.error_at(self.pos, "unexpected end of input, expected a value");
+            return None;
+        };
+        match byte {
+            b'{' => Some(self.parse_object()),
+            b'[' => Some(self.parse_array()),
+            b'"' | b'\'' => Some(Value::String(self.parse_string())),
+            _ => self.parse_literal(),
+        }
+    }
+
+    fn parse_object(&mut self) -> Value {
+        let open = self.pos;
+        self.pos += 1;
+        let mut map = Map::new();
+        loop {
+            self.skip_whitespace();
+            let Some(&byte) = self.bytes.get(self.pos) else {
+                self.error_at(open, "unclosed object, expected `}`");
+                break;
+            };
+            match byte {
+                b'}' => {
+                    self.pos += 1;
+                    break;
+                }
+                b',' => {
+                    self.error_at(self.pos, "expected a property name before `,`");
+                    self.pos += 1;
+                    continue;
+                }
+                b']' => {
+                    // Unbalanced close; the span parser skips it too.
+                    self.error_at(self.pos, "expected `}`, found `]`");
+                    self.pos += 1;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let Some(key) = self.parse_key() else {
+                continue;
+            };
+            self.skip_whitespace();
+            if self.bytes.get(self.pos) == Some(&b':') {
+                self.pos += 1;
+                self.skip_whitespace();
+            } else {
+                self.error_at(self.pos, "expected `:` after the property name");
+            }
+            let value = match self.bytes.get(self.pos) {
+                Some(b',' | b'}') => {
+                    self.error_at(self.pos, "missing value");
+                    None
+                }
+                _ => self.parse_value(),
+            };
+            if let Some(value) = value {
+                map.insert(key, value);
+            }
+
+            self.skip_whitespace();
+            match self.bytes.get(self.pos) {
+                Some(b',') => {
+                    let comma = self.pos;
+                    self.pos += 1;
+                    self.skip_whitespace();
+                    if self.bytes.get(self.pos) == Some(&b'}') && self.dialect != Dialect::Json5 {
+                        self.error_at(comma, "trailing comma");
+                    }
+                }
+                Some(b'}') | None => {}
+                Some(_) => self.error_at(self.pos, "expected `,` or `}`"),
+            }
+        }
+        Value::Object(map)
+    }
+
+    /// A quoted property name, or a bare word: an identifier in JSON5, reported in JSON.
+    /// `None` after skipping a byte that cannot start a name.
+    fn parse_key(&mut self) -> Option<String> {
+        if matches!(self.bytes[self.pos], b'"' | b'\'') {
+            return Some(self.parse_string());
+        }
+        let start = self.pos;
+        let word = self.take_token();
+        if word.is_empty() {
+            self.error_at(start, "expected a property name");
+            self.pos += 1;
+            return None;
+        }
+        match self.dialect {
+            Dialect::Json | Dialect::JsonLines => {
+                self.error_range(start, self.pos, "property names must be double-quoted")
+            }
+            Dialect::Json5 if !is_identifier(word) => self.error_range(
+                start,
+                self.pos,
+                "property names must be identifiers or quoted",
+            ),
+            Dialect::Json5 => {}
+        }
+        Some(word.to_owned())
+    }
+
+    fn parse_array(&mut self) -> Value {
+        let open = self.pos;
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let Some(&byte) = self.bytes.get(self.pos) else {
+                self.error_at(open, "unclosed array, expected `]`");
+                break;
+            };
+            match byte {
+                b']' => {
+                    self.pos += 1;
+                    break;
+                }
+                b',' => {
+                    self.error_at(self.pos, "missing value");
+                    self.pos += 1;
+                    continue;
+                }
+                b'}' => {
+                    // Let the enclosing object close.
+                    self.error_at(self.pos, "expected `]`, found `}`");
+                    break;
+                }
+                _ => {}
+            }
+
+            items.push(self.parse_value().unwrap_or(Value::Null));
+
+            self.skip_whitespace();
+            match self.bytes.get(self.pos) {
+                Some(b',') => {
+                    let comma = self.pos;
+                    self.pos += 1;
+                    self.skip_whitespace();
+                    if self.bytes.get(self.pos) == Some(&b']') && self.dialect != Dialect::Json5 {
+                        self.error_at(comma, "trailing comma");
+                    }
+                }
+                Some(b']' | b'}') | None => {}
+                Some(_) => self.error_at(self.pos, "expected `,` or `]`"),
+            }
+        }
+        Value::Array(items)
+    }
+
+    /// Consume a string (including quotes). An unterminated string ends at the line break.
+    /// Single quotes are JSON5; in JSON the string is read but reported.
+    fn parse_string(&mut self) -> String {
+        let open = self.pos;
+        let quote = self.bytes[open] as char;
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let Some(ch) = self.text[self.pos..].chars().next() else {
+                self.error_at(open, "unterminated string");
+                break;
+            };
+            match ch {
+                _ if ch == quote => {
+                    self.pos += 1;
+                    if quote == '\'' && self.dialect != Dialect::Json5 {
+                        self.error_range(open, self.pos, "strings must be double-quoted");
+                    }
+                    break;
+                }
+                '\n' | '\r' => {
+                    self.error_range(open, self.pos, "unterminated string");
+                    break;
+                }
+                '\\' => {
+                    let escape = self.pos;
+                    self.pos += 1;
+                    let Some(&esc) = self.bytes.get(self.pos) else {
+                        continue;
+                    };
+                    self.pos += 1;
+                    if self.dialect == Dialect::Json5 {
+                        if let Some(decoded) = self.json5_escape(esc) {
+                            out.extend(decoded);
+                            continue;
+                        }
+                    }
+                    out.push(match esc {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'u' => match try_decode_unicode_escape(self.bytes, &mut self.pos) {
+                            Some(ch) => ch,
+                            None => {
+                                self.error_range(escape, self.pos, "invalid unicode escape");
+                                char::REPLACEMENT_CHARACTER
+                            }
+                        },
+                        _ => {
+                            self.error_range(escape, self.pos, "invalid escape sequence");
+                            // Keep the escaped character, as the span parser does.
+                            self.pos -= 1;
+                            continue;
+                        }
+                    });
+                }
+                ch => {
+                    if (ch as u32) < 0x20 {
+                        self.error_at(self.pos, "control character in string");
+                    }
+                    out.push(ch);
+                    self.pos += ch.len_utf8();
+                }
+            }
+        }
+        out
+    }
+
+    /// The escapes JSON5 adds, with `pos` just past `esc`: `\'`, `\v`, `\0`, `\xHH` and
+    /// a backslash before a line break (a line continuation, yielding nothing).
+    fn json5_escape(&mut self, esc: u8) -> Option<Option<char>> {
+        let decoded = match esc {
+            b'\'' => '\'',
+            b'v' => '\u{b}',
+            b'0' if !self.bytes.get(self.pos).is_some_and(u8::is_ascii_digit) => '\0',
+            b'x' => {
+                let code = self
+                    .text
+                    .get(self.pos..self.pos + 2)
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())?;
+                self.pos += 2;
+                code as char
+            }
+            b'\n' => return Some(None),
+            b'\r' => {
+                if self.bytes.get(self.pos) == Some(&b'\n') {
+                    self.pos += 1;
+                }
+                return Some(None);
+            }
+            _ => return None,
+        };
+        Some(Some(decoded))
+    }
+
+    /// `true`, `false`, `null` or a number; anything else is reported and yields `None`.
+    fn parse_literal(&mut self) -> Option<Value> {
+        let start = self.pos;
+        let token = self.take_token();
+        if token.is_empty() {
+            let ch = self.text[start..].chars().next().unwrap_or_default();
+            self.error_at(start, &format!("unexpected `{ch}`, expected a value"));
+            self.pos += ch.len_utf8();
+            return None;
+        }
+        let value = match token {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            "null" => Some(Value::Null),
+            _ if self.dialect == Dialect::Json5 => json5_number(token),
+            _ => token.parse::<Number>().ok().map(Value::Number),
+        };
+        if value.is_none() {
+            let message = match token.trim_start_matches(['+', '-']) {
+                "Infinity" | "NaN" if self.dialect == Dialect::Json5 => {
+                    format!("`{token}` has no JSON equivalent and cannot be validated")
+                }
+                _ => format!("invalid value `{token}`"),
+            };
+            self.error_range(start, self.pos, &message);
+        }
+        value
+    }
+
+    /// Consume a run of bytes up to the next delimiter or whitespace.
+    fn take_token(&mut self) -> &'a str {
+        let start = self.pos;
+        while self.pos < self.bytes.len()
+            && !matches!(
+                self.bytes[self.pos],
+                b',' | b':' | b'{' | b'}' | b'[' | b']' | b'"' | b' ' | b'\t' | b'\r' | b'\n'
+            )
+        {
+            self.pos += 1;
+        }
+        // Only ASCII delimiters end a token, so both ends are char boundaries.
+        &self.text[start..self.pos]
+    }
+
+    /// Skip whitespace and `//` / `/* */` comments, reporting comments in JSON.
+    fn skip_whitespace(&mut self) {
+        loop {
+            while self.pos < self.bytes.len()
+                && matches!(self.bytes[self.pos], b' ' | b'\t' | b'\r' | b'\n')
+            {
+                self.pos += 1;
+            }
+            let rest = &self.text[self.pos..];
+            let len = if rest.starts_with("//") {
+                rest.find('\n').unwrap_or(rest.len())
+            } else if let Some(body) = rest.strip_prefix("/*") {
+                body.find("*/").map_or(rest.len(), |end| end + 4)
+            } else {
+                return;
+            };
+            if self.dialect != Dialect::Json5 {
+                self.error_range(self.pos, self.pos + len, "comments are not allowed in JSON");
+            }
+            self.pos += len;
+        }
+    }
+
+    /// Report an error on the character at `at` (an empty range at end of input).
+    fn error_at(&mut self, at: usize, message: &str) {
+        let end = self.text[at..]
+            .chars()
+            .next()
+            .map_or(at, |ch| at + ch.len_utf8());
+        self.error_range(at, end, message);
+    }
+
+    fn error_range(&mut self, start: usize, end: usize, message: &str) {
+        if self.at_eof {
+            return;
+        }
+        self.at_eof = self.pos >= self.bytes.len();
+        self.errors.push(SyntaxError {
+            start,
+            end,
+            message: message.to_owned(),
+        });
+    }
+}
+
+/// A JSON5 number: JSON's, plus hex digits, a leading `+`, and a leading or trailing `.`.
+fn json5_number(token: &str) -> Option<Value> {
+    let (negative, unsigned) = match token.as_bytes().first()? {
+        b'-' => (true, &token[1..]),
+        b'+' => (false, &token[1..]),
+        _ => (false, token),
+    };
+    if let Some(hex) = unsigned
+        .strip_prefix("0x")
+        .or_else(|| unsigned.strip_prefix("0X"))
+    {
+        let magnitude = u64::from_str_radix(hex, 16).ok()?;
+        let number = match (negative, i64::try_from(magnitude)) {
+            (false, _) => Number::from(magnitude),
+            (true, Ok(magnitude)) => Number::from(-magnitude),
+            (true, Err(_)) => Number::from_f64(-(magnitude as f64))?,
+        };
+        return Some(Value::Number(number));
+    }
+    // `.5` and `5.` (also `5.e3`) become `0.5` and `5.0`, which JSON accepts.
+    let mut json = String::from(if negative { "-" } else { "" });
+    if unsigned.starts_with('.') {
+        json.push('0');
+    }
+    let mut chars = unsigned.chars().peekable();
+    while let Some(ch) = chars.next() {
+        json.push(ch);
+        if ch == '.' && !chars.peek().is_some_and(char::is_ascii_digit) {
+            json.push('0');
+        }
+    }
+    json.parse::<Number>().ok().map(Value::Number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn messages(text: &str) -> Vec<String> {
+        parse_tolerant(text)
+            .errors
+            .into_iter()
+            .map(|e| format!("{}..{} {}", e.start, e.end, e.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_document_has_no_errors() {
+        let text = r#"{ "a": [1, -2.5e3, true, null], "b": { "c": "x\né" } }"#;
+        let parsed = parse_tolerant(text);
+        assert_eq!(parsed.errors, vec![]);
+        assert_eq!(parsed.value, serde_json::from_str(text).ok());
+    }
+
+    #[test]
+    fn test_reports_every_error_and_keeps_the_rest() {
+        let text = r#"{ "a": 1 "b": 2, "c": , d: 4, "e": [1 2,], "f": tru }"#;
+        let parsed = parse_tolerant(text);
+        assert_eq!(
+            messages(text),
+            [
+                "9..10 expected `,` or `}`",
+                "22..23 missing value",
+                "24..25 property names must be double-quoted",
+                "38..39 expected `,` or `]`",
+                "39..40 trailing comma",
+                "48..51 invalid value `tru`",
+            ]
+        );
+        assert_eq!(
+            parsed.value,
+            Some(json!({ "a": 1, "b": 2, "d": 4, "e": [1, 2] }))
+        );
+    }
+
+    #[test]
+    fn test_end_of_input_reported_once() {
+        let text = r#"{"name": "hello", "count":"#;
+        let parsed = parse_tolerant(text);
+        assert_eq!(
+            messages(text),
+            ["26..26 unexpected end of input, expected a value"]
+        );
+        assert_eq!(parsed.value, Some(json!({ "name": "hello" })));
+
+        assert_eq!(parse_tolerant("").value, None);
+        assert_eq!(parse_tolerant("  ").errors.len(), 1);
+    }
+
+    #[test]
+    fn test_unterminated_string_ends_at_line_break() {
+        let text = "{\n  \"a\": \"oops,\n  \"b\": 2\n}";
+        let parsed = parse_tolerant(text);
+        assert_eq!(
+            messages(text),
+            ["9..15 unterminated string", "18..19 expected `,` or `}`"]
+        );
+        assert_eq!(parsed.value, Some(json!({ "a": "oops,", "b": 2 })));
+    }
+
+    #[test]
+    fn test_comments_and_trailing_content() {
+        let text = "// header\n{ /* inline */ \"a\": 1 } x";
+        let parsed = parse_tolerant(text);
+        assert_eq!(
+            messages(text),
+            [
+                "0..9 comments are not allowed in JSON",
+                "12..24 comments are not allowed in JSON",
+                "34..35 unexpected content after the document's value",
+            ]
+        );
+        assert_eq!(parsed.value, Some(json!({ "a": 1 })));
+    }
+
+    #[test]
+    fn test_json5_dialect() {
+        let text = r#"// config
+{
+  name: 'it\'s "x"',
+  'quoted': "\x41\
+B",
+  hex: -0x1F, half: .5, whole: +5., exp: 1.e2,
+  list: [1, 2,],
+}"#;
+        let parsed = parse_tolerant_with(text, Dialect::Json5);
+        assert_eq!(parsed.errors, vec![]);
+        assert_eq!(
+            parsed.value,
+            Some(json!({
+                "name": "it's \"x\"",
+                "quoted": "AB",
+                "hex": -31,
+                "half": 0.5,
+                "whole": 5.0,
+                "exp": 100.0,
+                "list": [1, 2]
+            }))
+        );
+
+        let text = "{ 1a: Infinity }";
+        let messages: Vec<String> = parse_tolerant_with(text, Dialect::Json5)
+            .errors
+            .into_iter()
+            .map(|e| e.message)
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "property names must be identifiers or quoted",
+                "`Infinity` has no JSON equivalent and cannot be validated"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_single_quotes_reported_in_json() {
+        let text = "{ \"a\": 'x' }";
+        let parsed = parse_tolerant(text);
+        assert_eq!(messages(text), ["7..10 strings must be double-quoted"]);
+        assert_eq!(parsed.value, Some(json!({ "a": "x" })));
+    }
+
+    #[test]
+    fn test_lone_surrogate_escape_is_reported() {
+        let text = r#"["\ud83d\ude00", "\ud83d"]"#;
+        let parsed = parse_tolerant(text);
+        assert_eq!(messages(text), ["18..24 invalid unicode escape"]);
+        assert_eq!(parsed.value, Some(json!(["\u{1F600}", "\u{FFFD}"])));
+    }
+}
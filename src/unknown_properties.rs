@@ -0,0 +1,158 @@
+//! Opt-in strict mode: flag object members the schema does not declare in `properties` or
+//! `patternProperties`, even where `additionalProperties` lets them through — permissive
+//! schemas otherwise accept a misspelt optional key without a word.
+
+use crate::config::ServerConfig;
+use crate::fuzzy::closest_match;
+use crate::offsets::byte_range_to_lsp_range;
+use crate::position::PathSegment;
+use crate::schema::SchemaNode;
+use crate::span::{path_to_pointer, walk_objects, SpanNode};
+use crate::suggest::{did_you_mean, Suggestion, SuggestionTarget};
+use serde_json::Value;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString};
+
+/// Warn on every undeclared member when `strict_unknown_properties` is set. Objects whose
+/// schema declares nothing (free-form objects and maps) and closed objects, which
+/// validation already reports, are left alone.
+pub fn unknown_property_diagnostics(
+    text: &str,
+    spans: &SpanNode,
+    schema: &Value,
+    config: &ServerConfig,
+) -> Vec<Diagnostic> {
+    if !config.strict_unknown_properties {
+        return vec![];
+    }
+
+    let root = SchemaNode::new(schema, schema).with_limits(config.schema_limits);
+    let mut diagnostics = Vec::new();
+    let mut path = Vec::new();
+    walk_objects(spans, &mut path, &mut |path, members| {
+        let Some(node) = root.navigate(path) else {
+            return;
+        };
+        if is_closed(&node) {
+            return;
+        }
+        let Some(declared) = node.declared_properties() else {
+            return;
+        };
+        if declared.is_empty() {
+            return;
+        }
+        // Declared names not yet present are the rename candidates.
+        let mut missing: Vec<&str> = declared
+            .names
+            .iter()
+            .copied()
+            .filter(|name| members.iter().all(|member| member.key != *name))
+            .collect();
+        missing.sort_unstable();
+
+        for member in members {
+            if (path.is_empty() && member.key == "$schema") || declared.covers(&member.key) {
+                continue;
+            }
+            let mut member_path = path.to_vec();
+            member_path.push(PathSegment::Key(member.key.clone()));
+            let instance_path = path_to_pointer(&member_path);
+            let suggestions: Vec<Suggestion> = closest_match(&member.key, missing.iter().copied())
+                .map(|closest| Suggestion {
+                    instance_path: instance_path.clone(),
+                    target: SuggestionTarget::Key,
+                    replacement: closest.into(),
+                })
+                .into_iter()
+                .collect();
+            let mut data = serde_json::json!({ "instancePath": instance_path });
+            if !suggestions.is_empty() {
+                data["suggestions"] = serde_json::json!(suggestions);
+            }
+            diagnostics.push(Diagnostic {
+                range: byte_range_to_lsp_range(text, member.key_start, member.key_end),
+                severity: Some(DiagnosticSeverity::WARNING),
+                code: Some(NumberOrString::String("unknown-property".into())),
+                source: Some("json-ls".into()),
+                message: format!(
+                    "Property `{}` is not declared in the schema{}",
+                    member.key,
+                    did_you_mean(&suggestions)
+                ),
+                data: Some(data),
+                ..Default::default()
+            });
+        }
+    });
+    diagnostics
+}
+
+/// True if `additionalProperties` or `unevaluatedProperties` is `false` on the node.
+fn is_closed(node: &SchemaNode) -> bool {
+    ["additionalProperties", "unevaluatedProperties"]
+        .iter()
+        .any(|keyword| node.keyword(keyword) == Some(&Value::Bool(false)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::parse_spans;
+    use serde_json::json;
+
+    fn unknown(text: &str, schema: &Value) -> Vec<String> {
+        let config = ServerConfig {
+            strict_unknown_properties: true,
+            ..Default::default()
+        };
+        let spans = parse_spans(text).unwrap();
+        unknown_property_diagnostics(text, &spans, schema, &config)
+            .into_iter()
+            .map(|d| d.message)
+            .collect()
+    }
+
+    #[test]
+    fn test_undeclared_properties_in_permissive_schemas() {
+        let schema = json!({
+            "properties": {
+                "server": { "$ref": "#/$defs/Server" },
+                "labels": { "additionalProperties": { "type": "string" } }
+            },
+            "$defs": {
+                "Server": {
+                    "allOf": [{ "properties": { "host": {} } }],
+                    "properties": { "port": {}, "timeout": {} },
+                    "patternProperties": { "^x-": {} }
+                }
+            }
+        });
+        let text = r#"{
+  "$schema": "https://example.com/s.json",
+  "server": { "host": "a", "prot": 1, "x-note": "", "zzz": 0 },
+  "labels": { "anything": "goes" },
+  "extra": true
+}"#;
+        assert_eq!(
+            unknown(text, &schema),
+            [
+                "Property `extra` is not declared in the schema",
+                "Property `prot` is not declared in the schema — did you mean `port`?",
+                "Property `zzz` is not declared in the schema",
+            ]
+        );
+
+        let closed = json!({ "properties": { "a": {} }, "additionalProperties": false });
+        assert!(unknown(r#"{"b": 1}"#, &closed).is_empty());
+        assert!(unknown(r#"{"b": 1}"#, &json!({})).is_empty());
+    }
+
+    #[test]
+    fn test_disabled_by_default() {
+        let schema = json!({ "properties": { "a": {} } });
+        let text = r#"{"b": 1}"#;
+        let spans = parse_spans(text).unwrap();
+        let config = ServerConfig::default();
+        assert!(unknown_property_diagnostics(text, &spans, &schema, &config).is_empty());
+    }
+}
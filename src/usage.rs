@@ -0,0 +1,106 @@
+//! Opt-in local usage log (`usage_metrics`): the process counters from [`METRICS`], added to
+//! those of earlier sessions, kept in `usage.json` under the cache dir. Nothing is sent
+//! anywhere; bug reporters can attach the file or the `json-ls/status` response.
+
+use crate::metrics::METRICS;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+const USAGE_FILE: &str = "usage.json";
+
+#[derive(Debug)]
+pub struct UsageLog {
+    path: PathBuf,
+    /// Totals of earlier sessions, as read at startup.
+    previous: Value,
+}
+
+impl UsageLog {
+    /// The log in `cache_dir`; an unreadable or missing file starts from zero.
+    pub fn open(cache_dir: &Path) -> Self {
+        let path = cache_dir.join(USAGE_FILE);
+        let previous = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or(Value::Null);
+        Self { path, previous }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Earlier sessions plus this one.
+    pub fn totals(&self) -> Value {
+        let mut totals = self.previous.clone();
+        add_counts(&mut totals, &METRICS.snapshot());
+        totals
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(&self.totals())?)?;
+        std::fs::rename(&tmp, &self.path)
+    }
+}
+
+/// Add the numbers in `from` into `into`, key by key; keys missing from `into` are copied.
+fn add_counts(into: &mut Value, from: &Value) {
+    match (into, from) {
+        (Value::Object(into), Value::Object(from)) => {
+            for (key, value) in from {
+                match into.get_mut(key) {
+                    Some(existing) => add_counts(existing, value),
+                    None => {
+                        into.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (into @ Value::Number(_), Value::Number(from)) => {
+            *into = match (into.as_u64(), from.as_u64()) {
+                (Some(a), Some(b)) => a.saturating_add(b).into(),
+                _ => (into.as_f64().unwrap_or(0.0) + from.as_f64().unwrap_or(0.0)).into(),
+            };
+        }
+        (into, from) => *into = from.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_add_counts_merges_sessions() {
+        let mut totals = json!({ "features": { "hover": 3 }, "latency": { "sumSecs": 0.5 } });
+        add_counts(
+            &mut totals,
+            &json!({ "features": { "hover": 2, "completion": 1 }, "latency": { "sumSecs": 0.25 } }),
+        );
+        assert_eq!(
+            totals,
+            json!({ "features": { "hover": 5, "completion": 1 }, "latency": { "sumSecs": 0.75 } })
+        );
+
+        let mut empty = Value::Null;
+        add_counts(&mut empty, &json!({ "a": 1 }));
+        assert_eq!(empty, json!({ "a": 1 }));
+    }
+
+    #[test]
+    fn test_save_round_trips_through_cache_dir() {
+        let dir = std::env::temp_dir().join(format!("json-ls-usage-{}", std::process::id()));
+        let log = UsageLog::open(&dir);
+        log.save().unwrap();
+        let reopened = UsageLog::open(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(reopened.path(), dir.join(USAGE_FILE));
+        assert!(reopened.previous["features"].is_object());
+    }
+}
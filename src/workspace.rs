@@ -0,0 +1,131 @@
+//! The workspace as the server sees it: the client's root folders and the JSON files under
+//! them, for features that look beyond open documents.
+
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use tower_lsp::lsp_types::InitializeParams;
+
+/// Stop walking after this many files, so a huge monorepo cannot stall a request.
+const MAX_WORKSPACE_FILES: usize = 5000;
+
+/// Directories never descended into (besides hidden ones).
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target"];
+
+/// Extensions of the files walked: JSON, and JSON5 and JSON Lines read in their own dialect.
+const JSON_EXTENSIONS: &[&str] = &["json", "json5", "jsonl", "ndjson"];
+
+#[derive(Debug, Default)]
+pub struct Workspace {
+    roots: RwLock<Vec<PathBuf>>,
+}
+
+impl Workspace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_roots(&self, roots: Vec<PathBuf>) {
+        *self.roots.write().expect("workspace lock poisoned") = roots;
+    }
+
+    pub fn roots(&self) -> Vec<PathBuf> {
+        self.roots.read().expect("workspace lock poisoned").clone()
+    }
+
+    /// `.json`, `.json5`, `.jsonl` and `.ndjson` files under the roots, skipping hidden
+    /// directories, `node_modules` and `target`; at most `MAX_WORKSPACE_FILES`, in a stable
+    /// (sorted) walk order.
+    pub fn json_files(&self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for root in self.roots() {
+            walk(&root, &mut files);
+        }
+        files
+    }
+}
+
+/// Root folders from `initialize`: the workspace folders, else the (deprecated) root URI.
+pub fn workspace_roots(params: &InitializeParams) -> Vec<PathBuf> {
+    if let Some(folders) = &params.workspace_folders {
+        return folders
+            .iter()
+            .filter_map(|folder| folder.uri.to_file_path().ok())
+            .collect();
+    }
+    #[allow(deprecated)]
+    let root_uri = params.root_uri.as_ref();
+    root_uri
+        .and_then(|uri| uri.to_file_path().ok())
+        .into_iter()
+        .collect()
+}
+
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<_> = entries.filter_map(|entry| entry.ok()).collect();
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        if files.len() >= MAX_WORKSPACE_FILES {
+            return;
+        }
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            if !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_ref()) {
+                walk(&entry.path(), files);
+            }
+        } else if file_type.is_file() && has_json_extension(&name) {
+            files.push(entry.path());
+        }
+    }
+}
+
+fn has_json_extension(name: &str) -> bool {
+    name.rsplit_once('.')
+        .is_some_and(|(_, extension)| JSON_EXTENSIONS.contains(&extension))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_files_skip_hidden_and_vendored_dirs() {
+        let root = std::env::temp_dir().join(format!("json-ls-workspace-{}", std::process::id()));
+        for dir in ["a", ".git", "node_modules/pkg", "b/c"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            "a/one.json",
+            "a/notes.txt",
+            "a/three.json5",
+            ".git/config.json",
+            "node_modules/pkg/package.json",
+            "b/c/two.json",
+            "b/events.ndjson",
+        ] {
+            std::fs::write(root.join(file), "{}").unwrap();
+        }
+
+        let workspace = Workspace::new();
+        workspace.set_roots(vec![root.clone()]);
+        let files = workspace.json_files();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            files,
+            vec![
+                root.join("a/one.json"),
+                root.join("a/three.json5"),
+                root.join("b/c/two.json"),
+                root.join("b/events.ndjson")
+            ]
+        );
+    }
+}
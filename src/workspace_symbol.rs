@@ -0,0 +1,331 @@
+use crate::config::ServerConfig;
+use crate::document::DocumentStore;
+use crate::fuzzy::fuzzy_score;
+use crate::offsets::byte_range_to_lsp_range;
+use crate::span::{parse_spans, SpanKind, SpanNode};
+use crate::workspace::Workspace;
+use dashmap::DashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tower_lsp::lsp_types::{
+    Location, Range, SymbolInformation, SymbolKind, Url, WorkspaceSymbolParams,
+};
+use tracing::debug;
+
+/// Most symbols returned for one query.
+const MAX_RESULTS: usize = 256;
+
+/// Files larger than this are not indexed.
+const MAX_INDEXED_FILE_BYTES: u64 = 2 * 1024 * 1024;
+
+/// A key (or string value) found in a workspace document.
+#[derive(Debug, Clone, PartialEq)]
+struct SymbolEntry {
+    name: String,
+    /// Dotted path of the enclosing member, e.g. `spec.template`.
+    container: String,
+    kind: SymbolKind,
+    range: Range,
+}
+
+/// Symbols of workspace files on disk, reused until the file's modification time changes.
+#[derive(Debug, Default)]
+pub struct SymbolIndex {
+    files: DashMap<PathBuf, IndexedFile>,
+}
+
+#[derive(Debug)]
+struct IndexedFile {
+    modified: SystemTime,
+    include_values: bool,
+    entries: Arc<Vec<SymbolEntry>>,
+}
+
+impl SymbolIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Entries for `path`, re-reading it only if it changed since it was last indexed.
+    fn entries(&self, path: &PathBuf, include_values: bool) -> Option<Arc<Vec<SymbolEntry>>> {
+        let metadata = std::fs::metadata(path).ok()?;
+        if metadata.len() > MAX_INDEXED_FILE_BYTES {
+            return None;
+        }
+        let modified = metadata.modified().ok()?;
+        if let Some(indexed) = self.files.get(path) {
+            if indexed.modified == modified && indexed.include_values == include_values {
+                return Some(indexed.entries.clone());
+            }
+        }
+
+        let text = std::fs::read_to_string(path).ok()?;
+        let entries = Arc::new(document_entries(&text, include_values));
+        self.files.insert(
+            path.clone(),
+            IndexedFile {
+                modified,
+                include_values,
+                entries: entries.clone(),
+            },
+        );
+        Some(entries)
+    }
+}
+
+/// Fuzzy-search keys (and, with `workspace_symbols.include_values`, string values) across
+/// open documents and the `.json` files of the workspace folders. The folders are walked
+/// and their files indexed off the async runtime.
+pub async fn handle_workspace_symbol(
+    documents: &DocumentStore,
+    workspace: &Arc<Workspace>,
+    index: &Arc<SymbolIndex>,
+    config: &ServerConfig,
+    params: WorkspaceSymbolParams,
+) -> Option<Vec<SymbolInformation>> {
+    let query = params.query;
+    let include_values = config.workspace_symbols.include_values;
+    let mut matches: Vec<(i64, Url, SymbolEntry)> = Vec::new();
+
+    let open = documents.uris();
+    for uri in &open {
+        if let Some(text) = documents.get_text(uri) {
+            collect_matches(
+                &query,
+                uri,
+                &document_entries(&text, include_values),
+                &mut matches,
+            );
+        }
+    }
+
+    let closed = {
+        let (workspace, index, query) = (workspace.clone(), index.clone(), query.clone());
+        tokio::task::spawn_blocking(move || {
+            let mut matches = Vec::new();
+            for path in workspace.json_files() {
+                let Ok(uri) = Url::from_file_path(&path) else {
+                    continue;
+                };
+                if open.contains(&uri) {
+                    continue;
+                }
+                if let Some(entries) = index.entries(&path, include_values) {
+                    collect_matches(&query, &uri, &entries, &mut matches);
+                }
+            }
+            matches
+        })
+        .await
+    };
+    match closed {
+        Ok(found) => matches.extend(found),
+        Err(e) => debug!("Workspace symbol scan failed: {e}"),
+    }
+
+    debug!(
+        "Workspace symbols for {query:?}: {} match(es)",
+        matches.len()
+    );
+    matches.sort_by(|(a_score, a_uri, a), (b_score, b_uri, b)| {
+        b_score
+            .cmp(a_score)
+            .then_with(|| a.name.cmp(&b.name))
+            .then_with(|| a_uri.as_str().cmp(b_uri.as_str()))
+            .then_with(|| a.range.start.cmp(&b.range.start))
+    });
+    matches.truncate(MAX_RESULTS);
+
+    #[allow(deprecated)]
+    let symbols = matches
+        .into_iter()
+        .map(|(_, uri, entry)| SymbolInformation {
+            name: entry.name,
+            kind: entry.kind,
+            tags: None,
+            deprecated: None,
+            location: Location {
+                uri,
+                range: entry.range,
+            },
+            container_name: (!entry.container.is_empty()).then_some(entry.container),
+        })
+        .collect();
+    Some(symbols)
+}
+
+fn collect_matches(
+    query: &str,
+    uri: &Url,
+    entries: &[SymbolEntry],
+    matches: &mut Vec<(i64, Url, SymbolEntry)>,
+) {
+    for entry in entries {
+        if let Some(score) = fuzzy_score(query, &entry.name) {
+            matches.push((score, uri.clone(), entry.clone()));
+        }
+    }
+}
+
+fn document_entries(text: &str, include_values: bool) -> Vec<SymbolEntry> {
+    let mut entries = Vec::new();
+    if let Some(spans) = parse_spans(text) {
+        collect_entries(text, &spans, "", include_values, &mut entries);
+    }
+    entries
+}
+
+fn collect_entries(
+    text: &str,
+    node: &SpanNode,
+    container: &str,
+    include_values: bool,
+    entries: &mut Vec<SymbolEntry>,
+) {
+    match &node.kind {
+        SpanKind::Object(members) => {
+            for member in members {
+                entries.push(SymbolEntry {
+                    name: member.key.clone(),
+                    container: container.to_owned(),
+                    kind: value_kind(&member.value, text),
+                    range: byte_range_to_lsp_range(text, member.key_start, member.key_end),
+                });
+                let path = if container.is_empty() {
+                    member.key.clone()
+                } else {
+                    format!("{container}.{}", member.key)
+                };
+                collect_entries(text, &member.value, &path, include_values, entries);
+            }
+        }
+        SpanKind::Array(items) => {
+            for (idx, item) in items.iter().enumerate() {
+                let path = format!("{container}[{idx}]");
+                collect_entries(text, item, &path, include_values, entries);
+            }
+        }
+        SpanKind::String(value) if include_values => entries.push(SymbolEntry {
+            name: value.clone(),
+            container: container.to_owned(),
+            kind: SymbolKind::STRING,
+            range: byte_range_to_lsp_range(text, node.start, node.end),
+        }),
+        _ => {}
+    }
+}
+
+/// Symbol kind of a key, by the JSON type of its value.
+fn value_kind(value: &SpanNode, text: &str) -> SymbolKind {
+    match &value.kind {
+        SpanKind::Object(_) => SymbolKind::OBJECT,
+        SpanKind::Array(_) => SymbolKind::ARRAY,
+        SpanKind::String(_) => SymbolKind::STRING,
+        SpanKind::Literal => match &text[value.start..value.end] {
+            "true" | "false" => SymbolKind::BOOLEAN,
+            "null" => SymbolKind::NULL,
+            _ => SymbolKind::NUMBER,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WorkspaceSymbolsConfig;
+    use tower_lsp::lsp_types::Position;
+
+    async fn query(
+        documents: &DocumentStore,
+        config: &ServerConfig,
+        q: &str,
+    ) -> Vec<SymbolInformation> {
+        let params = WorkspaceSymbolParams {
+            query: q.into(),
+            ..Default::default()
+        };
+        handle_workspace_symbol(
+            documents,
+            &Arc::new(Workspace::new()),
+            &Arc::new(SymbolIndex::new()),
+            config,
+            params,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_finds_nested_keys_fuzzily() {
+        let documents = DocumentStore::new();
+        let uri = Url::parse("file:///tmp/service.json").unwrap();
+        let text = "{\n  \"spec\": { \"retryPolicy\": { \"max\": 3 } },\n  \"rp\": true\n}";
+        documents.open(uri.clone(), 1, text.into());
+
+        let symbols = query(&documents, &ServerConfig::default(), "retrypol").await;
+        assert_eq!(symbols.len(), 1);
+        assert_eq!(symbols[0].name, "retryPolicy");
+        assert_eq!(symbols[0].container_name.as_deref(), Some("spec"));
+        assert_eq!(symbols[0].kind, SymbolKind::OBJECT);
+        assert_eq!(symbols[0].location.uri, uri);
+        assert_eq!(symbols[0].location.range.start, Position::new(1, 12));
+
+        let symbols = query(&documents, &ServerConfig::default(), "rp").await;
+        let names: Vec<_> = symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["rp", "retryPolicy"]);
+    }
+
+    #[tokio::test]
+    async fn test_string_values_only_when_enabled() {
+        let documents = DocumentStore::new();
+        let uri = Url::parse("file:///tmp/values.json").unwrap();
+        documents.open(uri, 1, r#"{ "image": "registry/app:1.2" }"#.into());
+
+        assert!(query(&documents, &ServerConfig::default(), "registry")
+            .await
+            .is_empty());
+
+        let config = ServerConfig {
+            workspace_symbols: WorkspaceSymbolsConfig {
+                include_values: true,
+            },
+            ..ServerConfig::default()
+        };
+        let symbols = query(&documents, &config, "registry").await;
+        assert_eq!(symbols.len(), 1);
+        assert_eq!(symbols[0].kind, SymbolKind::STRING);
+        assert_eq!(symbols[0].container_name.as_deref(), Some("image"));
+    }
+
+    #[tokio::test]
+    async fn test_indexes_closed_workspace_files() {
+        let root = std::env::temp_dir().join(format!("json-ls-symbols-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let path = root.join("deploy.json");
+        std::fs::write(&path, r#"{ "retryPolicy": { "attempts": 3 } }"#).unwrap();
+
+        let workspace = Arc::new(Workspace::new());
+        workspace.set_roots(vec![root.clone()]);
+        let index = Arc::new(SymbolIndex::new());
+        let params = || WorkspaceSymbolParams {
+            query: "attempts".into(),
+            ..Default::default()
+        };
+        let documents = DocumentStore::new();
+        let config = ServerConfig::default();
+        let first = handle_workspace_symbol(&documents, &workspace, &index, &config, params())
+            .await
+            .unwrap();
+        let second = handle_workspace_symbol(&documents, &workspace, &index, &config, params())
+            .await
+            .unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].location.uri, Url::from_file_path(&path).unwrap());
+        assert_eq!(first[0].container_name.as_deref(), Some("retryPolicy"));
+        assert_eq!(first, second);
+        assert_eq!(index.files.len(), 1);
+    }
+}
@@ -0,0 +1,251 @@
+//! Validation of workspace files that are not open in the editor: each is read from disk
+//! into a transient [`DocumentStore`] and goes through the same association and checks as an
+//! open document.
+
+use crate::config::ServerConfig;
+use crate::diagnostics::validate_document;
+use crate::document::DocumentStore;
+use crate::schema::catalog::CatalogEntry;
+use crate::schema::SchemaCache;
+use crate::tolerant::Dialect;
+use serde_json::{json, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::Arc;
+use tower_lsp::lsp_types::{
+    Diagnostic, DiagnosticSeverity, FullDocumentDiagnosticReport,
+    UnchangedDocumentDiagnosticReport, Url, WorkspaceDocumentDiagnosticReport,
+    WorkspaceFullDocumentDiagnosticReport, WorkspaceUnchangedDocumentDiagnosticReport,
+};
+
+/// `workspace/executeCommand` taking `[{ "publish": bool }?]`: validates every workspace JSON
+/// file, returning a report and, with `publish`, publishing the closed files' diagnostics.
+pub const VALIDATE_WORKSPACE_COMMAND: &str = "json-ls.validateWorkspace";
+
+/// Diagnostics of closed files, validated one at a time.
+pub struct ClosedFiles {
+    documents: Arc<DocumentStore>,
+    language_schema_url: Option<String>,
+}
+
+impl ClosedFiles {
+    /// Files are read as languageId `json`, with `config`'s `$schema` precedence, and
+    /// associated by file name through the `schemas` setting and `catalog` entries.
+    pub fn new(config: &ServerConfig, catalog: Arc<Vec<CatalogEntry>>) -> Self {
+        let documents = DocumentStore::new();
+        documents.set_schema_key_precedence(config.schema_key_precedence);
+        documents.set_schema_associations(config.schemas.clone());
+        documents.set_catalog(catalog);
+        Self {
+            documents: Arc::new(documents),
+            language_schema_url: config.language_schemas.get("json").cloned(),
+        }
+    }
+
+    /// The file's URI and diagnostics, or `None` when it cannot be read.
+    pub async fn validate(
+        &self,
+        path: &Path,
+        schema_cache: &Arc<SchemaCache>,
+        config: &ServerConfig,
+    ) -> Option<(Url, Vec<Diagnostic>)> {
+        let uri = Url::from_file_path(path).ok()?;
+        // Read off the async runtime, as a large workspace reads many files in a row.
+        let text = {
+            let path = path.to_owned();
+            tokio::task::spawn_blocking(move || std::fs::read_to_string(path))
+                .await
+                .ok()?
+                .ok()?
+        };
+        self.documents.open(uri.clone(), 0, text);
+        self.documents
+            .set_language_schema_url(&uri, self.language_schema_url.clone());
+        self.documents
+            .set_dialect(&uri, Dialect::from_path(uri.path()));
+        let diagnostics = validate_document(&uri, &self.documents, schema_cache, config).await;
+        self.documents.close(&uri);
+        Some((uri, diagnostics.unwrap_or_default()))
+    }
+}
+
+/// `{ files, errors, warnings, results: [{ uri, diagnostics }] }`, listing only files with
+/// diagnostics.
+pub fn workspace_report(results: &[(Url, Vec<Diagnostic>)]) -> Value {
+    let count = |severity: DiagnosticSeverity| {
+        results
+            .iter()
+            .flat_map(|(_, diagnostics)| diagnostics)
+            .filter(|d| d.severity == Some(severity))
+            .count()
+    };
+    let listed: Vec<Value> = results
+        .iter()
+        .filter(|(_, diagnostics)| !diagnostics.is_empty())
+        .map(|(uri, diagnostics)| json!({ "uri": uri, "diagnostics": diagnostics }))
+        .collect();
+    json!({
+        "files": results.len(),
+        "errors": count(DiagnosticSeverity::ERROR),
+        "warnings": count(DiagnosticSeverity::WARNING),
+        "results": listed,
+    })
+}
+
+/// A `workspace/diagnostic` report for a closed file: `unchanged` when its diagnostics
+/// still hash to `previous_result_id`, else the full list under a new result id.
+pub fn document_report(
+    uri: Url,
+    diagnostics: Vec<Diagnostic>,
+    previous_result_id: Option<&str>,
+) -> WorkspaceDocumentDiagnosticReport {
+    let result_id = result_id(&diagnostics);
+    if previous_result_id == Some(result_id.as_str()) {
+        return WorkspaceDocumentDiagnosticReport::Unchanged(
+            WorkspaceUnchangedDocumentDiagnosticReport {
+                uri,
+                version: None,
+                unchanged_document_diagnostic_report: UnchangedDocumentDiagnosticReport {
+                    result_id,
+                },
+            },
+        );
+    }
+    WorkspaceDocumentDiagnosticReport::Full(WorkspaceFullDocumentDiagnosticReport {
+        uri,
+        version: None,
+        full_document_diagnostic_report: FullDocumentDiagnosticReport {
+            result_id: Some(result_id),
+            items: diagnostics,
+        },
+    })
+}
+
+/// Content hash of `diagnostics`, so an unchanged file costs the client nothing.
+fn result_id(diagnostics: &[Diagnostic]) -> String {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(diagnostics)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::EmbeddedLoader;
+
+    #[tokio::test]
+    async fn test_closed_file_validated_from_disk() {
+        let dir = std::env::temp_dir().join(format!("json-ls-closed-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let with_schema = dir.join("with-schema.json");
+        let by_language = dir.join("by-language.json");
+        std::fs::write(&with_schema, r#"{ "$schema": "mem://a.json", "n": "x" }"#).unwrap();
+        std::fs::write(&by_language, r#"{ "n": 1 }"#).unwrap();
+
+        let mut config = ServerConfig::default();
+        config
+            .language_schemas
+            .insert("json".into(), "mem://b.json".into());
+        let loader = EmbeddedLoader::new()
+            .with_schema(
+                "mem://a.json",
+                json!({ "properties": { "n": { "type": "integer" } } }),
+            )
+            .with_schema("mem://b.json", json!({ "required": ["name"] }));
+        let schema_cache = Arc::new(SchemaCache::with_loader(&config, Arc::new(loader)));
+
+        let closed = ClosedFiles::new(&config, Arc::default());
+        let mut results = Vec::new();
+        for path in [&with_schema, &by_language, &dir.join("missing.json")] {
+            results.extend(closed.validate(path, &schema_cache, &config).await);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].1.len(), 1, "got: {:?}", results[0].1);
+        assert_eq!(results[1].1.len(), 1, "got: {:?}", results[1].1);
+        let report = workspace_report(&results);
+        assert_eq!(report["files"], 2);
+        assert_eq!(report["errors"], 2);
+        assert_eq!(report["results"][0]["uri"], results[0].0.as_str());
+    }
+
+    #[tokio::test]
+    async fn test_closed_json5_file_read_as_json5() {
+        let dir = std::env::temp_dir().join(format!("json-ls-closed-json5-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json5");
+        std::fs::write(&path, "{\n  // no name\n  n: 1,\n}\n").unwrap();
+
+        let mut config = ServerConfig::default();
+        config
+            .language_schemas
+            .insert("json".into(), "mem://b.json".into());
+        let loader =
+            EmbeddedLoader::new().with_schema("mem://b.json", json!({ "required": ["name"] }));
+        let schema_cache = Arc::new(SchemaCache::with_loader(&config, Arc::new(loader)));
+
+        let closed = ClosedFiles::new(&config, Arc::default());
+        let result = closed.validate(&path, &schema_cache, &config).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let (_, diagnostics) = result.unwrap();
+        assert_eq!(diagnostics.len(), 1, "got: {diagnostics:?}");
+        assert!(
+            diagnostics[0].message.contains("name"),
+            "got: {diagnostics:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_closed_json_lines_file_validated_per_line() {
+        let dir = std::env::temp_dir().join(format!("json-ls-closed-jsonl-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("events.jsonl");
+        std::fs::write(&path, "{ \"name\": \"a\" }\n{ \"n\": 1 }\n").unwrap();
+
+        let mut config = ServerConfig::default();
+        config
+            .language_schemas
+            .insert("json".into(), "mem://b.json".into());
+        let loader =
+            EmbeddedLoader::new().with_schema("mem://b.json", json!({ "required": ["name"] }));
+        let schema_cache = Arc::new(SchemaCache::with_loader(&config, Arc::new(loader)));
+
+        let closed = ClosedFiles::new(&config, Arc::default());
+        let result = closed.validate(&path, &schema_cache, &config).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let (_, diagnostics) = result.unwrap();
+        assert_eq!(diagnostics.len(), 1, "got: {diagnostics:?}");
+        assert_eq!(diagnostics[0].range.start.line, 1);
+    }
+
+    #[test]
+    fn test_document_report_unchanged_for_same_diagnostics() {
+        let uri = Url::parse("file:///w/a.json").unwrap();
+        let diagnostics = vec![Diagnostic {
+            message: "bad".into(),
+            ..Default::default()
+        }];
+        let WorkspaceDocumentDiagnosticReport::Full(full) =
+            document_report(uri.clone(), diagnostics.clone(), None)
+        else {
+            panic!("expected a full report");
+        };
+        let result_id = full.full_document_diagnostic_report.result_id.unwrap();
+        assert_eq!(full.full_document_diagnostic_report.items, diagnostics);
+
+        assert!(matches!(
+            document_report(uri.clone(), diagnostics, Some(&result_id)),
+            WorkspaceDocumentDiagnosticReport::Unchanged(_)
+        ));
+        assert!(matches!(
+            document_report(uri, vec![], Some(&result_id)),
+            WorkspaceDocumentDiagnosticReport::Full(_)
+        ));
+    }
+}
@@ -0,0 +1,30 @@
+//! Embedding json-ls as a library: validate a document without the LSP transport.
+
+use json_ls::schema::EmbeddedLoader;
+use json_ls::{validate_document, DocumentStore, SchemaCache, ServerConfig};
+use serde_json::json;
+use std::sync::Arc;
+use tower_lsp::lsp_types::Url;
+
+#[tokio::test]
+async fn test_validate_document_via_library() {
+    let config = ServerConfig::default();
+    let documents = Arc::new(DocumentStore::new());
+    let loader = EmbeddedLoader::new().with_schema(
+        "mem://config.schema.json",
+        json!({ "type": "object", "required": ["name"] }),
+    );
+    let schema_cache = Arc::new(SchemaCache::with_loader(&config, Arc::new(loader)));
+
+    let uri = Url::parse("file:///tmp/library.json").unwrap();
+    let text = r#"{ "$schema": "mem://config.schema.json", "count": 1 }"#;
+    documents.open(uri.clone(), 1, text.into());
+
+    let diagnostics = validate_document(&uri, &documents, &schema_cache, &config)
+        .await
+        .unwrap();
+    assert_eq!(diagnostics.len(), 1, "got: {diagnostics:?}");
+    assert!(diagnostics[0]
+        .message
+        .contains("\"name\" is a required property"));
+}
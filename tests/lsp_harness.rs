@@ -1,223 +1,20 @@
 //! Integration tests: spawn json-ls as a child process and drive it via
-//! raw LSP JSON-RPC over stdin/stdout.
+//! raw LSP JSON-RPC over stdin/stdout (see `json_ls::testing`).
 
+use json_ls::testing::LspClient;
 use serde_json::{json, Value};
-use std::collections::VecDeque;
-use std::sync::atomic::{AtomicI64, Ordering};
-use std::sync::Arc;
 use std::time::Duration;
-use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
-use tokio::process::{Child, Command};
-use tokio::sync::Mutex;
-use tokio::time::timeout;
 
-const BINARY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/target/debug/json-ls");
+const BINARY: &str = env!("CARGO_BIN_EXE_json-ls");
 const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
-const DIAG_TIMEOUT_SECS: u64 = 6; // 300 ms debounce + network + headroom
 
 fn schema_file_url() -> String {
     format!("file://{FIXTURES}/simple-schema.json")
 }
 
-struct LspClient {
-    stdin: Mutex<tokio::process::ChildStdin>,
-    next_id: Arc<AtomicI64>,
-    pending_tx: Arc<Mutex<std::collections::HashMap<i64, tokio::sync::oneshot::Sender<Value>>>>,
-    notifications: Arc<Mutex<VecDeque<Value>>>,
-    _child: Child,
-}
-
-impl LspClient {
-    async fn spawn() -> Self {
-        let mut child = Command::new(BINARY)
-            .stdin(std::process::Stdio::piped())
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::null())
-            .spawn()
-            .expect("Failed to spawn json-ls. Run `cargo build` first.");
-
-        let stdin = child.stdin.take().unwrap();
-        let stdout = child.stdout.take().unwrap();
-
-        let pending_tx: Arc<
-            Mutex<std::collections::HashMap<i64, tokio::sync::oneshot::Sender<Value>>>,
-        > = Arc::new(Mutex::new(std::collections::HashMap::new()));
-        let notifications: Arc<Mutex<VecDeque<Value>>> = Arc::new(Mutex::new(VecDeque::new()));
-
-        // Background reader task
-        let pending_tx_bg = pending_tx.clone();
-        let notifications_bg = notifications.clone();
-        tokio::spawn(async move {
-            let mut reader = BufReader::new(stdout);
-            loop {
-                // Read headers
-                let mut content_length: Option<usize> = None;
-                loop {
-                    let mut line = String::new();
-                    if reader.read_line(&mut line).await.unwrap_or(0) == 0 {
-                        return; // EOF
-                    }
-                    let line = line.trim();
-                    if line.is_empty() {
-                        break;
-                    }
-                    if let Some(val) = line.strip_prefix("Content-Length: ") {
-                        content_length = val.trim().parse().ok();
-                    }
-                }
-                let len = match content_length {
-                    Some(l) => l,
-                    None => continue,
-                };
-                // Read body
-                let mut buf = vec![0u8; len];
-                if reader.read_exact(&mut buf).await.is_err() {
-                    return;
-                }
-                let msg: Value = match serde_json::from_slice(&buf) {
-                    Ok(v) => v,
-                    Err(_) => continue,
-                };
-                // Route: response (has id, no method) vs notification (has method, no id)
-                let id = msg.get("id").and_then(|v| v.as_i64());
-                let has_method = msg.get("method").is_some();
-
-                if let Some(id) = id {
-                    if !has_method {
-                        // Response to a request
-                        let sender = pending_tx_bg.lock().await.remove(&id);
-                        if let Some(tx) = sender {
-                            let _ = tx.send(msg);
-                        }
-                        continue;
-                    }
-                }
-                // Notification or server-initiated request
-                notifications_bg.lock().await.push_back(msg);
-            }
-        });
-
-        Self {
-            stdin: Mutex::new(stdin),
-            next_id: Arc::new(AtomicI64::new(1)),
-            pending_tx,
-            notifications,
-            _child: child,
-        }
-    }
-
-    async fn send_request(&self, method: &str, params: Option<Value>) -> Value {
-        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
-        let mut msg = json!({
-            "jsonrpc": "2.0",
-            "id": id,
-            "method": method,
-        });
-        if let Some(p) = params {
-            msg["params"] = p;
-        }
-        let (tx, rx) = tokio::sync::oneshot::channel();
-        self.pending_tx.lock().await.insert(id, tx);
-        self.write_message(&msg).await;
-        timeout(Duration::from_secs(10), rx)
-            .await
-            .expect("Request timed out")
-            .expect("Response channel dropped")
-    }
-
-    async fn send_notification(&self, method: &str, params: Option<Value>) {
-        let mut msg = json!({
-            "jsonrpc": "2.0",
-            "method": method,
-        });
-        if let Some(p) = params {
-            msg["params"] = p;
-        }
-        self.write_message(&msg).await;
-    }
-
-    async fn write_message(&self, msg: &Value) {
-        let body = serde_json::to_string(msg).unwrap();
-        let frame = format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
-        let mut stdin = self.stdin.lock().await;
-        stdin.write_all(frame.as_bytes()).await.unwrap();
-    }
-
-    async fn wait_for_notification(&self, method: &str) -> Value {
-        let deadline = tokio::time::Instant::now() + Duration::from_secs(DIAG_TIMEOUT_SECS);
-        loop {
-            assert!(
-                tokio::time::Instant::now() < deadline,
-                "Timeout waiting for notification: {method}"
-            );
-            let found = {
-                let mut queue = self.notifications.lock().await;
-                let pos = queue
-                    .iter()
-                    .position(|n| n["method"].as_str() == Some(method));
-                pos.map(|i| {
-                    let mut v: Vec<Value> = queue.drain(..).collect();
-                    let found = v.remove(i);
-                    *queue = v.into();
-                    found
-                })
-            };
-            if let Some(notif) = found {
-                return notif;
-            }
-            tokio::time::sleep(Duration::from_millis(50)).await;
-        }
-    }
-
-    async fn initialize(&self) -> Value {
-        let resp = self
-            .send_request(
-                "initialize",
-                Some(json!({
-                    "processId": null,
-                    "rootUri": null,
-                    "capabilities": {},
-                    "initializationOptions": {
-                        "schema_ttl_secs": 60,
-                        "schema_cache_capacity": 16
-                    }
-                })),
-            )
-            .await;
-        self.send_notification("initialized", Some(json!({}))).await;
-        resp
-    }
-
-    /// Open a document. `schema_url` is injected as the `$schema` value.
-    /// Use `None` to omit the `$schema` key entirely.
-    async fn open_document(&self, uri: &str, schema_url: Option<&str>, body_fields: &str) {
-        let text = match schema_url {
-            Some(url) => format!("{{\n  \"$schema\": \"{url}\",\n  {body_fields}\n}}"),
-            None => format!("{{\n  {body_fields}\n}}"),
-        };
-        self.send_notification(
-            "textDocument/didOpen",
-            Some(json!({
-                "textDocument": {
-                    "uri": uri,
-                    "languageId": "json",
-                    "version": 1,
-                    "text": text,
-                }
-            })),
-        )
-        .await;
-    }
-
-    async fn shutdown(&self) {
-        self.send_request("shutdown", None).await;
-        self.send_notification("exit", None).await;
-    }
-} // end impl LspClient
-
 #[tokio::test]
 async fn test_initialize() {
-    let client = LspClient::spawn().await;
+    let client = LspClient::spawn(BINARY);
     let resp = client.initialize().await;
 
     let caps = &resp["result"]["capabilities"];
@@ -239,7 +36,7 @@ async fn test_initialize() {
 
 #[tokio::test]
 async fn test_lifecycle_shutdown() {
-    let client = LspClient::spawn().await;
+    let client = LspClient::spawn(BINARY);
     client.initialize().await;
     let shutdown_resp = client.send_request("shutdown", None).await;
     // Shutdown must return null result, no error
@@ -256,7 +53,7 @@ async fn test_lifecycle_shutdown() {
 
 #[tokio::test]
 async fn test_diagnostics_valid_document() {
-    let client = LspClient::spawn().await;
+    let client = LspClient::spawn(BINARY);
     client.initialize().await;
 
     let schema_url = schema_file_url();
@@ -284,8 +81,9 @@ async fn test_diagnostics_valid_document() {
 }
 
 #[tokio::test]
+#[allow(clippy::len_zero)]
 async fn test_diagnostics_invalid_document() {
-    let client = LspClient::spawn().await;
+    let client = LspClient::spawn(BINARY);
     client.initialize().await;
 
     let schema_url = schema_file_url();
@@ -321,7 +119,7 @@ async fn test_diagnostics_invalid_document() {
 
 #[tokio::test]
 async fn test_hover_key() {
-    let client = LspClient::spawn().await;
+    let client = LspClient::spawn(BINARY);
     client.initialize().await;
 
     let schema_url = schema_file_url();
@@ -385,7 +183,7 @@ async fn test_hover_key() {
 async fn test_hover_on_key_string_returns_field_docs() {
     // Regression test: hovering on the key string itself (not the value) must return docs
     // for that field, not for the parent object.
-    let client = LspClient::spawn().await;
+    let client = LspClient::spawn(BINARY);
     client.initialize().await;
 
     let schema_url = schema_file_url();
@@ -450,7 +248,7 @@ async fn test_hover_on_key_string_returns_field_docs() {
 #[tokio::test]
 async fn test_hover_on_nested_key_returns_field_docs() {
     // Regression test: hovering on A.b.c key must return docs for c, not for b.
-    let client = LspClient::spawn().await;
+    let client = LspClient::spawn(BINARY);
     client.initialize().await;
 
     let schema_url = schema_file_url();
@@ -514,7 +312,7 @@ async fn test_hover_on_nested_key_returns_field_docs() {
 
 #[tokio::test]
 async fn test_completion_property_names() {
-    let client = LspClient::spawn().await;
+    let client = LspClient::spawn(BINARY);
     client.initialize().await;
 
     let schema_url = schema_file_url();
@@ -576,7 +374,7 @@ async fn test_completion_property_names() {
 
 #[tokio::test]
 async fn test_no_schema_key_produces_no_diagnostics() {
-    let client = LspClient::spawn().await;
+    let client = LspClient::spawn(BINARY);
     client.initialize().await;
 
     // Document with no "$schema" key
@@ -620,7 +418,7 @@ async fn test_no_schema_key_produces_no_diagnostics() {
 
 #[tokio::test]
 async fn test_malformed_json_produces_syntax_diagnostic() {
-    let client = LspClient::spawn().await;
+    let client = LspClient::spawn(BINARY);
     client.initialize().await;
 
     let schema_url = schema_file_url();
@@ -660,3 +458,2325 @@ async fn test_malformed_json_produces_syntax_diagnostic() {
 
     client.shutdown().await;
 }
+
+#[tokio::test]
+async fn test_syntax_errors_are_all_reported_and_validation_continues() {
+    let client = LspClient::spawn(BINARY);
+    client.initialize().await;
+
+    // A missing comma, a trailing comma, and a type error the schema still catches.
+    let body = r#""name": "hello" "count": "many", "enabled": true,"#;
+    client
+        .open_document(
+            "file:///tmp/recovering.json",
+            Some(&schema_file_url()),
+            body,
+        )
+        .await;
+
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    let diagnostics = notif["params"]["diagnostics"].as_array().unwrap();
+    let codes: Vec<&str> = diagnostics
+        .iter()
+        .filter_map(|d| d["code"].as_str())
+        .collect();
+    assert_eq!(
+        codes.iter().filter(|code| **code == "json-syntax").count(),
+        2,
+        "got: {diagnostics:?}"
+    );
+    assert!(
+        diagnostics
+            .iter()
+            .any(|d| d["code"] == "schema-validation" && d["data"]["instancePath"] == "/count"),
+        "validation should still run on the recovered value, got: {diagnostics:?}"
+    );
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_code_lens_reports_subtree_errors() {
+    let client = LspClient::spawn(BINARY);
+    client.initialize().await;
+
+    let schema_url = schema_file_url();
+    // "meta" spans well over the lens threshold and contains one type error ("author": 42).
+    let tags = (0..25)
+        .map(|i| format!("      \"tag{i}\""))
+        .collect::<Vec<_>>()
+        .join(",\n");
+    let text = format!(
+        "{{\n  \"$schema\": \"{schema_url}\",\n  \"name\": \"x\",\n  \"meta\": {{\n    \"author\": 42,\n    \"tags\": [\n{tags}\n    ]\n  }}\n}}"
+    );
+    client
+        .send_notification(
+            "textDocument/didOpen",
+            Some(json!({
+                "textDocument": {
+                    "uri": "file:///tmp/lens.json",
+                    "languageId": "json",
+                    "version": 1,
+                    "text": text,
+                }
+            })),
+        )
+        .await;
+
+    client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+
+    let resp = client
+        .send_request(
+            "textDocument/codeLens",
+            Some(json!({ "textDocument": { "uri": "file:///tmp/lens.json" } })),
+        )
+        .await;
+
+    let lenses = resp["result"]
+        .as_array()
+        .expect("codeLens result should be an array");
+    assert_eq!(
+        lenses.len(),
+        1,
+        "Expected one lens for 'meta', got: {lenses:?}"
+    );
+    assert_eq!(lenses[0]["range"]["start"]["line"].as_u64(), Some(3));
+    assert_eq!(
+        lenses[0]["command"]["title"].as_str(),
+        Some("Validate this subtree: 1 error")
+    );
+    assert_eq!(lenses[0]["command"]["arguments"][1].as_str(), Some("/meta"));
+
+    let resp = client
+        .send_request(
+            "workspace/executeCommand",
+            Some(json!({
+                "command": lenses[0]["command"]["command"],
+                "arguments": lenses[0]["command"]["arguments"],
+            })),
+        )
+        .await;
+    assert_eq!(resp["result"], json!({ "pointer": "/meta", "errors": 1 }));
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_schema_status_notification() {
+    let client = LspClient::spawn(BINARY);
+    client.initialize().await;
+
+    let schema_url = schema_file_url();
+    client
+        .open_document(
+            "file:///tmp/status_ok.json",
+            Some(&schema_url),
+            r#""name": "x""#,
+        )
+        .await;
+    let notif = client.wait_for_notification("json-ls/schemaStatus").await;
+    assert_eq!(
+        notif["params"]["uri"].as_str(),
+        Some("file:///tmp/status_ok.json")
+    );
+    assert_eq!(notif["params"]["state"].as_str(), Some("loaded"));
+    assert_eq!(
+        notif["params"]["schemaUrl"].as_str(),
+        Some(schema_url.as_str())
+    );
+
+    client
+        .open_document(
+            "file:///tmp/status_missing.json",
+            Some("file:///nonexistent/schema.json"),
+            r#""name": "x""#,
+        )
+        .await;
+    let notif = client.wait_for_notification("json-ls/schemaStatus").await;
+    assert_eq!(notif["params"]["state"].as_str(), Some("error"));
+    assert!(
+        notif["params"]["reason"].as_str().is_some(),
+        "Expected a failure reason, got: {notif}"
+    );
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_unfetchable_schema_warns_on_schema_value() {
+    let client = LspClient::spawn(BINARY);
+    client
+        .initialize_with(json!({ "error_retry_secs": 30 }))
+        .await;
+
+    client
+        .open_document(
+            "file:///tmp/fetch_failed.json",
+            Some("file:///nonexistent/schema.json"),
+            r#""name": "x""#,
+        )
+        .await;
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    let diagnostics = notif["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1, "got: {diagnostics:?}");
+    let diagnostic = &diagnostics[0];
+    assert_eq!(diagnostic["code"], "schema-fetch-failed");
+    assert_eq!(diagnostic["severity"], 2);
+    assert_eq!(
+        diagnostic["range"]["start"],
+        json!({ "line": 1, "character": 13 })
+    );
+    let message = diagnostic["message"].as_str().unwrap();
+    assert!(
+        message.contains("/nonexistent/schema.json"),
+        "got: {message}"
+    );
+    assert!(message.contains("30 s"), "got: {message}");
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_uncompilable_schema_is_reported_on_schema_value() {
+    let client = LspClient::spawn(BINARY);
+    client.initialize().await;
+
+    let schema_url = format!("file://{FIXTURES}/invalid-schema.json");
+    client
+        .open_document(
+            "file:///tmp/invalid_schema.json",
+            Some(&schema_url),
+            r#""code": "x""#,
+        )
+        .await;
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    let diagnostics = notif["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1, "got: {diagnostics:?}");
+    assert_eq!(diagnostics[0]["code"], "schema-invalid");
+    assert_eq!(diagnostics[0]["severity"], 1);
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], 1);
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_schema_errors_without_schema_key_are_reported_at_document_start() {
+    let client = LspClient::spawn(BINARY);
+    client
+        .initialize_with(json!({
+            "schemas": [
+                { "fileMatch": ["*.missing.json"], "url": "file:///nonexistent/schema.json" },
+                {
+                    "fileMatch": ["*.invalid.json"],
+                    "url": format!("file://{FIXTURES}/invalid-schema.json")
+                }
+            ]
+        }))
+        .await;
+
+    for (uri, code) in [
+        ("file:///tmp/app.missing.json", "schema-fetch-failed"),
+        ("file:///tmp/app.invalid.json", "schema-invalid"),
+    ] {
+        client.open_document(uri, None, r#""code": "x""#).await;
+        let notif = client
+            .wait_for_notification("textDocument/publishDiagnostics")
+            .await;
+        let diagnostics = notif["params"]["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1, "{uri}: {diagnostics:?}");
+        assert_eq!(diagnostics[0]["code"], code);
+        assert_eq!(
+            diagnostics[0]["range"]["start"],
+            json!({ "line": 0, "character": 0 })
+        );
+    }
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_schema_authoring_type_value_completion() {
+    let client = LspClient::spawn(BINARY);
+    client.initialize().await;
+
+    // A schema document: completion on the value of "type" offers the primitive types
+    // without needing to fetch the meta-schema.
+    // Line 2: `  "type": ""` — cursor at col 11 is inside the empty string
+    let text =
+        "{\n  \"$schema\": \"http://json-schema.org/draft-07/schema#\",\n  \"type\": \"\"\n}";
+    client
+        .send_notification(
+            "textDocument/didOpen",
+            Some(json!({
+                "textDocument": {
+                    "uri": "file:///tmp/authoring.schema.json",
+                    "languageId": "json",
+                    "version": 1,
+                    "text": text,
+                }
+            })),
+        )
+        .await;
+
+    let resp = client
+        .send_request(
+            "textDocument/completion",
+            Some(json!({
+                "textDocument": { "uri": "file:///tmp/authoring.schema.json" },
+                "position": { "line": 2, "character": 11 }
+            })),
+        )
+        .await;
+
+    let items = resp["result"]
+        .as_array()
+        .expect("completion result should be an array");
+    let labels: Vec<&str> = items.iter().filter_map(|i| i["label"].as_str()).collect();
+    for expected in ["string", "integer", "object", "null"] {
+        assert!(
+            labels.contains(&expected),
+            "Expected '{expected}' in completions, got: {labels:?}"
+        );
+    }
+    assert_eq!(
+        labels.len(),
+        7,
+        "Expected the seven primitive types: {labels:?}"
+    );
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_schema_authoring_keyword_hover() {
+    let client = LspClient::spawn(BINARY);
+    client.initialize().await;
+
+    // Line 2: `  "additionalProperties": false` — cursor at col 6 is inside the keyword
+    let text = "{\n  \"$schema\": \"https://json-schema.org/draft/2020-12/schema\",\n  \"additionalProperties\": false\n}";
+    client
+        .send_notification(
+            "textDocument/didOpen",
+            Some(json!({
+                "textDocument": {
+                    "uri": "file:///tmp/keyword_hover.schema.json",
+                    "languageId": "json",
+                    "version": 1,
+                    "text": text,
+                }
+            })),
+        )
+        .await;
+
+    let resp = client
+        .send_request(
+            "textDocument/hover",
+            Some(json!({
+                "textDocument": { "uri": "file:///tmp/keyword_hover.schema.json" },
+                "position": { "line": 2, "character": 6 }
+            })),
+        )
+        .await;
+
+    let contents = resp["result"]["contents"]["value"].as_str().unwrap_or("");
+    assert!(
+        contents.contains("additionalProperties") && contents.contains("Draft 4+"),
+        "Expected keyword reference for additionalProperties, got: {contents:?}"
+    );
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_goto_definition_jumps_to_schema_property() {
+    let client = LspClient::spawn(BINARY);
+    client.initialize().await;
+
+    let schema_url = schema_file_url();
+    // Line 2: `  "count": 42` — cursor at col 4 is inside the "count" key
+    let text = format!("{{\n  \"$schema\": \"{schema_url}\",\n  \"count\": 42\n}}");
+    client
+        .send_notification(
+            "textDocument/didOpen",
+            Some(json!({
+                "textDocument": {
+                    "uri": "file:///tmp/definition.json",
+                    "languageId": "json",
+                    "version": 1,
+                    "text": text,
+                }
+            })),
+        )
+        .await;
+
+    let resp = client
+        .send_request(
+            "textDocument/definition",
+            Some(json!({
+                "textDocument": { "uri": "file:///tmp/definition.json" },
+                "position": { "line": 2, "character": 4 }
+            })),
+        )
+        .await;
+
+    let result = &resp["result"];
+    assert!(
+        result["uri"]
+            .as_str()
+            .is_some_and(|u| u.ends_with("simple-schema.json")),
+        "Expected a location in simple-schema.json, got: {resp}"
+    );
+    // `"count": {` is on line 9 of the fixture
+    assert_eq!(result["range"]["start"]["line"].as_u64(), Some(9));
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_format_assertion_warning() {
+    let client = LspClient::spawn(BINARY);
+    client
+        .initialize_with(json!({ "format_assertion": "warning" }))
+        .await;
+
+    let schema_url = format!("file://{FIXTURES}/format-schema.json");
+    client
+        .open_document(
+            "file:///tmp/format.json",
+            Some(&schema_url),
+            r#""contact": "not-an-email""#,
+        )
+        .await;
+
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    let diagnostics = notif["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1, "got: {diagnostics:?}");
+    assert_eq!(
+        diagnostics[0]["severity"], 2,
+        "format failure should be a warning"
+    );
+    assert_eq!(
+        diagnostics[0]["data"]["schemaPath"], "/properties/contact/format",
+        "diagnostic should carry the failing keyword's location"
+    );
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_array_errors_have_precise_ranges() {
+    let client = LspClient::spawn(BINARY);
+    client.initialize().await;
+
+    let schema_url = format!("file://{FIXTURES}/array-schema.json");
+    // Line 2: tags, line 3: ports (line 0 is `{`, line 1 is `$schema`).
+    client
+        .open_document(
+            "file:///tmp/arrays.json",
+            Some(&schema_url),
+            "\"tags\": [\"a\", \"b\", \"a\"],\n  \"ports\": [80]",
+        )
+        .await;
+
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    let diagnostics = notif["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 2, "got: {diagnostics:?}");
+
+    let unique = diagnostics
+        .iter()
+        .find(|d| d["message"].as_str().unwrap().contains("non-unique"))
+        .expect("uniqueItems diagnostic");
+    // The second "a" — `  "tags": ["a", "b", "a"]` → columns 21..24
+    assert_eq!(
+        unique["range"]["start"],
+        json!({ "line": 2, "character": 21 })
+    );
+    assert_eq!(
+        unique["range"]["end"],
+        json!({ "line": 2, "character": 24 })
+    );
+    let related = &unique["relatedInformation"][0]["location"]["range"];
+    assert_eq!(related["start"], json!({ "line": 2, "character": 11 }));
+
+    let min_items = diagnostics
+        .iter()
+        .find(|d| d != &unique)
+        .expect("minItems diagnostic");
+    assert_eq!(
+        min_items["range"]["start"],
+        json!({ "line": 3, "character": 11 })
+    );
+    assert_eq!(
+        min_items["range"]["end"],
+        json!({ "line": 3, "character": 15 })
+    );
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_schema_url_pointing_at_non_schema_warns() {
+    let client = LspClient::spawn(BINARY);
+    client.initialize().await;
+
+    // A data document, not a schema.
+    let schema_url = format!("file://{FIXTURES}/no-schema.json");
+    client
+        .open_document(
+            "file:///tmp/not-schema.json",
+            Some(&schema_url),
+            r#""a": 1"#,
+        )
+        .await;
+
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    let diagnostics = notif["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1, "got: {diagnostics:?}");
+    assert_eq!(diagnostics[0]["code"], "schema-not-a-schema");
+    assert_eq!(diagnostics[0]["severity"], 2);
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], 1);
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_notebook_json_cell_diagnostics() {
+    let client = LspClient::spawn(BINARY);
+    client.initialize().await;
+
+    let schema_url = schema_file_url();
+    let cell_uri = "vscode-notebook-cell:/tmp/nb.ipynb#cell1";
+    client
+        .send_notification(
+            "notebookDocument/didOpen",
+            Some(json!({
+                "notebookDocument": {
+                    "uri": "file:///tmp/nb.ipynb",
+                    "notebookType": "jupyter-notebook",
+                    "version": 1,
+                    "cells": [
+                        { "kind": 2, "document": cell_uri },
+                        { "kind": 2, "document": "vscode-notebook-cell:/tmp/nb.ipynb#cell2" }
+                    ]
+                },
+                "cellTextDocuments": [
+                    {
+                        "uri": cell_uri,
+                        "languageId": "json",
+                        "version": 1,
+                        "text": format!("{{\"$schema\": \"{schema_url}\", \"count\": \"x\"}}")
+                    },
+                    {
+                        "uri": "vscode-notebook-cell:/tmp/nb.ipynb#cell2",
+                        "languageId": "python",
+                        "version": 1,
+                        "text": "print('hi')"
+                    }
+                ]
+            })),
+        )
+        .await;
+
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    assert_eq!(notif["params"]["uri"], cell_uri);
+    let diagnostics = notif["params"]["diagnostics"].as_array().unwrap();
+    assert!(!diagnostics.is_empty(), "JSON cell should be validated");
+
+    // Removing both cells clears the JSON cell only: the Python one was never opened.
+    client
+        .send_notification(
+            "notebookDocument/didChange",
+            Some(json!({
+                "notebookDocument": { "uri": "file:///tmp/nb.ipynb", "version": 2 },
+                "change": {
+                    "cells": {
+                        "structure": {
+                            "array": { "start": 0, "deleteCount": 2 },
+                            "didClose": [
+                                { "uri": "vscode-notebook-cell:/tmp/nb.ipynb#cell2" },
+                                { "uri": cell_uri }
+                            ]
+                        }
+                    }
+                }
+            })),
+        )
+        .await;
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    assert_eq!(notif["params"]["uri"], cell_uri);
+    assert_eq!(notif["params"]["diagnostics"], json!([]));
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_schema_resolver_command() {
+    let client = LspClient::spawn(BINARY);
+    let schema_url = schema_file_url();
+    client
+        .initialize_with(json!({
+            "schema_resolver": {
+                "command": ["sh", "-c", format!("echo {schema_url}"), "resolver"]
+            }
+        }))
+        .await;
+
+    client
+        .open_document(
+            "file:///tmp/generated.json",
+            None,
+            r#""name": "x", "count": "many""#,
+        )
+        .await;
+
+    let notif = client.wait_for_notification("json-ls/schemaStatus").await;
+    assert_eq!(notif["params"]["state"], "loaded");
+    assert_eq!(notif["params"]["source"], "resolver");
+    assert_eq!(notif["params"]["schemaUrl"], schema_url.as_str());
+
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    let diagnostics = notif["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1, "got: {diagnostics:?}");
+    assert_eq!(diagnostics[0]["data"]["instancePath"], "/count");
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_array_and_scalar_roots() {
+    let client = LspClient::spawn(BINARY);
+    let schema = r#"{"description": "Tag list", "type": "array", "items": {"type": "string", "description": "One tag"}}"#;
+    client
+        .initialize_with(json!({
+            "schema_resolver": { "command": ["sh", "-c", format!("echo '{schema}'"), "resolver"] }
+        }))
+        .await;
+
+    let open = |uri: &'static str, text: &'static str| {
+        client.send_notification(
+            "textDocument/didOpen",
+            Some(json!({
+                "textDocument": { "uri": uri, "languageId": "json", "version": 1, "text": text }
+            })),
+        )
+    };
+
+    // Array root: the offending element is marked.
+    open("file:///tmp/tags.json", r#"["a", 1]"#).await;
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    let diagnostics = notif["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1, "got: {diagnostics:?}");
+    assert_eq!(
+        diagnostics[0]["range"],
+        json!({ "start": { "line": 0, "character": 6 }, "end": { "line": 0, "character": 7 } })
+    );
+
+    let resp = client
+        .send_request(
+            "textDocument/hover",
+            Some(json!({
+                "textDocument": { "uri": "file:///tmp/tags.json" },
+                "position": { "line": 0, "character": 2 }
+            })),
+        )
+        .await;
+    let contents = resp["result"]["contents"]["value"].as_str().unwrap_or("");
+    assert!(contents.contains("One tag"), "hover: {resp}");
+
+    // Scalar root: the violation covers the whole value.
+    open("file:///tmp/scalar.json", "  42\n").await;
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    assert_eq!(notif["params"]["uri"], "file:///tmp/scalar.json");
+    let diagnostics = notif["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1, "got: {diagnostics:?}");
+    assert_eq!(
+        diagnostics[0]["range"],
+        json!({ "start": { "line": 0, "character": 2 }, "end": { "line": 0, "character": 4 } })
+    );
+
+    let resp = client
+        .send_request(
+            "textDocument/hover",
+            Some(json!({
+                "textDocument": { "uri": "file:///tmp/scalar.json" },
+                "position": { "line": 0, "character": 3 }
+            })),
+        )
+        .await;
+    let contents = resp["result"]["contents"]["value"].as_str().unwrap_or("");
+    assert!(contents.contains("Tag list"), "hover: {resp}");
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_change_to_unknown_document_with_full_text_opens_it() {
+    let client = LspClient::spawn(BINARY);
+    client.initialize().await;
+
+    let schema_url = schema_file_url();
+    let text = format!("{{\n  \"$schema\": \"{schema_url}\",\n  \"name\": 1\n}}");
+    client
+        .send_notification(
+            "textDocument/didChange",
+            Some(json!({
+                "textDocument": { "uri": "file:///tmp/never-opened.json", "version": 4 },
+                "contentChanges": [{ "text": text }]
+            })),
+        )
+        .await;
+
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    assert_eq!(notif["params"]["uri"], "file:///tmp/never-opened.json");
+    let diagnostics = notif["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1, "got: {diagnostics:?}");
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_cyclic_external_ref_is_reported_on_schema() {
+    let client = LspClient::spawn(BINARY);
+    client.initialize().await;
+
+    let schema_url = format!("file://{FIXTURES}/cyclic-a.json");
+    client
+        .open_document("file:///tmp/cyclic.json", Some(&schema_url), r#""a": 1"#)
+        .await;
+
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    let diagnostics = notif["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1, "got: {diagnostics:?}");
+    assert_eq!(diagnostics[0]["code"], "schema-ref-cycle");
+    assert_eq!(
+        diagnostics[0]["message"],
+        "cyclic $ref via cyclic-a.json → cyclic-b.json → cyclic-a.json"
+    );
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], 1);
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_slow_pattern_times_out_with_warning() {
+    let client = LspClient::spawn(BINARY);
+    client
+        .initialize_with(json!({ "validation_timeout_ms": 200 }))
+        .await;
+
+    let schema_url = format!("file://{FIXTURES}/slow-pattern-schema.json");
+    let words = vec![format!("{}!", "a".repeat(30)); 200];
+    let body = format!(r#""words": {}"#, serde_json::to_string(&words).unwrap());
+    client
+        .open_document("file:///tmp/slow.json", Some(&schema_url), &body)
+        .await;
+
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    let diagnostics = notif["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1, "got: {diagnostics:?}");
+    assert_eq!(diagnostics[0]["code"], "validation-timeout");
+    assert_eq!(diagnostics[0]["severity"], 2);
+    assert_eq!(
+        diagnostics[0]["data"]["schemaPath"],
+        "/properties/words/items/pattern"
+    );
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_status_reports_usage_metrics_and_saves_them() {
+    let cache_dir = std::env::temp_dir().join(format!("json-ls-status-{}", std::process::id()));
+    let client = LspClient::spawn(BINARY);
+    client
+        .initialize_with(json!({ "usage_metrics": true, "cache_dir": cache_dir }))
+        .await;
+
+    client
+        .send_request(
+            "textDocument/hover",
+            Some(json!({
+                "textDocument": { "uri": "file:///tmp/unopened.json" },
+                "position": { "line": 0, "character": 0 }
+            })),
+        )
+        .await;
+    let resp = client.send_request("json-ls/status", None).await;
+    let status = &resp["result"];
+    assert_eq!(status["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(status["usage"]["session"]["features"]["hover"], 1);
+    assert!(status["usage"]["total"]["features"]["hover"].as_u64() >= Some(1));
+
+    client.shutdown().await;
+    let saved: Value =
+        serde_json::from_str(&std::fs::read_to_string(cache_dir.join("usage.json")).unwrap())
+            .unwrap();
+    std::fs::remove_dir_all(&cache_dir).unwrap();
+    assert_eq!(saved["features"]["hover"], 1);
+}
+
+#[tokio::test]
+async fn test_completion_between_members_adds_commas() {
+    let client = LspClient::spawn(BINARY);
+    client.initialize().await;
+
+    let schema_url = schema_file_url();
+    // Line 2 lacks its comma; the key typed on line 3 sits before a sibling.
+    let text = format!(
+        "{{\n  \"$schema\": \"{schema_url}\",\n  \"name\": \"x\"\n  \"\"\n  \"count\": 1\n}}"
+    );
+    client
+        .send_notification(
+            "textDocument/didOpen",
+            Some(json!({
+                "textDocument": {
+                    "uri": "file:///tmp/commas.json",
+                    "languageId": "json",
+                    "version": 1,
+                    "text": text,
+                }
+            })),
+        )
+        .await;
+    client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+
+    let resp = client
+        .send_request(
+            "textDocument/completion",
+            Some(json!({
+                "textDocument": { "uri": "file:///tmp/commas.json" },
+                "position": { "line": 3, "character": 3 }
+            })),
+        )
+        .await;
+    let items = resp["result"].as_array().expect("completion items");
+    let enabled = items
+        .iter()
+        .find(|i| i["label"] == "enabled")
+        .expect("enabled completion");
+    assert_eq!(enabled["insertText"], "enabled\": $0,");
+    assert_eq!(enabled["insertTextFormat"], 2);
+    assert_eq!(
+        enabled["additionalTextEdits"],
+        json!([{
+            "range": {
+                "start": { "line": 2, "character": 13 },
+                "end": { "line": 2, "character": 13 }
+            },
+            "newText": ","
+        }])
+    );
+
+    let resp = client
+        .send_request(
+            "textDocument/onTypeFormatting",
+            Some(json!({
+                "textDocument": { "uri": "file:///tmp/commas.json" },
+                "position": { "line": 3, "character": 0 },
+                "ch": "\n",
+                "options": { "tabSize": 2, "insertSpaces": true }
+            })),
+        )
+        .await;
+    assert_eq!(resp["result"][0]["newText"], ",");
+    assert_eq!(resp["result"][0]["range"]["start"]["line"], 2);
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_hover_ends_with_schema_provenance() {
+    let client = LspClient::spawn(BINARY);
+    client.initialize().await;
+
+    let schema_url = schema_file_url();
+    let text =
+        format!("{{\n  \"$schema\": \"{schema_url}\",\n  \"meta\": {{ \"author\": \"x\" }}\n}}");
+    client
+        .send_notification(
+            "textDocument/didOpen",
+            Some(json!({
+                "textDocument": {
+                    "uri": "file:///tmp/provenance.json",
+                    "languageId": "json",
+                    "version": 1,
+                    "text": text,
+                }
+            })),
+        )
+        .await;
+    client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+
+    let resp = client
+        .send_request(
+            "textDocument/hover",
+            Some(json!({
+                "textDocument": { "uri": "file:///tmp/provenance.json" },
+                "position": { "line": 2, "character": 16 }
+            })),
+        )
+        .await;
+    let contents = resp["result"]["contents"]["value"].as_str().unwrap_or("");
+    assert!(
+        contents.ends_with(&format!(
+            "from `{schema_url}#/properties/meta/properties/author`"
+        )),
+        "hover: {contents:?}"
+    );
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_periodic_metrics_notification() {
+    let client = LspClient::spawn(BINARY);
+    client
+        .initialize_with(json!({ "metrics_notification_secs": 1 }))
+        .await;
+
+    let params = client.wait_for_notification("json-ls/metrics").await["params"].clone();
+    assert_eq!(params["openDocuments"], 0);
+    assert!(params["validations"].is_u64(), "metrics: {params}");
+    assert!(params.get("cacheHitRate").is_some(), "metrics: {params}");
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_language_schemas_associate_by_language_id() {
+    let client = LspClient::spawn(BINARY);
+    let schema_url = schema_file_url();
+    client
+        .initialize_with(json!({ "languageSchemas": { "kubernetes-json": schema_url } }))
+        .await;
+
+    client
+        .send_notification(
+            "textDocument/didOpen",
+            Some(json!({
+                "textDocument": {
+                    "uri": "file:///tmp/deployment.json",
+                    "languageId": "kubernetes-json",
+                    "version": 1,
+                    "text": "{ \"name\": \"web\", \"count\": \"three\" }",
+                }
+            })),
+        )
+        .await;
+
+    let status = client.wait_for_notification("json-ls/schemaStatus").await;
+    assert_eq!(status["params"]["source"], "language");
+    assert_eq!(status["params"]["schemaUrl"], schema_url.as_str());
+
+    let diagnostics = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    let messages = diagnostics["params"]["diagnostics"].to_string();
+    assert!(messages.contains("integer"), "diagnostics: {messages}");
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_add_schema_command_uses_argument_or_catalog() {
+    let catalog = std::env::temp_dir().join(format!("json-ls-catalog-{}.json", std::process::id()));
+    std::fs::write(
+        &catalog,
+        json!({ "schemas": [{
+            "name": "Simple",
+            "url": schema_file_url(),
+            "fileMatch": ["*.simple.json"]
+        }] })
+        .to_string(),
+    )
+    .unwrap();
+
+    let client = LspClient::spawn(BINARY);
+    client
+        .initialize_with(json!({ "catalog": { "sources": [catalog.to_string_lossy()] } }))
+        .await;
+    for uri in ["file:///tmp/app.simple.json", "file:///tmp/other.json"] {
+        client
+            .send_notification(
+                "textDocument/didOpen",
+                Some(json!({
+                    "textDocument": {
+                        "uri": uri,
+                        "languageId": "json",
+                        "version": 1,
+                        "text": "{\n  \"name\": \"x\"\n}",
+                    }
+                })),
+            )
+            .await;
+    }
+
+    let resp = client
+        .send_request(
+            "workspace/executeCommand",
+            Some(json!({
+                "command": "json-ls.addSchema",
+                "arguments": ["file:///tmp/app.simple.json"]
+            })),
+        )
+        .await;
+    let edit = &resp["result"]["changes"]["file:///tmp/app.simple.json"][0];
+    assert_eq!(
+        edit["newText"],
+        format!("\n  \"$schema\": \"{}\",", schema_file_url())
+    );
+
+    let resp = client
+        .send_request(
+            "workspace/executeCommand",
+            Some(json!({
+                "command": "json-ls.addSchema",
+                "arguments": ["file:///tmp/other.json", "https://example.com/s.json"]
+            })),
+        )
+        .await;
+    let edit = &resp["result"]["changes"]["file:///tmp/other.json"][0];
+    assert_eq!(
+        edit["newText"],
+        "\n  \"$schema\": \"https://example.com/s.json\","
+    );
+
+    let resp = client
+        .send_request(
+            "workspace/executeCommand",
+            Some(json!({
+                "command": "json-ls.addSchema",
+                "arguments": ["file:///tmp/other.json"]
+            })),
+        )
+        .await;
+    std::fs::remove_file(&catalog).unwrap();
+    assert!(resp["error"]["message"]
+        .as_str()
+        .is_some_and(|m| m.contains("no catalog entry")));
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_unchanged_diagnostics_are_not_republished() {
+    let client = LspClient::spawn(BINARY);
+    client.initialize().await;
+
+    let uri = "file:///tmp/republish.json";
+    let schema_url = schema_file_url();
+    client
+        .open_document(uri, Some(&schema_url), r#""name": 1"#)
+        .await;
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    assert_eq!(notif["params"]["version"], 1);
+    assert_eq!(notif["params"]["diagnostics"].as_array().unwrap().len(), 1);
+
+    let change = |version: i32, body: &str| {
+        json!({
+            "textDocument": { "uri": uri, "version": version },
+            "contentChanges": [{
+                "text": format!("{{\n  \"$schema\": \"{schema_url}\",\n  {body}\n}}")
+            }]
+        })
+    };
+    // Trailing whitespace leaves the diagnostics as they were: nothing is published.
+    client
+        .send_notification("textDocument/didChange", Some(change(2, r#""name": 1 "#)))
+        .await;
+    tokio::time::sleep(Duration::from_millis(800)).await;
+    client
+        .send_notification("textDocument/didChange", Some(change(3, r#""name": "ok""#)))
+        .await;
+
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    assert_eq!(notif["params"]["version"], 3);
+    assert_eq!(notif["params"]["diagnostics"], json!([]));
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_in_process_client_drives_backend() {
+    let client = LspClient::in_process();
+    let resp = client.initialize().await;
+    assert!(resp["result"]["capabilities"]["hoverProvider"]
+        .as_bool()
+        .unwrap_or(false));
+
+    let schema_url = schema_file_url();
+    client
+        .open_document(
+            "file:///tmp/in-process.json",
+            Some(&schema_url),
+            r#""name": 1"#,
+        )
+        .await;
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    assert_eq!(notif["params"]["diagnostics"].as_array().unwrap().len(), 1);
+
+    let status = client.send_request("json-ls/status", None).await;
+    assert!(status["result"].is_object(), "got: {status}");
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_validate_workspace_reads_closed_files() {
+    let root = std::env::temp_dir().join(format!("json-ls-validate-ws-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    let schema_url = schema_file_url();
+    let closed = root.join("closed.json");
+    std::fs::write(
+        &closed,
+        format!("{{ \"$schema\": \"{schema_url}\", \"name\": 1 }}"),
+    )
+    .unwrap();
+    std::fs::write(root.join("valid.json"), "{}").unwrap();
+    let closed_uri = format!("file://{}", closed.display());
+
+    let client = LspClient::spawn(BINARY);
+    client
+        .send_request(
+            "initialize",
+            Some(json!({
+                "processId": null,
+                "rootUri": format!("file://{}", root.display()),
+                "capabilities": {},
+            })),
+        )
+        .await;
+    client
+        .send_notification("initialized", Some(json!({})))
+        .await;
+
+    let resp = client
+        .send_request(
+            "workspace/executeCommand",
+            Some(json!({
+                "command": "json-ls.validateWorkspace",
+                "arguments": [{ "publish": true }]
+            })),
+        )
+        .await;
+    std::fs::remove_dir_all(&root).unwrap();
+
+    let report = &resp["result"];
+    assert_eq!(report["files"], 2, "got: {report}");
+    assert_eq!(report["errors"], 1, "got: {report}");
+    assert_eq!(report["results"][0]["uri"], closed_uri.as_str());
+
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    assert_eq!(notif["params"]["uri"], closed_uri.as_str());
+    assert_eq!(notif["params"]["diagnostics"].as_array().unwrap().len(), 1);
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_validate_workspace_clears_files_that_drop_out() {
+    let root = std::env::temp_dir().join(format!("json-ls-clear-ws-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    let schema_url = schema_file_url();
+    let closed = root.join("closed.json");
+    std::fs::write(
+        &closed,
+        format!("{{ \"$schema\": \"{schema_url}\", \"name\": 1 }}"),
+    )
+    .unwrap();
+    let closed_uri = format!("file://{}", closed.display());
+
+    let client = LspClient::spawn(BINARY);
+    client
+        .send_request(
+            "initialize",
+            Some(json!({
+                "processId": null,
+                "rootUri": format!("file://{}", root.display()),
+                "capabilities": {},
+            })),
+        )
+        .await;
+    client
+        .send_notification("initialized", Some(json!({})))
+        .await;
+
+    let validate = json!({
+        "command": "json-ls.validateWorkspace",
+        "arguments": [{ "publish": true }]
+    });
+    client
+        .send_request("workspace/executeCommand", Some(validate.clone()))
+        .await;
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    assert_eq!(notif["params"]["uri"], closed_uri.as_str());
+    assert_eq!(notif["params"]["diagnostics"].as_array().unwrap().len(), 1);
+
+    // Deleted since the first run: its diagnostics are cleared.
+    std::fs::remove_file(&closed).unwrap();
+    let resp = client
+        .send_request("workspace/executeCommand", Some(validate))
+        .await;
+    std::fs::remove_dir_all(&root).unwrap();
+    assert_eq!(resp["result"]["files"], 0, "got: {resp}");
+
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    assert_eq!(notif["params"]["uri"], closed_uri.as_str());
+    assert!(notif["params"]["diagnostics"]
+        .as_array()
+        .unwrap()
+        .is_empty());
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_references_include_closed_workspace_files() {
+    let root = std::env::temp_dir().join(format!("json-ls-refs-ws-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    let meta = "https://json-schema.org/draft/2020-12/schema";
+    std::fs::write(
+        root.join("app.json"),
+        format!(
+            "{{\"$schema\": \"{meta}\",\n\"properties\": {{ \"a\": {{ \"$ref\": \"common.json#/%24defs/Name\" }} }} }}"
+        ),
+    )
+    .unwrap();
+    let common = root.join("common.json");
+    // Line 2: `  "$defs": { "Name": ...` — cursor at col 14 is inside "Name"
+    let text = format!("{{\n  \"$schema\": \"{meta}\",\n  \"$defs\": {{ \"Name\": {{ \"type\": \"string\" }} }}\n}}");
+    std::fs::write(&common, &text).unwrap();
+    let common_uri = format!("file://{}", common.display());
+
+    let client = LspClient::spawn(BINARY);
+    client
+        .send_request(
+            "initialize",
+            Some(json!({
+                "processId": null,
+                "rootUri": format!("file://{}", root.display()),
+                "capabilities": {},
+            })),
+        )
+        .await;
+    client
+        .send_notification("initialized", Some(json!({})))
+        .await;
+    client
+        .send_notification(
+            "textDocument/didOpen",
+            Some(json!({
+                "textDocument": {
+                    "uri": common_uri,
+                    "languageId": "json",
+                    "version": 1,
+                    "text": text,
+                }
+            })),
+        )
+        .await;
+
+    let resp = client
+        .send_request(
+            "textDocument/references",
+            Some(json!({
+                "textDocument": { "uri": common_uri },
+                "position": { "line": 2, "character": 14 },
+                "context": { "includeDeclaration": false }
+            })),
+        )
+        .await;
+    std::fs::remove_dir_all(&root).unwrap();
+
+    let locations = resp["result"].as_array().unwrap();
+    assert_eq!(locations.len(), 1, "got: {resp}");
+    assert!(
+        locations[0]["uri"].as_str().unwrap().ends_with("/app.json"),
+        "got: {resp}"
+    );
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_catalog_associates_document_by_file_name() {
+    let catalog = std::env::temp_dir().join(format!("json-ls-assoc-{}.json", std::process::id()));
+    std::fs::write(
+        &catalog,
+        json!({ "schemas": [{
+            "name": "Simple",
+            "url": schema_file_url(),
+            "fileMatch": ["*.simple.json"]
+        }] })
+        .to_string(),
+    )
+    .unwrap();
+
+    let client = LspClient::spawn(BINARY);
+    client
+        .initialize_with(json!({ "catalog": { "sources": [catalog.to_string_lossy()] } }))
+        .await;
+    client
+        .open_document("file:///tmp/service.simple.json", None, r#""name": 1"#)
+        .await;
+
+    let status = client.wait_for_notification("json-ls/schemaStatus").await;
+    assert_eq!(status["params"]["source"], "catalog", "got: {status}");
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    std::fs::remove_file(&catalog).unwrap();
+    assert_eq!(notif["params"]["diagnostics"].as_array().unwrap().len(), 1);
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_schema_status_explains_overridden_associations() {
+    let client = LspClient::spawn(BINARY);
+    let configured = "file:///nonexistent/configured.json";
+    client
+        .initialize_with(json!({
+            "schemas": [{ "fileMatch": ["*.simple.json"], "url": configured }]
+        }))
+        .await;
+    client
+        .open_document(
+            "file:///tmp/inline.simple.json",
+            Some(&schema_file_url()),
+            r#""name": "x""#,
+        )
+        .await;
+
+    let status = client.wait_for_notification("json-ls/schemaStatus").await;
+    let params = &status["params"];
+    assert_eq!(params["source"], "inline", "got: {status}");
+    assert_eq!(
+        params["overridden"],
+        json!([{ "schemaUrl": configured, "source": "fileMatch" }])
+    );
+    let why = params["why"].as_str().unwrap();
+    assert!(
+        why.starts_with("inline `$schema` takes precedence over `schemas` fileMatch"),
+        "got: {why}"
+    );
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_file_match_setting_associates_schema() {
+    let client = LspClient::spawn(BINARY);
+    client
+        .initialize_with(json!({
+            "schemas": [{ "fileMatch": ["*.simple.json"], "url": schema_file_url() }]
+        }))
+        .await;
+    let uri = "file:///tmp/app.simple.json";
+    client.open_document(uri, None, r#""name": 1"#).await;
+
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    assert_eq!(notif["params"]["diagnostics"].as_array().unwrap().len(), 1);
+
+    let hover = client
+        .send_request(
+            "textDocument/hover",
+            Some(json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": 1, "character": 4 }
+            })),
+        )
+        .await;
+    assert!(
+        hover["result"]["contents"]["value"].is_string(),
+        "got: {hover}"
+    );
+
+    let incomplete = "file:///tmp/incomplete.simple.json";
+    client.open_document(incomplete, None, r#""""#).await;
+    let completion = client
+        .send_request(
+            "textDocument/completion",
+            Some(json!({
+                "textDocument": { "uri": incomplete },
+                "position": { "line": 1, "character": 3 }
+            })),
+        )
+        .await;
+    let labels: Vec<&str> = completion["result"]
+        .as_array()
+        .expect("completion result should be an array")
+        .iter()
+        .filter_map(|item| item["label"].as_str())
+        .collect();
+    assert!(labels.contains(&"count"), "got: {labels:?}");
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_did_change_configuration_revalidates_open_documents() {
+    let client = LspClient::spawn(BINARY);
+    client
+        .initialize_with(json!({ "catalog": { "sources": [] } }))
+        .await;
+    client
+        .open_document("file:///tmp/live.simple.json", None, r#""name": 1"#)
+        .await;
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    assert_eq!(notif["params"]["diagnostics"], json!([]));
+
+    client
+        .send_notification(
+            "workspace/didChangeConfiguration",
+            Some(json!({ "settings": { "json-ls": {
+                "catalog": { "sources": [] },
+                "schemas": [{ "fileMatch": ["*.simple.json"], "url": schema_file_url() }]
+            } } })),
+        )
+        .await;
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    assert_eq!(notif["params"]["diagnostics"].as_array().unwrap().len(), 1);
+
+    client
+        .send_notification(
+            "workspace/didChangeConfiguration",
+            Some(json!({ "settings": {
+                "catalog": { "sources": [] },
+                "schemas": [{ "fileMatch": ["*.simple.json"], "url": schema_file_url() }],
+                "diagnostics": { "rules": { "schema-validation": "off" } }
+            } })),
+        )
+        .await;
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    assert_eq!(notif["params"]["diagnostics"], json!([]));
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_preload_schemas_are_cached_after_initialized() {
+    let client = LspClient::spawn(BINARY);
+    client
+        .initialize_with(json!({ "preload_schemas": [schema_file_url()] }))
+        .await;
+
+    let mut cached = 0;
+    for _ in 0..50 {
+        let resp = client.send_request("json-ls/status", None).await;
+        cached = resp["result"]["cachedSchemas"].as_u64().unwrap();
+        if cached > 0 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(cached, 1);
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_external_ref_is_followed_by_hover_and_validation() {
+    let client = LspClient::spawn(BINARY);
+    client.initialize().await;
+    let schema_url = format!("file://{FIXTURES}/ref-root.json");
+    client
+        .open_document(
+            "file:///tmp/external_ref.json",
+            Some(&schema_url),
+            r#""owner": { "name": 5 }"#,
+        )
+        .await;
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    assert_eq!(
+        notif["params"]["diagnostics"].as_array().unwrap().len(),
+        1,
+        "got: {notif}"
+    );
+
+    let resp = client
+        .send_request(
+            "textDocument/hover",
+            Some(json!({
+                "textDocument": { "uri": "file:///tmp/external_ref.json" },
+                "position": { "line": 2, "character": 15 }
+            })),
+        )
+        .await;
+    let contents = resp["result"]["contents"]["value"].as_str().unwrap_or("");
+    assert!(contents.contains("Full name of the person"), "got: {resp}");
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_bundle_schema_inlines_external_refs() {
+    let client = LspClient::spawn(BINARY);
+    client.initialize().await;
+    let schema_url = format!("file://{FIXTURES}/ref-root.json");
+
+    let resp = client
+        .send_request(
+            "workspace/executeCommand",
+            Some(json!({ "command": "json-ls.bundleSchema", "arguments": [schema_url] })),
+        )
+        .await;
+    let result = &resp["result"];
+    assert_eq!(result["unresolved"], json!([]), "got: {resp}");
+    let common = format!("file://{FIXTURES}/ref-common.json");
+    let person = &result["schema"]["$defs"][&common]["definitions"]["person"];
+    assert_eq!(person["type"], "object", "got: {resp}");
+
+    let output = std::env::temp_dir().join(format!("json-ls-bundle-{}.json", std::process::id()));
+    let resp = client
+        .send_request(
+            "workspace/executeCommand",
+            Some(json!({
+                "command": "json-ls.bundleSchema",
+                "arguments": [schema_url, { "output": output }]
+            })),
+        )
+        .await;
+    let written: Value = serde_json::from_str(&std::fs::read_to_string(&output).unwrap()).unwrap();
+    std::fs::remove_file(&output).unwrap();
+    assert_eq!(resp["result"]["path"], output.to_str().unwrap());
+    assert_eq!(written, result["schema"]);
+
+    // Without a workspace folder there is nothing to resolve a relative path against.
+    let resp = client
+        .send_request(
+            "workspace/executeCommand",
+            Some(json!({
+                "command": "json-ls.bundleSchema",
+                "arguments": [schema_url, { "output": "bundled.json" }]
+            })),
+        )
+        .await;
+    assert_eq!(resp["error"]["code"], -32602, "got: {resp}");
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_inline_config_schema_and_data_url() {
+    let client = LspClient::spawn(BINARY);
+    client
+        .initialize_with(json!({
+            "schemas": [{ "fileMatch": ["*.inline.json"], "schema": { "required": ["name"] } }]
+        }))
+        .await;
+
+    client
+        .open_document("file:///tmp/settings.inline.json", None, r#""other": 1"#)
+        .await;
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    assert_eq!(
+        notif["params"]["diagnostics"].as_array().unwrap().len(),
+        1,
+        "got: {notif}"
+    );
+
+    let data_url =
+        "data:application/json,%7B%22properties%22:%7B%22n%22:%7B%22type%22:%22integer%22%7D%7D%7D";
+    client
+        .open_document("file:///tmp/data_url.json", Some(data_url), r#""n": "x""#)
+        .await;
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    assert_eq!(notif["params"]["uri"], "file:///tmp/data_url.json");
+    assert_eq!(
+        notif["params"]["diagnostics"].as_array().unwrap().len(),
+        1,
+        "got: {notif}"
+    );
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_did_you_mean_suggestions_and_quick_fixes() {
+    let client = LspClient::spawn(BINARY);
+    client
+        .initialize_with(json!({
+            "schemas": [{
+                "fileMatch": ["*.suggest.json"],
+                "schema": {
+                    "properties": { "level": { "enum": ["debug", "info", "warn"] }, "port": {} },
+                    "additionalProperties": false
+                }
+            }]
+        }))
+        .await;
+
+    let uri = "file:///tmp/app.suggest.json";
+    client
+        .open_document(uri, None, r#""level": "wran", "prot": 80"#)
+        .await;
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    let diagnostics = notif["params"]["diagnostics"].as_array().unwrap().clone();
+    assert_eq!(diagnostics.len(), 2, "got: {diagnostics:?}");
+    let messages: Vec<&str> = diagnostics
+        .iter()
+        .filter_map(|d| d["message"].as_str())
+        .collect();
+    assert!(
+        messages
+            .iter()
+            .any(|m| m.contains("did you mean `\"warn\"`?")),
+        "got: {messages:?}"
+    );
+    assert!(
+        messages.iter().any(|m| m.contains("did you mean `port`?")),
+        "got: {messages:?}"
+    );
+
+    let resp = client
+        .send_request(
+            "textDocument/codeAction",
+            Some(json!({
+                "textDocument": { "uri": uri },
+                "range": { "start": { "line": 1, "character": 2 }, "end": { "line": 1, "character": 2 } },
+                "context": { "diagnostics": diagnostics }
+            })),
+        )
+        .await;
+    let actions = resp["result"].as_array().expect("code actions");
+    let fixes: Vec<(&str, &str)> = actions
+        .iter()
+        .filter(|a| a["kind"] == "quickfix")
+        .map(|a| {
+            (
+                a["title"].as_str().unwrap(),
+                a["edit"]["changes"][uri][0]["newText"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert!(
+        fixes.contains(&("Replace with `\"warn\"`", "\"warn\"")),
+        "got: {fixes:?}"
+    );
+    assert!(
+        fixes.contains(&("Rename `prot` to `port`", "\"port\"")),
+        "got: {fixes:?}"
+    );
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_validation_error_points_at_schema_keyword() {
+    let client = LspClient::spawn(BINARY);
+    client.initialize().await;
+
+    client
+        .open_document(
+            "file:///tmp/keyword_source.json",
+            Some(&schema_file_url()),
+            r#""name": "x", "count": "many""#,
+        )
+        .await;
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    let diagnostics = notif["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1, "got: {diagnostics:?}");
+    let related = &diagnostics[0]["relatedInformation"][0];
+    assert!(
+        related["location"]["uri"]
+            .as_str()
+            .is_some_and(|uri| uri.ends_with("/tests/fixtures/simple-schema.json")),
+        "got: {related}"
+    );
+    // `"type": "integer"` of `count`, on line 11 of the fixture.
+    assert_eq!(related["location"]["range"]["start"]["line"], 10);
+    assert_eq!(related["message"], "\"type\": \"integer\"");
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_deprecated_property_is_tagged_and_noted_in_hover() {
+    let client = LspClient::spawn(BINARY);
+    client
+        .initialize_with(json!({
+            "schemas": [{
+                "fileMatch": ["*.deprecated.json"],
+                "schema": {
+                    "properties": {
+                        "legacy": {
+                            "type": "integer",
+                            "deprecated": true,
+                            "deprecationMessage": "Use `modern` instead."
+                        }
+                    }
+                }
+            }]
+        }))
+        .await;
+
+    let uri = "file:///tmp/app.deprecated.json";
+    client.open_document(uri, None, r#""legacy": 1"#).await;
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    let diagnostics = notif["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1, "got: {diagnostics:?}");
+    assert_eq!(diagnostics[0]["code"], "deprecated");
+    assert_eq!(diagnostics[0]["severity"], 2);
+    assert_eq!(diagnostics[0]["tags"], json!([2]));
+    assert_eq!(
+        diagnostics[0]["range"],
+        json!({ "start": { "line": 1, "character": 2 }, "end": { "line": 1, "character": 10 } })
+    );
+
+    let resp = client
+        .send_request(
+            "textDocument/hover",
+            Some(json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": 1, "character": 5 }
+            })),
+        )
+        .await;
+    let contents = resp["result"]["contents"]["value"].as_str().unwrap_or("");
+    assert!(
+        contents.starts_with("**Deprecated:** Use `modern` instead."),
+        "hover: {contents:?}"
+    );
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_deprecation_message_is_sanitized_in_hover() {
+    let client = LspClient::spawn(BINARY);
+    client
+        .initialize_with(json!({
+            "schemas": [{
+                "fileMatch": ["*.deprecated.json"],
+                "schema": {
+                    "properties": {
+                        "legacy": {
+                            "deprecated": true,
+                            "deprecationMessage":
+                                "Use <b>modern</b>, see [docs](javascript:alert(1)) or `<b>`."
+                        }
+                    }
+                }
+            }]
+        }))
+        .await;
+
+    let uri = "file:///tmp/app.deprecated.json";
+    client.open_document(uri, None, r#""legacy": 1"#).await;
+    client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+
+    let resp = client
+        .send_request(
+            "textDocument/hover",
+            Some(json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": 1, "character": 5 }
+            })),
+        )
+        .await;
+    let contents = resp["result"]["contents"]["value"].as_str().unwrap_or("");
+    assert!(
+        contents.starts_with("**Deprecated:** Use &lt;b>modern&lt;/b>, see [docs](#) or `<b>`."),
+        "hover: {contents:?}"
+    );
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_json5_document_is_validated_and_completes_unquoted_keys() {
+    let client = LspClient::spawn(BINARY);
+    client
+        .initialize_with(json!({
+            "schemas": [{
+                "fileMatch": ["*.json5"],
+                "schema": {
+                    "properties": {
+                        "name": { "type": "string", "description": "Service name" },
+                        "port": { "type": "integer" },
+                        "timeout-ms": { "type": "integer" }
+                    }
+                }
+            }]
+        }))
+        .await;
+
+    let uri = "file:///tmp/service.json5";
+    let text = "// service\n{\n  name: 'api',\n  port: '8080',\n  retries: 0x10,\n}\n";
+    client
+        .send_notification(
+            "textDocument/didOpen",
+            Some(json!({
+                "textDocument": {
+                    "uri": uri,
+                    "languageId": "json5",
+                    "version": 1,
+                    "text": text,
+                }
+            })),
+        )
+        .await;
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    let diagnostics = notif["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1, "got: {diagnostics:?}");
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], 3);
+    assert!(
+        diagnostics[0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("integer"),
+        "got: {diagnostics:?}"
+    );
+
+    let resp = client
+        .send_request(
+            "textDocument/hover",
+            Some(json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": 2, "character": 3 }
+            })),
+        )
+        .await;
+    let contents = resp["result"]["contents"]["value"].as_str().unwrap_or("");
+    assert!(contents.contains("Service name"), "hover: {contents:?}");
+
+    let resp = client
+        .send_request(
+            "textDocument/completion",
+            Some(json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": 4, "character": 4 }
+            })),
+        )
+        .await;
+    let items = resp["result"].as_array().expect("completion items");
+    let insert_text = |label: &str| {
+        items
+            .iter()
+            .find(|item| item["label"] == label)
+            .and_then(|item| item["insertText"].as_str())
+            .map(str::to_owned)
+    };
+    assert_eq!(insert_text("name").as_deref(), Some("name: "));
+    assert_eq!(
+        insert_text("timeout-ms").as_deref(),
+        Some("\"timeout-ms\": ")
+    );
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_json_lines_validates_each_line() {
+    let client = LspClient::spawn(BINARY);
+    client
+        .initialize_with(json!({
+            "schemas": [{
+                "fileMatch": ["*.ndjson"],
+                "schema": {
+                    "required": ["id"],
+                    "properties": {
+                        "id": { "type": "integer", "description": "Record id" },
+                        "name": { "type": "string" }
+                    }
+                }
+            }]
+        }))
+        .await;
+
+    // Opened as plain `json`: the extension selects JSON Lines.
+    let uri = "file:///tmp/records.ndjson";
+    let text =
+        "{\"id\": 1, \"name\": \"a\"}\n{\"id\": \"two\"}\n\n{\"name\": \"c\"}\n{\"id\": 4,\n";
+    client
+        .send_notification(
+            "textDocument/didOpen",
+            Some(json!({
+                "textDocument": {
+                    "uri": uri,
+                    "languageId": "json",
+                    "version": 1,
+                    "text": text,
+                }
+            })),
+        )
+        .await;
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    let diagnostics = notif["params"]["diagnostics"].as_array().unwrap();
+    let located: Vec<(u64, u64, &str)> = diagnostics
+        .iter()
+        .map(|d| {
+            (
+                d["range"]["start"]["line"].as_u64().unwrap(),
+                d["range"]["start"]["character"].as_u64().unwrap(),
+                d["code"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        located,
+        [
+            (1, 1, "schema-validation"),
+            (3, 0, "schema-validation"),
+            (4, 0, "json-syntax")
+        ],
+        "got: {diagnostics:?}"
+    );
+
+    let resp = client
+        .send_request(
+            "textDocument/hover",
+            Some(json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": 1, "character": 3 }
+            })),
+        )
+        .await;
+    let contents = resp["result"]["contents"]["value"].as_str().unwrap_or("");
+    assert!(contents.contains("Record id"), "hover: {contents:?}");
+
+    let resp = client
+        .send_request(
+            "textDocument/completion",
+            Some(json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": 3, "character": 2 }
+            })),
+        )
+        .await;
+    let labels: Vec<&str> = resp["result"]
+        .as_array()
+        .expect("completion items")
+        .iter()
+        .filter_map(|item| item["label"].as_str())
+        .collect();
+    assert!(labels.contains(&"id"), "labels: {labels:?}");
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_validate_on_save_skips_edits() {
+    let client = LspClient::spawn(BINARY);
+    client
+        .initialize_with(json!({ "validate_on": "save", "debounce_ms": 0 }))
+        .await;
+
+    let uri = "file:///tmp/validate-on-save.json";
+    let schema_url = schema_file_url();
+    client
+        .open_document(uri, Some(&schema_url), r#""name": 1"#)
+        .await;
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    assert_eq!(notif["params"]["diagnostics"].as_array().unwrap().len(), 1);
+
+    let change = |version: i32, body: &str| {
+        json!({
+            "textDocument": { "uri": uri, "version": version },
+            "contentChanges": [{
+                "text": format!("{{\n  \"$schema\": \"{schema_url}\",\n  {body}\n}}")
+            }]
+        })
+    };
+    client
+        .send_notification("textDocument/didChange", Some(change(2, r#""name": "ok""#)))
+        .await;
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    client
+        .send_notification("textDocument/didChange", Some(change(3, r#""name": true"#)))
+        .await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    client
+        .send_notification(
+            "textDocument/didSave",
+            Some(json!({ "textDocument": { "uri": uri } })),
+        )
+        .await;
+
+    // The edits published nothing; the save validates the latest text.
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    assert_eq!(notif["params"]["version"], 3);
+    let diagnostics = notif["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1, "got: {diagnostics:?}");
+    assert!(diagnostics[0]["message"].as_str().unwrap().contains("true"));
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_save_resyncs_text_and_validates_immediately() {
+    let client = LspClient::spawn(BINARY);
+    let resp = client
+        .initialize_with(json!({ "debounce_ms": 60000 }))
+        .await;
+    assert_eq!(
+        resp["result"]["capabilities"]["textDocumentSync"]["save"],
+        json!({ "includeText": true })
+    );
+
+    let uri = "file:///tmp/saved.json";
+    let schema_url = schema_file_url();
+    client
+        .open_document(uri, Some(&schema_url), r#""name": "ok""#)
+        .await;
+    // Let the open settle: notifications are handled concurrently.
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    client
+        .send_notification(
+            "textDocument/didSave",
+            Some(json!({
+                "textDocument": { "uri": uri },
+                "text": format!("{{\n  \"$schema\": \"{schema_url}\",\n  \"name\": 1\n}}")
+            })),
+        )
+        .await;
+
+    // The save replaces the 60 s debounce of the open, and validates what was saved.
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    let diagnostics = notif["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1, "got: {diagnostics:?}");
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], 2);
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_workspace_diagnostic_reports_closed_files() {
+    let root = std::env::temp_dir().join(format!("json-ls-ws-diag-{}", std::process::id()));
+    for dir in ["config", "other"] {
+        std::fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    let schema_url = schema_file_url();
+    let invalid = format!("{{ \"$schema\": \"{schema_url}\", \"name\": 1 }}");
+    let closed = root.join("config/closed.json");
+    std::fs::write(&closed, &invalid).unwrap();
+    std::fs::write(root.join("other/excluded.json"), &invalid).unwrap();
+    let closed_uri = format!("file://{}", closed.display());
+
+    let client = LspClient::spawn(BINARY);
+    let resp = client
+        .send_request(
+            "initialize",
+            Some(json!({
+                "processId": null,
+                "rootUri": format!("file://{}", root.display()),
+                "capabilities": {},
+                "initializationOptions": {
+                    "catalog": { "sources": [] },
+                    "workspace_diagnostics": { "include": ["config/*.json"] }
+                }
+            })),
+        )
+        .await;
+    assert_eq!(
+        resp["result"]["capabilities"]["diagnosticProvider"]["workspaceDiagnostics"],
+        true
+    );
+    client
+        .send_notification("initialized", Some(json!({})))
+        .await;
+
+    let resp = client
+        .send_request(
+            "workspace/diagnostic",
+            Some(json!({ "previousResultIds": [] })),
+        )
+        .await;
+    let items = resp["result"]["items"].as_array().unwrap();
+    assert_eq!(items.len(), 1, "got: {items:?}");
+    assert_eq!(items[0]["uri"], closed_uri.as_str());
+    assert_eq!(items[0]["kind"], "full");
+    assert_eq!(items[0]["items"].as_array().unwrap().len(), 1);
+    let result_id = items[0]["resultId"].clone();
+
+    // Same diagnostics as last time: only `unchanged`, streamed to the partial result token.
+    let resp = client
+        .send_request(
+            "workspace/diagnostic",
+            Some(json!({
+                "previousResultIds": [{ "uri": closed_uri, "value": result_id }],
+                "partialResultToken": "ws-diag"
+            })),
+        )
+        .await;
+    assert_eq!(resp["result"]["items"], json!([]));
+    let progress = client.wait_for_notification("$/progress").await;
+    std::fs::remove_dir_all(&root).unwrap();
+    assert_eq!(progress["params"]["token"], "ws-diag");
+    assert_eq!(progress["params"]["value"]["items"][0]["kind"], "unchanged");
+    assert_eq!(
+        progress["params"]["value"]["items"][0]["resultId"],
+        result_id
+    );
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_edit_during_open_validation_is_not_published_as_older_version() {
+    let client = LspClient::spawn(BINARY);
+    client
+        .initialize_with(json!({ "validate_on": "open" }))
+        .await;
+
+    let uri = "file:///tmp/validate-on-open.json";
+    let schema_url = schema_file_url();
+    client
+        .open_document(uri, Some(&schema_url), r#""name": 1"#)
+        .await;
+    // Lands within the debounce: the open's validation sees this text.
+    client
+        .send_notification(
+            "textDocument/didChange",
+            Some(json!({
+                "textDocument": { "uri": uri, "version": 2 },
+                "contentChanges": [{
+                    "text": format!("{{\n  \"$schema\": \"{schema_url}\",\n  \"name\": true\n}}")
+                }]
+            })),
+        )
+        .await;
+
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    assert_eq!(notif["params"]["version"], 2);
+    let diagnostics = notif["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1, "got: {diagnostics:?}");
+    assert!(diagnostics[0]["message"].as_str().unwrap().contains("true"));
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_large_document_skips_validation_but_keeps_hover() {
+    let client = LspClient::spawn(BINARY);
+    client
+        .initialize_with(json!({ "max_validate_bytes": 64 }))
+        .await;
+
+    let uri = "file:///tmp/large.json";
+    let schema_url = schema_file_url();
+    client
+        .open_document(uri, Some(&schema_url), r#""name": 1, "count": "many""#)
+        .await;
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    let diagnostics = notif["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1, "got: {diagnostics:?}");
+    assert_eq!(diagnostics[0]["code"], "validation-skipped");
+    assert_eq!(diagnostics[0]["severity"], 3);
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], 1);
+
+    let resp = client
+        .send_request(
+            "textDocument/hover",
+            Some(json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": 2, "character": 4 }
+            })),
+        )
+        .await;
+    let contents = resp["result"]["contents"]["value"].as_str().unwrap_or("");
+    assert!(
+        contents.contains("The name of the item"),
+        "hover: {contents:?}"
+    );
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_completion_in_top_level_array_items() {
+    let client = LspClient::spawn(BINARY);
+    client
+        .initialize_with(json!({
+            "schemas": [{
+                "fileMatch": ["*.services.json"],
+                "schema": {
+                    "type": "array",
+                    "items": {
+                        "properties": {
+                            "name": { "type": "string", "description": "Service name" },
+                            "kind": { "enum": ["web", "worker"] }
+                        }
+                    }
+                }
+            }]
+        }))
+        .await;
+
+    let uri = "file:///tmp/app.services.json";
+    let text = "[\n  { \"name\": \"api\", \"kind\": \"web\" },\n  { \"\" }\n]";
+    client
+        .send_notification(
+            "textDocument/didOpen",
+            Some(json!({
+                "textDocument": { "uri": uri, "languageId": "json", "version": 1, "text": text }
+            })),
+        )
+        .await;
+    client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+
+    let complete = |line: u32, character: u32| {
+        client.send_request(
+            "textDocument/completion",
+            Some(json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": line, "character": character }
+            })),
+        )
+    };
+    let labels = |resp: &Value| -> Vec<String> {
+        resp["result"]
+            .as_array()
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| item["label"].as_str().map(str::to_owned))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    // Inside the empty key of the second element.
+    let resp = complete(2, 5).await;
+    assert_eq!(labels(&resp), ["kind", "name"], "resp: {resp}");
+
+    // The enum value of the first element.
+    let resp = complete(1, 31).await;
+    assert!(
+        labels(&resp).contains(&"\"worker\"".to_owned()),
+        "resp: {resp}"
+    );
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_json_lines_share_one_validation_budget() {
+    let client = LspClient::spawn(BINARY);
+    let schema_url = format!("file://{FIXTURES}/slow-pattern-schema.json");
+    client
+        .initialize_with(json!({
+            "validation_timeout_ms": 200,
+            "catalog": { "sources": [] },
+            "schemas": [{ "fileMatch": ["*.jsonl"], "url": schema_url }]
+        }))
+        .await;
+
+    let words = vec![format!("{}!", "a".repeat(30)); 20];
+    let line = format!(r#"{{"words": {}}}"#, serde_json::to_string(&words).unwrap());
+    let text = format!("{}\n", vec![line; 40].join("\n"));
+    client
+        .send_notification(
+            "textDocument/didOpen",
+            Some(json!({
+                "textDocument": {
+                    "uri": "file:///tmp/slow.jsonl",
+                    "languageId": "json",
+                    "version": 1,
+                    "text": text,
+                }
+            })),
+        )
+        .await;
+
+    let notif = client
+        .wait_for_notification("textDocument/publishDiagnostics")
+        .await;
+    let diagnostics = notif["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1, "got: {diagnostics:?}");
+    assert_eq!(diagnostics[0]["code"], "validation-timeout");
+    assert_eq!(
+        diagnostics[0]["data"]["schemaPath"],
+        "/properties/words/items/pattern"
+    );
+    let pointer = diagnostics[0]["data"]["instancePath"].as_str().unwrap();
+    assert!(pointer.starts_with("/words/"), "got: {pointer}");
+    assert!(diagnostics[0]["range"]["start"]["line"].as_u64() < Some(40));
+
+    client.shutdown().await;
+}